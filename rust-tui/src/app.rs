@@ -1,54 +1,335 @@
 //! Main application with event loop
 
-use std::io::Stdout;
-use std::path::PathBuf;
-use std::sync::mpsc::{self, Receiver, TryRecvError};
-use std::time::Duration;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use chrono::{Local, Utc};
 use color_eyre::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::prelude::*;
 
-use crate::config::{mark_installed, mark_uninstalled};
-use crate::installer::{run_install_command, InstallerEvent};
-use crate::options::OPTIONS;
-use crate::state::{ActionChoice, AppMode, AppState};
-use crate::ui::render_layout;
+use crate::commands::{self, command_names, complete, parse};
+use crate::config::{load_prefs, RunAction};
+use crate::deps::install_order;
+use crate::editor_locks::find_editor_locks;
+use crate::export;
+use crate::journal::{discard_journal, BatchJournal, ItemStatus};
+use crate::jump::{self, JUMP_TIMEOUT};
+use crate::keymap::Action;
+use crate::input::accept_key;
+use crate::installer::{
+    command_line, describe_priority, run_install_command, Completion, InstallerEvent, InstallerHandle, PtySize,
+    RunSettings,
+};
+use crate::logs::{latest_log, option_history, read_log, RunLog};
+use crate::modified::{back_up, modified_files};
+use crate::options::InstallOption;
+use crate::notify::notify;
+use crate::panels::Panel;
+use crate::pending::{PendingChoice, PendingKind};
+use crate::registry::{self, Scope};
+use crate::persist::{PersistCommand, Persister};
+use crate::prefs::UiPrefs;
+use crate::script_env::script_env;
+use crate::presence::{check_all, Presence, PresenceEvent};
+use crate::requirements::unmet_requirements;
+use crate::run_id::RunId;
+use crate::state::{
+    ActionChoice, AppMode, AppState, CompatPending, LocalChanges, LocalChangesChoice, PendingRun, QueueResult,
+    ToastKind,
+};
+use crate::suspend::{catch_tstp, run_editor, run_shell, stop_self, StepOut};
+use crate::timeout::{describe_limit, Overrun, RunWatch, TimeoutAction};
+use crate::timings::RunTimer;
+use crate::tty::{enter_tui, restore_terminal, TerminalOut};
+use crate::ui::{display_path, render_layout, strip_ansi_codes};
+use crate::versions::{query_all, VersionEvent};
+use crate::update::{preview_update, pull, reinstall_candidates, UpdateEvent};
+use crate::wake::{relay, InputThread, Wake};
+
+/// Minimum interval between redraws while the UI is dimmed
+const DIMMED_REDRAW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Redraw interval while a run's spinner and clock are on screen
+const SPINNER_TICK: Duration = Duration::from_millis(100);
+
+/// Shortest time between redraws, so a burst of events costs one frame
+const MIN_FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Time spent taking in installer events before the next frame is drawn
+const INGEST_BUDGET: Duration = Duration::from_millis(10);
+
+/// Longest sleep when nothing is scheduled; only a safety net
+const IDLE_WAIT: Duration = Duration::from_secs(60);
+
+/// Longest gap between two clicks on a row that counts as a double-click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Output lines scrolled per mouse wheel step
+const WHEEL_SCROLL_LINES: usize = 3;
+
+/// Longest gap between Up/Down presses that still counts as the key being held
+const HELD_NAV_GAP: Duration = Duration::from_millis(150);
+
+/// Held Up/Down presses per extra row moved, up to MAX_NAV_STEP rows at once
+const NAV_ACCELERATION: usize = 10;
+const MAX_NAV_STEP: usize = 4;
+
+/// Text by which sudo's password prompt is recognised in the output
+const SUDO_PROMPT: &str = "[sudo] password for";
+
+/// Password prompts answered per run before it is aborted
+const MAX_PASSWORD_ATTEMPTS: u8 = 3;
+
+/// Room reserved for a typed password, so it isn't copied around while growing
+const PASSWORD_CAPACITY: usize = 256;
+
+/// How long quitting waits for pending state writes
+const PERSIST_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Main application
 pub struct App {
     /// Application state
     state: AppState,
-    /// Path to the script directory containing install
-    script_dir: PathBuf,
     /// Channel receiver for installer events
     installer_rx: Option<Receiver<InstallerEvent>>,
+    /// Handle to the running installer subprocess
+    installer: Option<InstallerHandle>,
+    /// Log file of the running (or last) action
+    run_log: Option<RunLog>,
+    /// Stage timings of the running (or last) action
+    run_timer: Option<RunTimer>,
+    /// Journal of the running queue
+    journal: Option<BatchJournal>,
+    /// Writer thread for state-file updates
+    persister: Persister,
+    /// Channel receiver for the running scripts update check or pull
+    update_rx: Option<Receiver<UpdateEvent>>,
+    /// Channel receiver for the running installed-status check
+    presence_rx: Option<Receiver<PresenceEvent>>,
+    /// Channel receiver for the running option version query
+    versions_rx: Option<Receiver<VersionEvent>>,
+    /// Whether the version query was asked for, so its result is announced
+    /// even when nothing has an update
+    versions_requested: bool,
+    /// Time and row of the last left click in the option list
+    last_click: Option<(Instant, usize)>,
+    /// Time and direction of the last Up/Down in the list, and how many
+    /// came in quick succession
+    held_nav: Option<(Instant, Action, usize)>,
+    /// Terminal size last reported to the running subprocess
+    pty_size_sent: Option<PtySize>,
+    /// Time of the last terminal draw
+    last_draw: Option<Instant>,
+    /// Time the spinners last moved on a frame
+    spinner_step: Instant,
+    /// Whether the terminal was lost and the app is supervising headlessly
+    detached: bool,
+    /// Results of runs that finished after the terminal was lost
+    detached_notes: Vec<String>,
+    /// Option id last written as the selection to restore next session
+    saved_selection: Option<String>,
+    /// Interface preferences as last saved
+    saved_prefs: UiPrefs,
+    /// Shell or suspend asked for, carried out by the loop which owns the terminal
+    step_out: Option<StepOut>,
+    /// Sender side of the channel the event loop sleeps on
+    wake_tx: Sender<Wake>,
+    /// Terminal events and worker wakeups
+    wake_rx: Receiver<Wake>,
 }
 
 impl App {
     /// Create a new App
-    pub fn new(state: AppState, script_dir: PathBuf) -> Self {
+    pub fn new(state: AppState, script_dir: PathBuf, entry_script: PathBuf) -> Self {
+        Self::with_persister(state, script_dir, entry_script, Persister::start())
+    }
+
+    /// Create the app with state writes going to `persister`
+    fn with_persister(
+        mut state: AppState,
+        script_dir: PathBuf,
+        entry_script: PathBuf,
+        mut persister: Persister,
+    ) -> Self {
+        state.script_dir = script_dir;
+        state.entry_script = entry_script;
+        let saved_selection = state.selected_option().map(|o| o.id.clone());
+        let saved_prefs = load_prefs();
+        let (wake_tx, wake_rx) = mpsc::channel();
+        persister.wake_with(wake_tx.clone());
         Self {
             state,
-            script_dir,
             installer_rx: None,
+            installer: None,
+            run_log: None,
+            run_timer: None,
+            journal: None,
+            persister,
+            update_rx: None,
+            presence_rx: None,
+            versions_rx: None,
+            versions_requested: false,
+            last_click: None,
+            held_nav: None,
+            pty_size_sent: None,
+            last_draw: None,
+            spinner_step: Instant::now(),
+            detached: false,
+            detached_notes: Vec::new(),
+            saved_selection,
+            saved_prefs,
+            step_out: None,
+            wake_tx,
+            wake_rx,
+        }
+    }
+
+    /// Whether the terminal was lost during the session
+    pub fn detached(&self) -> bool {
+        self.detached
+    }
+
+    /// Wait for pending state writes, describing any that didn't make it
+    pub fn finish_persisting(&mut self) -> Vec<String> {
+        let (errors, unwritten) = self.persister.drain(PERSIST_DRAIN_TIMEOUT);
+        let mut problems = errors;
+        if unwritten > 0 {
+            problems.push(format!(
+                "{} state update(s) still unwritten after {}s; installed state may be out of date",
+                unwritten,
+                PERSIST_DRAIN_TIMEOUT.as_secs()
+            ));
+        }
+        problems
+    }
+
+    /// Pick up finished state writes, reporting failures as toasts
+    fn poll_persister(&mut self) {
+        if let Some(error) = self.persister.poll().pop() {
+            self.state.toast(ToastKind::Error, error);
+        }
+        self.state.unsaved = self.persister.failed();
+        for (option_id, hashes) in self.persister.take_hashes() {
+            self.state.records.entry(option_id).or_default().target_hashes = hashes;
         }
+        self.state.saving = self.persister.busy();
     }
 
     /// Run the application event loop
-    pub fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    ///
+    /// If the terminal goes away (e.g. an SSH drop), rendering stops and any
+    /// running install is supervised to completion before returning.
+    pub fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<TerminalOut>>) -> Result<()> {
+        self.check_versions(false);
+        let session = self.run_interactive(terminal);
+        self.end_session(session);
+        Ok(())
+    }
+
+    /// Wrap up after the event loop, first seeing a run through without a UI
+    /// if the loop ended by losing the terminal
+    fn end_session(&mut self, session: io::Result<()>) {
+        if session.is_err() {
+            self.supervise_detached();
+        }
+        self.remember_selection();
+        self.remember_prefs();
+    }
+
+    /// Save the selected option for the next session, if it changed
+    ///
+    /// A category heading has no id, so the last option selected stands.
+    fn remember_selection(&mut self) {
+        let Some(id) = self.state.selected_option().map(|o| o.id.clone()) else {
+            return;
+        };
+        if self.saved_selection.as_ref() != Some(&id) {
+            self.persister.send(PersistCommand::SaveSelection(id.clone()));
+            self.saved_selection = Some(id);
+        }
+    }
+
+    /// Save the interface preferences, if they changed
+    fn remember_prefs(&mut self) {
+        let prefs = UiPrefs::of(&self.state);
+        if prefs != self.saved_prefs {
+            self.persister.send(PersistCommand::SavePrefs(prefs.clone()));
+            self.saved_prefs = prefs;
+        }
+    }
+
+    /// Interactive event loop; any terminal I/O error ends it
+    ///
+    /// Sleeps until input, a worker message or a tick arrives, and only
+    /// redraws when one of them did.
+    fn run_interactive(&mut self, terminal: &mut Terminal<CrosstermBackend<TerminalOut>>) -> io::Result<()> {
+        let mut input = InputThread::start(self.wake_tx.clone());
+        catch_tstp(self.wake_tx.clone());
+        let mut changed = true;
+        let mut backlog = false;
         loop {
-            // Render UI
-            terminal.draw(|frame| render_layout(frame, &self.state))?;
+            // Render UI (at a reduced rate while dimmed, at most one frame per
+            // MIN_FRAME_INTERVAL otherwise)
+            let now = Instant::now();
+            let was_dimmed = self.state.dimmed;
+            self.state.update_idle(now);
+            changed |= self.state.expire_toasts(now);
+            changed |= self.expire_jump(now);
+            if self.installer.is_some() && now.duration_since(self.spinner_step) >= SPINNER_TICK {
+                self.state.spinner_tick = self.state.spinner_tick.wrapping_add(1);
+                self.spinner_step = now;
+                changed = true;
+            }
+            let due = match self.last_draw {
+                _ if self.state.dimmed != was_dimmed => true,
+                Some(_) if self.state.dimmed => self.tick_due(now),
+                Some(last) if now.duration_since(last) < MIN_FRAME_INTERVAL => false,
+                _ => changed || self.tick_due(now),
+            };
+            if due {
+                self.draw(terminal, now)?;
+                changed = false;
+            }
+
+            // Wait for something to happen, then take everything that has
+            let wait = self.next_wait(Instant::now(), changed, backlog);
+            let wake = match self.wake_rx.recv_timeout(wait) {
+                Ok(wake) => Some(wake),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => unreachable!("the app holds a sender"),
+            };
+            changed |= wake.is_some() || backlog;
+            let pending = wake.into_iter().chain(std::iter::from_fn(|| self.wake_rx.try_recv().ok()));
+            for wake in pending.collect::<Vec<_>>() {
+                match wake {
+                    Wake::Terminal(event) => self.handle_terminal_event(event?),
+                    Wake::Suspend => self.request_step_out(StepOut::Suspend),
+                    Wake::Worker => {}
+                }
+            }
 
             // Handle installer events
-            self.handle_installer_events();
+            backlog = self.handle_installer_events();
+            changed |= self.check_run_timeout(Instant::now());
+            if self.state.quit_after_run && self.installer.is_none() {
+                self.state.should_quit = true;
+            }
+            self.poll_persister();
+            self.handle_update_events();
+            self.handle_presence_events();
+            self.handle_version_events();
 
-            // Handle keyboard events with timeout
-            if event::poll(Duration::from_millis(50))? {
-                if let Event::Key(key) = event::read()? {
-                    self.handle_key_event(key);
-                }
+            // Input is read by the shell (or nobody) until the terminal is back
+            if let Some(step) = self.step_out.take() {
+                drop(input);
+                self.step_outside(terminal, step)?;
+                input = InputThread::start(self.wake_tx.clone());
+                changed = true;
             }
 
             // Check if we should quit
@@ -60,196 +341,2756 @@ impl App {
         Ok(())
     }
 
+    /// Ask the loop to hand the terminal over
+    ///
+    /// Suspending stops every thread, so a running script would stall on a
+    /// full pty; the shell keeps its output flowing and stays allowed.
+    fn request_step_out(&mut self, step: StepOut) {
+        if step == StepOut::Suspend && self.installer.is_some() {
+            let shell = self.state.keymap.label(Action::Shell);
+            self.state.toast(
+                ToastKind::Warning,
+                format!("Can't suspend while a script runs - {} opens a shell instead", shell),
+            );
+            return;
+        }
+        self.step_out = Some(step);
+    }
+
+    /// Open the selected option's script in the user's editor
+    ///
+    /// Saving a script a run is reading could change it halfway, so there's
+    /// no editing while one runs.
+    fn edit_script(&mut self) {
+        if self.installer.is_some() {
+            self.state.toast(ToastKind::Warning, "Can't open an editor while a script runs");
+            return;
+        }
+        let Some(option) = self.state.selected_option() else {
+            return;
+        };
+        let path = option.script_path(&self.state.script_dir, &self.state.entry_script);
+        if !path.is_file() {
+            self.state.status_message = format!("{} doesn't exist", display_path(&path));
+            return;
+        }
+        self.request_step_out(StepOut::Edit(path));
+    }
+
+    /// Give the terminal to a shell, an editor or the parent shell's job
+    /// control, then take it back and redraw everything
+    ///
+    /// Installer output keeps arriving on its channel meanwhile and is picked
+    /// up on return.
+    fn step_outside(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<TerminalOut>>,
+        step: StepOut,
+    ) -> io::Result<()> {
+        restore_terminal();
+        match step {
+            StepOut::Shell => {
+                if let Err(e) = run_shell(&self.state.script_dir) {
+                    self.state.toast(ToastKind::Error, format!("Could not start a shell: {}", e));
+                }
+            }
+            StepOut::Edit(path) => match run_editor(&path, &self.state.script_dir) {
+                Ok(_) => self.state.status_message = format!("Closed {}", display_path(&path)),
+                Err(e) => self.state.toast(ToastKind::Error, format!("Could not start an editor: {}", e)),
+            },
+            StepOut::Suspend => stop_self(),
+        }
+        enter_tui(&mut TerminalOut::current())?;
+        terminal.clear()?;
+        self.state.register_input(Instant::now());
+        Ok(())
+    }
+
+    /// Apply one event read from the terminal
+    fn handle_terminal_event(&mut self, event: Event) {
+        match event {
+            // Focus changes aren't input; they only decide whether to notify
+            Event::FocusGained => self.state.focused = Some(true),
+            Event::FocusLost => self.state.focused = Some(false),
+            // The first input after dimming only wakes the UI
+            _ if self.state.register_input(Instant::now()) => {}
+            Event::Key(key) if accept_key(&key) => self.handle_key_event(key),
+            Event::Mouse(mouse) => self.handle_mouse_event(mouse),
+            _ => {}
+        }
+    }
+
+    /// When the parts of the screen that change by themselves next move
+    ///
+    /// `None` when nothing on screen moves until an event arrives.
+    fn next_tick(&self) -> Option<Instant> {
+        if self.state.dimmed {
+            self.last_draw.map(|last| last + DIMMED_REDRAW_INTERVAL)
+        } else if self.installer.is_some() {
+            Some(self.spinner_step + SPINNER_TICK)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a tick is due
+    fn tick_due(&self, now: Instant) -> bool {
+        self.next_tick().is_some_and(|tick| tick <= now)
+    }
+
+    /// How long the loop may sleep
+    ///
+    /// Not at all while installer events are waiting, until the next frame
+    /// slot when a change is waiting to be drawn, and otherwise until the
+    /// next tick or the idle dim.
+    fn next_wait(&self, now: Instant, changed: bool, backlog: bool) -> Duration {
+        match self.last_draw {
+            _ if backlog => Duration::ZERO,
+            Some(last) if changed && !self.state.dimmed => (last + MIN_FRAME_INTERVAL).saturating_duration_since(now),
+            _ => self.time_to_next_tick(now),
+        }
+    }
+
+    /// How long the loop may sleep before the next tick, the idle dim or a
+    /// toast expiring is due
+    fn time_to_next_tick(&self, now: Instant) -> Duration {
+        let tick = self.next_tick().map(|tick| tick.saturating_duration_since(now));
+        let dim = self
+            .state
+            .idle_dim_after
+            .filter(|_| !self.state.dimmed)
+            .map(|after| (self.state.last_input + after).saturating_duration_since(now));
+        let toast = self.state.toasts.iter().map(|toast| toast.expires.saturating_duration_since(now)).min();
+        let jump = (self.state.mode == AppMode::Jump)
+            .then(|| (self.state.jump_typed + JUMP_TIMEOUT).saturating_duration_since(now));
+        tick.into_iter().chain(dim).chain(toast).chain(jump).min().unwrap_or(IDLE_WAIT)
+    }
+
+    /// Draw a frame, failing once the terminal is gone
+    fn draw(&mut self, terminal: &mut Terminal<impl Backend>, now: Instant) -> io::Result<()> {
+        if let Some(installer) = &self.installer {
+            self.state.input_echo = installer.echo_enabled();
+        }
+        terminal.draw(|frame| render_layout(frame, &mut self.state))?;
+        self.last_draw = Some(now);
+        self.resize_pty();
+        Ok(())
+    }
+
+    /// Keep the running install (and queue) going without a terminal
+    fn supervise_detached(&mut self) {
+        self.detached = true;
+        if let Some(log) = &self.run_log {
+            log.line("# Terminal lost - continuing without a UI until the run finishes");
+        }
+
+        while let Some(rx) = &self.installer_rx {
+            // Nobody is left to ask about a run past its limit, so it is killed
+            let wait = self.state.run_watch.as_ref().and_then(|watch| watch.until_next(Instant::now()));
+            match rx.recv_timeout(wait.unwrap_or(IDLE_WAIT)) {
+                Ok(event) => self.handle_installer_event(event),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.check_run_timeout(Instant::now());
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.installer_rx = None;
+                    self.installer = None;
+                }
+            }
+        }
+
+        if !self.detached_notes.is_empty() {
+            self.persister.send(PersistCommand::SaveDetachedNote(self.detached_notes.join("\n")));
+        }
+    }
+
     /// Handle keyboard events
     fn handle_key_event(&mut self, key: KeyEvent) {
-        // Ctrl+C always quits
+        // Ctrl+C quits, asking first while something is running: a second
+        // press confirms and aborts the run, and a third kills it outright.
+        // Either way the app quits only once the run's end is recorded.
         if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
-            self.state.should_quit = true;
+            match self.state.mode {
+                _ if self.installer.is_none() => self.state.should_quit = true,
+                _ if self.state.quit_after_run => self.kill_action(),
+                AppMode::ConfirmQuit => self.handle_quit_key(KeyEvent::from(KeyCode::Enter)),
+                _ => self.state.mode = AppMode::ConfirmQuit,
+            }
+            return;
+        }
+
+        // Popups follow the configured confirm/cancel/up/down keys too
+        let key = if self.state.mode.is_popup() {
+            self.popup_key(key)
+        } else {
+            key
+        };
+
+        // Any key leaves the zen HUD
+        if self.state.zen {
+            self.state.zen = false;
             return;
         }
 
         match self.state.mode {
             AppMode::Normal => self.handle_normal_mode_key(key),
             AppMode::ConfirmAction => self.handle_popup_key(key),
-            AppMode::Installing => {
-                // Ignore keys during installation (except Ctrl+C handled above)
+            AppMode::ConfirmUninstall => self.handle_uninstall_confirm_key(key),
+            AppMode::ConfirmDependencies => self.handle_dependencies_key(key),
+            AppMode::ConfirmMark => self.handle_mark_popup_key(key),
+            AppMode::Help | AppMode::Timings => {
+                // Any key dismisses the overlay
+                self.state.mode = AppMode::Normal;
+            }
+            AppMode::StartupWarnings => {
+                // Any key dismisses the warnings, then offers any interrupted batch
+                self.state.mode = if !self.state.pending.is_empty() {
+                    AppMode::PendingItems
+                } else {
+                    AppMode::Normal
+                };
+            }
+            // Nothing can run against the manifest, so any key quits
+            AppMode::TooOld => self.state.should_quit = true,
+            AppMode::PendingItems => self.handle_pending_key(key),
+            AppMode::Profiles => self.handle_profiles_key(key),
+            AppMode::LogBrowser => self.handle_log_browser_key(key),
+            AppMode::QueueSummary => self.handle_summary_key(key),
+            AppMode::Filter => self.handle_filter_key(key),
+            AppMode::Jump => self.handle_jump_key(key),
+            AppMode::Command => self.handle_command_key(key),
+            AppMode::ConfirmEditorLocks => self.handle_editor_locks_key(key),
+            AppMode::ConfirmReboot => self.handle_reboot_key(key),
+            AppMode::ConfirmUpdate => self.handle_update_key(key),
+            AppMode::ConfirmReinstall => self.handle_reinstall_key(key),
+            AppMode::ConfirmIncompatible => self.handle_incompatible_key(key),
+            AppMode::ConfirmLocalChanges => self.handle_local_changes_key(key),
+            AppMode::ConfirmUnsandboxed => self.handle_unsandboxed_key(key),
+            AppMode::ConfirmQuit => self.handle_quit_key(key),
+            AppMode::ConfirmTimeout => self.handle_timeout_key(key),
+            AppMode::Unsupported => {
+                if matches!(key.code, KeyCode::Enter | KeyCode::Esc) {
+                    self.state.mode = AppMode::Normal;
+                }
+            }
+            AppMode::PasswordPrompt => self.handle_password_key(key),
+            AppMode::Layout => self.handle_layout_key(key),
+            AppMode::Installing => self.handle_installing_mode_key(key),
+        }
+    }
+
+    /// Handle clicks in the option list and action popup, and wheel scrolling of the output
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) {
+        let position = Position::new(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::ScrollUp if self.state.output_area.contains(position) => {
+                self.state.scroll_output_up(WHEEL_SCROLL_LINES);
+            }
+            MouseEventKind::ScrollDown if self.state.output_area.contains(position) => {
+                self.state.scroll_output_down(WHEEL_SCROLL_LINES);
             }
-            AppMode::Completed => self.handle_completed_mode_key(key),
+            MouseEventKind::Down(MouseButton::Left) => match self.state.mode {
+                AppMode::Normal => self.click_option_list(position),
+                AppMode::ConfirmAction => self.click_popup(position),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// Select the clicked row; a double-click acts like Enter
+    fn click_option_list(&mut self, position: Position) {
+        // Rows start inside the top border
+        let list = self.state.list_area.inner(Margin::new(1, 1));
+        if !list.contains(position) {
+            return;
+        }
+        let row = self.state.list_offset + (position.y - list.y) as usize;
+        if row >= self.state.rows.len() {
+            return;
+        }
+
+        let now = Instant::now();
+        let double = self
+            .last_click
+            .is_some_and(|(at, last)| last == row && now.duration_since(at) <= DOUBLE_CLICK_INTERVAL);
+        self.state.selected_index = row;
+        if double {
+            self.last_click = None;
+            self.activate_selection();
+        } else {
+            self.last_click = Some((now, row));
+            self.update_status_for_selection();
+        }
+    }
+
+    /// Clicking a popup choice selects it; clicking the selected one confirms it
+    fn click_popup(&mut self, position: Position) {
+        let area = self.state.popup_choices_area;
+        if !area.contains(position) {
+            return;
+        }
+        let choices = self.state.popup_choices();
+        let Some(&choice) = choices.get((position.y - area.y) as usize) else {
+            return;
+        };
+        if choice == self.state.popup_choice {
+            self.handle_popup_key(KeyEvent::from(KeyCode::Enter));
+        } else {
+            self.state.popup_choice = choice;
         }
     }
 
+    /// Translate a configured binding into the key popups handle
+    fn popup_key(&self, key: KeyEvent) -> KeyEvent {
+        let action = registry::entry_for(&self.state.keymap, Scope::Popup, &key).map(|entry| entry.action);
+        let code = match action {
+            Some(Action::Confirm) => KeyCode::Enter,
+            Some(Action::Cancel) => KeyCode::Esc,
+            Some(Action::Up) => KeyCode::Up,
+            Some(Action::Down) => KeyCode::Down,
+            _ => return key,
+        };
+        KeyEvent::from(code)
+    }
+
     /// Handle keys in normal mode
     fn handle_normal_mode_key(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Char('q') => {
-                self.state.should_quit = true;
+        // Lets the panic hook's terminal restore be checked by hand
+        #[cfg(feature = "debug-panic")]
+        if key.code == KeyCode::F(12) {
+            panic!("debug panic requested with F12");
+        }
+        self.dispatch(Scope::Browsing, &key);
+    }
+
+    /// Run the action a key is bound to in a scope, if it's available
+    fn dispatch(&mut self, scope: Scope, key: &KeyEvent) -> bool {
+        let Some(entry) = registry::entry_for(&self.state.keymap, scope, key) else {
+            return false;
+        };
+        if (entry.available)(&self.state) {
+            self.perform(entry.action);
+        }
+        true
+    }
+
+    /// Carry out an action from the registry
+    ///
+    /// Callers check the entry's availability first; the guards live there.
+    fn perform(&mut self, action: Action) {
+        let page = self.state.output_height.max(1);
+        match action {
+            Action::Quit if self.installer.is_some() => self.state.mode = AppMode::ConfirmQuit,
+            Action::Quit => self.state.should_quit = true,
+            Action::Up if self.state.output_focused() => self.state.scroll_output_up(1),
+            Action::Down if self.state.output_focused() => self.state.scroll_output_down(1),
+            Action::Up | Action::Down => {
+                // Holding the key speeds up, without wrapping past either end
+                let up = action == Action::Up;
+                match self.nav_step(action, Instant::now()) {
+                    1 if up => self.state.move_up(),
+                    1 => self.state.move_down(),
+                    step if up => self.state.move_by(-(step as isize)),
+                    step => self.state.move_by(step as isize),
+                }
+                self.update_status_for_selection();
             }
-            KeyCode::Up | KeyCode::Char('k') => {
-                self.state.move_up(OPTIONS.len());
+            Action::Top if self.state.output_focused() => self.state.scroll_output_up(self.state.output_len()),
+            Action::Bottom if self.state.output_focused() => self.state.follow_latest_output(),
+            Action::Top => {
+                self.state.move_to_first();
                 self.update_status_for_selection();
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                self.state.move_down(OPTIONS.len());
+            Action::Bottom => {
+                self.state.move_to_last();
                 self.update_status_for_selection();
             }
-            KeyCode::Enter => {
-                self.trigger_action();
+            Action::FocusNext => {
+                self.state.focus = if self.state.output_focused() { Panel::List } else { Panel::Output };
             }
-            KeyCode::Esc => {
+            Action::Confirm => self.activate_selection(),
+            Action::Cancel => {
                 // Clear output panel
                 self.state.clear_output();
                 self.state.show_output = false;
             }
+            Action::NarrowList | Action::WidenList => {
+                self.state.shift_split(action == Action::WidenList);
+                self.remember_prefs();
+            }
+            Action::FullOutput => {
+                self.state.output_full = !self.state.output_full;
+                self.state.show_output |= self.state.output_full;
+            }
+            Action::ToggleOutput => {
+                self.state.show_output = !self.state.show_output;
+                self.state.output_on_start = self.state.show_output;
+                self.remember_prefs();
+            }
+            // Outside a run the page keys follow the focus like the arrows do
+            Action::ScrollUp | Action::ScrollDown if self.state.mode == AppMode::Normal && !self.state.output_focused() => {
+                let page = self.state.list_page() as isize;
+                self.state.move_by(if action == Action::ScrollUp { -page } else { page });
+                self.update_status_for_selection();
+            }
+            Action::ScrollUp => self.state.scroll_output_up(page),
+            Action::ScrollDown => self.state.scroll_output_down(page),
+            Action::HalfPageUp => self.state.scroll_output_up(page / 2 + 1),
+            Action::HalfPageDown => self.state.scroll_output_down(page / 2 + 1),
+            Action::FollowOutput => self.state.follow_latest_output(),
+            Action::Queue => {
+                if let Some(option) = self.state.selected_option().cloned() {
+                    self.state.toggle_queued(&option.id);
+                    self.state.status_message = format!(
+                        "{} queued - press {} to install them in order",
+                        self.state.queued.len(),
+                        self.state.keymap.label(Action::InstallQueued)
+                    );
+                }
+            }
+            Action::InstallQueued => self.run_queue(),
+            Action::ContinueOnFailure => {
+                self.state.queue_continue_on_failure = !self.state.queue_continue_on_failure;
+                self.state.status_message = if self.state.queue_continue_on_failure {
+                    "Queue will continue after failures".to_string()
+                } else {
+                    "Queue will stop at the first failure".to_string()
+                };
+            }
+            Action::Mark => self.begin_mark(),
+            Action::Filter => self.state.mode = AppMode::Filter,
+            Action::Jump => {
+                self.state.jump_query.clear();
+                self.state.jump_typed = Instant::now();
+                self.state.status_message = "Type part of a name".to_string();
+                self.state.mode = AppMode::Jump;
+            }
+            Action::Collapse => self.fold_selected_category(true),
+            Action::Expand => self.fold_selected_category(false),
+            Action::Retry => self.retry_last_attempt(),
+            Action::ToggleDetails => self.state.show_details = Some(!self.state.details_shown),
+            Action::SwitchLayout => {
+                self.state.layouts.cycle();
+                self.persister.send(PersistCommand::SaveLayouts(self.state.layouts.clone()));
+                self.state.status_message = format!("Layout: {}", self.state.layouts.active);
+            }
+            Action::EditLayout => self.begin_layout_edit(),
+            Action::Timings => self.state.mode = AppMode::Timings,
+            Action::Help => self.state.mode = AppMode::Help,
+            Action::Update => self.check_for_update(),
+            Action::Reboot => self.state.mode = AppMode::ConfirmReboot,
+            Action::Pending => self.state.mode = AppMode::PendingItems,
+            Action::Profiles if self.state.profiles.is_empty() => {
+                self.state.status_message =
+                    "No profiles - add a [profiles] table to config.toml or the manifest".to_string();
+            }
+            Action::Profiles => {
+                self.state.profile_focus = self.state.profile_focus.min(self.state.profiles.len() - 1);
+                self.state.mode = AppMode::Profiles;
+            }
+            Action::ExportScript => self.export_script(),
+            Action::LogHistory => self.open_log_history(),
+            Action::Zen => self.state.zen = true,
+            Action::CancelRun => self.cancel_action(),
+            Action::CheckInstalled => self.check_presence(),
+            Action::CheckUpdates => self.check_versions(true),
+            Action::Shell => self.request_step_out(StepOut::Shell),
+            Action::EditScript => self.edit_script(),
+            Action::Suspend => self.request_step_out(StepOut::Suspend),
+            Action::RetrySave => {
+                let count = self.persister.retry();
+                self.state.unsaved = 0;
+                self.state.saving = self.persister.busy();
+                self.state.status_message = format!("Saving {} state update(s) again...", count);
+            }
+            Action::CommandLine => {
+                self.state.command_input.clear();
+                self.state.command_history_pos = None;
+                self.state.mode = AppMode::Command;
+                self.state.status_message = format!(
+                    "Commands: {}, or any keymap action",
+                    command_names().join(", ")
+                );
+            }
+        }
+    }
+
+    /// Write the installed options to a dated script in the home directory
+    fn export_script(&mut self) {
+        let path = export::default_export_path();
+        self.state.status_message = match export::export_installed(
+            &self.state.script_dir,
+            &self.state.entry_script,
+            &self.state.options,
+            &self.state.installed,
+            &path,
+            false,
+        ) {
+            Ok(count) => format!("Exported {} option(s) to {}", count, display_path(&path)),
+            Err(e) => format!("Export failed: {}", e),
+        };
+    }
+
+    /// Handle keys while installing
+    ///
+    /// Control keys scroll, cancel or toggle zen mode; typing goes to the
+    /// subprocess so it can answer prompts.
+    fn handle_installing_mode_key(&mut self, key: KeyEvent) {
+        // Text keys never match here, so they reach the subprocess below
+        if self.dispatch(Scope::Installing, &key) {
+            return;
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char(c) if !ctrl => {
+                let mut buf = [0u8; 4];
+                self.send_input(c.encode_utf8(&mut buf).as_bytes());
+            }
+            KeyCode::Enter => self.send_input(b"\r"),
+            KeyCode::Backspace => self.send_input(b"\x7f"),
+            KeyCode::Tab => self.send_input(b"\t"),
             _ => {}
         }
     }
 
-    /// Handle keys in completed mode - now same as normal but returns to normal immediately
-    fn handle_completed_mode_key(&mut self, key: KeyEvent) {
-        // Completed mode now behaves like normal mode - focus stays on option list
-        self.handle_normal_mode_key(key);
+    /// Forward typed input to the running subprocess
+    ///
+    /// Nothing is added to the output here: the terminal echoes input itself,
+    /// and stays silent while a password is read.
+    fn send_input(&mut self, bytes: &[u8]) {
+        if let Some(installer) = &self.installer {
+            installer.write_input(bytes);
+            self.state.follow_latest_output();
+        }
     }
 
     /// Handle keys in popup mode
     fn handle_popup_key(&mut self, key: KeyEvent) {
         match key.code {
-            KeyCode::Up | KeyCode::Char('k') => {
-                self.state.popup_choice = ActionChoice::Reinstall;
+            KeyCode::Up | KeyCode::Char('k') | KeyCode::Down | KeyCode::Char('j') => {
+                let choices = self.state.popup_choices();
+                let current = choices
+                    .iter()
+                    .position(|&c| c == self.state.popup_choice)
+                    .unwrap_or(0);
+                let next = if matches!(key.code, KeyCode::Up | KeyCode::Char('k')) {
+                    current.saturating_sub(1)
+                } else {
+                    (current + 1).min(choices.len().saturating_sub(1))
+                };
+                if let Some(&choice) = choices.get(next) {
+                    self.state.popup_choice = choice;
+                }
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                self.state.popup_choice = ActionChoice::Uninstall;
+            KeyCode::Enter => match self.state.popup_choice {
+                ActionChoice::Uninstall => {
+                    // Uninstalling is destructive, so ask once more
+                    self.state.mode = AppMode::ConfirmUninstall;
+                }
+                ActionChoice::Reinstall | ActionChoice::InstallSkipVerify => {
+                    self.state.skip_verify = self.state.popup_choice == ActionChoice::InstallSkipVerify;
+                    self.state.mode = AppMode::Normal;
+                    self.install_with_dependencies();
+                }
+            },
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+                self.update_status_for_selection();
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle keys while typing the option filter
+    fn handle_filter_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char(c) => {
+                let mut filter = self.state.filter.clone();
+                filter.push(c);
+                self.state.set_filter(filter);
             }
+            KeyCode::Backspace => {
+                let mut filter = self.state.filter.clone();
+                filter.pop();
+                self.state.set_filter(filter);
+            }
+            KeyCode::Up => self.state.move_up(),
+            KeyCode::Down => self.state.move_down(),
             KeyCode::Enter => {
-                let uninstall = self.state.popup_choice == ActionChoice::Uninstall;
                 self.state.mode = AppMode::Normal;
-                self.run_action(uninstall);
+                self.update_status_for_selection();
+                self.remember_prefs();
             }
             KeyCode::Esc => {
                 self.state.mode = AppMode::Normal;
+                self.state.clear_filter();
                 self.update_status_for_selection();
+                self.remember_prefs();
             }
             _ => {}
         }
     }
 
-    /// Trigger install or uninstall for the selected option
-    fn trigger_action(&mut self) {
-        if self.state.selected_index >= OPTIONS.len() {
-            return;
-        }
-
-        let option = &OPTIONS[self.state.selected_index];
-        let is_installed = self.state.is_installed(option.id);
-
-        if is_installed {
-            // Show popup to choose action
-            self.state.popup_choice = ActionChoice::Reinstall;
-            self.state.mode = AppMode::ConfirmAction;
-            self.state.status_message = format!("{} is installed - choose action", option.name);
-        } else {
-            // Directly install
-            self.run_action(false);
+    /// Handle keys while typing a jump query
+    ///
+    /// Keys that can't be part of a name end the query and then do what
+    /// they would have done without it.
+    fn handle_jump_key(&mut self, key: KeyEvent) {
+        let plain = !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Char(c) if plain => {
+                self.state.jump_query.push(c);
+                self.jump_to_best_match();
+            }
+            KeyCode::Backspace => {
+                self.state.jump_query.pop();
+                self.jump_to_best_match();
+            }
+            KeyCode::Enter | KeyCode::Esc => self.end_jump(),
+            _ => {
+                self.end_jump();
+                self.handle_normal_mode_key(key);
+            }
         }
     }
 
-    /// Run the install/uninstall action
-    fn run_action(&mut self, uninstall: bool) {
-        if self.state.selected_index >= OPTIONS.len() {
+    /// Select the listed option best matching the jump query
+    fn jump_to_best_match(&mut self) {
+        self.state.jump_typed = Instant::now();
+        let query = &self.state.jump_query;
+        if query.is_empty() {
+            self.state.status_message = "Type part of a name".to_string();
             return;
         }
+        let names = self.state.visible.iter().map(|&i| (i, self.state.options[i].name.as_str()));
+        match jump::best_match(query, names) {
+            Some(index) => {
+                self.state.select_option(index);
+                self.state.status_message = self.state.options[index].name.clone();
+            }
+            None => self.state.status_message = format!("No option matches {:?}", query),
+        }
+    }
 
-        let option = &OPTIONS[self.state.selected_index];
-
-        // Set up the action
-        let action = if uninstall {
-            format!("Uninstalling {}", option.name)
-        } else {
-            format!("Installing {}", option.name)
-        };
-
-        self.state.clear_output();
-        self.state.current_action = Some(action.clone());
-        self.state.status_message = action;
-        self.state.mode = AppMode::Installing;
-        self.state.show_output = true;
-        self.state.is_uninstalling = uninstall;
-
-        // Create channel for installer events
-        let (tx, rx) = mpsc::channel();
-        self.installer_rx = Some(rx);
+    /// Leave the jump query, keeping the selection it made
+    fn end_jump(&mut self) {
+        self.state.mode = AppMode::Normal;
+        self.state.jump_query.clear();
+        self.update_status_for_selection();
+    }
 
-        // Start the installer in a background thread
-        run_install_command(&self.script_dir, option.id, uninstall, tx);
+    /// End the jump query once it has gone untouched for a while
+    fn expire_jump(&mut self, now: Instant) -> bool {
+        if self.state.mode != AppMode::Jump || now < self.state.jump_typed + JUMP_TIMEOUT {
+            return false;
+        }
+        self.end_jump();
+        true
     }
 
-    /// Handle events from the installer subprocess
-    fn handle_installer_events(&mut self) {
-        if let Some(rx) = &self.installer_rx {
-            loop {
-                match rx.try_recv() {
-                    Ok(event) => match event {
-                        InstallerEvent::OutputLine(line) => {
-                            self.state.add_output_line(line);
-                            // Auto-scroll to bottom
-                            let lines = self.state.output_lines.len();
-                            if lines > 20 {
-                                self.state.output_scroll = lines - 20;
-                            }
-                        }
-                        InstallerEvent::Completed(exit_code) => {
-                            self.state.last_exit_code = Some(exit_code);
-                            self.state.mode = AppMode::Normal;
-
-                            // Update installed state based on the action we performed
-                            if let Some(option) = OPTIONS.get(self.state.selected_index) {
-                                if exit_code == 0 {
-                                    if self.state.is_uninstalling {
-                                        // Uninstall succeeded
-                                        mark_uninstalled(option.id);
-                                        self.state.installed.remove(option.id);
-                                        self.state.status_message =
-                                            format!("Uninstalled {} - Press Enter on another option", option.name);
-                                    } else {
-                                        // Install/update succeeded - mark as installed
-                                        mark_installed(option.id);
-                                        self.state.installed.insert(option.id.to_string());
-                                        self.state.status_message =
-                                            format!("Installed {} - Press Enter on another option", option.name);
-                                    }
-                                } else {
-                                    self.state.status_message = format!(
-                                        "Failed with exit code {} - Esc to close output",
-                                        exit_code
-                                    );
-                                }
-                            }
+    /// Handle keys while typing a `:` command
+    fn handle_command_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char(c) => self.state.command_input.push(c),
+            // Deleting past the start leaves the command line, as in vi
+            KeyCode::Backspace if self.state.command_input.is_empty() => self.state.mode = AppMode::Normal,
+            KeyCode::Backspace => {
+                self.state.command_input.pop();
+            }
+            KeyCode::Tab => match complete(&self.state.command_input, &self.state.options) {
+                Some(completed) => self.state.command_input = completed,
+                None => self.state.status_message = "Nothing to complete".to_string(),
+            },
+            KeyCode::Up | KeyCode::Down => {
+                let history = &self.state.command_history;
+                let pos = match (self.state.command_history_pos, key.code) {
+                    (None, KeyCode::Up) => history.len().checked_sub(1),
+                    (Some(pos), KeyCode::Up) => Some(pos.saturating_sub(1)),
+                    (Some(pos), _) if pos + 1 < history.len() => Some(pos + 1),
+                    _ => None,
+                };
+                self.state.command_input = pos.map(|pos| history[pos].clone()).unwrap_or_default();
+                self.state.command_history_pos = pos;
+            }
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                let input = std::mem::take(&mut self.state.command_input);
+                let input = input.trim();
+                if input.is_empty() {
+                    self.update_status_for_selection();
+                    return;
+                }
+                if self.state.command_history.last().map(String::as_str) != Some(input) {
+                    self.state.command_history.push(input.to_string());
+                }
+                match parse(input, &self.state.options) {
+                    Ok(command) => {
+                        self.update_status_for_selection();
+                        self.run_command(command);
+                    }
+                    Err(e) => self.state.status_message = format!(":{} - {}", input, e),
+                }
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+                self.update_status_for_selection();
+            }
+            _ => {}
+        }
+    }
 
-                            self.installer_rx = None;
-                            break;
-                        }
-                        InstallerEvent::Error(err) => {
-                            self.state.add_output_line(format!("Error: {}", err));
-                            self.state.last_exit_code = Some(-1);
-                            self.state.mode = AppMode::Normal;
-                            self.state.status_message = "Error occurred - Esc to close output".to_string();
-                            self.installer_rx = None;
-                            break;
-                        }
-                    },
-                    Err(TryRecvError::Empty) => break,
-                    Err(TryRecvError::Disconnected) => {
-                        self.installer_rx = None;
-                        break;
+    /// Carry out a `:` command the way its key or menu would
+    fn run_command(&mut self, command: commands::Command) {
+        match command {
+            commands::Command::Install(id) | commands::Command::Uninstall(id) | commands::Command::Log(id)
+                if self.state.option_index(&id).is_none() =>
+            {
+                self.state.status_message = format!("No option with id '{}'", id);
+            }
+            commands::Command::Install(id) => {
+                if let Some(index) = self.state.option_index(&id) {
+                    self.state.select_option(index);
+                    self.trigger_action();
+                }
+            }
+            commands::Command::Uninstall(id) => {
+                let Some(index) = self.state.option_index(&id) else {
+                    return;
+                };
+                self.state.select_option(index);
+                let option = &self.state.options[index];
+                if !option.tracks_state() {
+                    self.state.status_message = format!("{} has no installed state to remove", option.name);
+                } else if !self.state.is_installed(&id) {
+                    self.state.status_message = format!("{} isn't installed", option.name);
+                } else {
+                    self.state.mode = AppMode::ConfirmUninstall;
+                }
+            }
+            commands::Command::Log(id) => self.show_latest_log(&id),
+            commands::Command::Quit => self.perform(Action::Quit),
+            commands::Command::Action(action) => {
+                let entry = registry::entries(Scope::Browsing).find(|entry| entry.action == action);
+                match entry {
+                    Some(entry) if (entry.available)(&self.state) => self.perform(action),
+                    Some(_) => self.state.status_message = format!("{} isn't available right now", action.name()),
+                    None => {
+                        self.state.status_message = format!("{} only works inside a popup or a run", action.name())
                     }
                 }
             }
         }
     }
 
+    /// Load an option's newest run log into the output panel
+    fn show_latest_log(&mut self, option_id: &str) {
+        let Some(path) = latest_log(option_id) else {
+            self.state.status_message = format!("No logs for {} yet", option_id);
+            return;
+        };
+        self.show_log(&path);
+    }
+
+    /// Load a run log into the output panel, from the end if it's very long
+    fn show_log(&mut self, path: &Path) {
+        let (lines, skipped) = match read_log(path) {
+            Ok(log) => log,
+            Err(e) => {
+                self.state.status_message = format!("Could not read {}: {}", display_path(path), e);
+                return;
+            }
+        };
+        self.state.clear_output();
+        if skipped > 0 {
+            self.state.add_problem_line(format!(
+                "... first {:.1} MiB of the log not shown; open {} to read all of it",
+                skipped as f64 / (1024.0 * 1024.0),
+                display_path(path)
+            ));
+        }
+        for line in lines {
+            self.state.add_output_line(line);
+        }
+        self.state.active_option = None;
+        self.state.current_action = Some(format!("Log {}", display_path(path)));
+        self.state.show_output = true;
+        self.state.follow_latest_output();
+        self.state.status_message = format!("Showing {}", display_path(path));
+    }
+
+    /// List the selected option's past runs
+    fn open_log_history(&mut self) {
+        let Some(option) = self.state.selected_option().cloned() else {
+            return;
+        };
+        let history = option_history(&option.id);
+        if history.is_empty() {
+            self.state.status_message = format!("No logs for {} yet", option.name);
+            return;
+        }
+        self.state.log_history_option = option.name;
+        self.state.log_history = history;
+        self.state.log_focus = 0;
+        self.state.mode = AppMode::LogBrowser;
+    }
+
+    /// Handle keys in the log browser
+    ///
+    /// A run's output stays on screen while it is going, so a log only
+    /// opens once the run has finished.
+    fn handle_log_browser_key(&mut self, key: KeyEvent) {
+        let count = self.state.log_history.len();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.log_focus = (self.state.log_focus + count - 1) % count;
+            }
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                self.state.log_focus = (self.state.log_focus + 1) % count;
+            }
+            KeyCode::Home | KeyCode::Char('g') => self.state.log_focus = 0,
+            KeyCode::End | KeyCode::Char('G') => self.state.log_focus = count - 1,
+            KeyCode::Enter if self.installer.is_some() => {
+                self.state.status_message = "Logs open once the running action finishes".to_string();
+            }
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                let history = std::mem::take(&mut self.state.log_history);
+                self.show_log(&history[self.state.log_focus].path);
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+                self.state.log_history.clear();
+            }
+            _ => {}
+        }
+    }
+
+    /// Enter layout mode on the active layout, keeping a copy for Esc
+    fn begin_layout_edit(&mut self) {
+        self.state.layout_backup = Some(self.state.layouts.clone());
+        self.state.layout_focus = 0;
+        self.state.mode = AppMode::Layout;
+        self.state.status_message = format!("Editing layout \"{}\"", self.state.layouts.active);
+    }
+
+    /// Handle keys in layout mode: Tab focuses, arrows move and resize, Enter saves
+    fn handle_layout_key(&mut self, key: KeyEvent) {
+        let count = self.state.layouts.current().slots().len();
+        let focus = self.state.layout_focus;
+        match key.code {
+            KeyCode::Tab => self.state.layout_focus = (focus + 1) % count,
+            KeyCode::BackTab => self.state.layout_focus = (focus + count - 1) % count,
+            KeyCode::Left | KeyCode::Right => {
+                let right = key.code == KeyCode::Right;
+                self.state.layout_focus = self.state.layouts.current_mut().move_slot(focus, right);
+            }
+            KeyCode::Up | KeyCode::Down => {
+                self.state.layouts.current_mut().resize_slot(focus, key.code == KeyCode::Up);
+            }
+            KeyCode::Char('w') => {
+                self.state.layouts.cycle();
+                self.state.layout_focus = 0;
+                self.state.status_message = format!("Editing layout \"{}\"", self.state.layouts.active);
+            }
+            KeyCode::Enter => {
+                self.state.layout_backup = None;
+                self.state.mode = AppMode::Normal;
+                self.persister.send(PersistCommand::SaveLayouts(self.state.layouts.clone()));
+                self.state.status_message = format!("Layout \"{}\" saved", self.state.layouts.active);
+                // The saved weights decide the split again
+                self.state.split = None;
+                self.remember_prefs();
+            }
+            KeyCode::Esc => {
+                if let Some(backup) = self.state.layout_backup.take() {
+                    self.state.layouts = backup;
+                }
+                self.state.mode = AppMode::Normal;
+                self.update_status_for_selection();
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle keys in the uninstall confirmation
+    fn handle_uninstall_confirm_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                self.run_action(true);
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+                self.update_status_for_selection();
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle keys in the reboot confirmation
+    fn handle_reboot_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                self.reboot_now();
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    /// Ask systemd to reboot, reporting the result in the status bar
+    fn reboot_now(&mut self) {
+        self.state.status_message = match Command::new("systemctl").arg("reboot").status() {
+            Ok(status) if status.success() => "Rebooting...".to_string(),
+            Ok(status) => format!("systemctl reboot failed ({})", status),
+            Err(e) => format!("Could not run systemctl reboot: {}", e),
+        };
+    }
+
+    /// Fetch the scripts repository in the background to preview an update
+    fn check_for_update(&mut self) {
+        if self.update_rx.is_some() {
+            return;
+        }
+        if let Some(reason) = self.state.features.updates_unavailable() {
+            self.state.status_message = reason.to_string();
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let script_dir = self.state.script_dir.clone();
+        let options = self.state.options.clone();
+        thread::spawn(move || {
+            let _ = tx.send(UpdateEvent::Previewed(preview_update(&script_dir, &options)));
+        });
+        self.update_rx = Some(relay(rx, self.wake_tx.clone()));
+        self.state.status_message = "Checking for script updates...".to_string();
+    }
+
+    /// Pick up the result of a background update check or pull
+    fn handle_update_events(&mut self) {
+        let Some(rx) = &self.update_rx else {
+            return;
+        };
+        let event = match rx.try_recv() {
+            Ok(event) => event,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.update_rx = None;
+                return;
+            }
+        };
+        self.update_rx = None;
+
+        match event {
+            UpdateEvent::Previewed(Err(e)) => {
+                self.state.status_message = format!("Could not check for updates: {}", e);
+            }
+            UpdateEvent::Previewed(Ok(preview)) if preview.is_empty() => {
+                self.state.status_message = "Scripts are up to date".to_string();
+            }
+            // Don't cover a running install or another popup
+            UpdateEvent::Previewed(Ok(_)) if self.state.mode != AppMode::Normal => {
+                self.state.status_message =
+                    format!("Script update available - press {} to review it", self.state.keymap.label(Action::Update));
+            }
+            UpdateEvent::Previewed(Ok(preview)) => {
+                self.state.update_preview = Some(preview);
+                self.state.mode = AppMode::ConfirmUpdate;
+            }
+            UpdateEvent::Pulled(Err(e)) => {
+                self.state.update_preview = None;
+                self.state.status_message = format!("Update failed: {}", e);
+            }
+            UpdateEvent::Pulled(Ok(())) => {
+                let preview = self.state.update_preview.take().unwrap_or_default();
+                let candidates = reinstall_candidates(&preview, |id| self.state.is_installed(id));
+                self.state.status_message = format!("Scripts updated ({} commits)", preview.commits);
+                if candidates.is_empty() {
+                    return;
+                }
+                if self.state.mode == AppMode::Normal {
+                    self.state.reinstall_suggestion = candidates;
+                    self.state.mode = AppMode::ConfirmReinstall;
+                } else {
+                    self.state.status_message = format!(
+                        "Scripts updated - consider reinstalling {}",
+                        self.state.option_names(&candidates).join(", ")
+                    );
+                }
+            }
+        }
+    }
+
+    /// Ask the script which options are really installed, in the background
+    ///
+    /// Not while a run or queue is going: the check's output would land in
+    /// the run's panel, and its findings could predate what the run did.
+    fn check_presence(&mut self) {
+        if self.installer.is_some() || self.state.queue_active() {
+            self.state.toast(ToastKind::Warning, "Installed status can be checked once the run finishes");
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let ids = self
+            .state
+            .options
+            .iter()
+            .filter(|option| option.tracks_state())
+            .map(|option| option.id.clone())
+            .collect();
+        check_all(self.state.script_dir.clone(), self.state.entry_script.clone(), ids, tx);
+        self.presence_rx = Some(relay(rx, self.wake_tx.clone()));
+        self.state.checking_presence = true;
+        self.state.presence_unknown.clear();
+        self.state.clear_output();
+        self.state.show_output = true;
+        self.state.status_message = "Checking installed status...".to_string();
+    }
+
+    /// Reconcile the state file with what the check found
+    fn handle_presence_events(&mut self) {
+        let Some(rx) = &self.presence_rx else {
+            return;
+        };
+        let mut events = Vec::new();
+        let finished = loop {
+            match rx.try_recv() {
+                Ok(PresenceEvent::Checked { option_id, presence }) => events.push((option_id, presence)),
+                Ok(PresenceEvent::Done) | Err(TryRecvError::Disconnected) => break true,
+                Err(TryRecvError::Empty) => break false,
+            }
+        };
+
+        for (option_id, presence) in events {
+            let recorded = self.state.is_installed(&option_id);
+            let (line, installed) = match presence {
+                Presence::Installed if recorded => (format!("✓ {}: installed", option_id), None),
+                Presence::Installed => (format!("✓ {}: installed (was marked not installed)", option_id), Some(true)),
+                Presence::Absent if recorded => (format!("✗ {}: not installed (was marked installed)", option_id), Some(false)),
+                Presence::Absent => (format!("✗ {}: not installed", option_id), None),
+                Presence::Unknown(reason) => {
+                    self.state.presence_unknown.insert(option_id.clone());
+                    (format!("? {}: unknown - {}", option_id, reason), None)
+                }
+            };
+            self.state.add_output_line(line);
+            if let Some(installed) = installed {
+                if installed {
+                    self.state.installed.insert(option_id.clone());
+                } else {
+                    self.state.installed.remove(&option_id);
+                }
+                self.persister.send(PersistCommand::SetInstalled {
+                    option_ids: vec![option_id],
+                    installed,
+                });
+                self.state.saving = true;
+            }
+        }
+
+        if finished {
+            self.presence_rx = None;
+            self.state.checking_presence = false;
+            let unknown = self.state.presence_unknown.len();
+            self.state.status_message = if unknown == 0 {
+                "Installed status checked".to_string()
+            } else {
+                format!("Installed status checked - {} option(s) unknown", unknown)
+            };
+        }
+    }
+
+    /// Ask the script for the versions of the installed options, in the background
+    fn check_versions(&mut self, requested: bool) {
+        let ids: Vec<String> = self
+            .state
+            .options
+            .iter()
+            .filter(|option| option.tracks_state() && self.state.is_installed(&option.id))
+            .map(|option| option.id.clone())
+            .collect();
+        if ids.is_empty() {
+            if requested {
+                self.state.status_message = "Nothing installed to check for updates".to_string();
+            }
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        query_all(self.state.script_dir.clone(), self.state.entry_script.clone(), ids, tx);
+        self.versions_rx = Some(relay(rx, self.wake_tx.clone()));
+        self.versions_requested = requested;
+        self.state.checking_versions = true;
+        if requested {
+            self.state.status_message = "Checking installed options for updates...".to_string();
+        }
+    }
+
+    /// Store the versions the script reported, announcing updates once all are in
+    fn handle_version_events(&mut self) {
+        let Some(rx) = &self.versions_rx else {
+            return;
+        };
+        let finished = loop {
+            match rx.try_recv() {
+                Ok(VersionEvent::Checked { option_id, versions }) => match versions {
+                    Some(versions) => {
+                        self.state.versions.insert(option_id, versions);
+                    }
+                    None => {
+                        self.state.versions.remove(&option_id);
+                    }
+                },
+                Ok(VersionEvent::Done) | Err(TryRecvError::Disconnected) => break true,
+                Err(TryRecvError::Empty) => break false,
+            }
+        };
+        if !finished {
+            return;
+        }
+
+        self.versions_rx = None;
+        self.state.checking_versions = false;
+        // A run's own status messages matter more
+        if self.installer.is_some() {
+            return;
+        }
+        let mut updates: Vec<&str> = self
+            .state
+            .options
+            .iter()
+            .filter(|option| self.state.update_for(&option.id).is_some())
+            .map(|option| option.name.as_str())
+            .collect();
+        updates.sort_unstable();
+        if !updates.is_empty() {
+            self.state.status_message = format!("Updates available for: {}", updates.join(", "));
+        } else if self.versions_requested {
+            self.state.status_message = if self.state.versions.is_empty() {
+                "The script reports no versions for the installed options".to_string()
+            } else {
+                "Installed options are up to date".to_string()
+            };
+        }
+    }
+
+    /// Handle keys in the update preview
+    fn handle_update_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                let (tx, rx) = mpsc::channel();
+                let script_dir = self.state.script_dir.clone();
+                thread::spawn(move || {
+                    let _ = tx.send(UpdateEvent::Pulled(pull(&script_dir)));
+                });
+                self.update_rx = Some(relay(rx, self.wake_tx.clone()));
+                self.state.status_message = "Updating scripts...".to_string();
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+                self.state.update_preview = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle keys in the post-update reinstall offer
+    fn handle_reinstall_key(&mut self, key: KeyEvent) {
+        let ids = std::mem::take(&mut self.state.reinstall_suggestion);
+        match key.code {
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                self.state.skip_verify = false;
+                self.journal = None;
+                self.start_queue(ids);
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+            }
+            _ => self.state.reinstall_suggestion = ids,
+        }
+    }
+
+    /// Handle keys in the manual state edit popup
+    fn handle_mark_popup_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.mark_as_installed = true;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.state.mark_as_installed = false;
+            }
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                self.apply_mark();
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+                self.state.mark_targets.clear();
+                self.update_status_for_selection();
+            }
+            _ => {}
+        }
+    }
+
+    /// Open the popup for marking options installed/not installed without running them
+    ///
+    /// Applies to the queued selection if there is one, otherwise the highlighted option.
+    fn begin_mark(&mut self) {
+        let chosen: Vec<&InstallOption> = if self.state.queued.is_empty() {
+            self.state.selected_option().into_iter().collect()
+        } else {
+            self.state
+                .options
+                .iter()
+                .filter(|o| self.state.is_queued(&o.id))
+                .collect()
+        };
+        if chosen.is_empty() {
+            return;
+        }
+        let targets: Vec<String> = chosen
+            .into_iter()
+            .filter(|o| o.tracks_state())
+            .map(|o| o.id.clone())
+            .collect();
+        if targets.is_empty() {
+            self.state.status_message = "Nothing to mark - these options don't track installed state".to_string();
+            return;
+        }
+
+        // Default to flipping the state when everything targeted agrees
+        self.state.mark_as_installed = !targets.iter().all(|id| self.state.is_installed(id));
+        self.state.mark_targets = targets;
+        self.state.mode = AppMode::ConfirmMark;
+        self.state.status_message = "Edit installed state without running the install script".to_string();
+    }
+
+    /// Apply the pending manual state edit
+    fn apply_mark(&mut self) {
+        let targets = std::mem::take(&mut self.state.mark_targets);
+        let installed = self.state.mark_as_installed;
+
+        let now = Utc::now();
+        for id in &targets {
+            if installed {
+                self.state.installed.insert(id.clone());
+            } else {
+                self.state.installed.remove(id);
+                self.state.pending_reboot.retain(|pending| pending != id);
+            }
+            self.state.records.entry(id.clone()).or_default().apply_mark(installed, now);
+        }
+        self.state.queued.clear();
+
+        let label = if installed { "installed" } else { "not installed" };
+        self.state.status_message = format!("Marked {} option(s) as {}", targets.len(), label);
+        self.persister.send(PersistCommand::Mark {
+            option_ids: targets,
+            installed,
+        });
+        self.state.saving = true;
+    }
+
+    /// Act on the selected row like Enter: run the option, or fold the category
+    fn activate_selection(&mut self) {
+        if self.state.selected_option_index().is_some() {
+            self.trigger_action();
+        } else if let Some(category) = self.state.selected_category() {
+            let collapsed = self.state.collapsed.contains(&category);
+            self.fold_category(&category, !collapsed);
+        }
+    }
+
+    /// Collapse or expand the category of the selected row
+    fn fold_selected_category(&mut self, collapse: bool) {
+        if let Some(category) = self.state.selected_category() {
+            self.fold_category(&category, collapse);
+        }
+    }
+
+    fn fold_category(&mut self, category: &str, collapse: bool) {
+        // A typed filter shows every matching group expanded
+        if !self.state.filter.is_empty() {
+            return;
+        }
+        self.state.set_collapsed(category, collapse);
+        self.update_status_for_selection();
+    }
+
+    /// Whether runs have to wait for the installed status check, saying so
+    ///
+    /// A result found before the run finished could undo what it recorded.
+    fn held_by_presence_check(&mut self) -> bool {
+        if self.state.checking_presence {
+            self.state.toast(ToastKind::Warning, "Checking installed status - try again once it's done");
+        }
+        self.state.checking_presence
+    }
+
+    /// Trigger install or uninstall for the selected option
+    fn trigger_action(&mut self) {
+        if self.held_by_presence_check() {
+            return;
+        }
+        let Some(option) = self.state.selected_option().cloned() else {
+            return;
+        };
+        // Retrying is about the last run, not whatever is triggered next
+        if self.state.last_attempt.as_ref().is_some_and(|(id, _)| id != &option.id) {
+            self.state.last_attempt = None;
+        }
+        // Its run would only fail on an unknown parameter
+        if self.state.unsupported.contains(&option.id) {
+            self.state.mode = AppMode::Unsupported;
+            return;
+        }
+
+        // Dependencies that still need installing run first
+        self.state.dependency_plan =
+            match install_order(&self.state.options, &option.id, &self.state.installed) {
+                Ok(mut order) => {
+                    order.pop();
+                    order
+                }
+                Err(e) => {
+                    self.state.status_message = format!("Can't install {}: {}", option.name, e);
+                    return;
+                }
+            };
+
+        let mut ids = self.state.dependency_plan.clone();
+        ids.push(option.id);
+        if self.hold_for_compat(&ids, CompatPending::Action) {
+            return;
+        }
+        self.choose_action();
+    }
+
+    /// Offer the actions for the selected option once its plan is settled
+    fn choose_action(&mut self) {
+        let Some(option) = self.state.selected_option().cloned() else {
+            return;
+        };
+        let is_installed = self.state.is_installed(&option.id);
+
+        self.state.skip_verify = false;
+        // One-shot options are only ever run, so there's nothing to choose
+        if option.tracks_state() && (is_installed || option.verify.is_some()) {
+            // Show popup to choose action
+            self.state.popup_choice = ActionChoice::Reinstall;
+            self.state.mode = AppMode::ConfirmAction;
+            self.state.status_message = if let Some(versions) = self.state.update_for(&option.id) {
+                format!(
+                    "{} {} is installed, {} is available - choose action",
+                    option.name, versions.installed, versions.available
+                )
+            } else if is_installed {
+                format!("{} is installed - choose action", option.name)
+            } else {
+                format!("Install {} - choose whether to verify", option.name)
+            };
+        } else if !self.state.dependency_plan.is_empty() {
+            self.state.mode = AppMode::ConfirmDependencies;
+            self.state.status_message = format!("{} needs other options installed first", option.name);
+        } else {
+            // Directly install
+            self.run_action(false);
+        }
+    }
+
+    /// Install the selected option after its pending dependencies
+    fn install_with_dependencies(&mut self) {
+        if self.state.dependency_plan.is_empty() {
+            self.run_action(false);
+            return;
+        }
+        let Some(option) = self.state.selected_option().cloned() else {
+            return;
+        };
+        let mut chain = std::mem::take(&mut self.state.dependency_plan);
+        chain.push(option.id);
+        self.state.dependency_chain = true;
+        self.journal = None;
+        self.start_queue(chain);
+    }
+
+    /// Handle keys in the dependency confirmation popup
+    fn handle_dependencies_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                self.install_with_dependencies();
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+                self.state.dependency_plan.clear();
+                self.update_status_for_selection();
+            }
+            _ => {}
+        }
+    }
+
+    /// Run the last failed action again, on the option it was for
+    ///
+    /// The output stays as it is until the new run starts, so a held-back
+    /// retry leaves the failure in view.
+    fn retry_last_attempt(&mut self) {
+        let Some((option_id, uninstall)) = self.state.last_attempt.clone() else {
+            return;
+        };
+        let Some(index) = self.state.option_index(&option_id) else {
+            return;
+        };
+        self.state.select_option(index);
+        self.run_action(uninstall);
+    }
+
+    /// Run the install/uninstall action for the selected option
+    fn run_action(&mut self, uninstall: bool) {
+        if self.held_by_presence_check() {
+            return;
+        }
+        self.state.queue.clear();
+        let Some(index) = self.state.selected_option_index() else {
+            return;
+        };
+        let pending = PendingRun::Action { uninstall };
+        if self.hold_for_unsandboxed(&[index], pending) {
+            return;
+        }
+        self.review_local_changes(pending);
+    }
+
+    /// Indices of the running queue's options
+    fn queue_indices(&self) -> Vec<usize> {
+        self.state
+            .queue
+            .iter()
+            .filter_map(|id| self.state.option_index(id))
+            .collect()
+    }
+
+    /// Check a run's reinstalls for hand-edited files before starting it
+    fn review_local_changes(&mut self, pending: PendingRun) {
+        let indices = match pending {
+            PendingRun::Action { uninstall: true } => Vec::new(),
+            PendingRun::Action { uninstall: false } => self.state.selected_option_index().into_iter().collect(),
+            PendingRun::Queue => self.queue_indices(),
+        };
+        if self.hold_for_local_changes(&indices, pending) {
+            return;
+        }
+        self.continue_pending_run(pending);
+    }
+
+    /// Hold a run back if options that should be sandboxed can't be
+    ///
+    /// Returns true when the warning popup was shown instead of starting the run.
+    fn hold_for_unsandboxed(&mut self, option_indices: &[usize], pending: PendingRun) -> bool {
+        let names: Vec<String> = option_indices
+            .iter()
+            .filter_map(|&i| self.state.options.get(i))
+            .filter(|option| self.state.sandbox.unavailable_for(option))
+            .map(|option| option.name.clone())
+            .collect();
+        if names.is_empty() {
+            return false;
+        }
+
+        self.state.status_message = format!("{} option(s) can't be sandboxed on this system", names.len());
+        self.state.unsandboxed = names;
+        self.state.pending_run = Some(pending);
+        self.state.mode = AppMode::ConfirmUnsandboxed;
+        true
+    }
+
+    /// Handle keys in the popup about running without a sandbox
+    fn handle_unsandboxed_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                self.state.unsandboxed.clear();
+                if let Some(pending) = self.state.pending_run.take() {
+                    self.review_local_changes(pending);
+                }
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+                self.state.unsandboxed.clear();
+                self.drop_pending_run();
+                self.update_status_for_selection();
+            }
+            _ => {}
+        }
+    }
+
+    /// Start a run once hand-edited files have been dealt with
+    fn continue_pending_run(&mut self, pending: PendingRun) {
+        match pending {
+            PendingRun::Action { uninstall } => {
+                let Some(index) = self.state.selected_option_index() else {
+                    return;
+                };
+                if !self.hold_for_editor_locks(&[index], pending) {
+                    self.start_action(index, uninstall);
+                }
+            }
+            PendingRun::Queue => {
+                let indices = self.queue_indices();
+                if !self.hold_for_editor_locks(&indices, pending) {
+                    self.start_queue_item();
+                }
+            }
+        }
+    }
+
+    /// Forget a held-back run, putting a user's queue selection back
+    fn drop_pending_run(&mut self) {
+        if self.state.pending_run.take() == Some(PendingRun::Queue) {
+            // Put a user selection back so the queue can be retried; a
+            // profile only needs choosing again
+            let profile = self.state.queue_profile.take().is_some();
+            if !std::mem::take(&mut self.state.dependency_chain) && !profile {
+                self.state.queued = self.state.queue.iter().cloned().collect();
+            }
+            self.state.queue.clear();
+            self.journal = None;
+        }
+    }
+
+    /// Hold a reinstall back if files the last install wrote were edited since
+    ///
+    /// Returns true when the warning popup was shown instead of starting the run.
+    fn hold_for_local_changes(&mut self, option_indices: &[usize], pending: PendingRun) -> bool {
+        let changes: Vec<LocalChanges> = option_indices
+            .iter()
+            .filter_map(|&i| self.state.options.get(i))
+            .filter(|option| self.state.is_installed(&option.id))
+            .filter_map(|option| {
+                let record = self.state.records.get(&option.id)?;
+                let modified = modified_files(&record.target_hashes);
+                (!modified.is_empty()).then(|| LocalChanges {
+                    option_name: option.name.clone(),
+                    managed: record.target_hashes.len(),
+                    modified,
+                })
+            })
+            .collect();
+        if changes.is_empty() {
+            return false;
+        }
+
+        let count: usize = changes.iter().map(|c| c.modified.len()).sum();
+        self.state.status_message = format!("{} managed file(s) have local modifications", count);
+        self.state.local_changes = changes;
+        self.state.local_changes_choice = LocalChangesChoice::BackUp;
+        self.state.pending_run = Some(pending);
+        self.state.mode = AppMode::ConfirmLocalChanges;
+        true
+    }
+
+    /// Handle keys in the local modifications popup
+    fn handle_local_changes_key(&mut self, key: KeyEvent) {
+        let choices = [
+            LocalChangesChoice::BackUp,
+            LocalChangesChoice::Proceed,
+            LocalChangesChoice::Cancel,
+        ];
+        let current = choices
+            .iter()
+            .position(|&c| c == self.state.local_changes_choice)
+            .unwrap_or(0);
+        let choice = match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.local_changes_choice = choices[current.saturating_sub(1)];
+                return;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.state.local_changes_choice = choices[(current + 1).min(choices.len() - 1)];
+                return;
+            }
+            KeyCode::Enter => self.state.local_changes_choice,
+            KeyCode::Esc => LocalChangesChoice::Cancel,
+            _ => return,
+        };
+
+        self.state.mode = AppMode::Normal;
+        let changes = std::mem::take(&mut self.state.local_changes);
+        if choice == LocalChangesChoice::BackUp {
+            let files: Vec<_> = changes.into_iter().flat_map(|c| c.modified).collect();
+            match back_up(&files) {
+                Ok(backups) => {
+                    self.state.status_message = format!("Backed up {} file(s) before reinstalling", backups.len());
+                }
+                Err(e) => {
+                    self.state.status_message = format!("Backup failed, nothing was run: {}", e);
+                    self.drop_pending_run();
+                    return;
+                }
+            }
+        }
+        match (choice, self.state.pending_run.take()) {
+            (LocalChangesChoice::Cancel, pending) => {
+                self.state.pending_run = pending;
+                self.drop_pending_run();
+                self.update_status_for_selection();
+            }
+            (_, Some(pending)) => self.continue_pending_run(pending),
+            (_, None) => {}
+        }
+    }
+
+    /// Hold a run back if any of its target files is open in an editor
+    ///
+    /// Returns true when the warning popup was shown instead of starting the run.
+    fn hold_for_editor_locks(&mut self, option_indices: &[usize], pending: PendingRun) -> bool {
+        let targets: Vec<_> = option_indices
+            .iter()
+            .filter_map(|&i| self.state.options.get(i))
+            .flat_map(InstallOption::target_paths)
+            .collect();
+        let locks = find_editor_locks(&targets);
+        if locks.is_empty() {
+            return false;
+        }
+
+        self.state.status_message = format!("{} target file(s) appear to be open in an editor", locks.len());
+        self.state.editor_locks = locks;
+        self.state.pending_run = Some(pending);
+        self.state.mode = AppMode::ConfirmEditorLocks;
+        true
+    }
+
+    /// Handle keys in the editor lock warning popup
+    fn handle_editor_locks_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                self.state.editor_locks.clear();
+                match self.state.pending_run.take() {
+                    Some(PendingRun::Action { uninstall }) => {
+                        if let Some(index) = self.state.selected_option_index() {
+                            self.start_action(index, uninstall);
+                        }
+                    }
+                    Some(PendingRun::Queue) => self.start_queue_item(),
+                    None => {}
+                }
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+                self.state.editor_locks.clear();
+                self.drop_pending_run();
+                self.update_status_for_selection();
+            }
+            _ => {}
+        }
+    }
+
+    /// Hold work back if any of the options is outside its Omarchy version range
+    ///
+    /// Returns true when the mismatch popup was shown instead.
+    fn hold_for_compat(&mut self, option_ids: &[String], pending: CompatPending) -> bool {
+        let warnings = self.state.compat_issues(option_ids);
+        if warnings.is_empty() {
+            return false;
+        }
+
+        self.state.status_message = format!(
+            "{} option(s) don't target the installed Omarchy version",
+            warnings.len()
+        );
+        self.state.compat_warnings = warnings;
+        self.state.compat_pending = Some(pending);
+        self.state.mode = AppMode::ConfirmIncompatible;
+        true
+    }
+
+    /// Handle keys in the version mismatch popup
+    fn handle_incompatible_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                self.state.compat_warnings.clear();
+                match self.state.compat_pending.take() {
+                    Some(CompatPending::Action) => self.choose_action(),
+                    Some(CompatPending::Queue(queue)) => self.start_user_queue(queue),
+                    None => {}
+                }
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+                self.state.compat_warnings.clear();
+                self.state.compat_pending = None;
+                self.state.queue_profile = None;
+                self.state.dependency_plan.clear();
+                self.update_status_for_selection();
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle keys in the pending items overlay
+    ///
+    /// Up/Down pick an item, Left/Right its choice; Enter acts on every item
+    /// not left at "Decide later" and Esc puts everything off.
+    fn handle_pending_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.state.pending.move_focus(false),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => self.state.pending.move_focus(true),
+            KeyCode::Left | KeyCode::Char('h') => self.state.pending.cycle_choice(false),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') => self.state.pending.cycle_choice(true),
+            // Resuming would start runs, so the decisions wait for the check
+            KeyCode::Enter if self.held_by_presence_check() => {}
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                self.update_status_for_selection();
+                for item in self.state.pending.take_decided() {
+                    self.apply_pending_choice(item.choice());
+                }
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Normal;
+                self.state.pending.defer_all();
+                self.state.status_message =
+                    format!("Decisions put off - press {} to review them", self.state.keymap.label(Action::Pending));
+            }
+            _ => {}
+        }
+    }
+
+    /// Carry out the decision made for a pending item
+    fn apply_pending_choice(&mut self, choice: PendingChoice) {
+        let retry_failures = match choice {
+            PendingChoice::Later => return,
+            PendingChoice::RebootNow => {
+                if self.state.queue_active() {
+                    // Don't reboot out from under the batch just resumed
+                    self.state.status_message = format!(
+                        "Batch resumed - press {} to reboot once it's done",
+                        self.state.keymap.label(Action::Reboot)
+                    );
+                } else {
+                    self.reboot_now();
+                }
+                return;
+            }
+            PendingChoice::Prune => {
+                let ids = std::mem::take(&mut self.state.unknown_ids);
+                for id in &ids {
+                    self.state.installed.remove(id);
+                    self.state.records.remove(id);
+                }
+                self.state.status_message = format!("Removed {} unknown option(s) from the state file", ids.len());
+                self.persister.send(PersistCommand::Forget(ids));
+                return;
+            }
+            PendingChoice::Discard => {
+                self.state.resume_journal = None;
+                discard_journal();
+                self.state.status_message = "Discarded the interrupted batch".to_string();
+                return;
+            }
+            PendingChoice::Resume => false,
+            PendingChoice::RetryFailed => true,
+        };
+        let Some(journal) = self.state.resume_journal.take() else {
+            return;
+        };
+        // Options dropped from the manifest since can't run any more
+        let (queue, gone): (Vec<String>, Vec<String>) = journal
+            .resume_queue(retry_failures)
+            .into_iter()
+            .partition(|id| self.state.option_index(id).is_some());
+        if queue.is_empty() {
+            discard_journal();
+            self.state.status_message = "Nothing left to resume".to_string();
+            return;
+        }
+        self.state.queue_continue_on_failure = journal.continue_on_failure;
+        self.journal = Some(journal);
+        self.start_queue(queue);
+        if !gone.is_empty() {
+            self.state.toast(
+                ToastKind::Warning,
+                format!("Skipped options no longer in the manifest: {}", gone.join(", ")),
+            );
+        }
+    }
+
+    /// Install all options selected with Space, one after another
+    fn run_queue(&mut self) {
+        if self.state.queued.is_empty() {
+            self.state.status_message =
+                format!("Nothing queued - press {} to select options", self.state.keymap.label(Action::Queue));
+            return;
+        }
+
+        self.state.queue_profile = None;
+
+        // Run in list order so the sequence is predictable
+        let queue: Vec<String> = self
+            .state
+            .options
+            .iter()
+            .filter(|o| self.state.is_queued(&o.id))
+            .map(|o| o.id.clone())
+            .collect();
+        if self.hold_for_compat(&queue, CompatPending::Queue(queue.clone())) {
+            return;
+        }
+        self.start_user_queue(queue);
+    }
+
+    /// Handle keys in the profile popup
+    fn handle_profiles_key(&mut self, key: KeyEvent) {
+        let count = self.state.profiles.len();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.profile_focus = (self.state.profile_focus + count - 1) % count;
+            }
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                self.state.profile_focus = (self.state.profile_focus + 1) % count;
+            }
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Normal;
+                self.run_profile(self.state.profile_focus);
+            }
+            KeyCode::Esc => self.state.mode = AppMode::Normal,
+            _ => {}
+        }
+    }
+
+    /// Queue the members of a profile that aren't installed yet and run them
+    ///
+    /// Ids the catalog doesn't have are skipped and named in the summary.
+    fn run_profile(&mut self, index: usize) {
+        let Some(profile) = self.state.profiles.get(index).cloned() else {
+            return;
+        };
+        let unknown: Vec<String> = profile
+            .unknown_ids(&self.state.options)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let mut queue: Vec<String> = Vec::new();
+        for id in &profile.option_ids {
+            let Some(option) = self.state.options.iter().find(|o| &o.id == id) else {
+                continue;
+            };
+            let installed = option.tracks_state() && self.state.is_installed(id);
+            if !installed && !queue.contains(id) {
+                queue.push(id.clone());
+            }
+        }
+
+        if queue.is_empty() {
+            self.state.status_message = format!("Everything in {} is already installed", profile.name);
+            if !unknown.is_empty() {
+                self.state.status_message += &format!(" - unknown ids skipped: {}", unknown.join(", "));
+            }
+            return;
+        }
+        self.state.queue_profile = Some((profile.name, unknown));
+        if self.hold_for_compat(&queue, CompatPending::Queue(queue.clone())) {
+            return;
+        }
+        self.start_user_queue(queue);
+    }
+
+    /// Run a queue the user selected, dropping the selection
+    fn start_user_queue(&mut self, queue: Vec<String>) {
+        if self.held_by_presence_check() {
+            self.state.queue_profile = None;
+            return;
+        }
+        // A new queue's journal replaces the interrupted one
+        if self.state.resume_journal.take().is_some() {
+            self.state.pending.remove(PendingKind::Batch);
+        }
+        self.state.queued.clear();
+        self.state.skip_verify = false;
+        self.journal = None;
+        self.start_queue(queue);
+    }
+
+    /// Run the given option ids as a queue
+    fn start_queue(&mut self, queue: Vec<String>) {
+        if self.held_by_presence_check() {
+            self.state.dependency_chain = false;
+            self.state.queue_profile = None;
+            return;
+        }
+        self.state.queue = queue;
+        self.state.queue_position = 0;
+        self.state.queue_results.clear();
+
+        if self.hold_for_unsandboxed(&self.queue_indices(), PendingRun::Queue) {
+            return;
+        }
+        self.review_local_changes(PendingRun::Queue);
+    }
+
+    /// Start the queue item at `queue_position`
+    ///
+    /// Nothing starts once quitting was confirmed; the journal keeps the rest
+    /// for the next session.
+    fn start_queue_item(&mut self) {
+        if self.state.quit_after_run {
+            return;
+        }
+        let Some(id) = self.state.queue.get(self.state.queue_position).cloned() else {
+            return;
+        };
+        let journal = self.journal.get_or_insert_with(|| {
+            BatchJournal::new(&self.state.queue, self.state.queue_continue_on_failure)
+        });
+        journal.mark(&id, ItemStatus::Started);
+        match self.state.option_index(&id) {
+            Some(index) => self.start_action(index, false),
+            // Nothing would ever report back, so the queue moves on instead
+            None => {
+                self.record_queue_result(QueueResult::not_run(id, false));
+                self.advance_queue(false);
+            }
+        }
+    }
+
+    /// Spawn the installer for an option
+    fn start_action(&mut self, option_index: usize, uninstall: bool) {
+        let Some(option) = self.state.options.get(option_index).cloned() else {
+            return;
+        };
+        let mut timer = RunTimer::start();
+
+        // Set up the action
+        let verb = match (uninstall, option.tracks_state()) {
+            (true, _) => "Uninstalling",
+            (false, true) => "Installing",
+            (false, false) => "Running",
+        };
+        let action = if self.state.queue_active() {
+            format!(
+                "{} {} ({}/{})",
+                verb,
+                option.name,
+                self.state.queue_position + 1,
+                self.state.queue.len()
+            )
+        } else {
+            format!("{} {}", verb, option.name)
+        };
+
+        // Queued and retried runs get here without the popup Enter shows
+        if self.state.unsupported.contains(&option.id) {
+            self.fail_unsupported(option_index, &option, uninstall);
+            return;
+        }
+
+        // What the option declares it needs is checked before anything runs
+        if !uninstall && !self.state.skip_preflight {
+            let unmet = unmet_requirements(&option);
+            if !unmet.is_empty() {
+                self.fail_preflight(option_index, &option, &unmet);
+                return;
+            }
+        }
+
+        self.state.clear_output();
+        self.state.clear_filter();
+        self.state.password_prompt = None;
+        self.state.password_attempts = 0;
+        self.state.current_action = Some(action.clone());
+        self.state.status_message = action;
+        self.state.mode = AppMode::Installing;
+        self.state.show_output = true;
+        self.state.is_uninstalling = uninstall;
+        self.state.active_option = Some(option_index);
+        self.state.last_attempt = Some((option.id.clone(), uninstall));
+        self.state.run_started_at = Some(Instant::now());
+        self.state.run_watch = self
+            .state
+            .timeout_policy
+            .limit_for(&option)
+            .map(|limit| RunWatch::new(limit, Instant::now()));
+        self.state.timeout_prompt_pending = false;
+        let run_action = if uninstall {
+            RunAction::Uninstall
+        } else {
+            RunAction::Install
+        };
+        let run_id = RunId::new(&option.id, run_action);
+        self.run_log = RunLog::start(
+            &option.id,
+            &run_id,
+            &command_line(&self.state.entry_script, &option.id, uninstall),
+        );
+        self.state.current_run_id = Some(run_id);
+
+        // Create channel for installer events
+        let (tx, rx) = mpsc::channel();
+        self.installer_rx = Some(relay(rx, self.wake_tx.clone()));
+
+        // Start the installer in a background thread
+        let size = self.pty_size();
+        self.state.verify_failure = None;
+        self.state.hook_failures.clear();
+        let settings = RunSettings {
+            verify: !self.state.skip_verify,
+            sandbox: self.state.sandbox.sandbox_for(&self.state.script_dir, &option),
+            env: script_env(&self.state.script_env, &option),
+            priority: self.state.priority.for_option(&option),
+        };
+        self.installer = run_install_command(
+            &self.state.script_dir,
+            &self.state.entry_script,
+            &option,
+            uninstall,
+            settings,
+            size,
+            tx,
+        );
+        if let Some(installer) = &self.installer {
+            let (spawned_at, clock) = installer.timing();
+            timer.spawned(spawned_at, clock);
+            if let Some(log) = &self.run_log {
+                log.line(&format!("# Priority: {}", describe_priority(installer.priority())));
+            }
+        }
+        self.run_timer = Some(timer);
+        self.pty_size_sent = Some(size);
+        self.state.running_option = Some(option.id.clone());
+    }
+
+    /// Explain in the output panel why an install wasn't started
+    fn fail_preflight(&mut self, option_index: usize, option: &InstallOption, unmet: &[String]) {
+        self.state.clear_output();
+        self.state.clear_filter();
+        self.state.current_action = Some(format!("Pre-flight failed for {}", option.name));
+        self.state.mode = AppMode::Normal;
+        self.state.show_output = true;
+        self.state.is_uninstalling = false;
+        self.state.active_option = Some(option_index);
+        self.state.last_attempt = Some((option.id.clone(), false));
+
+        self.state
+            .add_problem_line(format!("{} needs what this system doesn't have:", option.name));
+        for problem in unmet {
+            self.state.add_problem_line(format!("  ✗ {}", problem));
+        }
+        self.state.add_output_line(String::new());
+        self.state
+            .add_output_line("Nothing was run. Start with --skip-preflight to install anyway.".to_string());
+        self.state.status_message = format!(
+            "Pre-flight failed for {}: {} requirement(s) not met - nothing was run",
+            option.name,
+            unmet.len()
+        );
+
+        if self.state.queue_active() {
+            self.record_queue_result(QueueResult::not_run(option.id.clone(), false));
+            self.advance_queue(false);
+        }
+    }
+
+    /// Explain in the output panel that the entry script doesn't accept an option
+    fn fail_unsupported(&mut self, option_index: usize, option: &InstallOption, uninstall: bool) {
+        self.state.clear_output();
+        self.state.clear_filter();
+        self.state.current_action = Some(format!("{} not supported", option.name));
+        self.state.mode = AppMode::Normal;
+        self.state.show_output = true;
+        self.state.is_uninstalling = uninstall;
+        self.state.active_option = Some(option_index);
+        self.state.last_attempt = Some((option.id.clone(), uninstall));
+
+        self.state.add_problem_line(format!(
+            "{} doesn't list {:?} among the options it accepts.",
+            display_path(&self.state.entry_script),
+            option.id
+        ));
+        self.state.add_output_line(String::new());
+        self.state.add_output_line(
+            "Nothing was run. Update the scripts, or fix the id in the manifest.".to_string(),
+        );
+        self.state.status_message = format!("{} is not supported by this script version - nothing was run", option.name);
+
+        if self.state.queue_active() {
+            self.record_queue_result(QueueResult::not_run(option.id.clone(), uninstall));
+            self.advance_queue(false);
+        }
+    }
+
+    /// Size of the subprocess terminal, matching the output panel once it has been drawn
+    fn pty_size(&self) -> PtySize {
+        if self.state.output_height == 0 || self.state.output_width == 0 {
+            return PtySize::default();
+        }
+        PtySize {
+            rows: self.state.output_height as u16,
+            cols: self.state.output_width as u16,
+        }
+    }
+
+    /// Propagate output panel size changes to the running subprocess
+    fn resize_pty(&mut self) {
+        let size = self.pty_size();
+        if let Some(installer) = &self.installer {
+            if self.pty_size_sent != Some(size) {
+                installer.resize(size);
+                self.pty_size_sent = Some(size);
+            }
+        }
+    }
+
+    /// Handle keys in the popup asking whether to abort the run and quit
+    fn handle_quit_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                // Quit once the run has ended and its outcome is recorded
+                self.state.quit_after_run = true;
+                self.state.mode = AppMode::Installing;
+                self.cancel_action();
+                self.state.status_message = "Aborting the run before quitting...".to_string();
+            }
+            KeyCode::Esc => self.state.mode = AppMode::Installing,
+            _ => {}
+        }
+    }
+
+    /// Handle the popup for a run that reached its time limit
+    fn handle_timeout_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.state.mode = AppMode::Installing;
+                self.time_out_run();
+            }
+            KeyCode::Esc => {
+                self.state.mode = AppMode::Installing;
+                self.state.status_message = "Still waiting - Esc or Ctrl+K cancels the run".to_string();
+            }
+            _ => {}
+        }
+    }
+
+    /// Flag a run that is taking long, and deal with one past its time limit
+    ///
+    /// Past the limit the run is killed, or the user is asked once whether
+    /// to; the question waits while another popup is open. Returns whether
+    /// anything on screen changed.
+    fn check_run_timeout(&mut self, now: Instant) -> bool {
+        let Some(watch) = self.state.run_watch.as_mut().filter(|_| self.installer.is_some()) else {
+            return false;
+        };
+        let limit = describe_limit(watch.limit());
+        match watch.advance(now) {
+            Some(Overrun::Expired) if self.detached || self.state.timeout_policy.action == TimeoutAction::Kill => {
+                self.time_out_run();
+                true
+            }
+            Some(Overrun::Expired) => {
+                self.state.timeout_prompt_pending = true;
+                self.open_timeout_prompt()
+            }
+            Some(_) => {
+                if let Some(log) = &self.run_log {
+                    log.line(&format!("# Running longer than expected ({} limit)", limit));
+                }
+                true
+            }
+            None => self.open_timeout_prompt(),
+        }
+    }
+
+    /// Ask whether to kill the timed-out run, if it's waiting to be asked and
+    /// nothing else is on screen
+    fn open_timeout_prompt(&mut self) -> bool {
+        if !self.state.timeout_prompt_pending || self.state.mode != AppMode::Installing {
+            return false;
+        }
+        self.state.timeout_prompt_pending = false;
+        self.state.mode = AppMode::ConfirmTimeout;
+        true
+    }
+
+    /// Kill the running script for going past its time limit
+    fn time_out_run(&mut self) {
+        let Some(installer) = &self.installer else {
+            return;
+        };
+        installer.time_out();
+        let limit = self.state.run_watch.as_ref().map(|watch| describe_limit(watch.limit()));
+        let line = format!("Stopping: past the {} time limit", limit.unwrap_or_default());
+        if let Some(log) = &self.run_log {
+            log.line(&line);
+        }
+        self.state.add_output_line(line.clone());
+        self.state.auto_scroll_output();
+        self.state.status_message = line;
+    }
+
+    /// Ask for the password sudo is waiting on, or give up after too many tries
+    ///
+    /// A prompt that comes back means the last password was wrong.
+    fn open_password_prompt(&mut self) {
+        let prompt = self.state.partial_output.as_deref().map(|text| strip_ansi_codes(text).trim().to_string());
+        self.state.password_prompt = prompt;
+        self.state.password_attempts += 1;
+        if self.state.password_attempts > MAX_PASSWORD_ATTEMPTS {
+            if self.state.mode == AppMode::PasswordPrompt {
+                self.state.mode = AppMode::Installing;
+            }
+            self.state.clear_password();
+            self.cancel_action();
+            self.state.status_message = format!(
+                "sudo rejected the password {} times - aborting the run",
+                MAX_PASSWORD_ATTEMPTS
+            );
+            return;
+        }
+        // Other popups, such as the quit confirmation, aren't replaced
+        if self.state.mode == AppMode::Installing {
+            self.state.password_input.reserve(PASSWORD_CAPACITY);
+            self.state.zen = false;
+            self.state.mode = AppMode::PasswordPrompt;
+        }
+    }
+
+    /// Handle keys in the sudo password popup
+    ///
+    /// The password goes to the script's terminal and nowhere else: not the
+    /// output, the log or the screen.
+    fn handle_password_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => {
+                self.state.password_input.push(b'\n');
+                if let Some(installer) = &self.installer {
+                    installer.write_input(&self.state.password_input);
+                }
+                self.state.clear_password();
+                self.state.mode = AppMode::Installing;
+                self.state.status_message = "Password sent to sudo".to_string();
+            }
+            KeyCode::Esc => {
+                self.state.clear_password();
+                self.state.mode = AppMode::Installing;
+                self.state.status_message = "Password popup closed - typing goes to the installer".to_string();
+            }
+            KeyCode::Backspace => {
+                // Drop a whole character, continuation bytes first
+                while let Some(byte) = self.state.password_input.pop() {
+                    let continuation = byte & 0b1100_0000 == 0b1000_0000;
+                    if !continuation {
+                        break;
+                    }
+                }
+            }
+            KeyCode::Char(c) if !ctrl => {
+                let mut buf = [0u8; 4];
+                self.state
+                    .password_input
+                    .extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                buf.fill(0);
+            }
+            _ => {}
+        }
+    }
+
+    /// Cancel the running install, killing its whole process group
+    fn cancel_action(&mut self) {
+        if let Some(installer) = &self.installer {
+            installer.cancel();
+            self.state.status_message = "Cancelling...".to_string();
+        }
+    }
+
+    /// Kill the running action without the SIGTERM grace period
+    fn kill_action(&mut self) {
+        if let Some(installer) = &self.installer {
+            installer.kill();
+            self.state.status_message = "Killing the run before quitting...".to_string();
+        }
+    }
+
+    /// Handle events from the installer subprocess
+    ///
+    /// Stops once `INGEST_BUDGET` is spent so a flood of output can't hold
+    /// off the next frame; returns whether events were left waiting.
+    fn handle_installer_events(&mut self) -> bool {
+        let deadline = Instant::now() + INGEST_BUDGET;
+        while let Some(rx) = &self.installer_rx {
+            if Instant::now() >= deadline {
+                return true;
+            }
+            match rx.try_recv() {
+                Ok(event) => {
+                    let output = matches!(
+                        event,
+                        InstallerEvent::OutputLine(_)
+                            | InstallerEvent::OutputLines(_)
+                            | InstallerEvent::ErrorLines(_)
+                            | InstallerEvent::PartialLine(_)
+                    );
+                    let started = Instant::now();
+                    self.handle_installer_event(event);
+                    if let Some(timer) = self.run_timer.as_mut().filter(|_| output) {
+                        timer.ingested(started.elapsed());
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.installer_rx = None;
+                    self.installer = None;
+                    self.state.running_option = None;
+                    break;
+                }
+            }
+        }
+        false
+    }
+
+    /// Log and append a batch of script output, scrolling once for all of it
+    fn ingest_lines(&mut self, lines: Vec<String>, stderr: bool) {
+        if let Some(log) = &self.run_log {
+            log.lines(&lines);
+        }
+        self.state.partial_output = None;
+        if stderr {
+            for line in lines {
+                self.state.add_stderr_line(line);
+            }
+        } else {
+            self.state.password_prompt = None;
+            for line in lines {
+                self.state.add_output_line(line);
+            }
+        }
+        self.state.auto_scroll_output();
+    }
+
+    /// Apply a single installer event to the application state
+    fn handle_installer_event(&mut self, event: InstallerEvent) {
+        match event {
+            InstallerEvent::OutputLine(line) => self.ingest_lines(vec![line], false),
+            InstallerEvent::OutputLines(lines) => self.ingest_lines(lines, false),
+            InstallerEvent::ErrorLines(lines) => self.ingest_lines(lines, true),
+            InstallerEvent::Frame(line) => {
+                if let Some(log) = &self.run_log {
+                    log.line(&line);
+                }
+                self.state.add_frame_line(line);
+                self.state.auto_scroll_output();
+            }
+            InstallerEvent::PartialLine(text) => {
+                let prompt = self.state.password_prompt.is_none() && strip_ansi_codes(&text).contains(SUDO_PROMPT);
+                self.state.partial_output = Some(text);
+                self.state.auto_scroll_output();
+                if prompt {
+                    self.open_password_prompt();
+                }
+            }
+            InstallerEvent::VerifyFailed(reason) => {
+                let line = format!("Verification failed: {}", reason);
+                if let Some(log) = &self.run_log {
+                    log.line(&line);
+                }
+                self.state.add_output_line(line);
+                self.state.auto_scroll_output();
+                self.state.verify_failure = Some(reason);
+            }
+            InstallerEvent::HookFailed(reason) => {
+                let line = format!("Post-install hook failed: {}", reason);
+                if let Some(log) = &self.run_log {
+                    log.line(&line);
+                }
+                self.state.add_problem_line(line);
+                self.state.auto_scroll_output();
+                self.state.hook_failures.push(reason);
+            }
+            InstallerEvent::Completed(completion) => {
+                self.notify_if_away(completion);
+                self.finish_run(Some(completion));
+                let verify_failure = self.state.verify_failure.take();
+                let log_path = self.finish_log(&match &verify_failure {
+                    Some(reason) => format!("{}, verification failed: {}", completion, reason),
+                    None => completion.to_string(),
+                });
+
+                // Update installed state based on the action we performed
+                if let Some(option) = self.active_option() {
+                    if self.detached {
+                        self.note_detached_result(&option, completion);
+                    }
+                    self.record_active_run(&option, completion, verify_failure.is_some());
+
+                    if let (true, Some(reason)) = (completion.success(), &verify_failure) {
+                        // Installed, but it doesn't check out
+                        if option.tracks_state() {
+                            self.state.installed.insert(option.id.clone());
+                        }
+                        self.state.toast(
+                            ToastKind::Error,
+                            format!("Installed {} but verification failed: {} - see output", option.name, reason),
+                        );
+                    } else if completion.success() {
+                        let done = if !option.tracks_state() {
+                            format!("Ran {} successfully at {}", option.name, Local::now().format("%H:%M"))
+                        } else if self.state.is_uninstalling {
+                            // Uninstall succeeded
+                            self.state.installed.remove(&option.id);
+                            format!("Uninstalled {}", option.name)
+                        } else {
+                            // Install/update succeeded - mark as installed; the
+                            // versions reported before no longer hold
+                            self.state.installed.insert(option.id.clone());
+                            self.state.versions.remove(&option.id);
+                            format!("Installed {}", option.name)
+                        };
+                        // Hooks don't decide success, but a failed one is worth knowing about
+                        if self.state.hook_failures.is_empty() {
+                            self.state.toast(ToastKind::Success, done);
+                        } else {
+                            self.state.toast(
+                                ToastKind::Warning,
+                                format!(
+                                    "{} succeeded but post-install hook failed: {} - see output",
+                                    option.name,
+                                    self.state.hook_failures.join("; ")
+                                ),
+                            );
+                        }
+                    } else {
+                        // The script's last complaint usually says what went wrong
+                        let said = self
+                            .state
+                            .last_stderr
+                            .as_deref()
+                            .map(|line| format!(": {}", strip_ansi_codes(line).trim()))
+                            .unwrap_or_default();
+                        let failed = match &log_path {
+                            Some(path) => {
+                                format!("{}{} - log: {}", completion.failure_headline(), said, path.display())
+                            }
+                            None => format!("{}{}", completion.failure_headline(), said),
+                        };
+                        self.state.toast(ToastKind::Error, failed);
+                    }
+                    self.update_status_for_selection();
+
+                    let result = self.run_result(option.id, completion.code(), verify_failure.is_some(), log_path);
+                    if self.state.queue_active() {
+                        self.record_queue_result(result);
+                        self.advance_queue(completion.success() && verify_failure.is_none());
+                    } else if self.state.always_show_summary {
+                        self.open_summary(vec![result]);
+                    }
+                }
+                self.remember_selection();
+            }
+            InstallerEvent::Cancelled => {
+                self.state.add_output_line("Cancelled by user".to_string());
+                self.finish_run(None);
+                self.finish_log("cancelled");
+                self.state.toast(ToastKind::Warning, "Cancelled - Esc to close output");
+                self.update_status_for_selection();
+                self.state.queue.clear();
+                self.state.queue_profile = None;
+                self.state.dependency_chain = false;
+                // Quitting mid-batch keeps the journal, the cancelled item still
+                // started, so the next session offers to resume
+                if self.state.quit_after_run {
+                    self.journal = None;
+                } else {
+                    self.end_journal();
+                }
+            }
+            InstallerEvent::Error(err) => {
+                let line = format!("Error: {}", err);
+                if let Some(log) = &self.run_log {
+                    log.line(&line);
+                }
+                self.state.add_output_line(line);
+                self.finish_run(Some(Completion::Exited(-1)));
+                let log_path = self.finish_log("error");
+                self.state.toast(ToastKind::Error, format!("Error: {} - Esc to close output", err));
+                self.update_status_for_selection();
+                if let Some(option) = self.active_option() {
+                    self.record_active_run(&option, Completion::Exited(-1), false);
+                    let result = self.run_result(option.id, -1, false, log_path);
+                    if self.state.queue_active() {
+                        self.record_queue_result(result);
+                        self.advance_queue(false);
+                    } else if self.state.always_show_summary {
+                        self.open_summary(vec![result]);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Announce the end of a long run on the desktop when the terminal isn't in focus
+    ///
+    /// Terminals that never report focus count as unfocused.
+    fn notify_if_away(&self, completion: Completion) {
+        let long = match (self.state.notify_after, self.state.run_elapsed(Instant::now())) {
+            (Some(after), Some(elapsed)) => elapsed >= after,
+            _ => false,
+        };
+        if !long || self.state.focused == Some(true) {
+            return;
+        }
+        let Some(option) = self.active_option() else {
+            return;
+        };
+        let summary = match (completion.success(), self.state.is_uninstalling) {
+            (true, false) if !option.tracks_state() => format!("Ran {}", option.name),
+            (true, false) => format!("Installed {}", option.name),
+            (true, true) => format!("Uninstalled {}", option.name),
+            (false, _) => format!("{} failed ({})", option.name, completion),
+        };
+        notify(&summary, "omarchy-cybex installer");
+    }
+
+    /// Persist the outcome of the finished run and mirror it in memory
+    fn record_active_run(&mut self, option: &InstallOption, completion: Completion, verify_failed: bool) {
+        let action = if self.state.is_uninstalling {
+            RunAction::Uninstall
+        } else {
+            RunAction::Install
+        };
+        let run_id = self
+            .state
+            .current_run_id
+            .clone()
+            .unwrap_or_else(|| RunId::new(&option.id, action));
+        let mut record = self.state.records.get(&option.id).cloned().unwrap_or_default();
+        let timings = self.run_timer.as_ref().and_then(RunTimer::finish);
+        record.apply_run(&run_id, action, completion, verify_failed, timings, Utc::now());
+        self.state.records.insert(option.id.clone(), record.clone());
+        // One-shot runs are session history only; the state file never hears of them
+        if !option.tracks_state() {
+            return;
+        }
+        self.persister.send(PersistCommand::Record {
+            option_id: option.id.clone(),
+            record,
+        });
+        // Hash what the install wrote so later hand edits can be spotted
+        if completion.success() && !option.targets.is_empty() {
+            let targets = match action {
+                RunAction::Install => option.target_paths(),
+                RunAction::Uninstall => Vec::new(),
+            };
+            self.persister.send(PersistCommand::HashTargets {
+                option_id: option.id.clone(),
+                targets,
+            });
+        }
+        if option.requires_reboot && action == RunAction::Install && completion.success() {
+            if !self.state.pending_reboot.contains(&option.id) {
+                self.state.pending_reboot.push(option.id.clone());
+            }
+            self.persister.send(PersistCommand::PendingReboot(option.id.clone()));
+        }
+        self.state.saving = true;
+    }
+
+    /// The option the running (or last) action applies to
+    fn active_option(&self) -> Option<InstallOption> {
+        self.state
+            .active_option
+            .and_then(|i| self.state.options.get(i))
+            .cloned()
+    }
+
+    /// Close the run's log file, returning its path
+    fn finish_log(&mut self, result: &str) -> Option<PathBuf> {
+        let log = self.run_log.take()?;
+        let path = log.path().to_path_buf();
+        // Detach the writer; it finishes flushing in the background
+        drop(log.finish(result));
+        Some(path)
+    }
+
+    /// Remember a run that finished after the terminal was lost
+    fn note_detached_result(&mut self, option: &InstallOption, completion: Completion) {
+        let action = if self.state.is_uninstalling { "uninstall" } else { "install" };
+        let result = match completion {
+            _ if completion.success() => "success".to_string(),
+            Completion::Exited(_) => format!("failed with {}", completion),
+            Completion::Signaled(_) | Completion::TimedOut => completion.to_string(),
+        };
+        self.detached_notes.push(format!(
+            "Your {} of {} completed after the session dropped: {}",
+            action, option.name, result
+        ));
+    }
+
+    /// Return to normal mode after the subprocess has finished
+    fn finish_run(&mut self, completion: Option<Completion>) {
+        self.state.end_run(completion, Instant::now());
+        self.installer_rx = None;
+        self.installer = None;
+    }
+
+    /// The outcome of the run that just finished
+    fn run_result(&self, option_id: String, exit_code: i32, verify_failed: bool, log: Option<PathBuf>) -> QueueResult {
+        QueueResult {
+            option_id,
+            uninstall: self.state.is_uninstalling,
+            exit_code,
+            verify_failed,
+            duration: self.state.run_duration,
+            log,
+        }
+    }
+
+    /// Note a queue item's outcome in memory and in the journal
+    fn record_queue_result(&mut self, result: QueueResult) {
+        if let Some(journal) = &mut self.journal {
+            // A failed verification is retried like any other failure
+            let status = if result.failed() {
+                ItemStatus::Failed
+            } else {
+                ItemStatus::Succeeded
+            };
+            journal.mark(&result.option_id, status);
+        }
+        self.state.queue_results.push(result);
+    }
+
+    /// List how each run went, starting on the first failure
+    fn open_summary(&mut self, results: Vec<QueueResult>) {
+        self.state.summary_focus = results.iter().position(QueueResult::failed).unwrap_or(0);
+        self.state.queue_results = results;
+        self.state.mode = AppMode::QueueSummary;
+    }
+
+    /// Handle keys in the queue summary
+    fn handle_summary_key(&mut self, key: KeyEvent) {
+        let count = self.state.queue_results.len();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.summary_focus = (self.state.summary_focus + count - 1) % count;
+            }
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                self.state.summary_focus = (self.state.summary_focus + 1) % count;
+            }
+            KeyCode::Home | KeyCode::Char('g') => self.state.summary_focus = 0,
+            KeyCode::End | KeyCode::Char('G') => self.state.summary_focus = count - 1,
+            KeyCode::Char('l') => {
+                let result = &self.state.queue_results[self.state.summary_focus];
+                match result.log.clone() {
+                    _ if !result.failed() => {
+                        self.state.status_message =
+                            format!("{} succeeded - l opens the log of a failure", result.option_id);
+                    }
+                    Some(path) => {
+                        self.state.mode = AppMode::Normal;
+                        self.show_log(&path);
+                    }
+                    None => {
+                        self.state.status_message = format!("{} has no log - nothing was run", result.option_id);
+                    }
+                }
+            }
+            KeyCode::Enter | KeyCode::Esc => self.state.mode = AppMode::Normal,
+            _ => {}
+        }
+    }
+
+    /// Drop the journal once the queue has ended in this session
+    fn end_journal(&mut self) {
+        if self.journal.take().is_some() {
+            discard_journal();
+        }
+    }
+
+    /// Move on to the next queue item, or finish the queue
+    fn advance_queue(&mut self, succeeded: bool) {
+        self.state.queue_position += 1;
+        let more = self.state.queue_position < self.state.queue.len();
+
+        let chain = self.state.dependency_chain;
+        if more && (succeeded || (self.state.queue_continue_on_failure && !chain)) {
+            self.start_queue_item();
+            return;
+        }
+        self.state.dependency_chain = false;
+
+        let failed = self.state.queue_results.iter().filter(|result| result.failed()).count();
+        let installed = self.state.queue_results.len() - failed;
+        let skipped = self.state.queue.len() - self.state.queue_results.len();
+        let (queue, unknown) = match self.state.queue_profile.take() {
+            Some((name, unknown)) => (format!("Profile {}", name), unknown),
+            None => ("Queue".to_string(), Vec::new()),
+        };
+        let mut summary = if chain {
+            self.chain_summary()
+        } else if skipped > 0 {
+            format!(
+                "{} stopped: {} installed, {} failed, {} skipped - Esc to close output",
+                queue, installed, failed, skipped
+            )
+        } else {
+            format!("{} finished: {} installed, {} failed", queue, installed, failed)
+        };
+        if !unknown.is_empty() {
+            summary += &format!(" (unknown ids skipped: {})", unknown.join(", "));
+        }
+        let kind = if failed > 0 { ToastKind::Error } else { ToastKind::Success };
+        self.state.toast(kind, summary);
+        self.state.queue.clear();
+        self.end_journal();
+        if self.state.queue_results.len() > 1 || self.state.always_show_summary {
+            let results = std::mem::take(&mut self.state.queue_results);
+            self.open_summary(results);
+        }
+    }
+
+    /// Status line for a finished dependency chain
+    fn chain_summary(&self) -> String {
+        let name = |id: &str| {
+            self.state
+                .options
+                .iter()
+                .find(|o| o.id == id)
+                .map_or(id, |o| o.name.as_str())
+                .to_string()
+        };
+        let target = self.state.queue.last().map(|id| name(id)).unwrap_or_default();
+        match self.state.queue_results.iter().find(|result| result.failed()) {
+            Some(result) if Some(&result.option_id) != self.state.queue.last() => {
+                format!("Dependency {} failed - {} was not installed", name(&result.option_id), target)
+            }
+            Some(_) => format!("{} failed after installing its dependencies - Esc to close output", target),
+            None => format!("Installed {} with {} dependencies", target, self.state.queue.len() - 1),
+        }
+    }
+
+    /// Rows one Up/Down press moves, growing while the key is held
+    fn nav_step(&mut self, action: Action, now: Instant) -> usize {
+        let held = match self.held_nav {
+            Some((at, last, held)) if last == action && now.duration_since(at) <= HELD_NAV_GAP => held + 1,
+            _ => 0,
+        };
+        self.held_nav = Some((now, action, held));
+        (held / NAV_ACCELERATION + 1).min(MAX_NAV_STEP)
+    }
+
     /// Update status bar based on current selection
     fn update_status_for_selection(&mut self) {
-        if let Some(option) = OPTIONS.get(self.state.selected_index) {
-            let action = if self.state.is_installed(option.id) {
+        if let Some(option) = self.state.selected_option().filter(|o| self.state.unsupported.contains(&o.id)) {
+            self.state.status_message = format!("{} is not supported by this script version", option.name);
+        } else if let Some(option) = self.state.selected_option() {
+            let action = if self.state.is_installed(&option.id) {
                 "uninstall"
             } else {
                 "install"
@@ -258,6 +3099,197 @@ impl App {
                 "Press Enter to {} {}",
                 action, option.name
             );
+        } else if let Some(category) = self.state.selected_category() {
+            let action = if self.state.collapsed.contains(&category) {
+                "expand"
+            } else {
+                "collapse"
+            };
+            self.state.status_message = format!("Press Enter to {} {}", action, category);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    use ratatui::backend::{ClearType, WindowSize};
+    use ratatui::buffer::Cell;
+    use ratatui::layout::{Position, Size};
+
+    use super::*;
+
+    /// A terminal that went away: every frame fails to reach it
+    struct LostTerminal;
+
+    impl Backend for LostTerminal {
+        fn draw<'a, I>(&mut self, _content: I) -> io::Result<()>
+        where
+            I: Iterator<Item = (u16, u16, &'a Cell)>,
+        {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+
+        fn hide_cursor(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn show_cursor(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn get_cursor_position(&mut self) -> io::Result<Position> {
+            Ok(Position::ORIGIN)
+        }
+
+        fn set_cursor_position<P: Into<Position>>(&mut self, _position: P) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn clear(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn clear_region(&mut self, _clear_type: ClearType) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn size(&self) -> io::Result<Size> {
+            Ok(Size::new(80, 24))
+        }
+
+        fn window_size(&mut self) -> io::Result<WindowSize> {
+            Ok(WindowSize {
+                columns_rows: Size::new(80, 24),
+                pixels: Size::new(0, 0),
+            })
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn fish() -> InstallOption {
+        InstallOption {
+            id: "fish".to_string(),
+            name: "Fish Shell".to_string(),
+            ..InstallOption::default()
         }
     }
+
+    /// An app with an install of `option` already running `body`, its persister recording
+    fn running(name: &str, body: &str, option: InstallOption) -> (App, Receiver<PersistCommand>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("omarchy-cybex-app-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("install.sh");
+        fs::write(&script, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let state = AppState::new(vec![option.clone()], HashSet::new(), None);
+        let (persister, written) = Persister::recording();
+        let mut app = App::with_persister(state, dir.clone(), script.clone(), persister);
+
+        let (tx, rx) = mpsc::channel();
+        let settings = RunSettings::default();
+        app.installer = run_install_command(&dir, &script, &option, false, settings, PtySize::default(), tx);
+        assert!(app.installer.is_some(), "the fake installer starts");
+        app.installer_rx = Some(rx);
+        app.state.mode = AppMode::Installing;
+        app.state.active_option = Some(0);
+        app.state.running_option = Some(option.id.clone());
+        app.state.run_started_at = Some(Instant::now());
+        app.state.current_action = Some(format!("Installing {}", option.name));
+        (app, written, dir)
+    }
+
+    /// Lose the terminal mid-run and see the session out, returning what it wrote
+    fn lose_terminal(app: &mut App, written: Receiver<PersistCommand>) -> Vec<PersistCommand> {
+        let mut terminal = Terminal::new(LostTerminal).unwrap();
+        let session = app.draw(&mut terminal, Instant::now());
+        assert!(session.is_err(), "drawing to a lost terminal fails");
+        app.end_session(session);
+        assert_eq!(app.finish_persisting(), Vec::<String>::new());
+        written.try_iter().collect()
+    }
+
+    /// The note left for the next session, if one was written
+    fn note(commands: &[PersistCommand]) -> Option<&str> {
+        commands.iter().find_map(|command| match command {
+            PersistCommand::SaveDetachedNote(note) => Some(note.as_str()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn a_lost_terminal_leaves_the_run_to_finish() {
+        let (mut app, written, dir) = running("success", "sleep 0.2", fish());
+        let commands = lose_terminal(&mut app, written);
+
+        assert!(app.detached());
+        assert!(app.installer.is_none() && app.installer_rx.is_none(), "the run was seen through");
+        assert_eq!(app.state.mode, AppMode::Normal);
+        assert!(app.state.installed.contains("fish"));
+        assert!(commands
+            .iter()
+            .any(|command| matches!(command, PersistCommand::Record { option_id, .. } if option_id == "fish")));
+        assert_eq!(
+            note(&commands),
+            Some("Your install of Fish Shell completed after the session dropped: success")
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_failure_after_the_terminal_is_lost_is_noted() {
+        let (mut app, written, dir) = running("failure", "exit 3", fish());
+        let commands = lose_terminal(&mut app, written);
+
+        assert!(!app.state.installed.contains("fish"));
+        let note = note(&commands).unwrap();
+        assert!(
+            note.starts_with("Your install of Fish Shell completed after the session dropped: failed with"),
+            "{}",
+            note
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn one_shot_runs_leave_the_state_file_alone() {
+        let option = InstallOption {
+            id: "font-cache".to_string(),
+            name: "Font Cache".to_string(),
+            track_state: Some(false),
+            targets: vec!["~/.cache/fontconfig".to_string()],
+            requires_reboot: true,
+            ..InstallOption::default()
+        };
+        let (mut app, written, dir) = running("one-shot", "true", option);
+        let commands = lose_terminal(&mut app, written);
+
+        assert!(!app.state.installed.contains("font-cache"));
+        assert!(app.state.records.contains_key("font-cache"), "the session still remembers the run");
+        assert!(app.state.pending_reboot.is_empty());
+        let stateful: Vec<_> = commands
+            .iter()
+            .filter(|command| {
+                matches!(
+                    command,
+                    PersistCommand::Record { .. }
+                        | PersistCommand::HashTargets { .. }
+                        | PersistCommand::PendingReboot(_)
+                )
+            })
+            .collect();
+        assert!(stateful.is_empty(), "{} state write(s) for a one-shot run", stateful.len());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,206 @@
+//! Results and exit codes of headless batch runs
+//!
+//! A headless invocation runs one or more actions in order. Its process exit
+//! code and the summary printed to stderr are derived from the per-action
+//! results here, so scripts get the same answer whatever produced the batch.
+
+use std::fmt::Write;
+
+use crate::installer::Completion;
+
+/// Every action succeeded (or there was nothing to do)
+pub const EXIT_OK: i32 = 0;
+/// At least one action failed
+pub const EXIT_FAILED: i32 = 1;
+/// Bad arguments, unknown option or unusable manifest
+pub const EXIT_USAGE: i32 = 2;
+/// Nothing ran: every action was blocked by a precondition
+pub const EXIT_BLOCKED: i32 = 3;
+
+/// How one action of a batch ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Succeeded,
+    /// Ran and failed, with a short reason
+    Failed(String),
+    /// Never started, with the precondition that stopped it
+    Blocked(String),
+}
+
+impl Outcome {
+    /// How a run that ended with `completion` went
+    ///
+    /// Verification gates success: an install that exited 0 but whose verify
+    /// command failed has failed too. A failed run never gets verified.
+    pub fn of_run(completion: Completion, verify_failure: Option<&str>) -> Self {
+        match verify_failure {
+            _ if !completion.success() => Outcome::Failed(completion.to_string()),
+            Some(reason) => Outcome::Failed(format!("verification failed: {}", reason)),
+            None => Outcome::Succeeded,
+        }
+    }
+}
+
+/// Result of one action in a batch
+#[derive(Debug, Clone)]
+pub struct BatchResult {
+    pub id: String,
+    pub name: String,
+    pub outcome: Outcome,
+}
+
+/// Process exit code for a finished batch
+pub fn exit_code(results: &[BatchResult]) -> i32 {
+    let blocked = results
+        .iter()
+        .filter(|r| matches!(r.outcome, Outcome::Blocked(_)))
+        .count();
+    if !results.is_empty() && blocked == results.len() {
+        EXIT_BLOCKED
+    } else if blocked > 0 || results.iter().any(|r| matches!(r.outcome, Outcome::Failed(_))) {
+        EXIT_FAILED
+    } else {
+        EXIT_OK
+    }
+}
+
+/// Plain-text table of every action and its result
+pub fn summary(results: &[BatchResult]) -> String {
+    let id_width = results.iter().map(|r| r.id.len()).max().unwrap_or(0);
+    let name_width = results.iter().map(|r| r.name.len()).max().unwrap_or(0);
+
+    let mut out = String::from("Summary:\n");
+    for result in results {
+        let outcome = match &result.outcome {
+            Outcome::Succeeded => "ok".to_string(),
+            Outcome::Failed(reason) => format!("failed ({})", reason),
+            Outcome::Blocked(reason) => format!("blocked ({})", reason),
+        };
+        let _ = writeln!(
+            out,
+            "  {:<id_width$}  {:<name_width$}  {}",
+            result.id, result.name, outcome
+        );
+    }
+
+    let count = |f: fn(&Outcome) -> bool| results.iter().filter(|r| f(&r.outcome)).count();
+    let _ = write!(
+        out,
+        "{} succeeded, {} failed, {} blocked",
+        count(|o| *o == Outcome::Succeeded),
+        count(|o| matches!(o, Outcome::Failed(_))),
+        count(|o| matches!(o, Outcome::Blocked(_))),
+    );
+    out
+}
+
+/// Exit code section of the usage text
+pub fn exit_code_help() -> String {
+    format!(
+        "Exit codes:
+  {EXIT_OK}  every action succeeded
+  {EXIT_FAILED}  at least one action failed
+  {EXIT_USAGE}  usage error, unknown option or invalid manifest
+  {EXIT_BLOCKED}  nothing ran because preconditions blocked every action"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(id: &str, outcome: Outcome) -> BatchResult {
+        BatchResult {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            outcome,
+        }
+    }
+
+    fn ok(id: &str) -> BatchResult {
+        result(id, Outcome::Succeeded)
+    }
+
+    fn failed(id: &str) -> BatchResult {
+        result(id, Outcome::Failed("exit code 1".to_string()))
+    }
+
+    fn blocked(id: &str) -> BatchResult {
+        result(id, Outcome::Blocked("files modified".to_string()))
+    }
+
+    #[test]
+    fn an_empty_batch_succeeds() {
+        assert_eq!(exit_code(&[]), EXIT_OK);
+        assert_eq!(summary(&[]), "Summary:\n0 succeeded, 0 failed, 0 blocked");
+    }
+
+    #[test]
+    fn all_succeeded_is_ok() {
+        assert_eq!(exit_code(&[ok("a"), ok("b")]), EXIT_OK);
+    }
+
+    #[test]
+    fn any_failure_fails_the_batch() {
+        assert_eq!(exit_code(&[failed("a")]), EXIT_FAILED);
+        assert_eq!(exit_code(&[ok("a"), failed("b"), ok("c")]), EXIT_FAILED);
+    }
+
+    #[test]
+    fn only_blocked_actions_mean_nothing_ran() {
+        assert_eq!(exit_code(&[blocked("a")]), EXIT_BLOCKED);
+        assert_eq!(exit_code(&[blocked("a"), blocked("b")]), EXIT_BLOCKED);
+    }
+
+    #[test]
+    fn a_partly_blocked_batch_fails() {
+        assert_eq!(exit_code(&[blocked("a"), ok("b")]), EXIT_FAILED);
+        assert_eq!(exit_code(&[ok("a"), blocked("b")]), EXIT_FAILED);
+    }
+
+    #[test]
+    fn failures_and_blocks_together_fail() {
+        assert_eq!(exit_code(&[failed("a"), blocked("b")]), EXIT_FAILED);
+        assert_eq!(exit_code(&[blocked("a"), failed("b"), blocked("c")]), EXIT_FAILED);
+    }
+
+    #[test]
+    fn summary_lists_every_action_aligned() {
+        let results = [ok("fish"), failed("neovim"), blocked("hypr")];
+        let text = summary(&results);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "Summary:",
+                "  fish    FISH    ok",
+                "  neovim  NEOVIM  failed (exit code 1)",
+                "  hypr    HYPR    blocked (files modified)",
+                "1 succeeded, 1 failed, 1 blocked",
+            ]
+        );
+    }
+
+    #[test]
+    fn verification_gates_a_run_s_success() {
+        let ok = Completion::Exited(0);
+        let failed = Completion::Exited(2);
+        assert_eq!(Outcome::of_run(ok, None), Outcome::Succeeded);
+        assert_eq!(
+            Outcome::of_run(ok, Some("`fish --version` exited with code 127")),
+            Outcome::Failed("verification failed: `fish --version` exited with code 127".to_string())
+        );
+        // A failure stays the script's, whatever verification would have said
+        assert_eq!(Outcome::of_run(failed, None), Outcome::Failed("exit code 2".to_string()));
+        assert_eq!(Outcome::of_run(failed, Some("not run")), Outcome::Failed("exit code 2".to_string()));
+        assert_eq!(Outcome::of_run(Completion::TimedOut, None), Outcome::Failed(Completion::TimedOut.to_string()));
+    }
+
+    #[test]
+    fn exit_code_help_lists_every_code() {
+        let help = exit_code_help();
+        for code in [EXIT_OK, EXIT_FAILED, EXIT_USAGE, EXIT_BLOCKED] {
+            assert!(help.contains(&format!("  {}  ", code)), "{}", help);
+        }
+    }
+}
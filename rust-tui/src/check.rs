@@ -0,0 +1,159 @@
+//! Bounded runner for short check commands
+//!
+//! Checks (such as post-install verification, or asking the install script
+//! whether an option is present) should finish quickly. They run in their own process group with their
+//! output collected, and are killed if they overrun their timeout.
+//! Post-install hooks run the same way, with their output passed on as it
+//! arrives.
+
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the child is polled for exit
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Output lines kept from a check
+const MAX_OUTPUT_LINES: usize = 200;
+
+/// How a check ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckStatus {
+    Passed,
+    /// Exited non-zero (or by signal, as -1)
+    Failed(i32),
+    TimedOut,
+    /// Couldn't be started
+    Error(String),
+}
+
+/// Result of running a check
+#[derive(Debug, Clone)]
+pub struct CheckOutcome {
+    pub status: CheckStatus,
+    /// Combined stdout and stderr, capped at `MAX_OUTPUT_LINES`
+    pub output: Vec<String>,
+}
+
+impl CheckOutcome {
+    /// One-line description of a failed check, `None` if it passed
+    pub fn failure(&self) -> Option<String> {
+        match &self.status {
+            CheckStatus::Passed => None,
+            CheckStatus::Failed(code) => Some(format!("exited with code {}", code)),
+            CheckStatus::TimedOut => Some("timed out".to_string()),
+            CheckStatus::Error(e) => Some(format!("could not run: {}", e)),
+        }
+    }
+}
+
+/// Forward each line of a pipe to `tx`
+///
+/// Lines are read as bytes, so output that isn't UTF-8 shows up with
+/// replacement characters instead of ending the stream (and leaving the
+/// check to block on a full pipe).
+fn collect_lines<R: Read + Send + 'static>(pipe: R, tx: Sender<String>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let text = String::from_utf8_lossy(&line);
+            if tx.send(text.trim_end_matches(['\n', '\r']).to_string()).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Run `command` with `sh -c` in `cwd`, killing it after `timeout`
+pub fn run_check(command: &str, cwd: &Path, timeout: Duration) -> CheckOutcome {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command).current_dir(cwd);
+    run_bounded(cmd, timeout, |_| {})
+}
+
+/// Run `command` like [`run_check`], handing each output line to `on_line`
+/// as soon as it is printed
+pub fn run_check_streaming(command: &str, cwd: &Path, timeout: Duration, on_line: impl FnMut(&str)) -> CheckOutcome {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command).current_dir(cwd);
+    run_bounded(cmd, timeout, on_line)
+}
+
+/// Run `program` with `args` in `cwd`, killing it after `timeout`
+pub fn run_program(program: &Path, args: &[&str], cwd: &Path, timeout: Duration) -> CheckOutcome {
+    let mut cmd = Command::new(program);
+    cmd.args(args).current_dir(cwd);
+    run_bounded(cmd, timeout, |_| {})
+}
+
+/// Run a prepared command in its own process group, collecting its output
+/// and passing each line to `on_line` as it arrives
+fn run_bounded(mut cmd: Command, timeout: Duration, mut on_line: impl FnMut(&str)) -> CheckOutcome {
+    let spawned = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            return CheckOutcome {
+                status: CheckStatus::Error(e.to_string()),
+                output: Vec::new(),
+            }
+        }
+    };
+
+    let (tx, rx) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        collect_lines(stdout, tx.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        collect_lines(stderr, tx);
+    }
+
+    let mut output = Vec::new();
+    let mut keep = |line: String| {
+        on_line(&line);
+        if output.len() < MAX_OUTPUT_LINES {
+            output.push(line);
+        }
+    };
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        rx.try_iter().for_each(&mut keep);
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break CheckStatus::Passed,
+            Ok(Some(status)) => break CheckStatus::Failed(status.code().unwrap_or(-1)),
+            Ok(None) if Instant::now() >= deadline => {
+                // SAFETY: kill(2) has no memory-safety requirements; a negative pid targets the group
+                unsafe {
+                    libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+                }
+                let _ = child.wait();
+                break CheckStatus::TimedOut;
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => break CheckStatus::Error(e.to_string()),
+        }
+    };
+
+    // Take what is left; a lingering grandchild mustn't hold us up
+    while let Ok(line) = rx.recv_timeout(POLL_INTERVAL) {
+        keep(line);
+    }
+
+    CheckOutcome { status, output }
+}
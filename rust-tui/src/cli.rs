@@ -0,0 +1,274 @@
+//! Command-line argument parsing
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::batch::exit_code_help;
+use crate::theme::Flavor;
+
+/// What the binary should do
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliCommand {
+    /// Launch the interactive TUI
+    Tui,
+    /// Install an option without the TUI
+    Install(String),
+    /// Uninstall an option without the TUI
+    Uninstall(String),
+    /// Install every option that isn't installed yet, without the TUI
+    InstallAll,
+    /// Print every option with its installed status
+    List(ListFormat),
+    /// Write a script reinstalling the installed options, replacing an
+    /// existing file only when forced
+    ExportScript { path: PathBuf, force: bool },
+    /// Print usage
+    Help,
+}
+
+/// How `--list` prints the options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    /// Aligned columns for people
+    Table,
+    /// A pretty-printed JSON array
+    Json,
+    /// A JSON array on one line
+    CompactJson,
+}
+
+/// How headless runs report on stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunFormat {
+    /// Script output as it comes, warnings and the summary on stderr
+    Text,
+    /// One JSON event per line (see event_stream.rs)
+    Events,
+}
+
+/// Parsed command-line arguments
+#[derive(Debug)]
+pub struct CliArgs {
+    /// Script directory (positional), defaulting to the current directory
+    pub script_dir: Option<PathBuf>,
+    /// Entry script overriding the probed `install`/`install.sh`
+    pub entry: Option<PathBuf>,
+    /// Requested command
+    pub command: CliCommand,
+    /// Run installs even when their declared requirements aren't met
+    pub skip_preflight: bool,
+    /// Color flavor overriding the config file and terminal background
+    pub theme: Option<Flavor>,
+    /// Draw with ASCII symbols and no colors
+    pub plain: bool,
+    /// How --install, --install-all and --uninstall report
+    pub run_format: RunFormat,
+    /// Run even with root's effective uid
+    pub allow_root: bool,
+    /// Set NONINTERACTIVE=1 for every script
+    pub noninteractive: bool,
+    /// Offer editing the installed state without running scripts
+    pub expert: bool,
+}
+
+/// Parse arguments (excluding the program name)
+///
+/// Arguments are taken as OS strings so a script directory that isn't valid
+/// UTF-8 still works; option ids and flags must be UTF-8.
+pub fn parse_args(args: impl IntoIterator<Item = OsString>) -> Result<CliArgs, String> {
+    let mut script_dir = None;
+    let mut entry = None;
+    let mut command = CliCommand::Tui;
+    let (mut json, mut compact) = (false, false);
+    let mut skip_preflight = false;
+    let mut theme = None;
+    let mut plain = false;
+    let mut force = false;
+    let mut run_format = RunFormat::Text;
+    let mut allow_root = false;
+    let mut noninteractive = false;
+    let mut expert = false;
+    let mut args = args.into_iter();
+
+    while let Some(raw) = args.next() {
+        let Some(arg) = raw.to_str() else {
+            if script_dir.is_none() {
+                script_dir = Some(PathBuf::from(raw));
+                continue;
+            }
+            return Err(format!("unexpected argument '{}'", raw.to_string_lossy()));
+        };
+        match arg {
+            "--install" | "--uninstall" => {
+                let id = args
+                    .next()
+                    .and_then(|id| id.into_string().ok())
+                    .filter(|id| !id.starts_with("--"))
+                    .ok_or_else(|| format!("{} requires an option id", arg))?;
+                let new = if arg == "--install" {
+                    CliCommand::Install(id)
+                } else {
+                    CliCommand::Uninstall(id)
+                };
+                set_command(&mut command, new)?;
+            }
+            "--entry" => {
+                let path = args.next().ok_or("--entry requires a path")?;
+                entry = Some(PathBuf::from(path));
+            }
+            "--export-script" => {
+                let path = args.next().ok_or("--export-script requires a path")?;
+                set_command(&mut command, CliCommand::ExportScript {
+                    path: PathBuf::from(path),
+                    force: false,
+                })?;
+            }
+            "--force" => force = true,
+            "--install-all" => set_command(&mut command, CliCommand::InstallAll)?,
+            "--list" => set_command(&mut command, CliCommand::List(ListFormat::Table))?,
+            "--json" => json = true,
+            "--compact" => compact = true,
+            "--skip-preflight" => skip_preflight = true,
+            "--plain" => plain = true,
+            "--allow-root" => allow_root = true,
+            "--noninteractive" => noninteractive = true,
+            "--expert" => expert = true,
+            "--theme" => {
+                let name = args
+                    .next()
+                    .and_then(|name| name.into_string().ok())
+                    .ok_or("--theme requires a flavor")?;
+                let flavor = Flavor::parse(&name)
+                    .ok_or_else(|| format!("unknown theme '{}' (expected {})", name, Flavor::NAMES))?;
+                theme = Some(flavor);
+            }
+            "-h" | "--help" => command = CliCommand::Help,
+            flag if flag.starts_with('-') => return Err(format!("unknown flag '{}'", flag)),
+            _ if script_dir.is_none() => script_dir = Some(PathBuf::from(raw)),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    // --json shapes --list and headless runs, --compact only --list
+    let runs = matches!(command, CliCommand::Install(_) | CliCommand::InstallAll | CliCommand::Uninstall(_));
+    match (&mut command, json, compact) {
+        (CliCommand::List(format), true, _) => {
+            *format = if compact { ListFormat::CompactJson } else { ListFormat::Json };
+        }
+        (CliCommand::Help, _, _) => {}
+        (_, true, false) if runs => run_format = RunFormat::Events,
+        (_, true, _) if runs => return Err("--compact requires --list --json".to_string()),
+        (_, true, _) => return Err("--json requires --list, --install, --install-all or --uninstall".to_string()),
+        (_, false, true) => return Err("--compact requires --list --json".to_string()),
+        _ => {}
+    }
+
+    // --force only lets --export-script replace a file
+    match &mut command {
+        CliCommand::ExportScript { force: forced, .. } => *forced = force,
+        CliCommand::Help => {}
+        _ if force => return Err("--force requires --export-script".to_string()),
+        _ => {}
+    }
+
+    Ok(CliArgs {
+        script_dir,
+        entry,
+        command,
+        skip_preflight,
+        theme,
+        plain,
+        run_format,
+        allow_root,
+        noninteractive,
+        expert,
+    })
+}
+
+/// Set the command, rejecting a second conflicting one
+fn set_command(current: &mut CliCommand, new: CliCommand) -> Result<(), String> {
+    if *current != CliCommand::Tui {
+        return Err("only one of --install, --install-all, --uninstall, --list and --export-script may be given".to_string());
+    }
+    *current = new;
+    Ok(())
+}
+
+/// Usage text shown for --help and argument errors
+pub fn usage(program: &str) -> String {
+    format!(
+        "Usage: {program} [script_dir] [--entry <path>] [--skip-preflight] [--theme <flavor>] [--plain] [--allow-root] [--noninteractive] [--expert] [--install <id> [--json] | --install-all [--json] | --uninstall <id> [--json] | --list [--json [--compact]] | --export-script <path> [--force]]
+
+Without flags the interactive installer is launched. Headless runs stream
+script output to stdout and print a summary to stderr; with --json, stdout
+carries one JSON event per line instead, ending each run with a completed
+or error event.
+
+Options:
+  --install <id>     Install an option (and its dependencies) without the TUI
+  --install-all      Install every option that isn't installed yet
+  --uninstall <id>   Uninstall an option without the TUI
+  --list             List options and whether they are installed
+  --json             With --list, print a JSON array for scripts (e.g. jq); with
+                     a headless run, print newline-delimited JSON events
+  --compact          With --list --json, print the array on one line
+  --export-script <path>
+                     Write a bash script that reinstalls the installed options
+                     in dependency order, e.g. on another machine
+  --force            With --export-script, replace an existing file
+  --entry <path>     Script to run, relative to script_dir (default: install or install.sh)
+  --skip-preflight   Run installs even if required commands, disk space or
+                     network are missing
+  --theme <flavor>   Colors: latte, frappe, macchiato or mocha (default: latte
+                     on a light terminal, mocha otherwise; or config.toml's theme)
+  --plain            Draw with ASCII symbols and no colors, for terminals that
+                     garble Unicode (NO_COLOR alone turns off just the colors)
+  --allow-root       Run as root anyway; scripts then leave root-owned files in
+                     root's or the invoking user's config
+  --noninteractive   Set NONINTERACTIVE=1 for the scripts; config.toml's [env]
+                     table and the manifest's env keys set other variables
+  --expert           Allow marking options installed or not without running
+                     anything (m); config.toml's expert = true does the same
+  -h, --help         Show this help
+
+{}",
+        exit_code_help()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    use super::*;
+
+    fn os_args(args: &[&[u8]]) -> Vec<OsString> {
+        args.iter().map(|arg| OsStr::from_bytes(arg).to_os_string()).collect()
+    }
+
+    #[test]
+    fn non_utf8_script_dir_is_kept_byte_for_byte() {
+        let args = parse_args(os_args(&[b"--list", b"/srv/cybex-\xff"])).unwrap();
+        assert_eq!(args.script_dir, Some(PathBuf::from(OsStr::from_bytes(b"/srv/cybex-\xff"))));
+        assert_eq!(args.command, CliCommand::List(ListFormat::Table));
+    }
+
+    #[test]
+    fn non_utf8_option_ids_and_extra_arguments_are_rejected() {
+        let error = parse_args(os_args(&[b"--install", b"caf\xe9"])).unwrap_err();
+        assert_eq!(error, "--install requires an option id");
+
+        let error = parse_args(os_args(&[b"/srv/cybex", b"stray-\xff"])).unwrap_err();
+        assert_eq!(error, "unexpected argument 'stray-\u{fffd}'");
+    }
+
+    #[test]
+    fn skip_preflight_goes_with_any_command() {
+        let args = parse_args(os_args(&[b"--skip-preflight", b"--install", b"fish"])).unwrap();
+        assert!(args.skip_preflight);
+        assert_eq!(args.command, CliCommand::Install("fish".to_string()));
+
+        assert!(!parse_args(os_args(&[b"--install", b"fish"])).unwrap().skip_preflight);
+    }
+}
@@ -0,0 +1,116 @@
+//! The `:` command line
+//!
+//! ```text
+//! :install <id>     install an option, through the usual confirmations
+//! :uninstall <id>   uninstall an option, after confirming
+//! :log <id>         show the option's newest run log in the output panel
+//! :quit
+//! :<action>         any keymap action name, e.g. :check-installed or :help
+//! ```
+//!
+//! Tab completes command names, action names and option ids.
+
+use crate::keymap::Action;
+use crate::options::InstallOption;
+
+/// Commands of their own, ahead of the action names
+const COMMANDS: [&str; 4] = ["install", "uninstall", "log", "quit"];
+
+/// A parsed command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Install(String),
+    Uninstall(String),
+    Log(String),
+    Quit,
+    /// A keymap action, run as if its key had been pressed
+    Action(Action),
+}
+
+/// Whether a command takes an option id
+fn takes_id(name: &str) -> bool {
+    matches!(name, "install" | "uninstall" | "log")
+}
+
+/// Parse a command line (without the `:`)
+pub fn parse(input: &str, options: &[InstallOption]) -> Result<Command, String> {
+    let mut words = input.split_whitespace();
+    let Some(name) = words.next() else {
+        return Err("no command given".to_string());
+    };
+    let argument = words.next();
+    if words.next().is_some() {
+        return Err(format!("{} takes at most one argument", name));
+    }
+
+    if takes_id(name) {
+        let Some(id) = argument else {
+            return Err(format!("{} needs an option id", name));
+        };
+        if !options.iter().any(|o| o.id == id) {
+            return Err(format!("no option with id '{}'", id));
+        }
+        let id = id.to_string();
+        return Ok(match name {
+            "install" => Command::Install(id),
+            "uninstall" => Command::Uninstall(id),
+            _ => Command::Log(id),
+        });
+    }
+    if argument.is_some() {
+        return Err(format!("{} takes no argument", name));
+    }
+    if name == "quit" {
+        return Ok(Command::Quit);
+    }
+    Action::ALL
+        .iter()
+        .find(|action| action.name() == name)
+        .map(|&action| Command::Action(action))
+        .ok_or_else(|| format!("unknown command '{}' - Tab lists the commands", name))
+}
+
+/// Complete the word being typed, or `None` if nothing matches
+///
+/// Completes as far as all candidates agree; a single match is finished
+/// with a space when an option id can follow.
+pub fn complete(input: &str, options: &[InstallOption]) -> Option<String> {
+    let (head, word, candidates): (&str, &str, Vec<&str>) = match input.split_once(' ') {
+        None => {
+            let names = COMMANDS.iter().copied().chain(Action::ALL.iter().map(|a| a.name()));
+            ("", input, names.collect())
+        }
+        Some((name, rest)) if takes_id(name) && !rest.contains(' ') => {
+            (&input[..name.len() + 1], rest, options.iter().map(|o| o.id.as_str()).collect())
+        }
+        Some(_) => return None,
+    };
+
+    let mut matching = candidates.into_iter().filter(|c| c.starts_with(word));
+    let first = matching.next()?;
+    let mut common = first.len();
+    let mut several = false;
+    for other in matching {
+        several = true;
+        common = first
+            .bytes()
+            .zip(other.bytes())
+            .take(common)
+            .take_while(|(a, b)| a == b)
+            .count();
+    }
+
+    while !first.is_char_boundary(common) {
+        common -= 1;
+    }
+    let mut completed = format!("{}{}", head, &first[..common]);
+    if !several && head.is_empty() && takes_id(first) {
+        completed.push(' ');
+    }
+    Some(completed)
+}
+
+/// Names Tab can complete to, for the hint when nothing was typed yet
+pub fn command_names() -> &'static [&'static str] {
+    &COMMANDS
+}
@@ -0,0 +1,346 @@
+//! Omarchy base version and per-option compatibility ranges
+//!
+//! Customizations target particular Omarchy releases. The installed base
+//! version is probed best effort at startup; options may declare the range
+//! they work with (`omarchy = ">=2.0, <3.0"` in the manifest). When the base
+//! version can't be determined the checks are skipped rather than guessed.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+
+/// A dotted version number, compared component by component
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    parts: Vec<u64>,
+}
+
+impl Version {
+    /// Parse "2.1.0", "v2.1" or a pacman version like "1:2.1.0-3"
+    ///
+    /// The epoch and anything after `-` or `+` (package release, build
+    /// metadata) are dropped, as are suffixes like "rc1" on a component.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text.split_once(':').map_or(text, |(_, rest)| rest);
+        let text = text.strip_prefix(['v', 'V']).unwrap_or(text);
+        let text = text.split(['-', '+']).next().unwrap_or("");
+
+        let mut parts = Vec::new();
+        for component in text.split('.') {
+            let digits: String = component.chars().take_while(char::is_ascii_digit).collect();
+            let Ok(number) = digits.parse() else {
+                break;
+            };
+            parts.push(number);
+            if digits.len() < component.len() {
+                break;
+            }
+        }
+        (!parts.is_empty()).then_some(Self { parts })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Missing components count as zero, so 2.0 == 2.0.0
+        let len = self.parts.len().max(other.parts.len());
+        (0..len)
+            .map(|i| {
+                let a = self.parts.get(i).copied().unwrap_or(0);
+                let b = other.parts.get(i).copied().unwrap_or(0);
+                a.cmp(&b)
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.parts.iter().map(u64::to_string).collect();
+        write!(f, "{}", parts.join("."))
+    }
+}
+
+/// Comparison in a range bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Ge,
+    Gt,
+    Le,
+    Lt,
+    Eq,
+}
+
+/// A set of bounds that must all hold, e.g. "≥2.0, <3.0"
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct VersionRange {
+    text: String,
+    bounds: Vec<(Op, Version)>,
+}
+
+impl VersionRange {
+    /// Parse comma-separated bounds using >=, ≥, >, <=, ≤, <, = or ==
+    ///
+    /// A bare version means exactly that version.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut bounds = Vec::new();
+        for bound in text.split(',').map(str::trim) {
+            if bound.is_empty() {
+                return Err(format!("empty bound in {:?}", text));
+            }
+            let (op, version) = [
+                (">=", Op::Ge),
+                ("≥", Op::Ge),
+                ("<=", Op::Le),
+                ("≤", Op::Le),
+                ("==", Op::Eq),
+                (">", Op::Gt),
+                ("<", Op::Lt),
+                ("=", Op::Eq),
+            ]
+            .iter()
+            .find_map(|(prefix, op)| bound.strip_prefix(prefix).map(|rest| (*op, rest)))
+            .unwrap_or((Op::Eq, bound));
+            let version = Version::parse(version)
+                .ok_or_else(|| format!("invalid version {:?} in {:?}", version.trim(), text))?;
+            bounds.push((op, version));
+        }
+        Ok(Self {
+            text: text.trim().to_string(),
+            bounds,
+        })
+    }
+
+    /// Whether `version` satisfies every bound
+    pub fn contains(&self, version: &Version) -> bool {
+        self.bounds.iter().all(|(op, bound)| {
+            let ordering = version.cmp(bound);
+            match op {
+                Op::Ge => ordering.is_ge(),
+                Op::Gt => ordering.is_gt(),
+                Op::Le => ordering.is_le(),
+                Op::Lt => ordering.is_lt(),
+                Op::Eq => ordering.is_eq(),
+            }
+        })
+    }
+}
+
+impl TryFrom<String> for VersionRange {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        Self::parse(&text)
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Find the installed Omarchy version, trying each known source in turn
+///
+/// Returns `None` when none of them yields a version.
+pub fn probe_base_version() -> Option<Version> {
+    probe_version_in(&dirs::home_dir()?.join(".local/share/omarchy"))
+}
+
+/// The version of an Omarchy installed to `omarchy_dir`
+fn probe_version_in(omarchy_dir: &Path) -> Option<Version> {
+    let from_file = || {
+        fs::read_to_string(omarchy_dir.join("version"))
+            .ok()
+            .and_then(|text| Version::parse(&text))
+    };
+    // Prints "omarchy 2.1.0-1"
+    let from_pacman = || {
+        command_output("pacman", &["-Q", "omarchy"])
+            .and_then(|out| out.split_whitespace().nth(1).and_then(Version::parse))
+    };
+    // A git checkout, as the upstream installer leaves it
+    let from_git = || {
+        let dir = omarchy_dir.to_string_lossy();
+        command_output("git", &["-C", &dir, "describe", "--tags", "--abbrev=0"])
+            .and_then(|tag| Version::parse(&tag))
+    };
+    from_file().or_else(from_pacman).or_else(from_git)
+}
+
+/// Stdout of a successful command, if it could be run
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Why an option doesn't fit the base version, if it doesn't
+///
+/// Nothing is reported when the version is unknown or the option declares no range.
+pub fn mismatch(range: Option<&VersionRange>, base: Option<&Version>) -> Option<String> {
+    let (range, base) = (range?, base?);
+    (!range.contains(base)).then(|| format!("targets Omarchy {}, this system runs {}", range, base))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    fn range(text: &str) -> VersionRange {
+        VersionRange::parse(text).unwrap()
+    }
+
+    #[test]
+    fn versions_parse_from_tags_and_package_versions() {
+        for (text, parsed) in [
+            ("2.1.0", "2.1.0"),
+            ("v2.1", "2.1"),
+            ("V3", "3"),
+            ("1:2.1.0-3", "2.1.0"),
+            ("2.1.0+build.7", "2.1.0"),
+            ("2.1rc1.4", "2.1"),
+            (" 2.0\n", "2.0"),
+        ] {
+            assert_eq!(version(text).to_string(), parsed, "{}", text);
+        }
+        for text in ["", "omarchy", "v", "-2.0", "rc1"] {
+            assert_eq!(Version::parse(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn missing_components_count_as_zero() {
+        assert_eq!(version("2.0").cmp(&version("2.0.0")), Ordering::Equal);
+        assert!(version("2.0.1") > version("2"));
+        assert!(version("2.10") > version("2.9"));
+    }
+
+    #[test]
+    fn ranges_hold_every_bound() {
+        let major = range(">=2.0, <3.0");
+        for (base, inside) in [("1.9.9", false), ("2.0", true), ("2.9.9", true), ("3.0", false), ("3.0.1", false)] {
+            assert_eq!(major.contains(&version(base)), inside, "{} in {}", base, major);
+        }
+        assert_eq!(major.to_string(), ">=2.0, <3.0");
+
+        let unicode = range("≥ 2.1,≤2.3");
+        assert!(unicode.contains(&version("2.3.0")));
+        assert!(!unicode.contains(&version("2.0")));
+
+        for exact in ["2.1", "=2.1", "==2.1"] {
+            assert!(range(exact).contains(&version("2.1.0")), "{}", exact);
+            assert!(!range(exact).contains(&version("2.1.1")), "{}", exact);
+        }
+        assert!(range(">2.1").contains(&version("2.1.1")));
+        assert!(!range(">2.1").contains(&version("2.1")));
+    }
+
+    #[test]
+    fn malformed_ranges_say_what_is_wrong() {
+        assert_eq!(VersionRange::parse("").unwrap_err(), "empty bound in \"\"");
+        assert_eq!(VersionRange::parse(">=2.0,").unwrap_err(), "empty bound in \">=2.0,\"");
+        assert_eq!(
+            VersionRange::parse(">=2.0, <three").unwrap_err(),
+            "invalid version \"three\" in \">=2.0, <three\""
+        );
+        assert_eq!(VersionRange::parse("~2.0").unwrap_err(), "invalid version \"~2.0\" in \"~2.0\"");
+    }
+
+    #[test]
+    fn ranges_deserialize_from_manifest_strings() {
+        #[derive(Deserialize)]
+        struct Entry {
+            omarchy: VersionRange,
+        }
+        let option: Entry = toml::from_str("omarchy = \">=2.0, <3.0\"").unwrap();
+        assert_eq!(option.omarchy, range(">=2.0, <3.0"));
+        let error = toml::from_str::<Entry>("omarchy = \">=two\"").err().unwrap();
+        assert!(error.to_string().contains("invalid version \"two\""), "{}", error);
+    }
+
+    #[test]
+    fn mismatch_needs_both_a_range_and_a_version() {
+        let major = range(">=2.0, <3.0");
+        assert_eq!(
+            mismatch(Some(&major), Some(&version("3.1"))).as_deref(),
+            Some("targets Omarchy >=2.0, <3.0, this system runs 3.1")
+        );
+        assert_eq!(mismatch(Some(&major), Some(&version("2.4"))), None);
+        assert_eq!(mismatch(Some(&major), None), None, "an unknown version is never a mismatch");
+        assert_eq!(mismatch(None, Some(&version("3.1"))), None);
+    }
+
+    /// Empty scratch directory for one test
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("omarchy-cybex-compat-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn version_file_is_probed_first() {
+        let dir = scratch_dir("file");
+        fs::write(dir.join("version"), "2.3.1\n").unwrap();
+        assert_eq!(probe_version_in(&dir), Some(version("2.3.1")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn git_tag_is_probed_without_a_version_file() {
+        // An installed omarchy package would answer before git does
+        if command_output("pacman", &["-Q", "omarchy"]).is_some() {
+            return;
+        }
+        let dir = scratch_dir("git");
+        let path = dir.to_string_lossy();
+        let git = |args: &[&str]| {
+            let identity = ["-c", "user.name=test", "-c", "user.email=test@example.com", "-C", &path];
+            command_output("git", &[&identity[..], args].concat())
+        };
+        if git(&["init", "-q"]).is_none() {
+            return; // git isn't installed here
+        }
+        git(&["commit", "-q", "--allow-empty", "-m", "release"]).unwrap();
+        git(&["tag", "v2.2.0"]).unwrap();
+        assert_eq!(probe_version_in(&dir), Some(version("2.2.0")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn nothing_is_guessed_without_a_source() {
+        if command_output("pacman", &["-Q", "omarchy"]).is_some() {
+            return;
+        }
+        let dir = scratch_dir("empty");
+        fs::write(dir.join("version"), "unknown\n").unwrap();
+        assert_eq!(probe_version_in(&dir), None);
+        assert_eq!(probe_version_in(&dir.join("missing")), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,62 +1,1040 @@
 //! State persistence for tracking installed options
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::fs;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::installer::Completion;
+use crate::prefs::UiPrefs;
+use crate::run_id::RunId;
+use crate::timings::StageTimings;
 
 /// State file structure (compatible with Python TUI)
+///
+/// `installed` stays a flat list so older readers keep working; per-option
+/// details live alongside it in `records` and default to empty for legacy files.
 #[derive(Serialize, Deserialize, Default)]
 struct InstallerState {
     installed: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    records: BTreeMap<String, OptionRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pending_reboot: Option<PendingReboot>,
+    /// Id of the option selected when the TUI last saved it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_selected: Option<String>,
+    /// Interface preferences, kept loose so a broken section can't spoil the file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prefs: Option<serde_json::Value>,
 }
 
-/// Get the path to the state file
-fn state_file_path() -> PathBuf {
+/// Options installed since the last reboot that need one to take effect
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingReboot {
+    options: Vec<String>,
+    /// When the first of them was installed
+    since: DateTime<Utc>,
+}
+
+/// Kind of action performed on an option
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunAction {
+    Install,
+    Uninstall,
+}
+
+/// How an option's installed state was last set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Provenance {
+    /// An install or uninstall script ran and succeeded
+    Run,
+    /// Marked by hand, nothing was run
+    ManualMark,
+}
+
+/// Persisted history for a single option
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OptionRecord {
+    /// When the option was last installed successfully
+    pub installed_at: Option<DateTime<Utc>>,
+    /// When the last install/uninstall attempt finished
+    pub last_run_at: Option<DateTime<Utc>>,
+    /// Exit code of the last attempt; 128 plus the signal when one killed it
+    pub last_exit_code: Option<i32>,
+    /// Signal that killed the last attempt, when it didn't exit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_signal: Option<i32>,
+    /// Whether the last attempt was killed for running past its time limit
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub last_timed_out: bool,
+    /// What the last attempt did
+    pub last_action: Option<RunAction>,
+    /// Identifier of the last attempt
+    pub last_run_id: Option<RunId>,
+    /// Whether the last install succeeded but its verify command failed
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub verify_failed: bool,
+    /// Content hashes of the target files as the last install left them
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub target_hashes: BTreeMap<String, String>,
+    /// How long each stage of the last attempt took
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_timings: Option<StageTimings>,
+    /// Wall times of the latest successful installs, oldest first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub install_durations_ms: Vec<u64>,
+    /// Wall times of the latest successful uninstalls, oldest first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub uninstall_durations_ms: Vec<u64>,
+    /// How the installed state was last set; None on records from before this
+    /// was kept, which only runs wrote
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+/// Successful runs of each action the typical duration is averaged over
+const DURATION_SAMPLES: usize = 5;
+
+impl OptionRecord {
+    /// Whether the last attempt failed
+    pub fn last_failed(&self) -> bool {
+        self.last_exit_code.is_some_and(|code| code != 0)
+    }
+
+    /// How the last attempt ended
+    pub fn last_completion(&self) -> Option<Completion> {
+        match (self.last_timed_out, self.last_signal, self.last_exit_code) {
+            (true, _, _) => Some(Completion::TimedOut),
+            (false, Some(signal), _) => Some(Completion::Signaled(signal)),
+            (false, None, code) => code.map(Completion::Exited),
+        }
+    }
+
+    /// Whether the installed state was last set by hand rather than by a run
+    pub fn marked_by_hand(&self) -> bool {
+        self.provenance == Some(Provenance::ManualMark)
+    }
+
+    /// How long a successful run of `action` usually takes, from the latest ones
+    pub fn typical_duration(&self, action: RunAction) -> Option<Duration> {
+        let durations = match action {
+            RunAction::Install => &self.install_durations_ms,
+            RunAction::Uninstall => &self.uninstall_durations_ms,
+        };
+        let total: u64 = durations.iter().sum();
+        let count = durations.len() as u64;
+        (count > 0).then(|| Duration::from_millis(total / count))
+    }
+
+    /// Note an attempt that finished at `now`
+    ///
+    /// Only runs that succeeded, verification included, count towards the
+    /// typical duration; a failure that stops early would drag it down.
+    pub fn apply_run(
+        &mut self,
+        run_id: &RunId,
+        action: RunAction,
+        completion: Completion,
+        verify_failed: bool,
+        timings: Option<StageTimings>,
+        now: DateTime<Utc>,
+    ) {
+        self.last_run_at = Some(now);
+        self.last_exit_code = Some(completion.code());
+        self.last_signal = match completion {
+            Completion::Signaled(signal) => Some(signal),
+            Completion::Exited(_) | Completion::TimedOut => None,
+        };
+        self.last_timed_out = completion == Completion::TimedOut;
+        self.last_action = Some(action);
+        self.last_run_id = Some(run_id.clone());
+        self.verify_failed = verify_failed;
+        if completion.success() {
+            self.provenance = Some(Provenance::Run);
+            if action == RunAction::Install {
+                self.installed_at = Some(now);
+            }
+        }
+        if let Some(timings) = timings.filter(|_| completion.success() && !verify_failed) {
+            let durations = match action {
+                RunAction::Install => &mut self.install_durations_ms,
+                RunAction::Uninstall => &mut self.uninstall_durations_ms,
+            };
+            durations.push(timings.total_ms());
+            let excess = durations.len().saturating_sub(DURATION_SAMPLES);
+            durations.drain(..excess);
+        }
+        self.last_timings = timings;
+    }
+
+    /// Note that the option was marked installed or not by hand at `now`
+    ///
+    /// Nothing ran, so what the last run left behind no longer describes the
+    /// option and is dropped, target hashes included. The typical durations
+    /// stay; they still say how long the scripts take.
+    pub fn apply_mark(&mut self, installed: bool, now: DateTime<Utc>) {
+        *self = Self {
+            installed_at: installed.then_some(now),
+            install_durations_ms: std::mem::take(&mut self.install_durations_ms),
+            uninstall_durations_ms: std::mem::take(&mut self.uninstall_durations_ms),
+            provenance: Some(Provenance::ManualMark),
+            ..Self::default()
+        };
+    }
+}
+
+/// Get the directory holding the installer's state files
+pub fn state_dir() -> PathBuf {
     dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join("omarchy-cybex")
-        .join("installer-state.json")
+}
+
+/// Get the path to the state file
+pub fn state_file_path() -> PathBuf {
+    state_file_in(&state_dir())
+}
+
+/// The state file kept in `dir`
+fn state_file_in(dir: &Path) -> PathBuf {
+    dir.join("installer-state.json")
+}
+
+/// Get the path to the note left by runs that outlived their terminal
+pub fn detached_note_path() -> PathBuf {
+    state_dir().join("detached-result.txt")
+}
+
+/// Why the state file couldn't be read
+enum StateError {
+    /// Reading failed; the file may be fine and must not be overwritten
+    Unreadable(io::Error),
+    /// The contents aren't a state file
+    Corrupt(serde_json::Error),
+}
+
+/// Marker for a write that went through after setting a corrupt state file aside
+///
+/// The persister reports it as is rather than as a failed save.
+#[derive(Debug)]
+pub struct StateRecovered(String);
+
+impl fmt::Display for StateRecovered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for StateRecovered {}
+
+/// Exclusive advisory lock on the state file, held until dropped
+///
+/// Every read-modify-write takes it, so instances running side by side don't
+/// overwrite each other's updates. It lives on a separate file because the
+/// state file itself is replaced on every write.
+struct StateLock(File);
+
+impl StateLock {
+    /// Wait for the lock on the state file in `dir`
+    fn acquire(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join("installer-state.json.lock"))?;
+        // SAFETY: the descriptor stays open for as long as `file` lives
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self(file))
+    }
+}
+
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        // SAFETY: as above; closing the file would release it too
+        unsafe { libc::flock(self.0.as_raw_fd(), libc::LOCK_UN) };
+    }
+}
+
+/// Where a corrupt state file is moved so it can be inspected
+fn bad_state_path(dir: &Path) -> PathBuf {
+    dir.join("installer-state.json.bad")
+}
+
+/// Read the state file in `dir`; a missing one is empty
+fn read_state(dir: &Path) -> Result<InstallerState, StateError> {
+    match fs::read_to_string(state_file_in(dir)) {
+        Ok(contents) => serde_json::from_str(&contents).map_err(StateError::Corrupt),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(InstallerState::default()),
+        Err(e) => Err(StateError::Unreadable(e)),
+    }
+}
+
+/// Read the state file, treating a missing or unreadable file as empty
+///
+/// Writers go through `update_state` instead, which won't overwrite a file it
+/// couldn't read.
+fn load_state() -> InstallerState {
+    read_state(&state_dir()).unwrap_or_default()
+}
+
+/// Move a corrupt state file to `installer-state.json.bad`, describing what happened
+fn set_aside(dir: &Path, e: &serde_json::Error) -> String {
+    let path = state_file_in(dir);
+    match fs::rename(&path, bad_state_path(dir)) {
+        Ok(()) => format!(
+            "state file {} was corrupt ({}); moved it to {} and started with nothing marked installed",
+            path.display(),
+            e,
+            bad_state_path(dir).display()
+        ),
+        Err(rename_error) => format!(
+            "state file {} is corrupt ({}) and could not be moved aside: {}",
+            path.display(),
+            e,
+            rename_error
+        ),
+    }
+}
+
+/// Describe why an existing state file can't be used, if it can't
+///
+/// A corrupt file is moved to `installer-state.json.bad` first, so startup
+/// reports it once instead of every load quietly reading it as empty.
+pub fn state_file_problem() -> Option<String> {
+    let dir = state_dir();
+    let _lock = StateLock::acquire(&dir).ok();
+    match read_state(&dir) {
+        Ok(_) => None,
+        Err(StateError::Corrupt(e)) => Some(set_aside(&dir, &e)),
+        Err(StateError::Unreadable(e)) => Some(format!(
+            "state file {} is unreadable ({}); starting with nothing marked installed",
+            state_file_path().display(),
+            e
+        )),
+    }
+}
+
+/// Describe why the state directory can't be written to, if it can't
+///
+/// Saves write a temporary file next to the state file and rename it over,
+/// so creating (and removing) a file there is what has to work.
+pub fn state_dir_problem() -> Option<String> {
+    let dir = state_dir();
+    let probe = dir.join(format!(".write-test.{}", std::process::id()));
+    let written = fs::create_dir_all(&dir).and_then(|()| File::create(&probe).map(drop));
+    let _ = fs::remove_file(&probe);
+    written.err().map(|e| {
+        format!(
+            "state directory {} is not writable ({}); installed state won't be saved",
+            dir.display(),
+            e
+        )
+    })
+}
+
+/// Write the state file atomically: a temporary file in the same directory
+/// renamed over the original, so a crash mid-write leaves the old file intact
+fn save_state(dir: &Path, state: &InstallerState) -> Result<(), std::io::Error> {
+    let path = state_file_in(dir);
+    fs::create_dir_all(dir)?;
+
+    let temp = path.with_extension(format!("json.tmp.{}", std::process::id()));
+    let written = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(serde_json::to_string_pretty(state)?.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp, &path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Read, modify and write the state file under the lock
+///
+/// A corrupt file is set aside and the update applied to an empty state; the
+/// write then still returns a [`StateRecovered`] error to say so.
+fn update_state<T>(modify: impl FnOnce(&mut InstallerState) -> T) -> Result<T, std::io::Error> {
+    update_state_in(&state_dir(), modify)
+}
+
+/// `update_state` on the state file in `dir`
+fn update_state_in<T>(dir: &Path, modify: impl FnOnce(&mut InstallerState) -> T) -> Result<T, std::io::Error> {
+    let _lock = StateLock::acquire(dir)?;
+    let (mut state, recovered) = match read_state(dir) {
+        Ok(state) => (state, None),
+        Err(StateError::Corrupt(e)) => (InstallerState::default(), Some(set_aside(dir, &e))),
+        Err(StateError::Unreadable(e)) => return Err(e),
+    };
+    let value = modify(&mut state);
+    save_state(dir, &state)?;
+    match recovered {
+        Some(message) => Err(io::Error::other(StateRecovered(message))),
+        None => Ok(value),
+    }
 }
 
 /// Load installed option IDs from state file
 pub fn load_installed() -> HashSet<String> {
-    let path = state_file_path();
-    if path.exists() {
-        if let Ok(contents) = fs::read_to_string(&path) {
-            if let Ok(state) = serde_json::from_str::<InstallerState>(&contents) {
-                return state.installed.into_iter().collect();
-            }
+    load_state().installed.into_iter().collect()
+}
+
+/// Load per-option records from state file
+pub fn load_records() -> HashMap<String, OptionRecord> {
+    load_state().records.into_iter().collect()
+}
+
+/// Id of the option that was selected at the end of the last session
+pub fn load_last_selected() -> Option<String> {
+    load_state().last_selected
+}
+
+/// Remember the selected option for the next session
+pub fn save_last_selected(option_id: &str) -> Result<(), std::io::Error> {
+    update_state(|state| state.last_selected = Some(option_id.to_string()))
+}
+
+/// Interface preferences saved by the last session
+pub fn load_prefs() -> UiPrefs {
+    UiPrefs::from_value(load_state().prefs)
+}
+
+/// Save the interface preferences
+pub fn save_prefs(prefs: &UiPrefs) -> Result<(), std::io::Error> {
+    let value = serde_json::to_value(prefs)?;
+    update_state(|state| state.prefs = Some(value))
+}
+
+/// Record a finished install/uninstall attempt, updating the installed set on success
+///
+/// `verify_failed` marks an install that exited 0 but failed verification; it
+/// still counts as installed. Returns the updated record for the option.
+pub fn record_run(
+    option_id: &str,
+    run_id: &RunId,
+    action: RunAction,
+    completion: Completion,
+    verify_failed: bool,
+    timings: Option<StageTimings>,
+) -> Result<OptionRecord, std::io::Error> {
+    update_state(|state| {
+        let mut record = state.records.get(option_id).cloned().unwrap_or_default();
+        record.apply_run(run_id, action, completion, verify_failed, timings, Utc::now());
+        store_record(state, option_id, &record);
+        record
+    })
+}
+
+/// Store an option's record, updating the installed set if its last run succeeded
+///
+/// Target hashes already on file are kept; only `save_target_hashes` changes them.
+pub fn save_record(option_id: &str, record: &OptionRecord) -> Result<(), std::io::Error> {
+    update_state(|state| store_record(state, option_id, record))
+}
+
+/// `save_record` on an already loaded state
+fn store_record(state: &mut InstallerState, option_id: &str, record: &OptionRecord) {
+    let mut record = record.clone();
+    record.target_hashes = state
+        .records
+        .remove(option_id)
+        .map(|r| r.target_hashes)
+        .unwrap_or_default();
+    state.records.insert(option_id.to_string(), record.clone());
+
+    if record.last_exit_code == Some(0) {
+        state.installed.retain(|id| id != option_id);
+        if record.last_action == Some(RunAction::Install) {
+            state.installed.push(option_id.to_string());
         }
+        state.installed.sort();
     }
-    HashSet::new()
 }
 
-/// Save installed option IDs to state file
-pub fn save_installed(installed: &HashSet<String>) -> Result<(), std::io::Error> {
-    let path = state_file_path();
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
+/// Replace the target hashes stored for an option
+pub fn save_target_hashes(option_id: &str, hashes: &BTreeMap<String, String>) -> Result<(), std::io::Error> {
+    update_state(|state| {
+        state.records.entry(option_id.to_string()).or_default().target_hashes = hashes.clone();
+    })
+}
+
+/// When the system last booted, from /proc/uptime
+fn boot_time() -> Option<DateTime<Utc>> {
+    let uptime = fs::read_to_string("/proc/uptime").ok()?;
+    let seconds: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+    Some(Utc::now() - chrono::Duration::milliseconds((seconds * 1000.0) as i64))
+}
+
+/// Options waiting for a reboot, clearing the list if the system has rebooted since
+pub fn load_pending_reboot() -> Vec<String> {
+    let mut state = load_state();
+    if clear_if_rebooted(&mut state) {
+        let _ = update_state(clear_if_rebooted);
     }
+    state.pending_reboot.map(|pending| pending.options).unwrap_or_default()
+}
 
-    let state = InstallerState {
-        installed: installed.iter().cloned().collect(),
+/// Drop the reboot-pending list if the system booted after it was started
+fn clear_if_rebooted(state: &mut InstallerState) -> bool {
+    let stale = match (&state.pending_reboot, boot_time()) {
+        (Some(pending), Some(booted)) => booted > pending.since,
+        _ => false,
     };
+    if stale {
+        state.pending_reboot = None;
+    }
+    stale
+}
 
-    fs::write(path, serde_json::to_string_pretty(&state)?)?;
-    Ok(())
+/// Add an option to the reboot-pending list
+pub fn add_pending_reboot(option_id: &str) -> Result<(), std::io::Error> {
+    update_state(|state| {
+        clear_if_rebooted(state);
+        let pending = state.pending_reboot.get_or_insert_with(|| PendingReboot {
+            options: Vec::new(),
+            since: Utc::now(),
+        });
+        if !pending.options.iter().any(|id| id == option_id) {
+            pending.options.push(option_id.to_string());
+        }
+    })
+}
+
+/// Set the installed state of several options in one write, without running anything
+pub fn set_installed_state(option_ids: &[String], installed_state: bool) -> Result<(), std::io::Error> {
+    update_state(|state| {
+        state.installed.retain(|id| !option_ids.contains(id));
+        if installed_state {
+            state.installed.extend(option_ids.iter().cloned());
+        }
+        state.installed.sort();
+        state.installed.dedup();
+    })
 }
 
-/// Mark an option as installed
-pub fn mark_installed(option_id: &str) {
-    let mut installed = load_installed();
-    installed.insert(option_id.to_string());
-    let _ = save_installed(&installed);
+/// Mark several options installed or not by hand in one write, without running anything
+///
+/// Unlike `set_installed_state` this rewrites their records too, see
+/// [`OptionRecord::apply_mark`].
+pub fn mark_installed(option_ids: &[String], installed: bool) -> Result<(), std::io::Error> {
+    update_state(|state| apply_marks(state, option_ids, installed, Utc::now()))
 }
 
-/// Mark an option as uninstalled
-pub fn mark_uninstalled(option_id: &str) {
-    let mut installed = load_installed();
-    installed.remove(option_id);
-    let _ = save_installed(&installed);
+/// `mark_installed` on an already loaded state
+fn apply_marks(state: &mut InstallerState, option_ids: &[String], installed: bool, now: DateTime<Utc>) {
+    for id in option_ids {
+        state.records.entry(id.clone()).or_default().apply_mark(installed, now);
+    }
+    state.installed.retain(|id| !option_ids.contains(id));
+    if installed {
+        state.installed.extend(option_ids.iter().cloned());
+    } else if let Some(pending) = &mut state.pending_reboot {
+        pending.options.retain(|id| !option_ids.contains(id));
+    }
+    state.installed.sort();
+    state.installed.dedup();
+}
+
+/// Drop everything the state file keeps for these option ids
+pub fn forget_options(option_ids: &[String]) -> Result<(), std::io::Error> {
+    update_state(|state| {
+        state.installed.retain(|id| !option_ids.contains(id));
+        state.records.retain(|id, _| !option_ids.contains(id));
+        if let Some(pending) = &mut state.pending_reboot {
+            pending.options.retain(|id| !option_ids.contains(id));
+        }
+    })
+}
+
+/// Installed ids that no option has, sorted
+pub fn unknown_installed<'a>(known: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let known: HashSet<&str> = known.into_iter().collect();
+    let mut unknown: Vec<String> = load_state()
+        .installed
+        .into_iter()
+        .filter(|id| !known.contains(id.as_str()))
+        .collect();
+    unknown.sort();
+    unknown.dedup();
+    unknown
+}
+
+/// Notice about installed ids no option has
+pub fn describe_unknown(ids: &[String]) -> String {
+    format!(
+        "{} installed {} unknown options: {}",
+        ids.len(),
+        if ids.len() == 1 { "entry references" } else { "entries reference" },
+        ids.join(", ")
+    )
+}
+
+/// Move what the state file keeps under renamed options' old ids to the new
+/// ones, given as (old, new) pairs; returns the old ids that were moved
+///
+/// Where the new id already has a record, that one is kept.
+pub fn migrate_renamed(renames: &[(String, String)]) -> Result<Vec<String>, std::io::Error> {
+    let state = load_state();
+    if !renames.iter().any(|(old, _)| holds_state(&state, old)) {
+        return Ok(Vec::new());
+    }
+    update_state(|state| apply_renames(state, renames))
+}
+
+/// Whether the state file keeps anything under an id
+fn holds_state(state: &InstallerState, id: &str) -> bool {
+    state.installed.iter().any(|installed| installed == id)
+        || state.records.contains_key(id)
+        || state.pending_reboot.as_ref().is_some_and(|p| p.options.iter().any(|pending| pending == id))
+}
+
+/// `migrate_renamed` on an already loaded state
+fn apply_renames(state: &mut InstallerState, renames: &[(String, String)]) -> Vec<String> {
+    let mut moved = Vec::new();
+    for (old, new) in renames {
+        let mut found = false;
+        if let Some(i) = state.installed.iter().position(|id| id == old) {
+            state.installed.remove(i);
+            state.installed.push(new.clone());
+            found = true;
+        }
+        if let Some(record) = state.records.remove(old) {
+            state.records.entry(new.clone()).or_insert(record);
+            found = true;
+        }
+        if let Some(pending) = &mut state.pending_reboot {
+            for id in pending.options.iter_mut().filter(|id| *id == old) {
+                *id = new.clone();
+                found = true;
+            }
+            let mut seen = HashSet::new();
+            pending.options.retain(|id| seen.insert(id.clone()));
+        }
+        if state.last_selected.as_ref() == Some(old) {
+            state.last_selected = Some(new.clone());
+        }
+        if found {
+            moved.push(old.clone());
+        }
+    }
+    state.installed.sort();
+    state.installed.dedup();
+    moved
+}
+
+/// Record results of runs that finished after the terminal was lost
+pub fn save_detached_note(note: &str) -> Result<(), std::io::Error> {
+    fs::create_dir_all(state_dir())?;
+    fs::write(detached_note_path(), note)
+}
+
+/// Read and remove the note left by a previous detached session
+pub fn take_detached_note() -> Option<String> {
+    let path = detached_note_path();
+    let note = fs::read_to_string(&path).ok()?;
+    let _ = fs::remove_file(&path);
+    let note = note.trim();
+    (!note.is_empty()).then(|| note.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("omarchy-cybex-state-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn timings(total_ms: u64) -> StageTimings {
+        StageTimings {
+            running_ms: total_ms,
+            ..StageTimings::default()
+        }
+    }
+
+    /// A record left by a successful install that took `took_ms`
+    fn installed_record(option_id: &str, took_ms: u64) -> OptionRecord {
+        let mut record = OptionRecord::default();
+        record.apply_run(
+            &RunId::new(option_id, RunAction::Install),
+            RunAction::Install,
+            Completion::Exited(0),
+            false,
+            Some(timings(took_ms)),
+            Utc::now(),
+        );
+        record.target_hashes.insert("/etc/demo.conf".to_string(), "abc".to_string());
+        record
+    }
+
+    #[test]
+    fn legacy_state_file_loads_and_saves_in_the_new_schema() {
+        let legacy = r#"{"installed": ["claude", "fish"]}"#;
+        let mut state: InstallerState = serde_json::from_str(legacy).unwrap();
+        assert_eq!(state.installed, ids(&["claude", "fish"]));
+        assert!(state.records.is_empty());
+
+        let mut record = OptionRecord::default();
+        record.apply_run(
+            &RunId::new("codex", RunAction::Install),
+            RunAction::Install,
+            Completion::Exited(0),
+            false,
+            None,
+            Utc::now(),
+        );
+        store_record(&mut state, "codex", &record);
+
+        let written: serde_json::Value = serde_json::to_value(&state).unwrap();
+        assert_eq!(written["installed"], serde_json::json!(["claude", "codex", "fish"]));
+        assert_eq!(written["records"]["codex"]["last_exit_code"], 0);
+        assert_eq!(written["records"]["codex"]["last_action"], "install");
+        assert!(written["records"]["codex"]["installed_at"].is_string());
+        assert!(written.get("pending_reboot").is_none());
+    }
+
+    #[test]
+    fn failed_runs_are_recorded_without_touching_the_install() {
+        let mut state = InstallerState {
+            installed: ids(&["demo"]),
+            ..InstallerState::default()
+        };
+        state.records.insert("demo".to_string(), installed_record("demo", 1000));
+        let installed_at = state.records["demo"].installed_at;
+
+        let mut record = state.records["demo"].clone();
+        let run_id = RunId::new("demo", RunAction::Uninstall);
+        record.apply_run(&run_id, RunAction::Uninstall, Completion::Exited(3), false, None, Utc::now());
+        store_record(&mut state, "demo", &record);
+
+        assert_eq!(state.installed, ids(&["demo"]));
+        let record = &state.records["demo"];
+        assert!(record.last_failed());
+        assert_eq!(record.last_action, Some(RunAction::Uninstall));
+        assert_eq!(record.last_run_id, Some(run_id));
+        assert_eq!(record.installed_at, installed_at);
+        // Hashes are only changed by save_target_hashes
+        assert!(!record.target_hashes.is_empty());
+    }
+
+    #[test]
+    fn successful_uninstall_leaves_the_installed_set() {
+        let mut state = InstallerState::default();
+        store_record(&mut state, "demo", &installed_record("demo", 1000));
+        let mut record = state.records["demo"].clone();
+        let run_id = RunId::new("demo", RunAction::Uninstall);
+        record.apply_run(&run_id, RunAction::Uninstall, Completion::Exited(0), false, None, Utc::now());
+        store_record(&mut state, "demo", &record);
+        assert!(state.installed.is_empty());
+    }
+
+    #[test]
+    fn apply_run_keeps_how_the_run_ended() {
+        let run_id = RunId::new("demo", RunAction::Install);
+        let mut record = OptionRecord::default();
+
+        record.apply_run(&run_id, RunAction::Install, Completion::Signaled(9), false, None, Utc::now());
+        assert_eq!(record.last_completion(), Some(Completion::Signaled(9)));
+        assert_eq!(record.last_exit_code, Some(137));
+        assert_eq!(record.installed_at, None);
+
+        record.apply_run(&run_id, RunAction::Install, Completion::TimedOut, false, None, Utc::now());
+        assert_eq!(record.last_completion(), Some(Completion::TimedOut));
+        assert_eq!(record.last_signal, None);
+
+        record.apply_run(&run_id, RunAction::Install, Completion::Exited(0), true, None, Utc::now());
+        assert_eq!(record.last_completion(), Some(Completion::Exited(0)));
+        assert!(record.verify_failed);
+        assert!(!record.last_timed_out);
+        assert!(record.installed_at.is_some());
+    }
+
+    #[test]
+    fn typical_duration_averages_the_latest_successes() {
+        let mut record = OptionRecord::default();
+        assert_eq!(record.typical_duration(RunAction::Install), None);
+
+        let run_id = RunId::new("demo", RunAction::Install);
+        for took_ms in [9000, 1000, 2000, 3000, 4000, 5000] {
+            let took = Some(timings(took_ms));
+            record.apply_run(&run_id, RunAction::Install, Completion::Exited(0), false, took, Utc::now());
+        }
+        // Only the last DURATION_SAMPLES count, so the slow first run is gone
+        assert_eq!(record.install_durations_ms, [1000, 2000, 3000, 4000, 5000]);
+        assert_eq!(record.typical_duration(RunAction::Install), Some(Duration::from_secs(3)));
+        assert_eq!(record.typical_duration(RunAction::Uninstall), None);
+    }
+
+    #[test]
+    fn failed_and_unverified_runs_dont_count_towards_the_duration() {
+        let mut record = OptionRecord::default();
+        let run_id = RunId::new("demo", RunAction::Uninstall);
+        let action = RunAction::Uninstall;
+        record.apply_run(&run_id, action, Completion::Exited(0), false, Some(timings(4000)), Utc::now());
+        record.apply_run(&run_id, action, Completion::Exited(1), false, Some(timings(10)), Utc::now());
+        record.apply_run(&run_id, action, Completion::Exited(0), true, Some(timings(10)), Utc::now());
+        record.apply_run(&run_id, action, Completion::TimedOut, false, Some(timings(90_000)), Utc::now());
+        record.apply_run(&run_id, action, Completion::Exited(0), false, None, Utc::now());
+
+        assert_eq!(record.typical_duration(action), Some(Duration::from_secs(4)));
+        // The stage breakdown still describes the latest run, whatever it was
+        assert_eq!(record.last_timings, None);
+    }
+
+    #[test]
+    fn marking_installed_replaces_the_run_history() {
+        let mut state = InstallerState::default();
+        let mut record = OptionRecord::default();
+        record.apply_run(
+            &RunId::new("demo", RunAction::Install),
+            RunAction::Install,
+            Completion::Exited(1),
+            false,
+            None,
+            Utc::now(),
+        );
+        state.records.insert("demo".to_string(), record);
+
+        let now = Utc::now();
+        apply_marks(&mut state, &ids(&["demo"]), true, now);
+
+        assert_eq!(state.installed, ids(&["demo"]));
+        let record = &state.records["demo"];
+        assert!(record.marked_by_hand());
+        assert_eq!(record.installed_at, Some(now));
+        assert!(!record.last_failed());
+        assert_eq!(record.last_run_id, None);
+    }
+
+    #[test]
+    fn marking_not_installed_drops_stale_install_details() {
+        let mut state = InstallerState {
+            installed: ids(&["demo"]),
+            pending_reboot: Some(PendingReboot {
+                options: ids(&["demo"]),
+                since: Utc::now(),
+            }),
+            ..InstallerState::default()
+        };
+        state.records.insert("demo".to_string(), installed_record("demo", 2000));
+
+        apply_marks(&mut state, &ids(&["demo"]), false, Utc::now());
+
+        assert!(state.installed.is_empty());
+        assert!(state.pending_reboot.as_ref().is_some_and(|p| p.options.is_empty()));
+        let record = &state.records["demo"];
+        assert!(record.marked_by_hand());
+        assert_eq!(record.installed_at, None);
+        assert_eq!(record.last_run_at, None);
+        assert_eq!(record.last_exit_code, None);
+        assert!(record.target_hashes.is_empty());
+        assert_eq!(record.typical_duration(RunAction::Install), Some(Duration::from_millis(2000)));
+    }
+
+    #[test]
+    fn bulk_marking_touches_only_the_given_options() {
+        let mut state = InstallerState {
+            installed: ids(&["kept"]),
+            ..InstallerState::default()
+        };
+        state.records.insert("kept".to_string(), installed_record("kept", 1000));
+
+        apply_marks(&mut state, &ids(&["b", "a", "b"]), true, Utc::now());
+
+        assert_eq!(state.installed, ids(&["a", "b", "kept"]));
+        assert!(state.records["a"].marked_by_hand());
+        assert!(state.records["b"].marked_by_hand());
+        assert_eq!(state.records["kept"].provenance, Some(Provenance::Run));
+        assert!(!state.records["kept"].target_hashes.is_empty());
+    }
+
+    #[test]
+    fn a_successful_run_takes_over_from_a_mark() {
+        let mut record = OptionRecord::default();
+        record.apply_mark(true, Utc::now());
+        let run_id = RunId::new("demo", RunAction::Uninstall);
+
+        record.apply_run(&run_id, RunAction::Uninstall, Completion::Exited(2), false, None, Utc::now());
+        assert!(record.marked_by_hand());
+
+        record.apply_run(&run_id, RunAction::Uninstall, Completion::Exited(0), false, None, Utc::now());
+        assert_eq!(record.provenance, Some(Provenance::Run));
+    }
+
+    #[test]
+    fn provenance_round_trips_and_defaults_for_old_records() {
+        let mut record = OptionRecord::default();
+        record.apply_mark(false, Utc::now());
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains("\"provenance\":\"manual-mark\""));
+        let read: OptionRecord = serde_json::from_str(&json).unwrap();
+        assert!(read.marked_by_hand());
+
+        let legacy: OptionRecord = serde_json::from_str(r#"{"installed_at":null}"#).unwrap();
+        assert_eq!(legacy.provenance, None);
+    }
+
+    #[test]
+    fn updates_replace_the_file_without_leaving_temporaries() {
+        let dir = scratch_dir("atomic");
+        update_state_in(&dir, |state| state.installed = ids(&["demo"])).unwrap();
+        update_state_in(&dir, |state| state.last_selected = Some("demo".to_string())).unwrap();
+
+        let state = read_state(&dir).ok().unwrap();
+        assert_eq!(state.installed, ids(&["demo"]));
+        assert_eq!(state.last_selected.as_deref(), Some("demo"));
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["installer-state.json", "installer-state.json.lock"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_failed_write_leaves_the_old_file_intact() {
+        let dir = scratch_dir("failed-write");
+        update_state_in(&dir, |state| state.installed = ids(&["demo"])).unwrap();
+        let before = fs::read_to_string(state_file_in(&dir)).unwrap();
+
+        // A directory where the temporary file goes makes creating it fail
+        let temp = state_file_in(&dir).with_extension(format!("json.tmp.{}", std::process::id()));
+        fs::create_dir(&temp).unwrap();
+        assert!(update_state_in(&dir, |state| state.installed.clear()).is_err());
+
+        assert_eq!(fs::read_to_string(state_file_in(&dir)).unwrap(), before);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_corrupt_file_is_set_aside_and_the_update_still_applies() {
+        let dir = scratch_dir("corrupt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(state_file_in(&dir), "{\"installed\": [\"dem").unwrap();
+
+        let error = update_state_in(&dir, |state| state.installed = ids(&["fresh"])).unwrap_err();
+        let recovered = error.get_ref().and_then(|inner| inner.downcast_ref::<StateRecovered>());
+        assert!(recovered.is_some_and(|r| r.to_string().contains("was corrupt")), "{}", error);
+
+        assert_eq!(fs::read_to_string(bad_state_path(&dir)).unwrap(), "{\"installed\": [\"dem");
+        assert_eq!(read_state(&dir).ok().unwrap().installed, ids(&["fresh"]));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn an_unreadable_file_is_never_overwritten() {
+        let dir = scratch_dir("unreadable");
+        fs::create_dir_all(state_file_in(&dir)).unwrap();
+        let error = update_state_in(&dir, |state| state.installed = ids(&["demo"])).unwrap_err();
+        assert!(error.get_ref().is_none_or(|inner| !inner.is::<StateRecovered>()));
+        assert!(state_file_in(&dir).is_dir());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn concurrent_updates_are_not_lost() {
+        let dir = scratch_dir("concurrent");
+        let writers: Vec<_> = (0..8)
+            .map(|writer| {
+                let dir = dir.clone();
+                std::thread::spawn(move || {
+                    for n in 0..10 {
+                        update_state_in(&dir, |state| state.installed.push(format!("{}-{}", writer, n))).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(read_state(&dir).ok().unwrap().installed.len(), 80);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn updates_wait_for_the_lock() {
+        let dir = scratch_dir("lock");
+        let lock = StateLock::acquire(&dir).unwrap();
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let writer = {
+            let dir = dir.clone();
+            std::thread::spawn(move || {
+                update_state_in(&dir, |state| state.installed = ids(&["demo"])).unwrap();
+                let _ = done_tx.send(());
+            })
+        };
+        assert!(done_rx.recv_timeout(Duration::from_millis(200)).is_err(), "the update ran while locked");
+        drop(lock);
+        assert!(done_rx.recv_timeout(Duration::from_secs(5)).is_ok());
+        writer.join().unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn renames(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(old, new)| (old.to_string(), new.to_string())).collect()
+    }
+
+    #[test]
+    fn renames_move_everything_kept_under_the_old_id() {
+        let mut state = InstallerState {
+            installed: ids(&["claude-cli", "fish"]),
+            pending_reboot: Some(PendingReboot {
+                options: ids(&["claude-cli"]),
+                since: Utc::now(),
+            }),
+            last_selected: Some("claude-cli".to_string()),
+            ..InstallerState::default()
+        };
+        state.records.insert("claude-cli".to_string(), installed_record("claude-cli", 1000));
+
+        let moved = apply_renames(&mut state, &renames(&[("claude-cli", "claude"), ("gone", "still-gone")]));
+
+        assert_eq!(moved, ids(&["claude-cli"]));
+        assert_eq!(state.installed, ids(&["claude", "fish"]));
+        assert!(state.records.contains_key("claude") && !state.records.contains_key("claude-cli"));
+        assert_eq!(state.pending_reboot.unwrap().options, ids(&["claude"]));
+        assert_eq!(state.last_selected.as_deref(), Some("claude"));
+        assert!(!holds_state(&InstallerState::default(), "claude"));
+    }
+
+    #[test]
+    fn renames_keep_what_the_new_id_already_has() {
+        let mut state = InstallerState {
+            installed: ids(&["claude", "claude-cli"]),
+            pending_reboot: Some(PendingReboot {
+                options: ids(&["claude", "fish", "claude-cli"]),
+                since: Utc::now(),
+            }),
+            ..InstallerState::default()
+        };
+        state.records.insert("claude-cli".to_string(), installed_record("claude-cli", 1000));
+        state.records.insert("claude".to_string(), installed_record("claude", 7000));
+
+        apply_renames(&mut state, &renames(&[("claude-cli", "claude")]));
+
+        assert_eq!(state.installed, ids(&["claude"]));
+        assert_eq!(state.records.len(), 1);
+        assert_eq!(state.records["claude"].install_durations_ms, [7000]);
+        assert_eq!(state.pending_reboot.unwrap().options, ids(&["claude", "fish"]));
+    }
 }
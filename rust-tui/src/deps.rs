@@ -0,0 +1,181 @@
+//! Dependency resolution between options
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::options::InstallOption;
+
+/// Why an option's dependencies can't be resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyError {
+    /// A dependency names an option that doesn't exist
+    Unknown { option: String, dependency: String },
+    /// Options depend on each other in a loop, listed in dependency order
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for DependencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DependencyError::Unknown { option, dependency } => {
+                write!(f, "option '{}' depends on unknown option '{}'", option, dependency)
+            }
+            DependencyError::Cycle(ids) => write!(f, "dependency cycle: {}", ids.join(" -> ")),
+        }
+    }
+}
+
+impl std::error::Error for DependencyError {}
+
+/// Options to run, dependencies first, to install `option_id`
+///
+/// Dependencies already in `installed` are skipped (along with their own
+/// dependencies); `option_id` itself is always last.
+pub fn install_order(
+    options: &[InstallOption],
+    option_id: &str,
+    installed: &HashSet<String>,
+) -> Result<Vec<String>, DependencyError> {
+    let mut order = Vec::new();
+    let mut path = Vec::new();
+    visit(options, option_id, installed, true, &mut path, &mut order)?;
+    Ok(order)
+}
+
+/// Check every option's dependencies resolve, ignoring installed state
+pub fn validate(options: &[InstallOption]) -> Result<(), DependencyError> {
+    let none = HashSet::new();
+    for option in options {
+        install_order(options, &option.id, &none)?;
+    }
+    Ok(())
+}
+
+/// Depth-first post-order walk, tracking the current path to spot cycles
+fn visit(
+    options: &[InstallOption],
+    id: &str,
+    installed: &HashSet<String>,
+    is_target: bool,
+    path: &mut Vec<String>,
+    order: &mut Vec<String>,
+) -> Result<(), DependencyError> {
+    if let Some(start) = path.iter().position(|p| p == id) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(id.to_string());
+        return Err(DependencyError::Cycle(cycle));
+    }
+    if order.iter().any(|o| o == id) || (!is_target && installed.contains(id)) {
+        return Ok(());
+    }
+
+    let Some(option) = options.iter().find(|o| o.id == id) else {
+        return Ok(());
+    };
+    path.push(id.to_string());
+    for dependency in &option.depends {
+        if !options.iter().any(|o| &o.id == dependency) {
+            return Err(DependencyError::Unknown {
+                option: id.to_string(),
+                dependency: dependency.clone(),
+            });
+        }
+        visit(options, dependency, installed, false, path, order)?;
+    }
+    path.pop();
+    order.push(id.to_string());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(id: &str, depends: &[&str]) -> InstallOption {
+        InstallOption {
+            id: id.to_string(),
+            depends: depends.iter().map(|d| d.to_string()).collect(),
+            ..InstallOption::default()
+        }
+    }
+
+    fn installed(ids: &[&str]) -> HashSet<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    /// neovim needs fonts and node; node needs fonts too
+    fn options() -> Vec<InstallOption> {
+        vec![
+            option("neovim", &["fonts", "node"]),
+            option("node", &["fonts"]),
+            option("fonts", &[]),
+            option("fish", &[]),
+        ]
+    }
+
+    #[test]
+    fn dependencies_come_first_in_declared_order() {
+        let order = install_order(&options(), "neovim", &HashSet::new()).unwrap();
+        assert_eq!(order, ["fonts", "node", "neovim"]);
+    }
+
+    #[test]
+    fn an_option_without_dependencies_runs_alone() {
+        assert_eq!(install_order(&options(), "fish", &HashSet::new()).unwrap(), ["fish"]);
+    }
+
+    #[test]
+    fn installed_dependencies_are_skipped_but_the_target_never_is() {
+        let order = install_order(&options(), "neovim", &installed(&["node"])).unwrap();
+        // node's own dependency goes with it
+        assert_eq!(order, ["fonts", "neovim"]);
+
+        let order = install_order(&options(), "neovim", &installed(&["neovim", "fonts", "node"])).unwrap();
+        assert_eq!(order, ["neovim"]);
+    }
+
+    #[test]
+    fn cycles_are_reported_in_dependency_order() {
+        let options = vec![option("a", &["b"]), option("b", &["c"]), option("c", &["a"])];
+        assert_eq!(
+            install_order(&options, "a", &HashSet::new()),
+            Err(DependencyError::Cycle(vec!["a".into(), "b".into(), "c".into(), "a".into()]))
+        );
+        assert_eq!(
+            install_order(&options, "a", &HashSet::new()).unwrap_err().to_string(),
+            "dependency cycle: a -> b -> c -> a"
+        );
+    }
+
+    #[test]
+    fn an_option_depending_on_itself_is_a_cycle() {
+        let options = vec![option("a", &["a"])];
+        assert_eq!(
+            install_order(&options, "a", &HashSet::new()),
+            Err(DependencyError::Cycle(vec!["a".into(), "a".into()]))
+        );
+    }
+
+    #[test]
+    fn unknown_dependencies_are_reported() {
+        let options = vec![option("neovim", &["node"]), option("node", &["npm"])];
+        let error = install_order(&options, "neovim", &HashSet::new()).unwrap_err();
+        assert_eq!(
+            error,
+            DependencyError::Unknown {
+                option: "node".into(),
+                dependency: "npm".into()
+            }
+        );
+        assert_eq!(error.to_string(), "option 'node' depends on unknown option 'npm'");
+    }
+
+    #[test]
+    fn validate_checks_every_option() {
+        assert!(validate(&options()).is_ok());
+
+        let mut options = options();
+        options.push(option("broken", &["missing"]));
+        assert!(matches!(validate(&options), Err(DependencyError::Unknown { .. })));
+    }
+}
@@ -0,0 +1,129 @@
+//! Detection of target files that are open in an editor
+//!
+//! Editors leave swap and lock files next to the files they have open. If an
+//! install is about to overwrite one of those, saving later would clobber the
+//! script's changes (or the script would clobber the unsaved edits). This is
+//! purely a filesystem heuristic; editors are never contacted.
+
+use std::path::{Path, PathBuf};
+
+/// A target file with an editor artifact next to it
+#[derive(Debug, Clone)]
+pub struct EditorLock {
+    /// File the install script writes
+    pub target: PathBuf,
+    /// Swap, lock or backup file found beside it
+    pub lock: PathBuf,
+    /// What left the artifact behind
+    pub editor: &'static str,
+}
+
+/// Artifact name patterns, as (prefix, suffix, editor)
+const PATTERNS: &[(&str, &str, &str)] = &[
+    (".", ".swp", "Vim swap file"),
+    (".", ".swo", "Vim swap file"),
+    (".#", "", "Emacs lock"),
+    ("", "~", "editor backup"),
+];
+
+/// Find editor artifacts for every target that has one
+pub fn find_editor_locks(targets: &[PathBuf]) -> Vec<EditorLock> {
+    targets.iter().flat_map(|target| locks_for(target)).collect()
+}
+
+/// Editor artifacts next to a single file
+fn locks_for(target: &Path) -> Vec<EditorLock> {
+    let (Some(dir), Some(name)) = (target.parent(), target.file_name()) else {
+        return Vec::new();
+    };
+    let name = name.to_string_lossy();
+
+    PATTERNS
+        .iter()
+        .map(|(prefix, suffix, editor)| (dir.join(format!("{}{}{}", prefix, name, suffix)), *editor))
+        // Emacs locks are dangling symlinks, so don't follow them
+        .filter(|(lock, _)| lock.symlink_metadata().is_ok())
+        .map(|(lock, editor)| EditorLock {
+            target: target.to_path_buf(),
+            lock,
+            editor,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::symlink;
+
+    /// Empty scratch directory for one test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("omarchy-cybex-locks-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Names of the artifacts found for `targets`, with who left them
+    fn found(targets: &[PathBuf]) -> Vec<(String, &'static str)> {
+        find_editor_locks(targets)
+            .into_iter()
+            .map(|lock| (lock.lock.file_name().unwrap().to_string_lossy().into_owned(), lock.editor))
+            .collect()
+    }
+
+    #[test]
+    fn each_kind_of_artifact_is_found() {
+        let dir = scratch_dir("kinds");
+        let targets = [dir.join("bindings.conf")];
+        for artifact in [".bindings.conf.swp", ".bindings.conf.swo", "bindings.conf~"] {
+            fs::write(dir.join(artifact), "").unwrap();
+        }
+        // Emacs points its lock at user@host.pid, which doesn't exist
+        symlink("agent@host.4242", dir.join(".#bindings.conf")).unwrap();
+
+        assert_eq!(
+            found(&targets),
+            [
+                (".bindings.conf.swp".to_string(), "Vim swap file"),
+                (".bindings.conf.swo".to_string(), "Vim swap file"),
+                (".#bindings.conf".to_string(), "Emacs lock"),
+                ("bindings.conf~".to_string(), "editor backup"),
+            ]
+        );
+        assert!(find_editor_locks(&targets).iter().all(|lock| lock.target == targets[0]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unrelated_files_are_not_artifacts() {
+        let dir = scratch_dir("unrelated");
+        let target = dir.join("bindings.conf");
+        fs::write(&target, "").unwrap();
+        let others = [".input.conf.swp", "bindings.conf.bak", "bindings.conf.swp", ".bindings.conf", "#bindings.conf#"];
+        for other in others {
+            fs::write(dir.join(other), "").unwrap();
+        }
+        assert!(found(&[target]).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn artifacts_are_reported_per_target() {
+        let dir = scratch_dir("targets");
+        fs::write(dir.join(".input.conf.swp"), "").unwrap();
+        fs::write(dir.join("looknfeel.conf~"), "").unwrap();
+        let targets = ["bindings.conf", "input.conf", "looknfeel.conf"].map(|name| dir.join(name));
+
+        let locks = find_editor_locks(&targets);
+        let pairs: Vec<_> = locks.iter().map(|lock| (lock.target.clone(), lock.editor)).collect();
+        assert_eq!(pairs, [(targets[1].clone(), "Vim swap file"), (targets[2].clone(), "editor backup")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn targets_without_a_file_name_have_none() {
+        assert!(find_editor_locks(&[PathBuf::from("/"), PathBuf::new()]).is_empty());
+    }
+}
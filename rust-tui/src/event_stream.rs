@@ -0,0 +1,86 @@
+//! Newline-delimited JSON events for headless runs (`--json`)
+//!
+//! Each line on stdout is one object whose `event` field says what it is,
+//! mirroring the installer's events for a wrapper to follow progress:
+//!
+//! ```text
+//! {"event":"frame","option":"fish","line":"$ ./install fish  (cwd: ...)"}
+//! {"event":"output","option":"fish","stream":"stdout","line":"..."}
+//! {"event":"completed","option":"fish","exit_code":0,"success":true,"duration_ms":12345}
+//! ```
+//!
+//! Every option of the batch ends with exactly one `completed` or `error`
+//! event; an option that was blocked before starting gets an `error` too.
+
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::installer::Completion;
+
+/// Where an output line came from
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stream {
+    /// The script's terminal
+    Stdout,
+    /// The script's stderr
+    Stderr,
+    /// The installer itself, such as a verify header
+    Installer,
+}
+
+/// One line of the stream
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StreamEvent<'a> {
+    Output {
+        option: &'a str,
+        stream: Stream,
+        line: &'a str,
+    },
+    /// The command about to run, or how it ended
+    Frame { option: &'a str, line: &'a str },
+    /// Something worth knowing that doesn't end the run, e.g. a failed hook
+    Warning { option: &'a str, message: &'a str },
+    /// The script finished; `success` also needs verification to have passed
+    Completed {
+        option: &'a str,
+        exit_code: i32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        signal: Option<i32>,
+        timed_out: bool,
+        success: bool,
+        duration_ms: u64,
+    },
+    /// The run failed to start, was stopped or never started
+    Error { option: &'a str, message: &'a str },
+}
+
+impl<'a> StreamEvent<'a> {
+    pub fn completed(option: &'a str, completion: Completion, success: bool, duration_ms: u64) -> Self {
+        StreamEvent::Completed {
+            option,
+            exit_code: completion.code(),
+            signal: match completion {
+                Completion::Signaled(signal) => Some(signal),
+                _ => None,
+            },
+            timed_out: completion == Completion::TimedOut,
+            success,
+            duration_ms,
+        }
+    }
+}
+
+/// Write one event and flush it, so a wrapper sees it straight away
+///
+/// A reader that went away isn't an error; the run carries on regardless.
+pub fn emit(event: &StreamEvent) {
+    let Ok(json) = serde_json::to_string(event) else {
+        return;
+    };
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{}", json);
+    let _ = stdout.flush();
+}
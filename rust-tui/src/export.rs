@@ -0,0 +1,138 @@
+//! Export of the installed options as a standalone shell script
+//!
+//! The script runs the entry script once per installed option, dependencies
+//! first, for machines where the TUI can't run. It `cd`s into the script
+//! directory given as its first argument, or the one it was exported from.
+
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+use crate::deps::install_order;
+use crate::options::InstallOption;
+
+/// Export the installed options to `path`, returning how many it lists
+///
+/// Nothing installed is an error: an empty script would reproduce nothing.
+pub fn export_installed(
+    script_dir: &Path,
+    entry: &Path,
+    options: &[InstallOption],
+    installed: &HashSet<String>,
+    path: &Path,
+    force: bool,
+) -> Result<usize, String> {
+    let ids = export_order(options, installed);
+    if ids.is_empty() {
+        return Err("nothing is installed, so there is nothing to export".to_string());
+    }
+    // The script may run from anywhere, so it needs the absolute directory
+    let script_dir = fs::canonicalize(script_dir).unwrap_or_else(|_| script_dir.to_path_buf());
+    let entry = fs::canonicalize(entry).unwrap_or_else(|_| entry.to_path_buf());
+    let script = render_script(&script_dir, &entry, options, &ids);
+    write_script(path, &script, force).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => format!("{} (pass --force to replace it)", e),
+        _ => format!("writing {}: {}", path.display(), e),
+    })?;
+    Ok(ids.len())
+}
+
+/// Installed options in the order the script installs them
+///
+/// Every option comes after its dependencies; dependencies that aren't
+/// installed are left out, as the script only reproduces what is there.
+pub fn export_order(options: &[InstallOption], installed: &HashSet<String>) -> Vec<String> {
+    let none = HashSet::new();
+    let mut order: Vec<String> = Vec::new();
+    for option in options.iter().filter(|o| o.tracks_state() && installed.contains(&o.id)) {
+        // The manifest was validated at startup, so the walk can't fail
+        let chain = install_order(options, &option.id, &none).unwrap_or_else(|_| vec![option.id.clone()]);
+        for id in chain {
+            if installed.contains(&id) && !order.contains(&id) {
+                order.push(id);
+            }
+        }
+    }
+    order
+}
+
+/// Text of the script installing `ids` through `entry`
+fn render_script(script_dir: &Path, entry: &Path, options: &[InstallOption], ids: &[String]) -> String {
+    let entry = entry
+        .strip_prefix(script_dir)
+        .map(|relative| Path::new(".").join(relative))
+        .unwrap_or_else(|_| entry.to_path_buf());
+    let mut script = format!(
+        "#!/bin/bash\n\
+         # Reinstalls the {} option(s) installed on {}\n\
+         # Exported by omarchy-cybex-installer on {}\n\
+         #\n\
+         # Usage: bash <this file> [omarchy-cybex checkout, default below]\n\
+         \n\
+         set -euo pipefail\n\
+         cd {}\n\
+         \n",
+        ids.len(),
+        hostname(),
+        Local::now().format("%Y-%m-%d %H:%M %z"),
+        shell_quote_default(&script_dir.display().to_string()),
+    );
+    for id in ids {
+        let name = options.iter().find(|o| &o.id == id).map_or(id.as_str(), |o| o.name.as_str());
+        script.push_str(&format!("{} {}  # {}\n", shell_quote(&entry.display().to_string()), shell_quote(id), name));
+    }
+    script
+}
+
+/// Write an executable script to `path`, refusing to replace a file unless `force`
+fn write_script(path: &Path, contents: &str, force: bool) -> io::Result<()> {
+    let mut open = OpenOptions::new();
+    open.write(true).mode(0o755);
+    if force {
+        open.create(true).truncate(true);
+    } else {
+        open.create_new(true);
+    }
+    let mut file = open.open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => io::Error::new(e.kind(), format!("{} already exists", path.display())),
+        _ => e,
+    })?;
+    file.write_all(contents.as_bytes())?;
+    // The mode only applies to new files; an overwritten one may not be executable
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+/// Where the TUI exports to: a dated file in the home directory
+pub fn default_export_path() -> PathBuf {
+    let name = format!("omarchy-cybex-{}-{}.sh", hostname(), Local::now().format("%Y%m%d-%H%M%S"));
+    dirs::home_dir().unwrap_or_default().join(name)
+}
+
+/// This machine's host name, for the script header and file name
+fn hostname() -> String {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Quote a word for bash unless it only has safe characters
+pub fn shell_quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// `"${1:-dir}"` with `dir` quoted so it survives as the default
+fn shell_quote_default(dir: &str) -> String {
+    format!("\"${{1:-{}}}\"", dir.replace('\\', r"\\").replace('"', "\\\"").replace('$', "\\$").replace('`', "\\`"))
+}
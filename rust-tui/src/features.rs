@@ -0,0 +1,226 @@
+//! What the script directory lets the installer do
+//!
+//! Scripts normally come from a git checkout the user owns, but they may also
+//! ship as a system package (under /usr/share/omarchy-cybex), read-only and
+//! updated by pacman. Features that assume a writable checkout are switched
+//! off there with a message saying why.
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Marker a package can put in the script directory to declare itself
+const PACKAGED_MARKER: &str = ".packaged";
+
+/// Package the scripts ship in
+const PACKAGE_NAME: &str = "omarchy-cybex";
+
+/// Where the scripts come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptSource {
+    /// A git checkout, updated with `git pull`
+    GitCheckout,
+    /// Installed by the system package manager, with its version if known
+    Packaged { version: Option<String> },
+    /// A plain, writable directory
+    Plain,
+}
+
+/// Capabilities that depend on the script source
+#[derive(Debug, Clone)]
+pub struct FeatureFlags {
+    pub source: ScriptSource,
+    /// Whether `U` can fetch and pull updates
+    pub updates: bool,
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        Self::for_source(ScriptSource::Plain)
+    }
+}
+
+impl FeatureFlags {
+    /// Flags for scripts from `source`
+    pub fn for_source(source: ScriptSource) -> Self {
+        Self {
+            updates: source == ScriptSource::GitCheckout,
+            source,
+        }
+    }
+
+    /// Why updating from the TUI isn't available, if it isn't
+    pub fn updates_unavailable(&self) -> Option<&'static str> {
+        match self.source {
+            _ if self.updates => None,
+            ScriptSource::Packaged { .. } => {
+                Some("Scripts are managed by the system package - update them with pacman")
+            }
+            _ => Some("Scripts aren't a git checkout - nothing to update from"),
+        }
+    }
+
+    /// Short description of the source for the header, e.g. "scripts 1.2-1 (package)"
+    pub fn describe(&self) -> String {
+        match &self.source {
+            ScriptSource::GitCheckout => "scripts from git".to_string(),
+            ScriptSource::Packaged { version: Some(version) } => format!("scripts {} (package)", version),
+            ScriptSource::Packaged { version: None } => "scripts from package".to_string(),
+            ScriptSource::Plain => "scripts from a local directory".to_string(),
+        }
+    }
+}
+
+/// Whether the current user may write to `dir`
+fn is_writable(dir: &Path) -> bool {
+    let Ok(path) = CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `path` is a valid NUL-terminated string for the duration of the call
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+/// Version of the installed package, from `pacman -Q`
+fn package_version() -> Option<String> {
+    let output = Command::new("pacman")
+        .args(["-Q", PACKAGE_NAME])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // Prints "omarchy-cybex 1.2.0-1"
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(1)
+        .map(str::to_string)
+}
+
+/// Whether `dir` is inside a git work tree
+fn in_git_checkout(dir: &Path) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|output| output.status.success() && output.stdout.starts_with(b"true"))
+}
+
+/// Work out where the scripts in `script_dir` come from
+///
+/// A marker file, a location under /usr or a read-only directory means a
+/// package; otherwise being inside a git work tree means a checkout.
+pub fn detect_source(script_dir: &Path) -> ScriptSource {
+    let packaged = script_dir.join(PACKAGED_MARKER).exists()
+        || script_dir.starts_with("/usr")
+        || !is_writable(script_dir);
+    if packaged {
+        ScriptSource::Packaged {
+            version: package_version(),
+        }
+    } else if in_git_checkout(script_dir) {
+        ScriptSource::GitCheckout
+    } else {
+        ScriptSource::Plain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+
+    /// Empty scratch directory for one test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("omarchy-cybex-source-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn plain_directory_is_plain() {
+        let dir = scratch_dir("plain");
+        fs::write(dir.join("install"), "").unwrap();
+        assert_eq!(detect_source(&dir), ScriptSource::Plain);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn marker_file_means_packaged() {
+        let dir = scratch_dir("marker");
+        fs::write(dir.join(PACKAGED_MARKER), "").unwrap();
+        assert!(matches!(detect_source(&dir), ScriptSource::Packaged { .. }));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn under_usr_means_packaged() {
+        assert!(matches!(detect_source(Path::new("/usr/share/omarchy-cybex")), ScriptSource::Packaged { .. }));
+    }
+
+    #[test]
+    fn read_only_directory_means_packaged() {
+        // root may write anywhere, so the directory would still count as writable
+        // SAFETY: geteuid only reads a process attribute
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let dir = scratch_dir("read-only");
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+        assert!(matches!(detect_source(&dir), ScriptSource::Packaged { .. }));
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn git_work_tree_is_a_checkout_down_to_its_subdirectories() {
+        let dir = scratch_dir("git");
+        let initialized = Command::new("git").arg("-C").arg(&dir).args(["init", "-q"]).status();
+        if !initialized.is_ok_and(|status| status.success()) {
+            return; // git isn't installed here
+        }
+        fs::create_dir_all(dir.join("scripts")).unwrap();
+        assert_eq!(detect_source(&dir), ScriptSource::GitCheckout);
+        assert_eq!(detect_source(&dir.join("scripts")), ScriptSource::GitCheckout);
+
+        // A packaged marker wins over the work tree
+        fs::write(dir.join("scripts").join(PACKAGED_MARKER), "").unwrap();
+        assert!(matches!(detect_source(&dir.join("scripts")), ScriptSource::Packaged { .. }));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_checkouts_can_update() {
+        let checkout = FeatureFlags::for_source(ScriptSource::GitCheckout);
+        assert!(checkout.updates);
+        assert_eq!(checkout.updates_unavailable(), None);
+
+        let packaged = FeatureFlags::for_source(ScriptSource::Packaged { version: None });
+        assert!(!packaged.updates);
+        assert!(packaged.updates_unavailable().unwrap().contains("pacman"));
+
+        let plain = FeatureFlags::default();
+        assert_eq!(plain.source, ScriptSource::Plain);
+        assert_eq!(plain.updates_unavailable(), Some("Scripts aren't a git checkout - nothing to update from"));
+    }
+
+    #[test]
+    fn sources_describe_themselves_for_the_header() {
+        let describe = |source| FeatureFlags::for_source(source).describe();
+        assert_eq!(describe(ScriptSource::GitCheckout), "scripts from git");
+        assert_eq!(
+            describe(ScriptSource::Packaged { version: Some("1.2.0-1".to_string()) }),
+            "scripts 1.2.0-1 (package)"
+        );
+        assert_eq!(describe(ScriptSource::Packaged { version: None }), "scripts from package");
+        assert_eq!(describe(ScriptSource::Plain), "scripts from a local directory");
+    }
+}
@@ -0,0 +1,528 @@
+//! Headless execution of install actions for scripted use
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::batch::{exit_code, summary, BatchResult, Outcome, EXIT_FAILED, EXIT_OK, EXIT_USAGE};
+use crate::cli::{ListFormat, RunFormat};
+use crate::compat::{mismatch, Version};
+use crate::config::{
+    add_pending_reboot, load_installed, load_records, record_run, save_target_hashes, state_file_path, OptionRecord,
+    RunAction,
+};
+use crate::deps::install_order;
+use crate::editor_locks::find_editor_locks;
+use crate::event_stream::{emit, Stream, StreamEvent};
+use crate::installer::{
+    command_line, describe_priority, run_install_command, Completion, InstallerEvent, InstallerHandle, PtySize,
+    RunSettings,
+};
+use crate::logs::RunLog;
+use crate::modified::{hash_targets, modified_files};
+use crate::options::InstallOption;
+use crate::prefs::Priority;
+use crate::requirements::unmet_requirements;
+use crate::run_id::RunId;
+use crate::sandbox::SandboxPolicy;
+use crate::script_env::script_env;
+use crate::timeout::{describe_limit, Overrun, RunWatch, TimeoutPolicy};
+use crate::timings::{RunTimer, StageTimings};
+use crate::ui::display_path;
+
+/// One option in `--list --json` output
+#[derive(Serialize)]
+struct ListedOption<'a> {
+    id: &'a str,
+    name: &'a str,
+    description: &'a str,
+    requires_reboot: bool,
+    installed: bool,
+    last_exit_code: Option<i32>,
+    installed_at: Option<DateTime<Utc>>,
+}
+
+/// Print each option id, name and installed status
+///
+/// The JSON formats are for scripts: plain JSON on stdout and nothing else.
+pub fn list_options(options: &[InstallOption], format: ListFormat) -> i32 {
+    let installed = load_installed();
+    if format != ListFormat::Table {
+        let listed = listed_options(options, &installed, &load_records());
+        let json = if format == ListFormat::CompactJson {
+            serde_json::to_string(&listed)
+        } else {
+            serde_json::to_string_pretty(&listed)
+        };
+        return match json {
+            // A reader that stops early (e.g. `| head`) isn't an error
+            Ok(json) => {
+                let _ = writeln!(io::stdout().lock(), "{}", json);
+                EXIT_OK
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                EXIT_FAILED
+            }
+        };
+    }
+    for option in options {
+        println!("{:<20} {:<24} {}", option.id, option.name, listed_status(option, &installed));
+    }
+    EXIT_OK
+}
+
+/// The options as `--list --json` describes them
+fn listed_options<'a>(
+    options: &'a [InstallOption],
+    installed: &HashSet<String>,
+    records: &HashMap<String, OptionRecord>,
+) -> Vec<ListedOption<'a>> {
+    options
+        .iter()
+        .map(|option| {
+            let record = records.get(&option.id);
+            ListedOption {
+                id: &option.id,
+                name: &option.name,
+                description: &option.description,
+                requires_reboot: option.requires_reboot,
+                installed: option.tracks_state() && installed.contains(&option.id),
+                last_exit_code: record.and_then(|r| r.last_exit_code),
+                installed_at: record.and_then(|r| r.installed_at),
+            }
+        })
+        .collect()
+}
+
+/// The status column of the `--list` table
+fn listed_status(option: &InstallOption, installed: &HashSet<String>) -> &'static str {
+    if !option.tracks_state() {
+        "not tracked"
+    } else if installed.contains(&option.id) {
+        "installed"
+    } else {
+        "not installed"
+    }
+}
+
+/// What a headless run checks an option against before starting it, and
+/// what it starts it with
+pub struct Safeguards<'a> {
+    /// Installed Omarchy version, for the options' version ranges
+    pub base_version: Option<&'a Version>,
+    pub sandbox: &'a SandboxPolicy,
+    /// Start installs whose declared requirements aren't met (`--skip-preflight`)
+    pub skip_preflight: bool,
+    /// Time limits; a run past its limit is always killed, as nobody can be asked
+    pub timeout: &'a TimeoutPolicy,
+    /// Variables for every run, under the option's own
+    pub env: &'a BTreeMap<String, String>,
+    /// Niceness and I/O class for options that don't set their own
+    pub priority: Priority,
+}
+
+/// Where a headless run's output and warnings go, by format
+struct Report<'a> {
+    format: RunFormat,
+    option: &'a str,
+}
+
+impl Report<'_> {
+    /// Script output: stdout and stderr as they came, or output events
+    fn lines(&self, stream: Stream, lines: &[String]) {
+        match (self.format, stream) {
+            (RunFormat::Events, _) => {
+                for line in lines {
+                    emit(&StreamEvent::Output { option: self.option, stream, line });
+                }
+            }
+            (RunFormat::Text, Stream::Stderr) => {
+                for line in lines {
+                    eprintln!("{}", line);
+                }
+            }
+            (RunFormat::Text, _) => {
+                let mut stdout = io::stdout().lock();
+                for line in lines {
+                    let _ = writeln!(stdout, "{}", line);
+                }
+                let _ = stdout.flush();
+            }
+        }
+    }
+
+    /// Framing isn't script output, so as text it stays off stdout
+    fn frame(&self, line: &str) {
+        match self.format {
+            RunFormat::Events => emit(&StreamEvent::Frame { option: self.option, line }),
+            RunFormat::Text => eprintln!("{}", line),
+        }
+    }
+
+    fn warning(&self, message: &str) {
+        match self.format {
+            RunFormat::Events => emit(&StreamEvent::Warning { option: self.option, message }),
+            RunFormat::Text => eprintln!("warning: {}", message),
+        }
+    }
+
+    fn error(&self, message: &str) {
+        match self.format {
+            RunFormat::Events => emit(&StreamEvent::Error { option: self.option, message }),
+            RunFormat::Text => eprintln!("Error: {}", message),
+        }
+    }
+
+    /// The option ended without completing; as text the summary says so
+    fn ended(&self, message: &str) {
+        if self.format == RunFormat::Events {
+            emit(&StreamEvent::Error { option: self.option, message });
+        }
+    }
+}
+
+/// Run installs/uninstalls in order, streaming output to stdout
+///
+/// Each option's dependencies that aren't installed yet are installed first.
+/// Installs outside an option's Omarchy version range, reinstalls over
+/// hand-edited files and installs whose requirements aren't met are blocked,
+/// since there's nobody to confirm them. A summary goes to stderr; returns
+/// the exit code the process should exit with.
+pub fn run_batch(
+    script_dir: &Path,
+    entry: &Path,
+    options: &[InstallOption],
+    option_ids: &[String],
+    uninstall: bool,
+    safeguards: &Safeguards,
+    format: RunFormat,
+) -> i32 {
+    let Safeguards {
+        base_version,
+        sandbox,
+        skip_preflight,
+        timeout,
+        env,
+        priority,
+    } = *safeguards;
+    let mut installed = load_installed();
+    let records = load_records();
+    let mut order: Vec<String> = Vec::new();
+    for id in option_ids {
+        let Some(option) = options.iter().find(|o| &o.id == id) else {
+            eprintln!("Error: unknown option '{}'", id);
+            return EXIT_USAGE;
+        };
+        if uninstall && !option.tracks_state() {
+            eprintln!("Error: '{}' doesn't track installed state, so there's nothing to uninstall", id);
+            return EXIT_USAGE;
+        }
+        let ids = if uninstall {
+            vec![id.clone()]
+        } else {
+            match install_order(options, id, &installed) {
+                Ok(ids) => ids,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return EXIT_USAGE;
+                }
+            }
+        };
+        for id in ids {
+            if !order.contains(&id) {
+                order.push(id);
+            }
+        }
+    }
+    if order.is_empty() {
+        eprintln!("Nothing to do");
+        return EXIT_OK;
+    }
+
+    let mut results: Vec<BatchResult> = Vec::new();
+    for id in &order {
+        let option = options.iter().find(|o| &o.id == id).expect("ids come from the option list");
+        let failed_dependency = option.depends.iter().find(|dep| {
+            results
+                .iter()
+                .any(|r| &r.id == *dep && r.outcome != Outcome::Succeeded)
+        });
+        let locks = find_editor_locks(&option.target_paths());
+        let incompatible = mismatch(option.omarchy.as_ref(), base_version).filter(|_| !uninstall);
+        let modified = match records.get(&option.id) {
+            Some(record) if !uninstall && installed.contains(&option.id) => modified_files(&record.target_hashes),
+            _ => Vec::new(),
+        };
+        let unmet = if uninstall || skip_preflight {
+            Vec::new()
+        } else {
+            unmet_requirements(option)
+        };
+
+        let outcome = if let Some(dep) = failed_dependency.filter(|_| !uninstall) {
+            Outcome::Blocked(format!("dependency {} did not install", dep))
+        } else if let Some(issue) = incompatible {
+            // There's nobody to confirm the mismatch, so treat it like a lock
+            Outcome::Blocked(issue)
+        } else if let Some(path) = modified.first() {
+            Outcome::Blocked(format!(
+                "{} file(s) changed since the last install, e.g. {}",
+                modified.len(),
+                display_path(path)
+            ))
+        } else if let Some(lock) = locks.first() {
+            Outcome::Blocked(format!(
+                "{} is open in an editor ({})",
+                display_path(&lock.target),
+                lock.editor
+            ))
+        } else if sandbox.unavailable_for(option) {
+            Outcome::Blocked("needs a sandbox, but neither bwrap nor firejail is installed".to_string())
+        } else if !unmet.is_empty() {
+            Outcome::Blocked(format!("pre-flight: {}", unmet.join("; ")))
+        } else {
+            let settings = RunSettings {
+                verify: true,
+                sandbox: sandbox.sandbox_for(script_dir, option),
+                env: script_env(env, option),
+                priority: priority.for_option(option),
+            };
+            run_action(script_dir, entry, option, uninstall, settings, timeout.limit_for(option), format)
+        };
+        if let Outcome::Blocked(reason) = &outcome {
+            Report { format, option: &option.id }.ended(&format!("blocked: {}", reason));
+        }
+        if outcome == Outcome::Succeeded && !uninstall {
+            installed.insert(option.id.clone());
+        }
+        results.push(BatchResult {
+            id: option.id.clone(),
+            name: option.name.clone(),
+            outcome,
+        });
+    }
+
+    eprintln!("{}", summary(&results));
+    exit_code(&results)
+}
+
+/// Store a finished run in the state file
+fn record_outcome(
+    option: &InstallOption,
+    run_id: &RunId,
+    action: RunAction,
+    completion: Completion,
+    verify_failed: bool,
+    timings: Option<StageTimings>,
+) {
+    if let Err(e) = record_run(&option.id, run_id, action, completion, verify_failed, timings) {
+        eprintln!("warning: could not save {}: {}", state_file_path().display(), e);
+    }
+    let uninstall = action == RunAction::Uninstall;
+    if completion.success() && !option.targets.is_empty() {
+        let targets = if uninstall { Vec::new() } else { option.target_paths() };
+        if let Err(e) = save_target_hashes(&option.id, &hash_targets(&targets)) {
+            eprintln!("warning: could not save {}: {}", state_file_path().display(), e);
+        }
+    }
+    if option.requires_reboot && !uninstall && completion.success() {
+        let _ = add_pending_reboot(&option.id);
+        eprintln!("note: {} requires a reboot to take effect", option.name);
+    }
+}
+
+/// Run one install/uninstall, streaming output to stdout
+fn run_action(
+    script_dir: &Path,
+    entry: &Path,
+    option: &InstallOption,
+    uninstall: bool,
+    settings: RunSettings,
+    limit: Option<Duration>,
+    format: RunFormat,
+) -> Outcome {
+    let report = Report { format, option: &option.id };
+    let started_at = Instant::now();
+    let action = if uninstall {
+        RunAction::Uninstall
+    } else {
+        RunAction::Install
+    };
+    let run_id = RunId::new(&option.id, action);
+    let log = RunLog::start(&option.id, &run_id, &command_line(entry, &option.id, uninstall));
+
+    let (tx, rx) = mpsc::channel();
+    let mut timer = RunTimer::start();
+    let size = crossterm::terminal::size()
+        .map(|(cols, rows)| PtySize { rows, cols })
+        .unwrap_or_default();
+    // Headless runs can't be cancelled, so the handle is only read for
+    // timings and to stop a run past its time limit
+    let installer = run_install_command(script_dir, entry, option, uninstall, settings, size, tx);
+    let mut watch = limit.map(|limit| RunWatch::new(limit, Instant::now()));
+    if let Some(installer) = &installer {
+        let (spawned_at, clock) = installer.timing();
+        timer.spawned(spawned_at, clock);
+        if let Some(log) = &log {
+            log.line(&format!("# Priority: {}", describe_priority(installer.priority())));
+        }
+    }
+
+    let mut verify_failure = None;
+    while let Some(event) = next_event(&rx, watch.as_mut(), installer.as_ref(), option, &report) {
+        match event {
+            InstallerEvent::OutputLine(line) => {
+                let started = Instant::now();
+                if let Some(log) = &log {
+                    log.line(&line);
+                }
+                report.lines(Stream::Installer, std::slice::from_ref(&line));
+                timer.ingested(started.elapsed());
+            }
+            InstallerEvent::OutputLines(lines) => {
+                let started = Instant::now();
+                if let Some(log) = &log {
+                    log.lines(&lines);
+                }
+                report.lines(Stream::Stdout, &lines);
+                timer.ingested(started.elapsed());
+            }
+            InstallerEvent::ErrorLines(lines) => {
+                let started = Instant::now();
+                if let Some(log) = &log {
+                    log.lines(&lines);
+                }
+                report.lines(Stream::Stderr, &lines);
+                timer.ingested(started.elapsed());
+            }
+            InstallerEvent::Frame(line) => {
+                if let Some(log) = &log {
+                    log.line(&line);
+                }
+                report.frame(&line);
+            }
+            // Each partial is superseded by the completed line that follows
+            InstallerEvent::PartialLine(_) => {}
+            InstallerEvent::VerifyFailed(reason) => {
+                report.warning(&format!("installed but verification failed: {}", reason));
+                verify_failure = Some(reason);
+            }
+            // Hooks don't decide success
+            InstallerEvent::HookFailed(reason) => {
+                report.warning(&format!("post-install hook failed: {}", reason));
+            }
+            InstallerEvent::Completed(completion) => {
+                // One-shot tasks leave the state file alone
+                if option.tracks_state() {
+                    record_outcome(option, &run_id, action, completion, verify_failure.is_some(), timer.finish());
+                }
+                if let Some(log) = log {
+                    let result = match &verify_failure {
+                        Some(reason) => format!("{}, verification failed: {}", completion, reason),
+                        None => completion.to_string(),
+                    };
+                    let _ = log.finish(&result).join();
+                }
+                let outcome = Outcome::of_run(completion, verify_failure.as_deref());
+                if format == RunFormat::Events {
+                    let duration_ms = started_at.elapsed().as_millis() as u64;
+                    let success = outcome == Outcome::Succeeded;
+                    emit(&StreamEvent::completed(&option.id, completion, success, duration_ms));
+                }
+                return outcome;
+            }
+            InstallerEvent::Cancelled => {
+                report.ended("cancelled");
+                return Outcome::Failed("cancelled".to_string());
+            }
+            InstallerEvent::Error(err) => {
+                report.error(&err);
+                if let Some(log) = log {
+                    log.line(&format!("Error: {}", err));
+                    let _ = log.finish("error").join();
+                }
+                return Outcome::Failed(err);
+            }
+        }
+    }
+
+    let reason = "installer stopped without reporting";
+    report.ended(reason);
+    Outcome::Failed(reason.to_string())
+}
+
+/// Wait for the next installer event, warning about a run that takes long
+/// and killing it at its time limit; None once the installer has gone
+fn next_event(
+    rx: &Receiver<InstallerEvent>,
+    mut watch: Option<&mut RunWatch>,
+    installer: Option<&InstallerHandle>,
+    option: &InstallOption,
+    report: &Report,
+) -> Option<InstallerEvent> {
+    loop {
+        let Some(wait) = watch.as_ref().and_then(|watch| watch.until_next(Instant::now())) else {
+            return rx.recv().ok();
+        };
+        match rx.recv_timeout(wait) {
+            Ok(event) => return Some(event),
+            Err(RecvTimeoutError::Disconnected) => return None,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        let Some(watch) = watch.as_deref_mut() else {
+            continue;
+        };
+        let limit = describe_limit(watch.limit());
+        match watch.advance(Instant::now()) {
+            Some(Overrun::Overdue) => {
+                report.warning(&format!("{} is running longer than expected ({} limit)", option.name, limit));
+            }
+            Some(Overrun::Expired) => {
+                report.warning(&format!("{} ran past its {} limit; stopping it", option.name, limit));
+                if let Some(installer) = installer {
+                    installer.time_out();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> Vec<InstallOption> {
+        let option = |id: &str, track_state: Option<bool>| InstallOption {
+            id: id.to_string(),
+            name: id.to_string(),
+            track_state,
+            ..InstallOption::default()
+        };
+        vec![option("fish", None), option("waybar", None), option("font-cache", Some(false))]
+    }
+
+    #[test]
+    fn one_shot_options_are_listed_as_not_tracked() {
+        let options = options();
+        // Left over from before font-cache stopped tracking state
+        let installed: HashSet<String> = ["fish", "font-cache"].map(String::from).into();
+        let statuses: Vec<&str> = options.iter().map(|o| listed_status(o, &installed)).collect();
+        assert_eq!(statuses, ["installed", "not installed", "not tracked"]);
+    }
+
+    #[test]
+    fn one_shot_options_are_never_listed_as_installed_in_json() {
+        let options = options();
+        let installed: HashSet<String> = ["fish", "font-cache"].map(String::from).into();
+        let listed = listed_options(&options, &installed, &HashMap::new());
+        let installed: Vec<(&str, bool)> = listed.iter().map(|o| (o.id, o.installed)).collect();
+        assert_eq!(installed, [("fish", true), ("waybar", false), ("font-cache", false)]);
+    }
+}
@@ -0,0 +1,98 @@
+//! Keyboard event normalization
+//!
+//! Terminals speaking the kitty keyboard protocol report repeats and
+//! releases as well as presses, while legacy terminals only send presses.
+//! Actions fire on presses alone; held keys may repeat for navigation and
+//! scrolling, but never for keys that start or confirm something.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+/// Whether a key event should be handled
+pub fn accept_key(key: &KeyEvent) -> bool {
+    match key.kind {
+        KeyEventKind::Press => true,
+        KeyEventKind::Repeat => is_repeatable(key),
+        KeyEventKind::Release => false,
+    }
+}
+
+/// Keys that are safe to auto-repeat while held
+fn is_repeatable(key: &KeyEvent) -> bool {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown | KeyCode::Backspace => true,
+        KeyCode::Char('j') | KeyCode::Char('k') => !ctrl,
+        KeyCode::Char('u') | KeyCode::Char('d') => ctrl,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyEventState;
+
+    use super::*;
+
+    fn event(code: KeyCode, modifiers: KeyModifiers, kind: KeyEventKind) -> KeyEvent {
+        KeyEvent {
+            code,
+            modifiers,
+            kind,
+            state: KeyEventState::NONE,
+        }
+    }
+
+    /// Key events a terminal sends for `code` held through `repeats` repeats
+    fn held(code: KeyCode, modifiers: KeyModifiers, repeats: usize) -> Vec<KeyEvent> {
+        let mut events = vec![event(code, modifiers, KeyEventKind::Press)];
+        events.extend((0..repeats).map(|_| event(code, modifiers, KeyEventKind::Repeat)));
+        events.push(event(code, modifiers, KeyEventKind::Release));
+        events
+    }
+
+    fn accepted(events: &[KeyEvent]) -> usize {
+        events.iter().filter(|key| accept_key(key)).count()
+    }
+
+    #[test]
+    fn presses_are_always_handled() {
+        for code in [KeyCode::Enter, KeyCode::Esc, KeyCode::Char('q'), KeyCode::Char('i'), KeyCode::Up] {
+            assert!(accept_key(&event(code, KeyModifiers::NONE, KeyEventKind::Press)), "{:?}", code);
+        }
+        // Legacy terminals send plain presses, built like this
+        assert!(accept_key(&KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn releases_are_never_handled() {
+        for code in [KeyCode::Enter, KeyCode::Down, KeyCode::Char('j')] {
+            assert!(!accept_key(&event(code, KeyModifiers::NONE, KeyEventKind::Release)), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn held_enter_triggers_at_most_one_action() {
+        assert_eq!(accepted(&held(KeyCode::Enter, KeyModifiers::NONE, 20)), 1);
+        assert_eq!(accepted(&held(KeyCode::Char(' '), KeyModifiers::NONE, 20)), 1);
+        assert_eq!(accepted(&held(KeyCode::Char('i'), KeyModifiers::NONE, 20)), 1);
+        assert_eq!(accepted(&held(KeyCode::Char('u'), KeyModifiers::NONE, 20)), 1);
+    }
+
+    #[test]
+    fn navigation_and_scrolling_repeat_while_held() {
+        for code in [KeyCode::Up, KeyCode::Down, KeyCode::PageUp, KeyCode::PageDown, KeyCode::Backspace] {
+            assert_eq!(accepted(&held(code, KeyModifiers::NONE, 5)), 6, "{:?}", code);
+        }
+        assert_eq!(accepted(&held(KeyCode::Char('j'), KeyModifiers::NONE, 5)), 6);
+        assert_eq!(accepted(&held(KeyCode::Char('k'), KeyModifiers::NONE, 5)), 6);
+        assert_eq!(accepted(&held(KeyCode::Char('u'), KeyModifiers::CONTROL, 5)), 6);
+        assert_eq!(accepted(&held(KeyCode::Char('d'), KeyModifiers::CONTROL, 5)), 6);
+    }
+
+    #[test]
+    fn modifiers_decide_whether_a_letter_repeats() {
+        // Ctrl+K cancels a run, so it must not repeat like k does
+        assert!(!accept_key(&event(KeyCode::Char('k'), KeyModifiers::CONTROL, KeyEventKind::Repeat)));
+        assert!(!accept_key(&event(KeyCode::Char('d'), KeyModifiers::NONE, KeyEventKind::Repeat)));
+    }
+}
@@ -1,92 +1,1132 @@
 //! Subprocess execution for install script
+//!
+//! The script runs on a pseudo-terminal so it behaves as it would in a real
+//! terminal: pacman keeps its progress bars, tools don't switch to block
+//! buffering, and `sudo` prompts become visible. Only stderr goes through a
+//! pipe of its own, so warnings and errors can be told apart from the rest;
+//! the two streams are read separately, so their relative order is only as
+//! exact as the reads.
 
-use std::io::{BufRead, BufReader};
-use std::path::Path;
-use std::process::{Command, Stdio};
-use std::sync::mpsc::Sender;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::iter;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::check::{run_check, run_check_streaming};
+use crate::export::shell_quote;
+use crate::options::{InstallOption, IoniceClass};
+use crate::prefs::Priority;
+use crate::sandbox::Sandbox;
+use crate::timings::StageClock;
+
+/// Grace period between SIGTERM and SIGKILL when cancelling
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+/// Time allowed for an option's verify command
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Time allowed for each post-install hook
+const HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long to wait for remaining output after the process exits
+const READER_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How the script ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completion {
+    /// It exited with this code
+    Exited(i32),
+    /// A signal killed it; there is no exit code
+    Signaled(i32),
+    /// It ran past its time limit and was killed (see timeout.rs)
+    TimedOut,
+}
+
+impl Completion {
+    /// Read a finished process's status
+    pub fn from_status(status: ExitStatus) -> Self {
+        match (status.code(), status.signal()) {
+            (Some(code), _) => Completion::Exited(code),
+            (None, Some(signal)) => Completion::Signaled(signal),
+            // Neither is possible for a process that was waited for
+            (None, None) => Completion::Exited(-1),
+        }
+    }
+
+    pub fn success(self) -> bool {
+        self == Completion::Exited(0)
+    }
+
+    /// Number stored as the exit code: a signal counts as 128 plus its
+    /// number, as shells report it, and a timeout as 124, as timeout(1)
+    /// reports it, so neither is ever mistaken for success
+    pub fn code(self) -> i32 {
+        match self {
+            Completion::Exited(code) => code,
+            Completion::Signaled(signal) => 128 + signal,
+            Completion::TimedOut => 124,
+        }
+    }
+
+    /// Status-bar wording of a failure, e.g. "Killed by SIGKILL, likely OOM"
+    pub fn failure_headline(self) -> String {
+        match self {
+            Completion::Exited(code) => format!("Failed with exit code {}", code),
+            Completion::Signaled(_) | Completion::TimedOut => {
+                let mut text = self.to_string();
+                text[..1].make_ascii_uppercase();
+                text
+            }
+        }
+    }
+}
+
+/// "exit code 3" or "killed by SIGSEGV, crashed"
+impl fmt::Display for Completion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Completion::Exited(code) => write!(f, "exit code {}", code),
+            Completion::Signaled(signal) => {
+                match signal_name(signal) {
+                    Some(name) => write!(f, "killed by {}", name)?,
+                    None => write!(f, "killed by signal {}", signal)?,
+                }
+                match signal_hint(signal) {
+                    Some(hint) => write!(f, ", {}", hint),
+                    None => Ok(()),
+                }
+            }
+            Completion::TimedOut => write!(f, "timed out"),
+        }
+    }
+}
+
+/// Name of a signal that commonly ends a script
+fn signal_name(signal: i32) -> Option<&'static str> {
+    Some(match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        _ => return None,
+    })
+}
+
+/// The usual reason behind a signal, where there is one
+///
+/// Cancelling is reported separately, so a SIGKILL here came from outside;
+/// most often that is the kernel's out-of-memory killer.
+fn signal_hint(signal: i32) -> Option<&'static str> {
+    match signal {
+        libc::SIGKILL => Some("likely OOM"),
+        libc::SIGSEGV | libc::SIGBUS | libc::SIGILL | libc::SIGFPE => Some("crashed"),
+        libc::SIGABRT => Some("aborted"),
+        libc::SIGHUP => Some("terminal closed"),
+        _ => None,
+    }
+}
 
 /// Events sent from the installer subprocess
 #[derive(Debug, Clone)]
 pub enum InstallerEvent {
-    /// A line of output from the subprocess
+    /// A line of output from the installer itself, such as a verify header
     OutputLine(String),
-    /// The process completed with an exit code
-    Completed(i32),
+    /// A line framing the script's output: the command run, then how it ended
+    Frame(String),
+    /// Complete lines the subprocess wrote to the terminal, in one read
+    OutputLines(Vec<String>),
+    /// Complete lines the subprocess wrote to stderr, in one read
+    ErrorLines(Vec<String>),
+    /// Text after the last newline so far, such as a prompt awaiting input
+    PartialLine(String),
+    /// The install succeeded but its verify command did not, with the reason
+    VerifyFailed(String),
+    /// A post-install hook failed, with the reason; the install still counts
+    HookFailed(String),
+    /// The process exited or was killed by a signal
+    Completed(Completion),
+    /// The process was terminated at the user's request
+    Cancelled,
     /// An error occurred
     Error(String),
 }
 
+/// Terminal dimensions reported to the subprocess
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PtySize {
+    pub rows: u16,
+    pub cols: u16,
+}
+
+impl Default for PtySize {
+    fn default() -> Self {
+        Self { rows: 24, cols: 80 }
+    }
+}
+
+impl PtySize {
+    fn winsize(self) -> libc::winsize {
+        libc::winsize {
+            ws_row: self.rows.max(1),
+            ws_col: self.cols.max(1),
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
+
+/// Handle to a running install subprocess
+///
+/// The child leads its own session (and so process group) so cancelling also
+/// reaches anything it spawned (pacman, curl, ...).
+#[derive(Debug)]
+pub struct InstallerHandle {
+    pid: u32,
+    master: File,
+    cancelled: Arc<AtomicBool>,
+    /// Set when the cancel was for running past the time limit
+    timed_out: Arc<AtomicBool>,
+    exited: Arc<AtomicBool>,
+    spawned_at: Instant,
+    clock: Arc<StageClock>,
+    /// Niceness read back from the child, and the I/O class it was given
+    priority: Priority,
+}
+
+impl InstallerHandle {
+    /// Tell the subprocess its terminal changed size (it receives SIGWINCH)
+    pub fn resize(&self, size: PtySize) {
+        let winsize = size.winsize();
+        // SAFETY: TIOCSWINSZ reads a winsize struct that outlives the call
+        unsafe {
+            libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &winsize);
+        }
+    }
+
+    /// Send keyboard input to the subprocess's terminal
+    pub fn write_input(&self, bytes: &[u8]) {
+        let _ = (&self.master).write_all(bytes);
+    }
+
+    /// Whether the terminal echoes input; off while a password is being read
+    pub fn echo_enabled(&self) -> bool {
+        // SAFETY: tcgetattr fills a termios struct we own
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            libc::tcgetattr(self.master.as_raw_fd(), &mut termios) != 0
+                || termios.c_lflag & libc::ECHO != 0
+        }
+    }
+
+    /// When the child was spawned, and the clock noting its first output and exit
+    pub fn timing(&self) -> (Instant, Arc<StageClock>) {
+        (self.spawned_at, Arc::clone(&self.clock))
+    }
+
+    /// Priority the child actually started at, as written to the run's log
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Terminate the process group for running past its time limit; the run
+    /// then completes as timed out rather than cancelled
+    pub fn time_out(&self) {
+        self.timed_out.store(true, Ordering::SeqCst);
+        self.cancel();
+    }
+
+    /// Terminate the process group, escalating to SIGKILL if it lingers
+    pub fn cancel(&self) {
+        if self.exited.load(Ordering::SeqCst) || self.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        signal_group(self.pid, libc::SIGTERM);
+
+        let pid = self.pid;
+        let exited = Arc::clone(&self.exited);
+        thread::spawn(move || {
+            thread::sleep(KILL_GRACE_PERIOD);
+            if !exited.load(Ordering::SeqCst) {
+                signal_group(pid, libc::SIGKILL);
+            }
+        });
+    }
+
+    /// Kill the process group at once, without waiting out the grace period;
+    /// the run still completes as cancelled
+    pub fn kill(&self) {
+        if self.exited.load(Ordering::SeqCst) {
+            return;
+        }
+        self.cancelled.store(true, Ordering::SeqCst);
+        signal_group(self.pid, libc::SIGKILL);
+    }
+}
+
+/// Send a signal to every process in the group led by `pid`
+fn signal_group(pid: u32, signal: libc::c_int) {
+    // SAFETY: kill(2) has no memory-safety requirements; a negative pid targets the group
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), signal);
+    }
+}
+
+/// Forward output read from the PTY master or the stderr pipe as events,
+/// signalling `done` at EOF
+///
+/// The complete lines of each read go out together as one `lines` event
+/// (`OutputLines` or `ErrorLines`), so a flood of output costs one message
+/// per read instead of one per line. An unterminated tail (a prompt, or a
+/// progress bar redrawing itself after `\r`) is sent as a `PartialLine`
+/// that replaces the previous one, so it shows before the line ends. PTY
+/// reads fail with EIO once every slave descriptor is closed, which ends
+/// the stream.
+fn stream_output(
+    mut master: impl Read + Send + 'static,
+    lines_event: fn(Vec<String>) -> InstallerEvent,
+    tx: Sender<InstallerEvent>,
+    done: Sender<()>,
+    clock: Arc<StageClock>,
+) {
+    thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        let mut pending = Vec::new();
+        loop {
+            let n = match master.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            };
+            clock.output();
+            pending.extend_from_slice(&buf[..n]);
+            if let Some(end) = pending.iter().rposition(|&b| b == b'\n') {
+                let complete: Vec<u8> = pending.drain(..=end).collect();
+                let lines = complete[..end].split(|&b| b == b'\n').map(clean_line).collect();
+                let _ = tx.send(lines_event(lines));
+            }
+            if !pending.is_empty() {
+                // Progress redraws before the last \r are dead; a trailing \r may
+                // still turn out to be half of \r\n
+                let redraws = &pending[..pending.len() - 1];
+                if let Some(pos) = redraws.iter().rposition(|&b| b == b'\r') {
+                    pending.drain(..=pos);
+                }
+                let _ = tx.send(InstallerEvent::PartialLine(clean_line(&pending)));
+            }
+        }
+        if !pending.is_empty() {
+            let _ = tx.send(lines_event(vec![clean_line(&pending)]));
+        }
+        let _ = done.send(());
+    });
+}
+
+/// Decode a line of terminal output
+fn clean_line(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    // The terminal translates \n to \r\n; a progress bar redraws after bare \r
+    let text = text.trim_end_matches('\r');
+    text.rsplit('\r').next().unwrap_or(text).to_string()
+}
+
+/// Allocate a pseudo-terminal pair of the given size, returning (master, slave)
+fn open_pty(size: PtySize) -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut master = -1;
+    let mut slave = -1;
+    let winsize = size.winsize();
+    // SAFETY: openpty writes two descriptors we take ownership of on success
+    let result = unsafe {
+        libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &winsize)
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: both descriptors were just opened and are owned by nobody else
+    unsafe { Ok((OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave))) }
+}
+
+/// How the install script will be spawned, including priority wrappers
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnPlan {
+    /// Program to execute (the script, or ionice wrapping it)
+    pub program: PathBuf,
+    /// Arguments passed to `program` (OS strings, since the script path may not be UTF-8)
+    pub args: Vec<OsString>,
+    /// Niceness to set in the child before exec
+    pub nice: Option<i32>,
+    /// I/O class ionice starts the script in
+    pub ionice_class: Option<IoniceClass>,
+    /// Problems applying the requested priority; the run proceeds regardless
+    pub warnings: Vec<String>,
+}
+
+/// Build the spawn plan for running option `option_id` at `priority`
+///
+/// `ionice` is the resolved path of the ionice binary, if one was found,
+/// and `current_nice` the niceness of this process.
+pub fn build_spawn_plan(
+    install_script: &Path,
+    option_id: &str,
+    priority: Priority,
+    uninstall: bool,
+    ionice: Option<&Path>,
+    current_nice: i32,
+    is_root: bool,
+) -> SpawnPlan {
+    let mut script_args: Vec<OsString> = Vec::new();
+    if uninstall {
+        script_args.push("uninstall".into());
+    }
+    script_args.push(option_id.into());
+
+    let mut warnings = Vec::new();
+
+    // Lowering niceness below the current value needs privileges
+    let nice = match priority.nice {
+        Some(nice) if nice < current_nice && !is_root => {
+            warnings.push(format!(
+                "warning: cannot raise priority to nice {} without root; running at nice {}",
+                nice, current_nice
+            ));
+            None
+        }
+        other => other,
+    };
+
+    // ionice refuses to run the script at all when it can't set the class
+    let ionice_class = match priority.ionice_class {
+        Some(IoniceClass::Realtime) if !is_root => {
+            warnings.push("warning: the realtime I/O class needs root; running without one".to_string());
+            None
+        }
+        other => other,
+    };
+
+    let (program, args) = match (ionice_class, ionice) {
+        (Some(class), Some(ionice)) => {
+            let mut args: Vec<OsString> = vec!["-c".into(), class.as_arg().into(), "--".into()];
+            args.push(install_script.as_os_str().to_os_string());
+            args.extend(script_args);
+            (ionice.to_path_buf(), args)
+        }
+        (Some(_), None) => {
+            warnings.push("warning: ionice not found; running without an I/O scheduling class".to_string());
+            (install_script.to_path_buf(), script_args)
+        }
+        (None, _) => (install_script.to_path_buf(), script_args),
+    };
+
+    SpawnPlan {
+        program,
+        args,
+        nice,
+        ionice_class: ionice_class.filter(|_| ionice.is_some()),
+        warnings,
+    }
+}
+
+/// Warning for a child that didn't end up at the niceness it was given
+fn nice_warning(wanted: Option<i32>, actual: Option<i32>) -> Option<String> {
+    match (wanted, actual) {
+        (Some(wanted), Some(actual)) if wanted != actual => Some(format!(
+            "warning: could not set nice {}; running at nice {}",
+            wanted, actual
+        )),
+        _ => None,
+    }
+}
+
+/// Niceness of process `pid`, None if it can't be read
+fn niceness_of(pid: u32) -> Option<i32> {
+    // SAFETY: getpriority only reads a process attribute. -1 is a valid
+    // niceness, so errno is cleared first to tell it from a failure.
+    unsafe {
+        *libc::__errno_location() = 0;
+        let nice = libc::getpriority(libc::PRIO_PROCESS, pid as libc::id_t);
+        (nice != -1 || *libc::__errno_location() == 0).then_some(nice)
+    }
+}
+
+/// Log wording of the priority a run started at, e.g. "nice 10, ionice idle"
+pub fn describe_priority(priority: Priority) -> String {
+    let nice = priority.nice.map_or_else(|| "unknown".to_string(), |nice| nice.to_string());
+    let class = priority.ionice_class.map_or("none", IoniceClass::name);
+    format!("nice {}, ionice {}", nice, class)
+}
+
+/// How a run is carried out, beyond the option and action
+#[derive(Debug, Clone, Default)]
+pub struct RunSettings {
+    /// Run the option's verify command after a successful install
+    pub verify: bool,
+    /// Contain the script in this sandbox
+    pub sandbox: Option<Sandbox>,
+    /// Variables set over the inherited environment (see script_env.rs)
+    pub env: Vec<(String, String)>,
+    /// Niceness and I/O class to run the script at
+    pub priority: Priority,
+}
+
+/// Human-readable command line for an action, as used in logs
+pub fn command_line(script: &Path, option_id: &str, uninstall: bool) -> String {
+    if uninstall {
+        format!("{} uninstall {}", script.display(), option_id)
+    } else {
+        format!("{} {}", script.display(), option_id)
+    }
+}
+
+/// Whether a file exists with any execute bit set
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+/// Find an executable in PATH
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
 /// Run an install/uninstall command asynchronously
 ///
-/// Spawns the subprocess and streams output via the provided sender.
+/// `install_script` is the entry script, run with `script_dir` as its
+/// working directory. Spawns the subprocess on a PTY of the given size and streams output via
+/// the provided sender, inside `settings.sandbox` if there is one. After a
+/// successful install the option's verify command runs too, unless
+/// `settings.verify` is false, followed by its post-install hooks, outside
+/// any sandbox so they can reach the desktop session. Returns a handle for
+/// cancellation and resizing, or `None` if spawning failed (in which case an
+/// `Error` event has already been sent).
 pub fn run_install_command(
     script_dir: &Path,
-    option_id: &str,
+    install_script: &Path,
+    option: &InstallOption,
     uninstall: bool,
+    settings: RunSettings,
+    size: PtySize,
     event_tx: Sender<InstallerEvent>,
-) {
-    let install_script = script_dir.join("install");
-    let script_dir = script_dir.to_path_buf();
-    let option_id = option_id.to_string();
+) -> Option<InstallerHandle> {
+    // Spawning would only say "permission denied"; say what to do about it
+    if !is_executable(install_script) {
+        let _ = event_tx.send(InstallerEvent::Error(format!(
+            "{} is not executable - run chmod +x on it",
+            install_script.display()
+        )));
+        return None;
+    }
 
-    thread::spawn(move || {
-        let mut cmd = Command::new(&install_script);
-        cmd.current_dir(&script_dir);
-
-        if uninstall {
-            cmd.arg("uninstall");
-        }
-        cmd.arg(&option_id);
-
-        // Capture stdout and stderr
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        match cmd.spawn() {
-            Ok(mut child) => {
-                // Stream stdout
-                if let Some(stdout) = child.stdout.take() {
-                    let tx = event_tx.clone();
-                    let reader = BufReader::new(stdout);
-                    thread::spawn(move || {
-                        for line in reader.lines().map_while(Result::ok) {
-                            let _ = tx.send(InstallerEvent::OutputLine(line));
-                        }
-                    });
-                }
+    let ionice = settings.priority.ionice_class.and_then(|_| find_in_path("ionice"));
+    // SAFETY: getpriority/geteuid only read process attributes
+    let (current_nice, is_root) = unsafe {
+        (
+            libc::getpriority(libc::PRIO_PROCESS, 0),
+            libc::geteuid() == 0,
+        )
+    };
+    let mut plan = build_spawn_plan(
+        install_script,
+        &option.id,
+        settings.priority,
+        uninstall,
+        ionice.as_deref(),
+        current_nice,
+        is_root,
+    );
+    for warning in &plan.warnings {
+        let _ = event_tx.send(InstallerEvent::OutputLine(warning.clone()));
+    }
+    if let Some(sandbox) = &settings.sandbox {
+        if let Err(e) = sandbox.prepare() {
+            let _ = event_tx.send(InstallerEvent::Error(format!("Can't set up the sandbox: {}", e)));
+            return None;
+        }
+        let _ = event_tx.send(InstallerEvent::OutputLine(format!(
+            "sandboxed: {}",
+            sandbox.preview(&plan.program, &plan.args)
+        )));
+        (plan.program, plan.args) = sandbox.wrap(&plan.program, &plan.args);
+    }
 
-                // Stream stderr
-                if let Some(stderr) = child.stderr.take() {
-                    let tx = event_tx.clone();
-                    let reader = BufReader::new(stderr);
-                    thread::spawn(move || {
-                        for line in reader.lines().map_while(Result::ok) {
-                            let _ = tx.send(InstallerEvent::OutputLine(line));
-                        }
-                    });
-                }
+    let spawn_error = |e: io::Error| {
+        let _ = event_tx.send(InstallerEvent::Error(format!(
+            "Failed to spawn install: {}",
+            e
+        )));
+    };
+
+    let (master, slave) = match open_pty(size) {
+        Ok(pair) => pair,
+        Err(e) => {
+            spawn_error(e);
+            return None;
+        }
+    };
+    let stdio = || slave.try_clone().map(Stdio::from);
+    let (stdin, stdout) = match (stdio(), stdio()) {
+        (Ok(stdin), Ok(stdout)) => (stdin, stdout),
+        (Err(e), _) | (_, Err(e)) => {
+            spawn_error(e);
+            return None;
+        }
+    };
+
+    let mut cmd = Command::new(&plan.program);
+    cmd.current_dir(script_dir);
+    cmd.args(&plan.args);
+    cmd.envs(settings.env.iter().map(|(name, value)| (name, value)));
+    cmd.stdin(stdin).stdout(stdout).stderr(Stdio::piped());
+
+    let nice = plan.nice;
+    // SAFETY: setsid, ioctl and setpriority are async-signal-safe. Leading a
+    // new session also makes the child a process group leader, so the whole
+    // tree can be signalled; the PTY slave (fd 0) becomes its controlling
+    // terminal. A failed setpriority mustn't stop the run, so it's found by
+    // reading the child's niceness back once it has started.
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            if let Some(nice) = nice {
+                libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+            }
+            Ok(())
+        });
+    }
+
+    let shown: Vec<String> = iter::once(plan.program.to_string_lossy())
+        .chain(plan.args.iter().map(|arg| arg.to_string_lossy()))
+        .map(|word| shell_quote(&word))
+        .collect();
+    let _ = event_tx.send(InstallerEvent::Frame(format!(
+        "$ {}  (cwd: {})",
+        shown.join(" "),
+        script_dir.display()
+    )));
 
-                // Wait for completion
-                match child.wait() {
-                    Ok(status) => {
-                        let exit_code = status.code().unwrap_or(-1);
-                        let _ = event_tx.send(InstallerEvent::Completed(exit_code));
+    let spawned = cmd.spawn();
+    let spawned_at = Instant::now();
+    // Only the child may hold the slave, or reads would never hit EOF
+    drop(cmd);
+    drop(slave);
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            spawn_error(e);
+            return None;
+        }
+    };
+
+    // The child exec'd, so whatever niceness it has now is the one it runs at
+    let applied_nice = niceness_of(child.id());
+    if let Some(warning) = nice_warning(plan.nice, applied_nice) {
+        let _ = event_tx.send(InstallerEvent::OutputLine(warning.clone()));
+        plan.warnings.push(warning);
+    }
+    let priority = Priority {
+        nice: applied_nice,
+        ionice_class: plan.ionice_class,
+    };
+
+    let errors = child.stderr.take();
+    let master = File::from(master);
+    let reader = match master.try_clone() {
+        Ok(reader) => reader,
+        Err(e) => {
+            // Without a reader the child would block once the PTY buffer fills
+            signal_group(child.id(), libc::SIGKILL);
+            let _ = child.wait();
+            spawn_error(e);
+            return None;
+        }
+    };
+
+    let handle = InstallerHandle {
+        pid: child.id(),
+        master,
+        cancelled: Arc::new(AtomicBool::new(false)),
+        timed_out: Arc::new(AtomicBool::new(false)),
+        exited: Arc::new(AtomicBool::new(false)),
+        spawned_at,
+        clock: Arc::default(),
+        priority,
+    };
+    let cancelled = Arc::clone(&handle.cancelled);
+    let timed_out = Arc::clone(&handle.timed_out);
+    let exited = Arc::clone(&handle.exited);
+    let clock = Arc::clone(&handle.clock);
+    let verify_command = option.verify.clone().filter(|_| settings.verify && !uninstall);
+    let post_install = if uninstall { Vec::new() } else { option.post_install.clone() };
+    let verify_dir = script_dir.to_path_buf();
+
+    thread::spawn(move || {
+        // Stream the terminal output and stderr
+        let (done_tx, done_rx) = mpsc::channel();
+        let mut readers = 1;
+        stream_output(reader, InstallerEvent::OutputLines, event_tx.clone(), done_tx.clone(), Arc::clone(&clock));
+        if let Some(errors) = errors {
+            stream_output(errors, InstallerEvent::ErrorLines, event_tx.clone(), done_tx, Arc::clone(&clock));
+            readers += 1;
+        }
+
+        // Wait for completion, then for the readers so no output trails the result.
+        // A daemon started by the script may keep the terminal open, so bound the wait.
+        let result = child.wait();
+        clock.exited();
+        exited.store(true, Ordering::SeqCst);
+        let deadline = Instant::now() + READER_DRAIN_TIMEOUT;
+        for _ in 0..readers {
+            if done_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())).is_err() {
+                break;
+            }
+        }
+        match result {
+            Ok(_) if timed_out.load(Ordering::SeqCst) => {
+                let _ = event_tx.send(InstallerEvent::Frame(format!("── {} ──", Completion::TimedOut)));
+                let _ = event_tx.send(InstallerEvent::Completed(Completion::TimedOut));
+            }
+            Ok(_) if cancelled.load(Ordering::SeqCst) => {
+                let _ = event_tx.send(InstallerEvent::Cancelled);
+            }
+            Ok(status) => {
+                let completion = Completion::from_status(status);
+                if let Some(command) = verify_command.filter(|_| completion.success()) {
+                    let _ = event_tx.send(InstallerEvent::OutputLine(format!("── Verifying: {} ──", command)));
+                    let outcome = run_check(&command, &verify_dir, VERIFY_TIMEOUT);
+                    for line in outcome.output.iter() {
+                        let _ = event_tx.send(InstallerEvent::OutputLine(line.clone()));
+                    }
+                    if let Some(failure) = outcome.failure() {
+                        let _ = event_tx.send(InstallerEvent::VerifyFailed(format!("`{}` {}", command, failure)));
                     }
-                    Err(e) => {
-                        let _ = event_tx.send(InstallerEvent::Error(format!(
-                            "Failed to wait for process: {}",
-                            e
-                        )));
+                }
+                for hook in post_install.iter().filter(|_| completion.success()) {
+                    let _ = event_tx.send(InstallerEvent::OutputLine(format!("── post-install hook: {} ──", hook)));
+                    let outcome = run_check_streaming(hook, &verify_dir, HOOK_TIMEOUT, |line| {
+                        let _ = event_tx.send(InstallerEvent::OutputLine(line.to_string()));
+                    });
+                    if let Some(failure) = outcome.failure() {
+                        let _ = event_tx.send(InstallerEvent::HookFailed(format!("`{}` {}", hook, failure)));
                     }
                 }
+                let _ = event_tx.send(InstallerEvent::Frame(format!("── {} ──", completion)));
+                let _ = event_tx.send(InstallerEvent::Completed(completion));
             }
             Err(e) => {
                 let _ = event_tx.send(InstallerEvent::Error(format!(
-                    "Failed to spawn install: {}",
+                    "Failed to wait for process: {}",
                     e
                 )));
             }
         }
     });
+
+    Some(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashSet, VecDeque};
+
+    use super::*;
+    use crate::options::IoniceClass;
+    use crate::state::AppState;
+
+    /// Hands out one chunk per read, like a terminal delivering output in bursts
+    struct Chunks(VecDeque<Vec<u8>>);
+
+    impl Read for Chunks {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some(chunk) = self.0.pop_front() else {
+                return Ok(0);
+            };
+            buf[..chunk.len()].copy_from_slice(&chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    /// Events `stream_output` sends for the given reads
+    fn streamed(chunks: &[&str]) -> Vec<InstallerEvent> {
+        let chunks: Vec<&[u8]> = chunks.iter().map(|chunk| chunk.as_bytes()).collect();
+        streamed_bytes(&chunks)
+    }
+
+    fn streamed_bytes(chunks: &[&[u8]]) -> Vec<InstallerEvent> {
+        let (tx, rx) = mpsc::channel();
+        let (done_tx, done_rx) = mpsc::channel();
+        let chunks = Chunks(chunks.iter().map(|chunk| chunk.to_vec()).collect());
+        stream_output(chunks, InstallerEvent::OutputLines, tx, done_tx, Arc::default());
+        done_rx.recv_timeout(Duration::from_secs(5)).expect("the stream ends");
+        rx.try_iter().collect()
+    }
+
+    fn priority(nice: Option<i32>, ionice_class: Option<IoniceClass>) -> Priority {
+        Priority { nice, ionice_class }
+    }
+
+    fn args(plan: &SpawnPlan) -> Vec<&str> {
+        plan.args.iter().map(|arg| arg.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn plain_options_run_the_script_directly() {
+        let script = Path::new("/scripts/install");
+        let plan = build_spawn_plan(script, "demo", priority(None, None), true, None, 0, false);
+        assert_eq!(plan.program, script);
+        assert_eq!(args(&plan), ["uninstall", "demo"]);
+        assert_eq!(plan.nice, None);
+        assert!(plan.warnings.is_empty());
+    }
+
+    #[test]
+    fn ionice_class_wraps_the_script() {
+        let ionice = Path::new("/usr/bin/ionice");
+        let priority = priority(Some(10), Some(IoniceClass::Idle));
+        let plan = build_spawn_plan(Path::new("/scripts/install"), "demo", priority, false, Some(ionice), 0, false);
+        assert_eq!(plan.program, ionice);
+        assert_eq!(args(&plan), ["-c", "3", "--", "/scripts/install", "demo"]);
+        assert_eq!(plan.nice, Some(10));
+        assert!(plan.warnings.is_empty());
+    }
+
+    #[test]
+    fn non_utf8_script_paths_reach_ionice_unchanged() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let script = Path::new(std::ffi::OsStr::from_bytes(b"/srv/cybex-\xff/install"));
+        let priority = priority(None, Some(IoniceClass::Idle));
+        let plan = build_spawn_plan(script, "demo", priority, false, Some(Path::new("/usr/bin/ionice")), 0, false);
+        assert_eq!(plan.args[3], script.as_os_str());
+    }
+
+    #[test]
+    fn missing_ionice_warns_and_runs_anyway() {
+        let priority = priority(None, Some(IoniceClass::BestEffort));
+        let plan = build_spawn_plan(Path::new("/scripts/install"), "demo", priority, false, None, 0, false);
+        assert_eq!(plan.program, Path::new("/scripts/install"));
+        assert_eq!(plan.warnings.len(), 1);
+        assert!(plan.warnings[0].contains("ionice not found"));
+    }
+
+    #[test]
+    fn raising_priority_needs_root() {
+        let script = Path::new("/scripts/install");
+        let plan = build_spawn_plan(script, "demo", priority(Some(-5), None), false, None, 0, false);
+        assert_eq!(plan.nice, None);
+        assert!(plan.warnings[0].contains("without root"));
+
+        let plan = build_spawn_plan(script, "demo", priority(Some(-5), None), false, None, 0, true);
+        assert_eq!(plan.nice, Some(-5));
+        assert!(plan.warnings.is_empty());
+
+        // Staying at or above the current niceness is always allowed
+        let plan = build_spawn_plan(script, "demo", priority(Some(5), None), false, None, 5, false);
+        assert_eq!(plan.nice, Some(5));
+    }
+
+    #[test]
+    fn realtime_io_needs_root() {
+        let priority = priority(None, Some(IoniceClass::Realtime));
+        let ionice = Some(Path::new("/usr/bin/ionice"));
+        let plan = build_spawn_plan(Path::new("/scripts/install"), "demo", priority, false, ionice, 0, false);
+        assert_eq!(plan.program, Path::new("/scripts/install"));
+        assert_eq!(plan.ionice_class, None);
+        assert!(plan.warnings[0].contains("needs root"));
+
+        let plan = build_spawn_plan(Path::new("/scripts/install"), "demo", priority, false, ionice, 0, true);
+        assert_eq!(plan.ionice_class, Some(IoniceClass::Realtime));
+        assert_eq!(args(&plan)[..2], ["-c", "1"]);
+    }
+
+    #[test]
+    fn a_niceness_that_did_not_apply_warns() {
+        assert_eq!(nice_warning(None, Some(0)), None);
+        assert_eq!(nice_warning(Some(10), Some(10)), None);
+        // A child gone before it could be read says nothing either way
+        assert_eq!(nice_warning(Some(10), None), None);
+        assert_eq!(
+            nice_warning(Some(-5), Some(0)).as_deref(),
+            Some("warning: could not set nice -5; running at nice 0")
+        );
+    }
+
+    #[test]
+    fn priority_is_described_for_the_log() {
+        assert_eq!(describe_priority(priority(Some(10), Some(IoniceClass::Idle))), "nice 10, ionice idle");
+        assert_eq!(describe_priority(priority(Some(0), None)), "nice 0, ionice none");
+        let unread = priority(None, Some(IoniceClass::BestEffort));
+        assert_eq!(describe_priority(unread), "nice unknown, ionice best-effort");
+    }
+
+    /// A scratch directory holding an executable `install` running `script_body`
+    fn scratch_script(name: &str, script_body: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("omarchy-cybex-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("install");
+        fs::write(&script, format!("#!/bin/sh\n{}\n", script_body)).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        (dir, script)
+    }
+
+    fn demo() -> InstallOption {
+        InstallOption {
+            id: "demo".to_string(),
+            ..InstallOption::default()
+        }
+    }
+
+    /// Every event of running `script_body` as `option`, up to its completion,
+    /// and the priority the run reported starting at
+    fn run_events(
+        name: &str,
+        script_body: &str,
+        option: &InstallOption,
+        settings: RunSettings,
+    ) -> (Vec<InstallerEvent>, Priority) {
+        let (dir, script) = scratch_script(name, script_body);
+        let (tx, rx) = mpsc::channel();
+        let handle = run_install_command(&dir, &script, option, false, settings, PtySize::default(), tx)
+            .expect("the script spawns");
+        let mut events = Vec::new();
+        loop {
+            let event = rx.recv_timeout(Duration::from_secs(10)).expect("the run finishes");
+            let last = matches!(event, InstallerEvent::Completed(_) | InstallerEvent::Cancelled);
+            if let InstallerEvent::Error(e) = &event {
+                panic!("{}", e);
+            }
+            events.push(event);
+            if last {
+                break;
+            }
+        }
+        let _ = fs::remove_dir_all(&dir);
+        (events, handle.priority())
+    }
+
+    /// Output lines of running `script_body` at `priority`, which must
+    /// succeed, and the priority the run reported starting at
+    fn run_output(name: &str, script_body: &str, priority: Priority) -> (Vec<String>, Priority) {
+        let settings = RunSettings {
+            priority,
+            ..RunSettings::default()
+        };
+        let (events, applied) = run_events(name, script_body, &demo(), settings);
+        match events.last() {
+            Some(InstallerEvent::Completed(completion)) => assert!(completion.success(), "{}", completion),
+            other => panic!("ended with {:?}", other),
+        }
+        (lines_of(&events), applied)
+    }
+
+    /// How a run of `script_body` with `verify` ended, and its verify failure
+    fn verified(name: &str, script_body: &str, verify: &str, enabled: bool) -> (Completion, Option<String>) {
+        let option = InstallOption {
+            verify: Some(verify.to_string()),
+            ..demo()
+        };
+        let settings = RunSettings {
+            verify: enabled,
+            ..RunSettings::default()
+        };
+        let (events, _) = run_events(name, script_body, &option, settings);
+        let failure = events.iter().find_map(|event| match event {
+            InstallerEvent::VerifyFailed(reason) => Some(reason.clone()),
+            _ => None,
+        });
+        match events.last() {
+            Some(InstallerEvent::Completed(completion)) => (*completion, failure),
+            other => panic!("ended with {:?}", other),
+        }
+    }
+
+    #[test]
+    fn verification_runs_only_after_a_successful_install() {
+        // exit 0, verify passes
+        assert_eq!(verified("verify-ok", "exit 0", "true", true), (Completion::Exited(0), None));
+        // exit 0, verify fails
+        let (completion, failure) = verified("verify-bad", "exit 0", "exit 3", true);
+        assert_eq!(completion, Completion::Exited(0));
+        assert_eq!(failure.as_deref(), Some("`exit 3` exited with code 3"));
+        // exit 0, verify skipped for this run
+        assert_eq!(verified("verify-skip", "exit 0", "exit 3", false), (Completion::Exited(0), None));
+        // exit != 0: nothing to verify, skipped or not
+        assert_eq!(verified("failed", "exit 4", "exit 3", true), (Completion::Exited(4), None));
+        assert_eq!(verified("failed-skip", "exit 4", "exit 3", false), (Completion::Exited(4), None));
+    }
+
+    #[test]
+    fn child_runs_at_the_requested_niceness() {
+        // SAFETY: getpriority only reads a process attribute
+        let current = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+        let wanted = (current + 5).min(19);
+        // Field 19 of /proc/<pid>/stat is the niceness
+        let (output, applied) = run_output("nice", "cut -d' ' -f19 /proc/self/stat", priority(Some(wanted), None));
+        assert_eq!(output, [wanted.to_string()]);
+        assert_eq!(applied, priority(Some(wanted), None));
+    }
+
+    #[test]
+    fn child_runs_in_the_requested_io_class() {
+        if find_in_path("ionice").is_none() {
+            return;
+        }
+        let (output, applied) = run_output("ionice", "ionice -p $$", priority(None, Some(IoniceClass::Idle)));
+        assert_eq!(output, ["idle"]);
+        assert_eq!(applied.ionice_class, Some(IoniceClass::Idle));
+    }
+
+    #[test]
+    fn kill_ends_a_run_that_ignores_sigterm() {
+        let (dir, script) = scratch_script("kill", "trap '' TERM\necho ready\nsleep 30");
+        let (tx, rx) = mpsc::channel();
+        let handle = run_install_command(&dir, &script, &demo(), false, RunSettings::default(), PtySize::default(), tx)
+            .expect("the script spawns");
+        while !matches!(rx.recv_timeout(Duration::from_secs(10)), Ok(InstallerEvent::OutputLines(_))) {}
+
+        handle.cancel();
+        let killed_at = Instant::now();
+        handle.kill();
+        let ended = loop {
+            match rx.recv_timeout(Duration::from_secs(10)).expect("the run ends") {
+                InstallerEvent::Cancelled => break Instant::now(),
+                InstallerEvent::Completed(completion) => panic!("completed as {}", completion),
+                _ => {}
+            }
+        };
+        // Well inside the grace period the SIGTERM alone would have waited out
+        assert!(ended - killed_at < KILL_GRACE_PERIOD, "{:?}", ended - killed_at);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn completion_reads_exit_codes_and_signals() {
+        assert_eq!(Completion::from_status(ExitStatus::from_raw(3 << 8)), Completion::Exited(3));
+        assert_eq!(Completion::from_status(ExitStatus::from_raw(libc::SIGSEGV)), Completion::Signaled(libc::SIGSEGV));
+        assert!(Completion::from_status(ExitStatus::from_raw(0)).success());
+    }
+
+    #[test]
+    fn completion_code_is_never_zero_for_a_kill() {
+        assert_eq!(Completion::Exited(0).code(), 0);
+        assert_eq!(Completion::Exited(2).code(), 2);
+        assert_eq!(Completion::Signaled(libc::SIGKILL).code(), 137);
+        assert_eq!(Completion::Signaled(libc::SIGTERM).code(), 143);
+        assert_eq!(Completion::TimedOut.code(), 124);
+        assert!(!Completion::Signaled(0).success());
+    }
+
+    #[test]
+    fn completion_describes_how_the_script_ended() {
+        assert_eq!(Completion::Exited(3).to_string(), "exit code 3");
+        assert_eq!(Completion::Signaled(libc::SIGKILL).to_string(), "killed by SIGKILL, likely OOM");
+        assert_eq!(Completion::Signaled(libc::SIGTERM).to_string(), "killed by SIGTERM");
+        assert_eq!(Completion::Signaled(64).to_string(), "killed by signal 64");
+        assert_eq!(Completion::Exited(3).failure_headline(), "Failed with exit code 3");
+        assert_eq!(Completion::Signaled(libc::SIGSEGV).failure_headline(), "Killed by SIGSEGV, crashed");
+        assert_eq!(Completion::TimedOut.failure_headline(), "Timed out");
+    }
+
+    #[test]
+    fn carriage_return_redraws_collapse_before_coalescing() {
+        let events = streamed(&[
+            "pacman 10%\rpacman 50%",
+            "\rpacman 100%\r\nwarning: stale\r\n",
+            "warning: stale\r\nwarning: stale\r\n",
+        ]);
+        let partials: Vec<&str> = events
+            .iter()
+            .filter_map(|event| match event {
+                InstallerEvent::PartialLine(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(partials, ["pacman 50%"]);
+
+        let mut state = AppState::new(Vec::new(), HashSet::new(), None);
+        for event in events {
+            if let InstallerEvent::OutputLines(lines) = event {
+                lines.into_iter().for_each(|line| state.add_output_line(line));
+            }
+        }
+        let rows: Vec<(&str, usize)> = state
+            .output_lines
+            .iter()
+            .map(|line| (line.text.as_str(), line.repeat))
+            .collect();
+        assert_eq!(rows, [("pacman 100%", 1), ("warning: stale", 3)]);
+        assert_eq!(state.warning_count, 3);
+    }
+
+    #[test]
+    fn an_unterminated_tail_is_flushed_at_the_end() {
+        let events = streamed(&["done\r\nPassword: "]);
+        let last = events.iter().rev().find_map(|event| match event {
+            InstallerEvent::OutputLines(lines) => Some(lines.clone()),
+            _ => None,
+        });
+        assert_eq!(last, Some(vec!["Password: ".to_string()]));
+    }
+
+    /// Complete lines among `events`, in order
+    fn lines_of(events: &[InstallerEvent]) -> Vec<String> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                InstallerEvent::OutputLines(lines) => Some(lines.clone()),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_and_the_stream_goes_on() {
+        let events = streamed_bytes(&[b"latin-1: caf\xe9 au lait\r\nstill here\r\n", b"and here\r\n"]);
+        assert_eq!(lines_of(&events), ["latin-1: caf\u{fffd} au lait", "still here", "and here"]);
+    }
+
+    #[test]
+    fn bytes_split_across_reads_decode_as_one_line() {
+        // A valid character cut in two by the read, then junk spanning one
+        let events = streamed_bytes(&[b"caf\xc3", b"\xa9\r\njunk \xff", b"\xfe tail\r\nafter\r\n"]);
+        assert_eq!(lines_of(&events), ["caf\u{e9}", "junk \u{fffd}\u{fffd} tail", "after"]);
+    }
+
+    #[test]
+    fn a_script_printing_binary_junk_keeps_streaming() {
+        let script = "printf 'progress \\377\\376 50%%\\n'\necho after";
+        let (output, _) = run_output("junk", script, Priority::default());
+        assert_eq!(output, ["progress \u{fffd}\u{fffd} 50%", "after"]);
+    }
 }
@@ -0,0 +1,250 @@
+//! Batch journal for resuming an interrupted queue
+//!
+//! While a queue runs, its plan and per-item results are written to
+//! `batch-journal.json` in the state directory after every change. The file
+//! is removed when the queue ends in-session, so one that is still around at
+//! startup means the previous session quit or crashed midway.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::state_dir;
+
+/// Progress of a single queued option
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemStatus {
+    /// Not reached yet
+    Pending,
+    /// Started but never reported back; the outcome is unknown
+    Started,
+    Succeeded,
+    Failed,
+}
+
+/// One option in the batch plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalItem {
+    pub id: String,
+    pub status: ItemStatus,
+}
+
+/// Persisted plan and results of a queue run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchJournal {
+    pub items: Vec<JournalItem>,
+    #[serde(default)]
+    pub continue_on_failure: bool,
+}
+
+/// Counts shown when offering to resume
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JournalSummary {
+    pub done: usize,
+    pub failed: usize,
+    pub unknown: usize,
+    pub remaining: usize,
+}
+
+impl BatchJournal {
+    /// Start a journal for a new queue, everything pending
+    pub fn new(ids: &[String], continue_on_failure: bool) -> Self {
+        Self {
+            items: ids
+                .iter()
+                .map(|id| JournalItem {
+                    id: id.clone(),
+                    status: ItemStatus::Pending,
+                })
+                .collect(),
+            continue_on_failure,
+        }
+    }
+
+    /// Update an item's status and write the journal out
+    pub fn mark(&mut self, option_id: &str, status: ItemStatus) {
+        for item in self.items.iter_mut().filter(|item| item.id == option_id) {
+            item.status = status;
+        }
+        let _ = save_journal(self);
+    }
+
+    /// Whether any item still needs to run
+    pub fn is_incomplete(&self) -> bool {
+        self.items
+            .iter()
+            .any(|item| matches!(item.status, ItemStatus::Pending | ItemStatus::Started))
+    }
+
+    /// Tally the items by outcome
+    pub fn summary(&self) -> JournalSummary {
+        let count = |status| self.items.iter().filter(|item| item.status == status).count();
+        JournalSummary {
+            done: count(ItemStatus::Succeeded),
+            failed: count(ItemStatus::Failed),
+            unknown: count(ItemStatus::Started),
+            remaining: count(ItemStatus::Pending),
+        }
+    }
+
+    /// Option ids to run when resuming, in plan order
+    ///
+    /// Items whose outcome is unknown run again; failures only if asked.
+    pub fn resume_queue(&self, retry_failures: bool) -> Vec<String> {
+        self.items
+            .iter()
+            .filter(|item| match item.status {
+                ItemStatus::Pending | ItemStatus::Started => true,
+                ItemStatus::Failed => retry_failures,
+                ItemStatus::Succeeded => false,
+            })
+            .map(|item| item.id.clone())
+            .collect()
+    }
+}
+
+/// Path of the batch journal
+fn journal_path() -> PathBuf {
+    state_dir().join("batch-journal.json")
+}
+
+/// Load the journal left by an interrupted batch, if there is one
+pub fn load_journal() -> Option<BatchJournal> {
+    read_journal(&journal_path())
+}
+
+fn read_journal(path: &Path) -> Option<BatchJournal> {
+    let contents = fs::read_to_string(path).ok()?;
+    let journal: BatchJournal = serde_json::from_str(&contents).ok()?;
+    journal.is_incomplete().then_some(journal)
+}
+
+/// Write the journal
+pub fn save_journal(journal: &BatchJournal) -> Result<(), std::io::Error> {
+    write_journal(&journal_path(), journal)
+}
+
+fn write_journal(path: &Path, journal: &BatchJournal) -> Result<(), std::io::Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(journal)?)?;
+    Ok(())
+}
+
+/// Remove the journal once its batch is over
+pub fn discard_journal() {
+    let _ = fs::remove_file(journal_path());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    /// A journal with one item in each state, in plan order
+    fn midway() -> BatchJournal {
+        let mut journal = BatchJournal::new(&ids(&["done", "broken", "running", "next"]), true);
+        journal.items[0].status = ItemStatus::Succeeded;
+        journal.items[1].status = ItemStatus::Failed;
+        journal.items[2].status = ItemStatus::Started;
+        journal
+    }
+
+    fn scratch_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("omarchy-cybex-journal-{}-{}", std::process::id(), name))
+            .join("batch-journal.json")
+    }
+
+    fn statuses(journal: &BatchJournal) -> Vec<ItemStatus> {
+        journal.items.iter().map(|item| item.status).collect()
+    }
+
+    #[test]
+    fn new_journal_is_all_pending() {
+        let journal = BatchJournal::new(&ids(&["a", "b"]), false);
+        assert_eq!(statuses(&journal), [ItemStatus::Pending, ItemStatus::Pending]);
+        assert!(journal.is_incomplete());
+        assert!(!journal.continue_on_failure);
+    }
+
+    #[test]
+    fn summary_tallies_each_outcome() {
+        let summary = midway().summary();
+        assert_eq!(
+            summary,
+            JournalSummary {
+                done: 1,
+                failed: 1,
+                unknown: 1,
+                remaining: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn resume_reruns_unknown_and_pending_items() {
+        assert_eq!(midway().resume_queue(false), ids(&["running", "next"]));
+    }
+
+    #[test]
+    fn resume_retries_failures_when_asked() {
+        assert_eq!(midway().resume_queue(true), ids(&["broken", "running", "next"]));
+    }
+
+    #[test]
+    fn written_journal_reads_back() {
+        let path = scratch_path("roundtrip");
+        let journal = midway();
+        write_journal(&path, &journal).unwrap();
+        let read = read_journal(&path).unwrap();
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+        let read_ids: Vec<&str> = read.items.iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(read_ids, ["done", "broken", "running", "next"]);
+        assert_eq!(statuses(&read), statuses(&journal));
+        assert!(read.continue_on_failure);
+    }
+
+    #[test]
+    fn crash_midway_leaves_a_journal_to_resume() {
+        // The session died while "b" ran: it never reported back
+        let path = scratch_path("crash");
+        let mut journal = BatchJournal::new(&ids(&["a", "b", "c"]), false);
+        journal.items[0].status = ItemStatus::Succeeded;
+        journal.items[1].status = ItemStatus::Started;
+        write_journal(&path, &journal).unwrap();
+        let read = read_journal(&path);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+        let read = read.expect("an interrupted batch is offered for resuming");
+        assert_eq!(read.summary().unknown, 1);
+        assert_eq!(read.resume_queue(false), ids(&["b", "c"]));
+    }
+
+    #[test]
+    fn finished_journal_is_not_offered() {
+        let path = scratch_path("finished");
+        let mut journal = BatchJournal::new(&ids(&["a", "b"]), false);
+        journal.items[0].status = ItemStatus::Succeeded;
+        journal.items[1].status = ItemStatus::Failed;
+        write_journal(&path, &journal).unwrap();
+        let read = read_journal(&path);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+        assert!(read.is_none());
+    }
+
+    #[test]
+    fn unreadable_journal_is_ignored() {
+        let path = scratch_path("garbage");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{ not json").unwrap();
+        let read = read_journal(&path);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+        assert!(read.is_none());
+    }
+}
@@ -0,0 +1,39 @@
+//! Type-ahead selection: `'` then a few letters of an option's name
+//!
+//! Unlike the `/` filter nothing is hidden; the selection moves to the best
+//! match as each letter is typed, and the query ends after a second without
+//! a key, on Esc, or on any key that isn't part of a name.
+
+use std::time::Duration;
+
+/// How long a query survives without a key press
+pub const JUMP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How well `query` matches `name`, lower is better; None when it doesn't
+///
+/// The query's characters must appear in the name in order, ignoring case.
+/// Each is taken at its first chance, and every character skipped on the
+/// way counts against the match, so "fi" scores 0 on "Fish" and 10 on
+/// "Neovim config".
+pub fn score(query: &str, name: &str) -> Option<usize> {
+    let mut name = name.chars().flat_map(char::to_lowercase);
+    let mut skipped = 0;
+    for wanted in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            if name.next()? == wanted {
+                break;
+            }
+            skipped += 1;
+        }
+    }
+    Some(skipped)
+}
+
+/// The best match among `names`, earlier ones winning ties
+pub fn best_match<'a>(query: &str, names: impl IntoIterator<Item = (usize, &'a str)>) -> Option<usize> {
+    names
+        .into_iter()
+        .filter_map(|(index, name)| score(query, name).map(|score| (score, index)))
+        .min_by_key(|&(score, _)| score)
+        .map(|(_, index)| index)
+}
@@ -0,0 +1,430 @@
+//! User-configurable key bindings
+//!
+//! The `[keymap]` table of `config.toml` in the state directory maps action
+//! names to one key spec or a list of them:
+//!
+//! ```toml
+//! [keymap]
+//! up = ["up"]
+//! down = ["down"]
+//! confirm = "i"
+//! quit = ["q", "ctrl+q"]
+//! toggle-details = "D"
+//! ```
+//!
+//! Every key the browsing and running screens react to is an action here;
+//! see [`Action::name`] for the names. Actions left out keep their default keys. Problems with the file are
+//! collected rather than fatal; the defaults cover whatever couldn't be read.
+
+use std::collections::HashMap;
+use std::fs;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::state_dir;
+
+/// A bindable action
+///
+/// What each one does, and where, is described by the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Up,
+    Down,
+    Confirm,
+    Cancel,
+    ToggleOutput,
+    ScrollUp,
+    ScrollDown,
+    Queue,
+    InstallQueued,
+    ContinueOnFailure,
+    Mark,
+    Filter,
+    Collapse,
+    Expand,
+    Retry,
+    ToggleDetails,
+    SwitchLayout,
+    EditLayout,
+    Timings,
+    Help,
+    Update,
+    Reboot,
+    Pending,
+    HalfPageUp,
+    HalfPageDown,
+    Zen,
+    CancelRun,
+    CheckInstalled,
+    FocusNext,
+    FollowOutput,
+    CommandLine,
+    Profiles,
+    ExportScript,
+    LogHistory,
+    CheckUpdates,
+    RetrySave,
+    Shell,
+    Suspend,
+    Top,
+    Bottom,
+    NarrowList,
+    WidenList,
+    FullOutput,
+    Jump,
+    EditScript,
+}
+
+impl Action {
+    /// Every action, in the order the config file documents them
+    pub const ALL: [Action; 46] = [
+        Action::Quit,
+        Action::Up,
+        Action::Down,
+        Action::Confirm,
+        Action::Cancel,
+        Action::ToggleOutput,
+        Action::ScrollUp,
+        Action::ScrollDown,
+        Action::Queue,
+        Action::InstallQueued,
+        Action::ContinueOnFailure,
+        Action::Mark,
+        Action::Filter,
+        Action::Collapse,
+        Action::Expand,
+        Action::Retry,
+        Action::ToggleDetails,
+        Action::SwitchLayout,
+        Action::EditLayout,
+        Action::Timings,
+        Action::Help,
+        Action::Update,
+        Action::Reboot,
+        Action::Pending,
+        Action::HalfPageUp,
+        Action::HalfPageDown,
+        Action::Zen,
+        Action::CancelRun,
+        Action::CheckInstalled,
+        Action::FocusNext,
+        Action::FollowOutput,
+        Action::CommandLine,
+        Action::Profiles,
+        Action::ExportScript,
+        Action::LogHistory,
+        Action::CheckUpdates,
+        Action::RetrySave,
+        Action::Shell,
+        Action::Suspend,
+        Action::Top,
+        Action::Bottom,
+        Action::NarrowList,
+        Action::WidenList,
+        Action::FullOutput,
+        Action::Jump,
+        Action::EditScript,
+    ];
+
+    /// Name used in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Confirm => "confirm",
+            Action::Cancel => "cancel",
+            Action::ToggleOutput => "toggle-output",
+            Action::ScrollUp => "scroll-up",
+            Action::ScrollDown => "scroll-down",
+            Action::Queue => "queue",
+            Action::InstallQueued => "install-queued",
+            Action::ContinueOnFailure => "continue-on-failure",
+            Action::Mark => "mark",
+            Action::Filter => "filter",
+            Action::Collapse => "collapse",
+            Action::Expand => "expand",
+            Action::Retry => "retry",
+            Action::ToggleDetails => "toggle-details",
+            Action::SwitchLayout => "switch-layout",
+            Action::EditLayout => "edit-layout",
+            Action::Timings => "timings",
+            Action::Help => "help",
+            Action::Update => "update",
+            Action::Reboot => "reboot",
+            Action::Pending => "pending",
+            Action::HalfPageUp => "half-page-up",
+            Action::HalfPageDown => "half-page-down",
+            Action::Zen => "zen",
+            Action::CancelRun => "cancel-run",
+            Action::CheckInstalled => "check-installed",
+            Action::FocusNext => "focus-next",
+            Action::FollowOutput => "follow-output",
+            Action::CommandLine => "command-line",
+            Action::Profiles => "profiles",
+            Action::ExportScript => "export-script",
+            Action::LogHistory => "log-history",
+            Action::CheckUpdates => "check-updates",
+            Action::RetrySave => "retry-save",
+            Action::Shell => "shell",
+            Action::Suspend => "suspend",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::NarrowList => "narrow-list",
+            Action::WidenList => "widen-list",
+            Action::FullOutput => "full-output",
+            Action::Jump => "jump",
+            Action::EditScript => "edit-script",
+        }
+    }
+
+    /// Keys bound when the config doesn't say otherwise
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q"],
+            Action::Up => &["up", "k"],
+            Action::Down => &["down", "j"],
+            Action::Confirm => &["enter"],
+            Action::Cancel => &["esc"],
+            Action::ToggleOutput => &["o"],
+            Action::ScrollUp => &["pgup"],
+            Action::ScrollDown => &["pgdn"],
+            Action::Queue => &["space"],
+            Action::InstallQueued => &["i"],
+            Action::ContinueOnFailure => &["c"],
+            Action::Mark => &["m"],
+            Action::Filter => &["/"],
+            Action::Collapse => &["h", "left"],
+            Action::Expand => &["l", "right"],
+            Action::Retry => &["r"],
+            Action::ToggleDetails => &["d"],
+            Action::SwitchLayout => &["w"],
+            Action::EditLayout => &["ctrl+l"],
+            Action::Timings => &["t"],
+            Action::Help => &["?"],
+            Action::Update => &["U"],
+            Action::Reboot => &["R"],
+            Action::Pending => &["P"],
+            Action::HalfPageUp => &["ctrl+u"],
+            Action::HalfPageDown => &["ctrl+d"],
+            Action::Zen => &["ctrl+z"],
+            Action::CancelRun => &["esc", "ctrl+k"],
+            Action::CheckInstalled => &["v"],
+            Action::FocusNext => &["tab"],
+            Action::FollowOutput => &["G", "end"],
+            Action::CommandLine => &[":"],
+            Action::Profiles => &["p"],
+            Action::ExportScript => &["e"],
+            Action::LogHistory => &["L"],
+            Action::CheckUpdates => &["u"],
+            Action::RetrySave => &["S"],
+            Action::Shell => &["!", "ctrl+s"],
+            Action::Suspend => &["ctrl+z"],
+            Action::Top => &["home", "g"],
+            Action::Bottom => &["end", "G"],
+            Action::NarrowList => &["[", "ctrl+left"],
+            Action::WidenList => &["]", "ctrl+right"],
+            Action::FullOutput => &["f"],
+            Action::Jump => &["'"],
+            Action::EditScript => &["E"],
+        }
+    }
+}
+
+/// A key with modifiers, as written in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySpec {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeySpec {
+    /// Parse specs like "j", "enter", "pgdn" or "ctrl+c"
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        // A trailing "+" (as in "+" or "ctrl++") is the plus key itself
+        let (prefix, key) = match spec.rfind('+') {
+            Some(i) if i + 1 < spec.len() => (&spec[..i], &spec[i + 1..]),
+            Some(i) => (spec[..i].strip_suffix('+').unwrap_or(&spec[..i]), "+"),
+            None => ("", spec),
+        };
+        if key.is_empty() {
+            return Err(format!("empty key in {:?}", spec));
+        }
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in prefix.split('+').filter(|m| !m.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => return Err(format!("unknown modifier {:?} in {:?}", other, spec)),
+            };
+        }
+
+        let code = match key.to_ascii_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pgup" | "pageup" => KeyCode::PageUp,
+            "pgdn" | "pagedown" => KeyCode::PageDown,
+            lower => match (lower.strip_prefix('f').and_then(|n| n.parse().ok()), key.chars().count()) {
+                (Some(n), _) => KeyCode::F(n),
+                (None, 1) => KeyCode::Char(key.chars().next().unwrap_or(' ')),
+                _ => return Err(format!("unknown key {:?}", key)),
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+
+    /// Whether a key event is this key
+    ///
+    /// Shift is ignored for characters, which already carry their case.
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        let mut modifiers = key.modifiers;
+        if matches!(key.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        if let (KeyCode::Char(a), KeyCode::Char(b)) = (self.code, key.code) {
+            // Ctrl combinations may arrive in either case
+            let ctrl = self.modifiers.contains(KeyModifiers::CONTROL);
+            let same = if ctrl { a.eq_ignore_ascii_case(&b) } else { a == b };
+            return same && modifiers == self.modifiers;
+        }
+        self.code == key.code && modifiers == self.modifiers
+    }
+
+    /// Whether typing this key would produce text
+    pub fn is_text(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_))
+            && !self.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+
+    /// Short label for the footer
+    pub fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Enter => "Enter".to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::Backspace => "Bksp".to_string(),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::PageUp => "PgUp".to_string(),
+            KeyCode::PageDown => "PgDn".to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            // Ctrl combinations read as Ctrl+L whichever case they were written in
+            KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => c.to_ascii_uppercase().to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            other => format!("{:?}", other),
+        };
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push_str("Ctrl+");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("Alt+");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            label.push_str("Shift+");
+        }
+        label.push_str(&key);
+        label
+    }
+}
+
+/// Keys bound to each action
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<Action, Vec<KeySpec>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .map(|&action| {
+                let keys = action
+                    .default_keys()
+                    .iter()
+                    .filter_map(|spec| KeySpec::parse(spec).ok())
+                    .collect();
+                (action, keys)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Load the keymap from config.toml, with the problems found in it
+    pub fn load() -> (Self, Vec<String>) {
+        let path = state_dir().join("config.toml");
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return (Self::default(), Vec::new());
+        };
+        match contents.parse::<toml::Table>() {
+            Ok(table) => Self::from_table(&table),
+            Err(e) => (
+                Self::default(),
+                vec![format!("{}: {}", path.display(), e.message())],
+            ),
+        }
+    }
+
+    /// Build from a parsed config, falling back to defaults per action
+    fn from_table(table: &toml::Table) -> (Self, Vec<String>) {
+        let mut keymap = Self::default();
+        let mut problems = Vec::new();
+        let Some(section) = table.get("keymap") else {
+            return (keymap, problems);
+        };
+        let Some(section) = section.as_table() else {
+            return (keymap, vec!["keymap must be a table".to_string()]);
+        };
+
+        for (name, value) in section {
+            let Some(&action) = Action::ALL.iter().find(|a| a.name() == name) else {
+                problems.push(format!("keymap: unknown action {:?}", name));
+                continue;
+            };
+            let specs: Vec<&str> = match value {
+                toml::Value::String(spec) => vec![spec.as_str()],
+                toml::Value::Array(specs) => specs.iter().filter_map(|s| s.as_str()).collect(),
+                _ => {
+                    problems.push(format!("keymap.{}: expected a key or a list of keys", name));
+                    continue;
+                }
+            };
+            match specs.iter().map(|spec| KeySpec::parse(spec)).collect::<Result<Vec<_>, _>>() {
+                Ok(keys) if !keys.is_empty() => {
+                    keymap.bindings.insert(action, keys);
+                }
+                Ok(_) => problems.push(format!("keymap.{}: no keys given", name)),
+                Err(e) => problems.push(format!("keymap.{}: {}", name, e)),
+            }
+        }
+        (keymap, problems)
+    }
+
+    /// Keys bound to an action
+    pub fn keys(&self, action: Action) -> &[KeySpec] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Footer label for an action: its first key, e.g. "↑"
+    pub fn label(&self, action: Action) -> String {
+        self.keys(action).first().map(KeySpec::label).unwrap_or_default()
+    }
+}
@@ -0,0 +1,306 @@
+//! Per-run log files on disk
+//!
+//! Every install/uninstall run is teed to
+//! `~/.local/state/omarchy-cybex/logs/<option-id>-<timestamp>.log`. Writing
+//! happens on a dedicated thread so a slow disk never stalls rendering.
+//! Past runs are listed from the file names plus each log's header and
+//! trailer, so browsing them never reads a whole log.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+use chrono::Local;
+
+use crate::config::RunAction;
+use crate::run_id::RunId;
+
+/// Logs kept per option when OMARCHY_CYBEX_LOG_KEEP is unset
+const DEFAULT_LOGS_KEPT: usize = 10;
+
+/// Most of a log loaded for viewing; longer logs are shown from the end
+const VIEW_LIMIT_BYTES: u64 = 2 * 1024 * 1024;
+
+/// How far back from the end of a log the trailer is looked for
+const TRAILER_SEARCH_BYTES: u64 = 4096;
+
+/// Messages handled by the writer thread
+enum LogMessage {
+    Line(String),
+    Lines(Vec<String>),
+    Finish(String),
+}
+
+/// Handle to a log file being written in the background
+pub struct RunLog {
+    path: PathBuf,
+    tx: Sender<LogMessage>,
+    writer: JoinHandle<()>,
+}
+
+impl RunLog {
+    /// Create the log file for a run and write its header
+    ///
+    /// Returns `None` if the log directory can't be created; logging is best-effort.
+    pub fn start(option_id: &str, run_id: &RunId, command: &str) -> Option<Self> {
+        let dir = log_dir();
+        fs::create_dir_all(&dir).ok()?;
+
+        let started = Local::now();
+        let stem = format!("{}-{}", option_id, started.format("%Y%m%d-%H%M%S"));
+        let path = unique_path(&dir, &stem);
+        let file = File::create(&path).ok()?;
+
+        let header = format!(
+            "# Run: {}\n# Command: {}\n# Started: {}\n",
+            run_id,
+            command,
+            started.format("%Y-%m-%d %H:%M:%S %z")
+        );
+
+        let (tx, rx) = mpsc::channel();
+        let option_id = option_id.to_string();
+        let writer = thread::spawn(move || {
+            let mut writer = BufWriter::new(file);
+            let _ = writer.write_all(header.as_bytes());
+            for message in rx {
+                match message {
+                    LogMessage::Line(line) => {
+                        let _ = writeln!(writer, "{}", line);
+                    }
+                    LogMessage::Lines(lines) => {
+                        for line in lines {
+                            let _ = writeln!(writer, "{}", line);
+                        }
+                    }
+                    LogMessage::Finish(trailer) => {
+                        let _ = writeln!(writer, "{}", trailer);
+                        break;
+                    }
+                }
+            }
+            let _ = writer.flush();
+            drop(writer);
+            prune_logs(&dir, &option_id, logs_kept());
+        });
+
+        Some(Self { path, tx, writer })
+    }
+
+    /// Path of the log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a line of output
+    pub fn line(&self, line: &str) {
+        let _ = self.tx.send(LogMessage::Line(line.to_string()));
+    }
+
+    /// Append several lines of output at once
+    pub fn lines(&self, lines: &[String]) {
+        let _ = self.tx.send(LogMessage::Lines(lines.to_vec()));
+    }
+
+    /// Write the trailer describing how the run ended and close the file
+    ///
+    /// Returns the writer thread so callers about to exit can wait for the flush.
+    pub fn finish(self, result: &str) -> JoinHandle<()> {
+        let trailer = format!(
+            "# Finished: {} ({})",
+            Local::now().format("%Y-%m-%d %H:%M:%S %z"),
+            result
+        );
+        let _ = self.tx.send(LogMessage::Finish(trailer));
+        self.writer
+    }
+}
+
+/// Directory holding run logs
+pub fn log_dir() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("omarchy-cybex")
+        .join("logs")
+}
+
+/// Append a line about a manual state edit to `state-edits.log` in the log directory
+///
+/// Edits run nothing, so they get no run log and stay out of the history;
+/// this file is the only trace of them. Best-effort like the run logs.
+pub fn note_state_edit(edit: &str) {
+    let dir = log_dir();
+    let _ = fs::create_dir_all(&dir).and_then(|()| {
+        let mut file = OpenOptions::new().create(true).append(true).open(dir.join("state-edits.log"))?;
+        writeln!(file, "{} {}", Local::now().format("%Y-%m-%d %H:%M:%S %z"), edit)
+    });
+}
+
+/// Number of logs to keep per option
+fn logs_kept() -> usize {
+    env::var("OMARCHY_CYBEX_LOG_KEEP")
+        .ok()
+        .and_then(|keep| keep.trim().parse().ok())
+        .unwrap_or(DEFAULT_LOGS_KEPT)
+}
+
+/// Pick a file name that doesn't exist yet, suffixing `-N` on collisions
+fn unique_path(dir: &Path, stem: &str) -> PathBuf {
+    let mut path = dir.join(format!("{}.log", stem));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.log", stem, n));
+        n += 1;
+    }
+    path
+}
+
+/// Whether a log file name belongs to the given option
+///
+/// Option ids may prefix each other (`ssh` / `ssh-key`), so the remainder
+/// must look like the `YYYYmmdd-HHMMSS` timestamp.
+fn is_log_for(file_name: &str, option_id: &str) -> bool {
+    let Some(rest) = file_name
+        .strip_prefix(option_id)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .and_then(|rest| rest.strip_suffix(".log"))
+    else {
+        return false;
+    };
+    let bytes = rest.as_bytes();
+    bytes.len() >= 15
+        && bytes[..8].iter().all(u8::is_ascii_digit)
+        && bytes[8] == b'-'
+        && bytes[9..15].iter().all(u8::is_ascii_digit)
+        && (bytes.len() == 15 || bytes[15] == b'-')
+}
+
+/// List an option's log files, oldest first
+fn option_logs(dir: &Path, option_id: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut logs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| is_log_for(name, option_id))
+        })
+        .collect();
+    // Timestamped names sort chronologically; compare stems so `-N` suffixes follow
+    logs.sort_by_key(|path| path.file_stem().map(|stem| stem.to_os_string()));
+    logs
+}
+
+/// The newest log file of an option, if it has any
+pub fn latest_log(option_id: &str) -> Option<PathBuf> {
+    option_logs(&log_dir(), option_id).pop()
+}
+
+/// A past run, as its log file records it
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub path: PathBuf,
+    /// Start time from the header, e.g. "2026-10-14 10:18:29"
+    pub started: String,
+    pub action: RunAction,
+    /// How the run ended, e.g. "exit code 0"; None when the log has no
+    /// trailer because the run is still going or the app died during it
+    pub result: Option<String>,
+    /// Size of the log in bytes
+    pub size: u64,
+}
+
+impl LogEntry {
+    /// Read the header and trailer of a log file
+    fn read(path: PathBuf) -> io::Result<Self> {
+        let mut file = File::open(&path)?;
+        let size = file.metadata()?.len();
+
+        let mut started = None;
+        let mut action = RunAction::Install;
+        for line in BufReader::new(&mut file).lines().take(3) {
+            let line = line?;
+            if let Some(time) = line.strip_prefix("# Started: ") {
+                // Drop the UTC offset
+                started = Some(time.rsplit_once(' ').map_or(time, |(time, _)| time).to_string());
+            } else if let Some(command) = line.strip_prefix("# Command: ") {
+                if command.split_whitespace().rev().nth(1) == Some("uninstall") {
+                    action = RunAction::Uninstall;
+                }
+            }
+        }
+
+        file.seek(SeekFrom::Start(size.saturating_sub(TRAILER_SEARCH_BYTES)))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+        let result = String::from_utf8_lossy(&tail)
+            .lines()
+            .rev()
+            .find_map(|line| line.strip_prefix("# Finished: "))
+            .and_then(|trailer| trailer.split_once(" (")?.1.strip_suffix(')').map(str::to_string));
+
+        let started = started.unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        Ok(Self {
+            path,
+            started,
+            action,
+            result,
+            size,
+        })
+    }
+
+    /// Whether the run ended successfully
+    pub fn succeeded(&self) -> bool {
+        self.result.as_deref() == Some("exit code 0")
+    }
+}
+
+/// An option's past runs, newest first; logs that can't be read are left out
+pub fn option_history(option_id: &str) -> Vec<LogEntry> {
+    option_logs(&log_dir(), option_id)
+        .into_iter()
+        .rev()
+        .filter_map(|path| LogEntry::read(path).ok())
+        .collect()
+}
+
+/// Read a log for viewing
+///
+/// A log longer than `VIEW_LIMIT_BYTES` is read from that far before its
+/// end, starting at a line boundary. Returns the lines and the number of
+/// bytes skipped at the start.
+pub fn read_log(path: &Path) -> io::Result<(Vec<String>, u64)> {
+    let mut file = File::open(path)?;
+    let mut skipped = file.metadata()?.len().saturating_sub(VIEW_LIMIT_BYTES);
+    file.seek(SeekFrom::Start(skipped))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    // The first line is most likely cut, so start after it
+    let start = match bytes.iter().position(|&b| b == b'\n') {
+        Some(newline) if skipped > 0 => newline + 1,
+        _ => 0,
+    };
+    skipped += start as u64;
+    let text = String::from_utf8_lossy(&bytes[start..]);
+    Ok((text.lines().map(str::to_string).collect(), skipped))
+}
+
+/// Remove all but the newest `keep` logs for an option
+fn prune_logs(dir: &Path, option_id: &str, keep: usize) {
+    let logs = option_logs(dir, option_id);
+    let excess = logs.len().saturating_sub(keep);
+    for path in &logs[..excess] {
+        let _ = fs::remove_file(path);
+    }
+}
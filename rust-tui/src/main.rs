@@ -3,68 +3,364 @@
 //! A terminal user interface for installing Omarchy Cybex customizations.
 
 mod app;
+mod batch;
+mod check;
+mod cli;
+mod commands;
+mod compat;
 mod config;
+mod deps;
+mod editor_locks;
+mod event_stream;
+mod export;
+mod features;
+mod headless;
+mod input;
 mod installer;
+mod journal;
+mod jump;
+mod keymap;
+mod logs;
+mod manifest;
+mod modified;
+mod notify;
 mod options;
+mod panels;
+mod pending;
+mod persist;
+mod prefs;
+mod presence;
+mod profiles;
+mod registry;
+mod requirements;
+mod run_id;
+mod sandbox;
+mod script_env;
+mod semver;
+mod startup;
 mod state;
+mod support;
+mod suspend;
 mod theme;
+mod timeout;
+mod timings;
+mod tty;
 mod ui;
+mod update;
+mod versions;
+mod view;
+mod wake;
 
 use std::env;
-use std::io::stdout;
-use std::path::PathBuf;
 
 use color_eyre::Result;
-use crossterm::{
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
 use ratatui::prelude::*;
 
 use app::App;
-use config::load_installed;
-use state::AppState;
+use batch::{EXIT_FAILED, EXIT_USAGE};
+use cli::{parse_args, usage, CliCommand};
+use config::{
+    describe_unknown, load_installed, load_last_selected, load_pending_reboot, load_prefs, load_records, migrate_renamed,
+    take_detached_note, unknown_installed,
+};
+use journal::load_journal;
+use keymap::Keymap;
+use manifest::TooOld;
+use panels::LayoutPrefs;
+use pending::PendingItems;
+use prefs::{configured_always_show_summary, configured_expert};
+use profiles::load_profiles;
+use startup::preflight;
+use state::{AppMode, AppState, ToastKind};
+use theme::{configured_flavor, no_color_requested, terminal_flavor, Flavor};
+use tty::{enter_tui, restore_terminal, stdout_is_tty, terminal_target, TerminalOut, TerminalTarget};
 
 fn main() -> Result<()> {
     // Install color-eyre panic handler
     color_eyre::install()?;
 
+    let program = env::args_os()
+        .next()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let args = match parse_args(env::args_os().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!("{}", usage(&program));
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+    if args.command == CliCommand::Help {
+        println!("{}", usage(&program));
+        return Ok(());
+    }
+
+    // Scripts run as root drop root-owned files into the config, which later
+    // runs as the user then can't change; the scripts use sudo where needed
+    // SAFETY: geteuid only reads a process attribute
+    let as_root = unsafe { libc::geteuid() } == 0;
+    if as_root && !args.allow_root {
+        eprintln!("Error: refusing to run as root");
+        eprintln!("Scripts run as root leave root-owned files behind that break later runs as your user.");
+        eprintln!("Run {} as your user (the scripts ask for sudo when they need it), or pass --allow-root.", program);
+        std::process::exit(EXIT_FAILED);
+    }
+
     // Get script directory from args or use current directory
-    let script_dir = env::args()
-        .nth(1)
-        .map(PathBuf::from)
+    let script_dir = args
+        .script_dir
         .unwrap_or_else(|| env::current_dir().expect("Failed to get current directory"));
 
-    // Verify install script exists
-    let install_script = script_dir.join("install");
-    if !install_script.exists() {
-        eprintln!("Error: install not found in {:?}", script_dir);
-        eprintln!("Usage: {} [script_dir]", env::args().next().unwrap_or_default());
-        std::process::exit(1);
+    // Gather every startup problem before touching the terminal
+    let report = preflight(&script_dir, args.entry.as_deref());
+    if report.is_fatal() {
+        for error in &report.fatal {
+            eprintln!("Error: {}", error);
+        }
+        eprintln!("Run {} --help for usage", program);
+        std::process::exit(EXIT_USAGE);
+    }
+
+    // A manifest written for a newer installer stops headless runs here; the
+    // TUI explains it on a screen of its own
+    if let Some(too_old) = report.too_old.as_ref().filter(|_| args.command != CliCommand::Tui) {
+        eprintln!("Error: {}", too_old);
+        eprintln!("{}", TooOld::ADVICE);
+        std::process::exit(EXIT_USAGE);
+    }
+    let options = report.options;
+    let mut report_env = report.env;
+    if args.noninteractive {
+        report_env.insert(script_env::NONINTERACTIVE.to_string(), "1".to_string());
+    }
+
+    // State kept under an option's former ids moves to its current one; what
+    // is left under ids no option has is only offered for removal
+    let renames: Vec<(String, String)> = options
+        .iter()
+        .flat_map(|option| option.renamed_from.iter().map(|old| (old.clone(), option.id.clone())))
+        .collect();
+    let renamed = migrate_renamed(&renames).unwrap_or_else(|e| {
+        eprintln!("warning: could not move state of renamed options: {}", e);
+        Vec::new()
+    });
+    let renamed_note = (!renamed.is_empty())
+        .then(|| format!("Carried installed state over from renamed options: {}", renamed.join(", ")));
+    let unknown_ids = if report.too_old.is_some() {
+        Vec::new()
+    } else {
+        unknown_installed(options.iter().map(|option| option.id.as_str()))
+    };
+
+    // Headless modes never touch the terminal
+    if args.command != CliCommand::Tui {
+        if as_root {
+            eprintln!("warning: running as root (--allow-root)");
+        }
+        if let Some(note) = &renamed_note {
+            eprintln!("note: {}", note);
+        }
+        if !unknown_ids.is_empty() {
+            eprintln!("warning: {}", describe_unknown(&unknown_ids));
+        }
+        for warning in &report.warnings {
+            eprintln!("warning: {}", warning);
+        }
+    }
+    let safeguards = headless::Safeguards {
+        base_version: report.base_version.as_ref(),
+        sandbox: &report.sandbox,
+        skip_preflight: args.skip_preflight,
+        timeout: &report.timeout,
+        env: &report_env,
+        priority: report.priority,
+    };
+    let batch = |ids: &[String], uninstall| {
+        headless::run_batch(&script_dir, &report.entry, &options, ids, uninstall, &safeguards, args.run_format)
+    };
+    let exit_code = match &args.command {
+        CliCommand::List(format) => Some(headless::list_options(&options, *format)),
+        CliCommand::Install(id) => Some(batch(std::slice::from_ref(id), false)),
+        CliCommand::InstallAll => {
+            let installed = load_installed();
+            let ids: Vec<String> = options
+                .iter()
+                .filter(|o| !installed.contains(&o.id))
+                .map(|o| o.id.clone())
+                .collect();
+            Some(batch(&ids, false))
+        }
+        CliCommand::Uninstall(id) => Some(batch(std::slice::from_ref(id), true)),
+        CliCommand::ExportScript { path, force } => {
+            let installed = load_installed();
+            Some(
+                match export::export_installed(&script_dir, &report.entry, &options, &installed, path, *force) {
+                    Ok(count) => {
+                        println!("{}", path.display());
+                        eprintln!("Exported {} option(s)", count);
+                        batch::EXIT_OK
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        batch::EXIT_FAILED
+                    }
+                },
+            )
+        }
+        CliCommand::Tui | CliCommand::Help => None,
+    };
+    if let Some(code) = exit_code {
+        std::process::exit(code);
     }
 
     // Load installed state
     let installed = load_installed();
-    let state = AppState::new(installed);
+    let mut state = AppState::new(options, installed, load_last_selected().as_deref());
+    state.records = load_records();
+    state.pending_reboot = load_pending_reboot();
+
+    // Bad bindings fall back to the defaults and are mentioned once
+    let (keymap, mut keymap_problems) = Keymap::load();
+    keymap_problems.extend(registry::conflicts(&keymap));
+
+    // Interface preferences from the last session; a flavor picked with the
+    // flag is remembered for sessions started without it
+    let prefs = load_prefs();
+    prefs.apply(&mut state);
+    if let Some(flavor) = args.theme {
+        state.theme_choice = Some(flavor.name().to_string());
+    }
+    let remembered = prefs.theme.as_deref().and_then(Flavor::parse);
+
+    // The flag beats the config file, which beats the remembered flavor and
+    // then the terminal's background; asking for no colors beats them all
+    let configured = configured_flavor().unwrap_or_else(|problem| {
+        keymap_problems.push(problem);
+        None
+    });
+    state.always_show_summary = configured_always_show_summary().unwrap_or_else(|problem| {
+        keymap_problems.push(problem);
+        false
+    });
+    state.expert = args.expert
+        || configured_expert().unwrap_or_else(|problem| {
+            keymap_problems.push(problem);
+            false
+        });
+    if args.plain || no_color_requested() {
+        theme::select_colorless();
+    } else {
+        theme::select(args.theme.or(configured).or(remembered).unwrap_or_else(terminal_flavor));
+    }
+    if args.plain {
+        ui::use_ascii();
+    }
+    state.keymap = keymap;
+    if !keymap_problems.is_empty() {
+        state.status_message = format!("config.toml: {}", keymap_problems.join("; "));
+    }
+
+    // Report runs that finished after the previous session's terminal dropped
+    if let Some(note) = take_detached_note() {
+        state.status_message = note.replace('\n', " · ");
+    }
+
+    state.idle_dim_after = report.idle_dim_after;
+    state.output_limit = report.output_limit;
+    state.base_version = report.base_version.clone();
+    state.features = report.features;
+    state.sandbox = report.sandbox;
+    state.notify_after = report.notify_after;
+    state.timeout_policy = report.timeout.clone();
+    state.unsupported = report.unsupported;
+    state.skip_preflight = args.skip_preflight;
+    state.script_env = report_env;
+    state.priority = report.priority;
+    state.as_root = as_root;
+    state.layouts = LayoutPrefs::load();
+
+    // Ask about what the previous session left undecided, all in one overlay
+    state.resume_journal = load_journal();
+    state.unknown_ids = unknown_ids;
+    state.pending = PendingItems::collect(
+        state.resume_journal.as_ref(),
+        !state.pending_reboot.is_empty(),
+        !state.unknown_ids.is_empty(),
+    );
+    if let Some(note) = renamed_note {
+        state.toast(ToastKind::Success, note);
+    }
+    if !state.pending.is_empty() {
+        state.mode = AppMode::PendingItems;
+    }
+
+    // Non-fatal startup problems are shown on an overlay once the UI is up
+    let (profiles, profile_problems) = load_profiles(&script_dir);
+    state.profiles = profiles;
+    let mut warnings = report.warnings;
+    warnings.extend(profile_problems);
+    if !warnings.is_empty() {
+        state.startup_warnings = warnings;
+        state.mode = AppMode::StartupWarnings;
+    }
+    if let Some(too_old) = report.too_old {
+        state.mode = AppMode::TooOld;
+        state.too_old = Some(too_old);
+    }
+
+    // Survive terminal hangup (e.g. SSH drop) so a running install can finish;
+    // terminal loss is then detected through failing reads and draws
+    // SAFETY: setting a signal disposition to SIG_IGN is always sound
+    unsafe {
+        libc::signal(libc::SIGHUP, libc::SIG_IGN);
+    }
+
+    // With stdout redirected, draw on the controlling terminal (or refuse)
+    let target = terminal_target(stdout_is_tty(), report.redirect_policy);
+    if target == TerminalTarget::Refuse {
+        eprintln!("Error: stdout is not a terminal, so the interactive installer can't be shown");
+        eprintln!("Use --list, --install <id> or --install-all for scripted runs");
+        std::process::exit(EXIT_USAGE);
+    }
+    let mut out = match TerminalOut::open(target) {
+        Ok(out) => out,
+        Err(e) => {
+            eprintln!("Error: stdout is not a terminal and /dev/tty can't be opened: {}", e);
+            eprintln!("Use --list, --install <id> or --install-all for scripted runs");
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+
+    // Put the terminal back before color-eyre prints the panic report
+    let report_panic = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        report_panic(info);
+    }));
 
     // Initialize terminal
-    enable_raw_mode()?;
-    let mut stdout = stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
+    enter_tui(&mut out)?;
+    let backend = CrosstermBackend::new(out);
     let mut terminal = Terminal::new(backend)?;
 
     // Clear terminal and hide cursor
     terminal.clear()?;
 
     // Create and run app
-    let mut app = App::new(state, script_dir);
+    let mut app = App::new(state, script_dir, report.entry);
     let result = app.run(&mut terminal);
+    let persist_problems = app.finish_persisting();
+
+    // Nothing left to restore (or report to) once the terminal is gone
+    if app.detached() {
+        return result;
+    }
 
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    restore_terminal();
+    for problem in &persist_problems {
+        eprintln!("warning: {}", problem);
+    }
 
     result
 }
@@ -0,0 +1,340 @@
+//! Option manifest loaded from the script directory
+//!
+//! A `options.toml` (or `manifest.toml`) next to the install script replaces
+//! the compiled-in option list, so new components don't need a rebuild:
+//!
+//! ```toml
+//! [[option]]
+//! id = "claude"
+//! name = "Claude Code"
+//! description = "Anthropic's AI coding assistant CLI"
+//! category = "AI Tools"     # optional: heading it's grouped under in the list
+//! requires_reboot = false
+//! nice = 10                  # optional niceness for heavy builds
+//! ionice_class = "idle"      # optional: realtime, best-effort or idle
+//! targets = ["~/.config/hypr/bindings.conf"]  # optional: files the script writes
+//! depends = ["fish"]         # optional: options installed first
+//! verify = "claude --version"  # optional: check run after a successful install
+//! post_install = ["hyprctl reload"]  # optional: commands run after a successful install
+//! sources = ["config/hyprland"]  # optional: script-dir paths it installs from
+//! omarchy = ">=2.0, <3.0"   # optional: Omarchy versions it works with
+//! sandbox = true             # optional: run contained (see sandbox.rs)
+//! requires_network = true    # optional: needs a connection; kept when sandboxed
+//! requires_commands = ["npm"]  # optional: commands checked for before installing
+//! min_free_disk_mb = 500     # optional: free space on / checked before installing
+//! timeout_minutes = 30       # optional: time limit per run, 0 for none (see timeout.rs)
+//! env = { FORCE_COLOR = "1" }  # optional: variables for its runs (see script_env.rs)
+//! renamed_from = ["claude-cli"]  # optional: former ids, their installed state moves here
+//! script = "lib/claude.sh"   # optional: file E opens in $EDITOR, instead of the install script
+//! ```
+//!
+//! A `[profiles]` table can sit alongside the options; see [`crate::profiles`].
+//! A top-level `min_tui_version = "0.5"` refuses installers older than that.
+//! `nice` and `ionice_class` left out fall back to the same keys of config.toml.
+//! Keys this installer doesn't know are warned about and ignored, so a
+//! manifest written for a newer one still loads when it can.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::deps;
+use crate::options::{builtin_options, InstallOption};
+use crate::semver::SemVer;
+
+/// Manifest file names probed in the script directory, in order
+const MANIFEST_NAMES: [&str; 2] = ["options.toml", "manifest.toml"];
+
+/// Top-level keys read outside the `Manifest` struct
+const READ_ELSEWHERE: [&str; 2] = ["min_tui_version", "profiles"];
+
+/// Top-level manifest structure
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(default, rename = "option")]
+    options: Vec<ManifestOption>,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+/// An `[[option]]` entry, with whatever keys `InstallOption` doesn't have
+#[derive(Deserialize)]
+struct ManifestOption {
+    #[serde(flatten)]
+    option: InstallOption,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+/// A manifest that exists but could not be used
+#[derive(Debug)]
+pub struct ManifestError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid manifest {}: {}", self.path.display(), self.message)
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// A manifest asking for a newer installer than this one
+#[derive(Debug, Clone)]
+pub struct TooOld {
+    pub path: PathBuf,
+    pub required: SemVer,
+    pub running: SemVer,
+}
+
+impl TooOld {
+    /// What to do about it
+    pub const ADVICE: &'static str =
+        "Update omarchy-cybex (git pull) and rebuild the installer with cargo build --release in rust-tui, then start it again.";
+}
+
+impl fmt::Display for TooOld {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} needs omarchy-cybex-installer {} or newer, but this is {}",
+            self.path.display(),
+            self.required,
+            self.running
+        )
+    }
+}
+
+/// Why the option list couldn't be loaded
+#[derive(Debug)]
+pub enum LoadError {
+    /// The manifest is unusable
+    Invalid(ManifestError),
+    /// The manifest is for a newer installer
+    TooOld(TooOld),
+}
+
+/// Find the manifest file in the script directory, if any
+pub fn manifest_path(script_dir: &Path) -> Option<PathBuf> {
+    MANIFEST_NAMES
+        .iter()
+        .map(|name| script_dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Load the option list, falling back to the built-in options without a manifest
+///
+/// Also returns warnings about manifest keys that were ignored.
+pub fn load_options(script_dir: &Path) -> Result<(Vec<InstallOption>, Vec<String>), LoadError> {
+    match manifest_path(script_dir) {
+        Some(path) => load_manifest(&path),
+        None => Ok((builtin_options(), Vec::new())),
+    }
+}
+
+/// Read, parse and validate a manifest file
+fn load_manifest(path: &Path) -> Result<(Vec<InstallOption>, Vec<String>), LoadError> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        LoadError::Invalid(ManifestError {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })
+    })?;
+    parse_manifest(path, &contents, &SemVer::running())
+}
+
+/// Parse and validate a manifest's contents for an installer at version `running`
+fn parse_manifest(
+    path: &Path,
+    contents: &str,
+    running: &SemVer,
+) -> Result<(Vec<InstallOption>, Vec<String>), LoadError> {
+    let error = |message: String| {
+        LoadError::Invalid(ManifestError {
+            path: path.to_path_buf(),
+            message,
+        })
+    };
+
+    // The version is checked first: newer manifests may not parse at all
+    let table: toml::Table = contents.parse().map_err(|e: toml::de::Error| error(e.to_string()))?;
+    if let Some(required) = table.get("min_tui_version") {
+        let required = required
+            .as_str()
+            .and_then(SemVer::parse)
+            .ok_or_else(|| error("min_tui_version must be a version string like \"0.5\"".to_string()))?;
+        if required > *running {
+            return Err(LoadError::TooOld(TooOld {
+                path: path.to_path_buf(),
+                required,
+                running: running.clone(),
+            }));
+        }
+    }
+
+    let manifest: Manifest = toml::from_str(contents).map_err(|e| error(e.to_string()))?;
+
+    if manifest.options.is_empty() {
+        return Err(error("no [[option]] entries defined".to_string()));
+    }
+
+    let mut warnings = Vec::new();
+    let ignored = |keys: Vec<&String>| {
+        let keys: Vec<String> = keys.into_iter().map(|key| format!("'{}'", key)).collect();
+        format!("ignoring unknown {} {}", if keys.len() == 1 { "key" } else { "keys" }, keys.join(", "))
+    };
+    let top_level: Vec<&String> = manifest
+        .unknown
+        .keys()
+        .filter(|key| !READ_ELSEWHERE.contains(&key.as_str()))
+        .collect();
+    if !top_level.is_empty() {
+        warnings.push(format!("{}: {}", path.display(), ignored(top_level)));
+    }
+
+    let mut seen = HashSet::new();
+    for (i, entry) in manifest.options.iter().enumerate() {
+        let option = &entry.option;
+        if option.id.trim().is_empty() {
+            return Err(error(format!("option #{} has no id", i + 1)));
+        }
+        if !seen.insert(option.id.as_str()) {
+            return Err(error(format!("duplicate option id '{}'", option.id)));
+        }
+        if option.nice.is_some_and(|nice| !(-20..=19).contains(&nice)) {
+            return Err(error(format!("option '{}': nice must be between -20 and 19", option.id)));
+        }
+        if !entry.unknown.is_empty() {
+            let keys = entry.unknown.keys().collect();
+            warnings.push(format!("{}: option '{}': {}", path.display(), option.id, ignored(keys)));
+        }
+    }
+
+    let options: Vec<InstallOption> = manifest
+        .options
+        .into_iter()
+        .map(|entry| {
+            let mut option = entry.option;
+            if option.name.is_empty() {
+                option.name = option.id.clone();
+            }
+            option
+        })
+        .collect();
+    deps::validate(&options).map_err(|e| error(e.to_string()))?;
+
+    Ok((options, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "/scripts/options.toml";
+
+    fn parse(contents: &str, running: &str) -> Result<(Vec<InstallOption>, Vec<String>), LoadError> {
+        parse_manifest(Path::new(PATH), contents, &SemVer::parse(running).unwrap())
+    }
+
+    const DEMO: &str = r#"
+[[option]]
+id = "demo"
+requires_reboot = true
+"#;
+
+    #[test]
+    fn min_tui_version_at_or_below_the_running_one_loads() {
+        for required in ["0.5", "0.5.0", "0.4.9", "0.5.0-rc.1"] {
+            let contents = format!("min_tui_version = \"{}\"\n{}", required, DEMO);
+            let (options, warnings) = parse(&contents, "0.5.0").map_err(|_| required).unwrap();
+            assert_eq!(options[0].id, "demo");
+            assert!(warnings.is_empty(), "{:?}", warnings);
+        }
+    }
+
+    #[test]
+    fn newer_min_tui_version_is_refused_with_both_versions() {
+        let contents = format!("min_tui_version = \"0.5.1\"\n{}", DEMO);
+        let Err(LoadError::TooOld(too_old)) = parse(&contents, "0.5.0-rc.2") else {
+            panic!("a newer minimum should be refused");
+        };
+        assert_eq!(
+            too_old.to_string(),
+            "/scripts/options.toml needs omarchy-cybex-installer 0.5.1 or newer, but this is 0.5.0-rc.2"
+        );
+    }
+
+    #[test]
+    fn version_is_checked_before_the_options_parse() {
+        // A newer format may not fit this installer's types at all
+        let contents = "min_tui_version = \"2.0\"\n[[option]]\nid = 7\n";
+        assert!(matches!(parse(contents, "1.0.0"), Err(LoadError::TooOld(_))));
+    }
+
+    #[test]
+    fn malformed_min_tui_version_is_invalid() {
+        for value in ["\"soon\"", "5"] {
+            let contents = format!("min_tui_version = {}\n{}", value, DEMO);
+            let Err(LoadError::Invalid(e)) = parse(&contents, "0.1.0") else {
+                panic!("{} should be rejected", value);
+            };
+            assert!(e.message.contains("min_tui_version"), "{}", e.message);
+        }
+    }
+
+    #[test]
+    fn unknown_keys_are_warned_about_and_ignored() {
+        let contents = r#"
+layout_version = 3
+[theme]
+accent = "pink"
+
+[profiles]
+dev = ["demo"]
+
+[[option]]
+id = "demo"
+requires_reboot = true
+icon = "rocket"
+hooks = { before = "true" }
+"#;
+        let (options, warnings) = parse(contents, "0.1.0").unwrap();
+        assert!(options[0].requires_reboot, "known keys still apply");
+        assert_eq!(
+            warnings,
+            [
+                "/scripts/options.toml: ignoring unknown keys 'layout_version', 'theme'",
+                "/scripts/options.toml: option 'demo': ignoring unknown keys 'hooks', 'icon'",
+            ]
+        );
+    }
+
+    #[test]
+    fn documented_keys_are_all_known() {
+        // The example at the top of this file, without its comment markers,
+        // plus the option it depends on
+        let mut example: String = include_str!("manifest.rs")
+            .lines()
+            .skip_while(|line| *line != "//! ```toml")
+            .skip(1)
+            .take_while(|line| *line != "//! ```")
+            .map(|line| format!("{}\n", line.trim_start_matches("//!").trim_start()))
+            .collect();
+        example.push_str("[[option]]\nid = \"fish\"\n");
+        let (options, warnings) = parse(&example, "0.1.0").unwrap();
+        assert_eq!(options[0].id, "claude");
+        assert_eq!(options[0].nice, Some(10));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn known_keys_with_the_wrong_type_still_fail() {
+        let contents = "[[option]]\nid = \"demo\"\nrequires_reboot = \"yes\"\n";
+        assert!(matches!(parse(contents, "0.1.0"), Err(LoadError::Invalid(_))));
+    }
+}
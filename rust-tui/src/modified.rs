@@ -0,0 +1,157 @@
+//! Detecting hand edits to files an option manages
+//!
+//! After a successful install the option's target files are hashed and the
+//! hashes kept in its record. Before the next reinstall the files are hashed
+//! again; any that differ were changed by hand since and would be overwritten.
+//!
+//! The hashes are keyed by path in the state file. A path that isn't valid
+//! UTF-8 is stored as a NUL followed by its bytes in hex: no path can contain
+//! NUL, so such a key never clashes with a real one.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+/// Bytes of a file that go into its hash; the full length is always included
+const HASH_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// FNV-1a over the first `HASH_LIMIT` bytes, with the file length
+///
+/// Returns None for files that can't be read (usually: don't exist).
+pub fn hash_file(path: &Path) -> Option<String> {
+    let len = fs::metadata(path).ok()?.len();
+    let mut reader = BufReader::new(File::open(path).ok()?).take(HASH_LIMIT);
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buf).ok()?;
+        if n == 0 {
+            break;
+        }
+        for &byte in &buf[..n] {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    Some(format!("{}:{:016x}", len, hash))
+}
+
+/// Marker starting the key of a path that isn't valid UTF-8
+const RAW_PATH_MARKER: char = '\0';
+
+/// State-file key for a path
+fn path_key(path: &Path) -> String {
+    match path.to_str() {
+        Some(text) => text.to_string(),
+        None => path.as_os_str().as_bytes().iter().fold(RAW_PATH_MARKER.to_string(), |mut key, byte| {
+            let _ = write!(key, "{:02x}", byte);
+            key
+        }),
+    }
+}
+
+/// The path a state-file key stands for; None for a mangled raw key
+fn key_path(key: &str) -> Option<PathBuf> {
+    let Some(hex) = key.strip_prefix(RAW_PATH_MARKER) else {
+        return Some(PathBuf::from(key));
+    };
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect::<Option<Vec<u8>>>()?;
+    Some(PathBuf::from(OsString::from_vec(bytes)))
+}
+
+/// Hashes of the target files that exist, keyed by path
+pub fn hash_targets(targets: &[PathBuf]) -> BTreeMap<String, String> {
+    targets
+        .iter()
+        .filter_map(|path| hash_file(path).map(|hash| (path_key(path), hash)))
+        .collect()
+}
+
+/// Recorded files whose contents no longer match their hash
+///
+/// Files that have since been removed don't count; a reinstall loses nothing there.
+pub fn modified_files(recorded: &BTreeMap<String, String>) -> Vec<PathBuf> {
+    recorded
+        .iter()
+        .filter_map(|(key, hash)| Some((key_path(key)?, hash)))
+        .filter(|(path, hash)| hash_file(path).is_some_and(|now| &now != *hash))
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// Copy each file next to itself with a timestamped `.bak` suffix
+///
+/// Returns the backup paths, stopping at the first failure.
+pub fn back_up(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    paths
+        .iter()
+        .map(|path| {
+            let mut backup = path.clone().into_os_string();
+            backup.push(format!(".bak.{}", stamp));
+            let backup = PathBuf::from(backup);
+            fs::copy(path, &backup)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+            Ok(backup)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("omarchy-cybex-modified-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn utf8_paths_are_their_own_key() {
+        let path = Path::new("/home/me/.config/hypr/bindings.conf");
+        assert_eq!(path_key(path), "/home/me/.config/hypr/bindings.conf");
+        assert_eq!(key_path(&path_key(path)).as_deref(), Some(path));
+    }
+
+    #[test]
+    fn non_utf8_paths_round_trip_through_their_key() {
+        let path = PathBuf::from(OsStr::from_bytes(b"/home/me/caf\xe9.conf"));
+        let key = path_key(&path);
+        assert_eq!(key, "\u{0}2f686f6d652f6d652f636166e92e636f6e66");
+        assert_eq!(key_path(&key), Some(path));
+        assert_eq!(key_path("\0zz"), None);
+        assert_eq!(key_path("\0abc"), None);
+    }
+
+    #[test]
+    fn edits_to_a_non_utf8_target_are_detected_after_a_json_round_trip() {
+        let dir = scratch_dir("raw");
+        let target = dir.join(OsStr::from_bytes(b"bindings-\xff.conf"));
+        let untouched = dir.join("untouched.conf");
+        fs::write(&target, "bind = SUPER, Q").unwrap();
+        fs::write(&untouched, "same").unwrap();
+
+        let hashes = hash_targets(&[target.clone(), untouched.clone(), dir.join("missing.conf")]);
+        assert_eq!(hashes.len(), 2);
+        let json = serde_json::to_string(&hashes).unwrap();
+        let stored: BTreeMap<String, String> = serde_json::from_str(&json).unwrap();
+        assert!(modified_files(&stored).is_empty());
+
+        fs::write(&target, "bind = SUPER, W").unwrap();
+        assert_eq!(modified_files(&stored), [target]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,25 @@
+//! Desktop notifications for runs that finish while nobody is watching
+
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// How long a run must take before its end is announced, when not configured
+pub const DEFAULT_NOTIFY_AFTER: Duration = Duration::from_secs(30);
+
+/// Show a desktop notification via notify-send, ignoring any failure
+///
+/// Never blocks: the process is reaped on a throwaway thread.
+pub fn notify(summary: &str, body: &str) {
+    let spawned = Command::new("notify-send")
+        .args(["--app-name=omarchy-cybex", summary, body])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Ok(mut child) = spawned {
+        thread::spawn(move || {
+            let _ = child.wait();
+        });
+    }
+}
@@ -1,128 +1,314 @@
 //! Installation options with metadata
 
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::compat::VersionRange;
+
 /// Represents a single installation option
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct InstallOption {
-    pub id: &'static str,
-    pub name: &'static str,
-    pub description: &'static str,
-    pub category: &'static str,
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Group the option is listed under
+    pub category: String,
     pub requires_reboot: bool,
     pub excluded_from_all: bool,
+    /// Niceness to run the install script at (-20..=19)
+    pub nice: Option<i32>,
+    /// I/O scheduling class applied via ionice
+    pub ionice_class: Option<IoniceClass>,
+    /// Files the script writes, with `~` for the home directory
+    pub targets: Vec<String>,
+    /// Options that must be installed first
+    pub depends: Vec<String>,
+    /// Shell command run after a successful install to confirm it works
+    pub verify: Option<String>,
+    /// Shell commands run after a successful install so it takes effect,
+    /// such as `hyprctl reload`
+    pub post_install: Vec<String>,
+    /// Files or directories in the script directory the option installs from
+    pub sources: Vec<String>,
+    /// Omarchy base versions the option is known to work with
+    pub omarchy: Option<VersionRange>,
+    /// Run the script in a sandbox that can only write next to its targets
+    pub sandbox: bool,
+    /// Keep network access when sandboxed, and check for a connection
+    /// before installing
+    pub requires_network: bool,
+    /// Commands that must be on PATH before the install runs
+    pub requires_commands: Vec<String>,
+    /// Free space needed on `/` before the install runs, in MiB
+    pub min_free_disk_mb: Option<u64>,
+    /// Time limit for a run, overriding the global one; 0 for none
+    pub timeout_minutes: Option<u64>,
+    /// Whether the state file tracks the option; `false` for one-shot tasks
+    /// that are simply run again whenever chosen
+    pub track_state: Option<bool>,
+    /// Environment variables for the option's runs, over config.toml's `[env]`
+    pub env: BTreeMap<String, String>,
+    /// Ids the option had before, whose state carries over to this one
+    pub renamed_from: Vec<String>,
+    /// File to open when editing the option, relative to the script directory
+    pub script: Option<String>,
+}
+
+impl InstallOption {
+    /// Target files with `~` expanded to the home directory
+    pub fn target_paths(&self) -> Vec<PathBuf> {
+        let home = dirs::home_dir();
+        self.targets
+            .iter()
+            .map(|target| match (target.strip_prefix("~/"), &home) {
+                (Some(rest), Some(home)) => home.join(rest),
+                _ => PathBuf::from(target),
+            })
+            .collect()
+    }
+
+    /// The file editing the option opens: its own script, or else the entry script
+    pub fn script_path(&self, script_dir: &Path, entry_script: &Path) -> PathBuf {
+        match &self.script {
+            Some(script) => script_dir.join(script),
+            None => entry_script.to_path_buf(),
+        }
+    }
+
+    /// Whether the option has an installed state at all (the default)
+    pub fn tracks_state(&self) -> bool {
+        self.track_state.unwrap_or(true)
+    }
+}
+
+/// I/O scheduling classes understood by ionice(1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IoniceClass {
+    Realtime,
+    BestEffort,
+    Idle,
+}
+
+impl IoniceClass {
+    /// Numeric class passed to `ionice -c`
+    pub fn as_arg(self) -> &'static str {
+        match self {
+            IoniceClass::Realtime => "1",
+            IoniceClass::BestEffort => "2",
+            IoniceClass::Idle => "3",
+        }
+    }
+
+    /// Name used in manifests and config.toml
+    pub fn name(self) -> &'static str {
+        match self {
+            IoniceClass::Realtime => "realtime",
+            IoniceClass::BestEffort => "best-effort",
+            IoniceClass::Idle => "idle",
+        }
+    }
+
+    /// Read a class by its name
+    pub fn parse(name: &str) -> Option<Self> {
+        [IoniceClass::Realtime, IoniceClass::BestEffort, IoniceClass::Idle]
+            .into_iter()
+            .find(|class| class.name() == name)
+    }
+}
+
+/// Compiled-in option definition used when no manifest is present
+struct BuiltinOption {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    category: &'static str,
+    requires_reboot: bool,
+    excluded_from_all: bool,
+    targets: &'static [&'static str],
+    depends_on: &'static [&'static str],
+    sources: &'static [&'static str],
+}
+
+impl From<&BuiltinOption> for InstallOption {
+    fn from(option: &BuiltinOption) -> Self {
+        Self {
+            id: option.id.to_string(),
+            name: option.name.to_string(),
+            description: option.description.to_string(),
+            category: option.category.to_string(),
+            requires_reboot: option.requires_reboot,
+            excluded_from_all: option.excluded_from_all,
+            targets: option.targets.iter().map(|t| t.to_string()).collect(),
+            depends: option.depends_on.iter().map(|d| d.to_string()).collect(),
+            sources: option.sources.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Built-in installation options
+pub fn builtin_options() -> Vec<InstallOption> {
+    BUILTIN_OPTIONS.iter().map(InstallOption::from).collect()
 }
 
-/// All available installation options
-pub const OPTIONS: &[InstallOption] = &[
-    InstallOption {
+/// All options known to this binary, matching the install script
+const BUILTIN_OPTIONS: &[BuiltinOption] = &[
+    BuiltinOption {
         id: "claude",
         name: "Claude Code",
         description: "Anthropic's AI coding assistant CLI",
         category: "AI Tools",
         requires_reboot: false,
         excluded_from_all: false,
+        targets: &[],
+        depends_on: &[],
+        sources: &[],
     },
-    InstallOption {
+    BuiltinOption {
         id: "codex",
         name: "Codex CLI",
         description: "OpenAI's Codex command-line interface",
         category: "AI Tools",
         requires_reboot: false,
         excluded_from_all: false,
+        targets: &[],
+        depends_on: &[],
+        sources: &[],
     },
-    InstallOption {
+    BuiltinOption {
         id: "screensaver",
         name: "Custom Screensaver",
         description: "Personalized ASCII art screensaver",
         category: "Customization",
         requires_reboot: false,
         excluded_from_all: false,
+        targets: &["~/.config/omarchy/branding/screensaver.txt"],
+        depends_on: &[],
+        sources: &["config/screensaver"],
     },
-    InstallOption {
+    BuiltinOption {
         id: "plymouth",
         name: "Plymouth Theme",
         description: "Cybex boot splash theme",
         category: "System",
         requires_reboot: true,
         excluded_from_all: false,
+        targets: &[],
+        depends_on: &[],
+        sources: &["config/plymouth"],
     },
-    InstallOption {
+    BuiltinOption {
         id: "fish",
         name: "Fish Shell",
         description: "Modern shell with Starship prompt",
         category: "Shell",
         requires_reboot: false,
         excluded_from_all: false,
+        targets: &[],
+        depends_on: &[],
+        sources: &["config/fish", "config/starship"],
     },
-    InstallOption {
+    BuiltinOption {
         id: "hyprland",
         name: "Hyprland Bindings",
         description: "Custom key bindings and input config",
         category: "Desktop",
         requires_reboot: false,
         excluded_from_all: false,
+        targets: &["~/.config/hypr/bindings.conf"],
+        depends_on: &[],
+        sources: &["config/hyprland/bindings.conf", "config/hyprland/input.conf"],
     },
-    InstallOption {
+    BuiltinOption {
         id: "waycorner",
         name: "Hot Corners",
         description: "macOS-style hot corners for Hyprland",
         category: "Desktop",
         requires_reboot: false,
         excluded_from_all: false,
+        targets: &[],
+        depends_on: &[],
+        sources: &["config/waycorner"],
     },
-    InstallOption {
+    BuiltinOption {
         id: "waybar",
         name: "Waybar Idle Toggle",
         description: "Click to toggle idle lock indicator",
         category: "Desktop",
         requires_reboot: false,
         excluded_from_all: false,
+        targets: &[],
+        depends_on: &[],
+        sources: &["config/waybar"],
     },
-    InstallOption {
+    BuiltinOption {
         id: "ssh",
         name: "SSH Key",
         description: "Generate SSH key for GitHub",
         category: "Security",
         requires_reboot: false,
         excluded_from_all: false,
+        targets: &[],
+        depends_on: &[],
+        sources: &[],
     },
-    InstallOption {
+    BuiltinOption {
         id: "passwordless-sudo",
         name: "Passwordless Sudo",
         description: "Enable passwordless sudo for user",
         category: "Security",
         requires_reboot: false,
         excluded_from_all: true,
+        targets: &[],
+        depends_on: &[],
+        sources: &[],
     },
-    InstallOption {
+    BuiltinOption {
         id: "brave",
         name: "Brave Browser",
         description: "Privacy-focused browser as default",
         category: "Applications",
         requires_reboot: false,
         excluded_from_all: false,
+        targets: &[],
+        depends_on: &[],
+        sources: &[],
     },
-    InstallOption {
+    BuiltinOption {
         id: "mainline",
         name: "Mainline Kernel",
         description: "Latest mainline Linux kernel",
         category: "System",
         requires_reboot: true,
         excluded_from_all: true,
+        targets: &[],
+        depends_on: &[],
+        sources: &[],
     },
-    InstallOption {
+    BuiltinOption {
         id: "noctalia",
         name: "Noctalia Shell",
         description: "Modern desktop shell (replaces Waybar)",
         category: "Desktop",
         requires_reboot: false,
         excluded_from_all: false,
+        targets: &[],
+        depends_on: &[],
+        sources: &[],
     },
-    InstallOption {
+    BuiltinOption {
         id: "looknfeel",
         name: "Animations",
         description: "Improved Hyprland window animations",
         category: "Customization",
         requires_reboot: false,
         excluded_from_all: false,
+        targets: &["~/.config/hypr/looknfeel.conf"],
+        depends_on: &[],
+        sources: &["config/hyprland/looknfeel.conf"],
     },
 ];
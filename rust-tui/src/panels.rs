@@ -0,0 +1,451 @@
+//! Arrangement of the main content panels
+//!
+//! The option list, output panel and detail pane sit side by side in the
+//! order and with the relative widths of a [`PanelLayout`]. Layouts are kept
+//! by name in `layouts.json` in the state directory; "default" and
+//! "debugging" always exist, and any saved layout that no longer makes sense
+//! (an unknown panel, one listed twice) is replaced by the default.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ratatui::layout::Rect;
+use serde::{Deserialize, Serialize};
+
+use crate::config::state_dir;
+
+/// Relative widths are kept within this range
+pub const MIN_WEIGHT: u16 = 1;
+pub const MAX_WEIGHT: u16 = 10;
+
+/// Columns a visible panel gets at least, when the terminal has them
+const MIN_PANEL_WIDTH: u16 = 16;
+
+/// The list's share of the list and output width, set with `[` and `]`, in
+/// percent: moved in steps and kept within a range
+pub const SPLIT_STEP: u16 = 5;
+pub const MIN_SPLIT: u16 = 20;
+pub const MAX_SPLIT: u16 = 80;
+
+/// A panel of the main content area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    List,
+    Output,
+    Details,
+}
+
+impl Panel {
+    const ALL: [Panel; 3] = [Panel::List, Panel::Output, Panel::Details];
+
+    /// Name used in the layouts file and the UI
+    pub fn name(self) -> &'static str {
+        match self {
+            Panel::List => "list",
+            Panel::Output => "output",
+            Panel::Details => "details",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Panel::ALL.into_iter().find(|panel| panel.name() == name)
+    }
+}
+
+/// A panel with its relative width
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelSlot {
+    pub panel: Panel,
+    pub weight: u16,
+}
+
+/// Panels from left to right
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanelLayout {
+    slots: Vec<PanelSlot>,
+}
+
+/// On-disk form of a slot; panel names are checked when converting
+#[derive(Serialize, Deserialize)]
+struct SavedSlot {
+    panel: String,
+    weight: u16,
+}
+
+impl PanelLayout {
+    fn from_weights(slots: &[(Panel, u16)]) -> Self {
+        Self {
+            slots: slots
+                .iter()
+                .map(|&(panel, weight)| PanelSlot { panel, weight })
+                .collect(),
+        }
+    }
+
+    /// List on the left, output and details to its right
+    pub fn standard() -> Self {
+        Self::from_weights(&[(Panel::List, 4), (Panel::Output, 6), (Panel::Details, 3)])
+    }
+
+    /// Output first and widest, the list narrow
+    pub fn debugging() -> Self {
+        Self::from_weights(&[(Panel::Output, 7), (Panel::List, 3), (Panel::Details, 3)])
+    }
+
+    /// Build from a saved layout, or None if it doesn't describe every panel once
+    fn from_saved(saved: &[SavedSlot]) -> Option<Self> {
+        let mut slots = Vec::new();
+        for slot in saved {
+            let panel = Panel::from_name(&slot.panel)?;
+            if slots.iter().any(|s: &PanelSlot| s.panel == panel) {
+                return None;
+            }
+            slots.push(PanelSlot {
+                panel,
+                weight: slot.weight.clamp(MIN_WEIGHT, MAX_WEIGHT),
+            });
+        }
+        (slots.len() == Panel::ALL.len()).then_some(Self { slots })
+    }
+
+    fn to_saved(&self) -> Vec<SavedSlot> {
+        self.slots
+            .iter()
+            .map(|slot| SavedSlot {
+                panel: slot.panel.name().to_string(),
+                weight: slot.weight,
+            })
+            .collect()
+    }
+
+    /// Slots from left to right
+    pub fn slots(&self) -> &[PanelSlot] {
+        &self.slots
+    }
+
+    /// Swap the slot at `index` with its neighbour, returning its new index
+    pub fn move_slot(&mut self, index: usize, right: bool) -> usize {
+        let target = if right {
+            (index + 1).min(self.slots.len().saturating_sub(1))
+        } else {
+            index.saturating_sub(1)
+        };
+        if index < self.slots.len() {
+            self.slots.swap(index, target);
+        }
+        target
+    }
+
+    /// Grow or shrink the slot at `index` by one step, within the weight range
+    pub fn resize_slot(&mut self, index: usize, grow: bool) {
+        if let Some(slot) = self.slots.get_mut(index) {
+            slot.weight = if grow {
+                (slot.weight + 1).min(MAX_WEIGHT)
+            } else {
+                slot.weight.saturating_sub(1).max(MIN_WEIGHT)
+            };
+        }
+    }
+
+    fn weight(&self, panel: Panel) -> u16 {
+        self.slots.iter().find(|slot| slot.panel == panel).map_or(0, |slot| slot.weight)
+    }
+
+    /// The list's share of the list and output width by weight, in percent
+    pub fn list_share(&self) -> u16 {
+        let list = self.weight(Panel::List);
+        list * 100 / (list + self.weight(Panel::Output)).max(1)
+    }
+
+    /// Split `area` across the panels `visible` accepts, in layout order
+    ///
+    /// Each panel gets `MIN_PANEL_WIDTH` columns first (or an equal share of a
+    /// narrower area); the rest is divided by weight. A `list_share` divides
+    /// the list's and output's weights between them by that percentage instead.
+    pub fn split(&self, area: Rect, list_share: Option<u16>, visible: impl Fn(Panel) -> bool) -> Vec<(Panel, Rect)> {
+        let pair = u32::from(self.weight(Panel::List) + self.weight(Panel::Output));
+        let shown: Vec<(Panel, u32)> = self
+            .slots
+            .iter()
+            .filter(|s| visible(s.panel))
+            .map(|s| {
+                let weight = match (s.panel, list_share) {
+                    (Panel::List, Some(share)) => pair * u32::from(share),
+                    (Panel::Output, Some(share)) => pair * u32::from(100 - share),
+                    _ => u32::from(s.weight) * 100,
+                };
+                (s.panel, weight)
+            })
+            .collect();
+        if shown.is_empty() {
+            return Vec::new();
+        }
+        let count = shown.len() as u16;
+        let min = MIN_PANEL_WIDTH.min(area.width / count);
+        let spare = area.width - min * count;
+        let total: u32 = shown.iter().map(|&(_, weight)| weight).sum();
+
+        let mut x = area.x;
+        let mut given = 0;
+        shown
+            .iter()
+            .enumerate()
+            .map(|(i, &(panel, weight))| {
+                // The last panel takes the rounding remainder
+                let extra = if i + 1 == shown.len() {
+                    spare - given
+                } else {
+                    (u32::from(spare) * weight / total.max(1)) as u16
+                };
+                given += extra;
+                let rect = Rect { x, width: min + extra, ..area };
+                x += rect.width;
+                (panel, rect)
+            })
+            .collect()
+    }
+}
+
+/// Named layouts and which one is in use
+#[derive(Debug, Clone)]
+pub struct LayoutPrefs {
+    pub active: String,
+    layouts: BTreeMap<String, PanelLayout>,
+}
+
+/// On-disk form of the layouts file
+#[derive(Serialize, Deserialize)]
+struct SavedPrefs {
+    active: String,
+    layouts: BTreeMap<String, Vec<SavedSlot>>,
+}
+
+impl Default for LayoutPrefs {
+    fn default() -> Self {
+        let layouts = BTreeMap::from([
+            ("default".to_string(), PanelLayout::standard()),
+            ("debugging".to_string(), PanelLayout::debugging()),
+        ]);
+        Self {
+            active: "default".to_string(),
+            layouts,
+        }
+    }
+}
+
+impl LayoutPrefs {
+    /// Load saved layouts, keeping the built-in ones for anything missing or broken
+    pub fn load() -> Self {
+        fs::read_to_string(layouts_path()).map_or_else(|_| Self::default(), |contents| Self::from_json(&contents))
+    }
+
+    /// Layouts from the contents of a layouts file
+    fn from_json(contents: &str) -> Self {
+        let mut prefs = Self::default();
+        let Ok(saved) = serde_json::from_str::<SavedPrefs>(contents) else {
+            return prefs;
+        };
+        for (name, slots) in &saved.layouts {
+            let layout = PanelLayout::from_saved(slots).unwrap_or_else(PanelLayout::standard);
+            prefs.layouts.insert(name.clone(), layout);
+        }
+        if prefs.layouts.contains_key(&saved.active) {
+            prefs.active = saved.active;
+        }
+        prefs
+    }
+
+    /// Write all layouts to the layouts file
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        fs::create_dir_all(state_dir())?;
+        fs::write(layouts_path(), self.to_json()?)
+    }
+
+    /// Contents of the layouts file for these layouts
+    fn to_json(&self) -> serde_json::Result<String> {
+        let saved = SavedPrefs {
+            active: self.active.clone(),
+            layouts: self
+                .layouts
+                .iter()
+                .map(|(name, layout)| (name.clone(), layout.to_saved()))
+                .collect(),
+        };
+        serde_json::to_string_pretty(&saved)
+    }
+
+    /// The layout in use
+    pub fn current(&self) -> &PanelLayout {
+        self.layouts.get(&self.active).expect("the active layout always exists")
+    }
+
+    /// The layout in use, for editing
+    pub fn current_mut(&mut self) -> &mut PanelLayout {
+        self.layouts.get_mut(&self.active).expect("the active layout always exists")
+    }
+
+    /// Switch to the next layout by name, wrapping around
+    pub fn cycle(&mut self) {
+        let names: Vec<&String> = self.layouts.keys().collect();
+        let next = names
+            .iter()
+            .position(|name| **name == self.active)
+            .map_or(0, |i| (i + 1) % names.len());
+        self.active = names[next].clone();
+    }
+}
+
+pub fn layouts_path() -> PathBuf {
+    state_dir().join("layouts.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Panels with their columns, like "list 0+38"
+    fn columns(split: &[(Panel, Rect)]) -> String {
+        split
+            .iter()
+            .map(|(panel, rect)| format!("{} {}+{}", panel.name(), rect.x, rect.width))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn area(width: u16) -> Rect {
+        Rect::new(0, 1, width, 30)
+    }
+
+    fn weights(layout: &PanelLayout) -> Vec<(&'static str, u16)> {
+        layout.slots().iter().map(|slot| (slot.panel.name(), slot.weight)).collect()
+    }
+
+    #[test]
+    fn layouts_survive_a_save_and_load() {
+        let mut prefs = LayoutPrefs::default();
+        prefs.cycle();
+        prefs.current_mut().resize_slot(0, true);
+        prefs.current_mut().move_slot(2, false);
+        prefs.layouts.insert(
+            "wide".to_string(),
+            PanelLayout::from_weights(&[(Panel::Details, 1), (Panel::List, 10), (Panel::Output, 5)]),
+        );
+
+        let loaded = LayoutPrefs::from_json(&prefs.to_json().unwrap());
+        assert_eq!(loaded.active, "debugging");
+        assert_eq!(loaded.layouts, prefs.layouts);
+        assert_eq!(weights(loaded.current()), [("output", 8), ("details", 3), ("list", 3)]);
+    }
+
+    #[test]
+    fn saved_weights_are_clamped_into_range() {
+        let json = r#"{"active": "default", "layouts": {"default": [
+            {"panel": "list", "weight": 0},
+            {"panel": "output", "weight": 40},
+            {"panel": "details", "weight": 3}
+        ]}}"#;
+        let prefs = LayoutPrefs::from_json(json);
+        assert_eq!(weights(prefs.current()), [("list", MIN_WEIGHT), ("output", MAX_WEIGHT), ("details", 3)]);
+    }
+
+    #[test]
+    fn broken_layouts_fall_back_to_the_standard_one() {
+        let broken = [
+            r#"[{"panel": "list", "weight": 4}, {"panel": "output", "weight": 6}, {"panel": "graph", "weight": 3}]"#,
+            r#"[{"panel": "list", "weight": 4}, {"panel": "list", "weight": 6}, {"panel": "details", "weight": 3}]"#,
+            r#"[{"panel": "list", "weight": 4}, {"panel": "output", "weight": 6}]"#,
+            "[]",
+        ];
+        for slots in broken {
+            let json = format!(r#"{{"active": "mine", "layouts": {{"mine": {}}}}}"#, slots);
+            let prefs = LayoutPrefs::from_json(&json);
+            assert_eq!(prefs.active, "mine", "{}", slots);
+            assert_eq!(*prefs.current(), PanelLayout::standard(), "{}", slots);
+            assert!(prefs.layouts.contains_key("debugging"), "built-in layouts stay");
+        }
+    }
+
+    #[test]
+    fn unreadable_files_and_unknown_active_layouts_use_the_defaults() {
+        for json in ["", "not json", r#"{"active": "default"}"#, r#"{"active": "gone", "layouts": {}}"#] {
+            let prefs = LayoutPrefs::from_json(json);
+            assert_eq!(prefs.active, "default", "{:?}", json);
+            assert_eq!(prefs.layouts, LayoutPrefs::default().layouts, "{:?}", json);
+        }
+    }
+
+    #[test]
+    fn slots_move_and_resize_within_bounds() {
+        let mut layout = PanelLayout::standard();
+        assert_eq!(layout.move_slot(0, false), 0, "the leftmost slot stays put");
+        assert_eq!(layout.move_slot(2, true), 2, "the rightmost slot stays put");
+        assert_eq!(layout.move_slot(0, true), 1);
+        assert_eq!(weights(&layout), [("output", 6), ("list", 4), ("details", 3)]);
+
+        for _ in 0..20 {
+            layout.resize_slot(0, true);
+            layout.resize_slot(2, false);
+        }
+        layout.resize_slot(7, true);
+        assert_eq!(weights(&layout), [("output", MAX_WEIGHT), ("list", 4), ("details", MIN_WEIGHT)]);
+    }
+
+    #[test]
+    fn list_share_is_the_lists_part_of_the_pair() {
+        assert_eq!(PanelLayout::standard().list_share(), 40);
+        assert_eq!(PanelLayout::debugging().list_share(), 30);
+    }
+
+    #[test]
+    fn cycling_visits_every_layout_and_wraps() {
+        let mut prefs = LayoutPrefs::default();
+        prefs.layouts.insert("zen".to_string(), PanelLayout::standard());
+        let visited: Vec<String> = (0..4)
+            .map(|_| {
+                prefs.cycle();
+                prefs.active.clone()
+            })
+            .collect();
+        assert_eq!(visited, ["zen", "debugging", "default", "zen"]);
+    }
+
+    #[test]
+    fn split_snapshots() {
+        let standard = PanelLayout::standard();
+        let all = |_| true;
+        assert_eq!(columns(&standard.split(area(120), None, all)), "list 0+38, output 38+49, details 87+33");
+        assert_eq!(columns(&standard.split(area(80), None, all)), "list 0+25, output 25+30, details 55+25");
+        assert_eq!(
+            columns(&standard.split(area(30), None, all)),
+            "list 0+10, output 10+10, details 20+10",
+            "narrower than the minimums, panels share equally"
+        );
+        assert_eq!(
+            columns(&standard.split(area(100), None, |panel| panel != Panel::Details)),
+            "list 0+43, output 43+57"
+        );
+        assert_eq!(
+            columns(&standard.split(area(120), Some(50), all)),
+            "list 0+43, output 43+43, details 86+34"
+        );
+        assert_eq!(
+            columns(&PanelLayout::debugging().split(area(120), None, all)),
+            "output 0+54, list 54+32, details 86+34"
+        );
+        assert!(standard.split(area(120), None, |_| false).is_empty());
+    }
+
+    #[test]
+    fn split_fills_the_area_exactly() {
+        let layout = PanelLayout::debugging();
+        for width in [0, 1, 17, 47, 48, 49, 133, 500] {
+            let area = Rect::new(3, 2, width, 10);
+            let split = layout.split(area, Some(35), |_| true);
+            assert_eq!(split.first().unwrap().1.x, 3);
+            assert_eq!(split.iter().map(|(_, rect)| rect.width).sum::<u16>(), width, "width {}", width);
+            assert!(split.windows(2).all(|pair| pair[0].1.right() == pair[1].1.x), "width {}", width);
+            assert!(split.iter().all(|(_, rect)| rect.y == 2 && rect.height == 10));
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Decisions left over from earlier sessions
+//!
+//! An interrupted batch, a reboot still owed and installed entries no option
+//! has any more are gathered at startup into one overlay where each gets its
+//! own choice, instead of a popup apiece.
+//! Anything put off stays listed here and is shown as a status bar badge
+//! until it's decided.
+
+use crate::journal::BatchJournal;
+
+/// What a pending item is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingKind {
+    /// A queue the last session didn't finish, from the batch journal
+    Batch,
+    /// Options installed since the last boot that need a reboot
+    Reboot,
+    /// Installed ids in the state file that no option has, e.g. after a rename
+    UnknownIds,
+}
+
+/// What to do about a pending item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingChoice {
+    Resume,
+    RetryFailed,
+    Discard,
+    RebootNow,
+    Prune,
+    Later,
+}
+
+impl PendingChoice {
+    /// Label shown in the overlay
+    pub fn label(self) -> &'static str {
+        match self {
+            PendingChoice::Resume => "Resume",
+            PendingChoice::RetryFailed => "Resume + retry failures",
+            PendingChoice::Discard => "Discard",
+            PendingChoice::RebootNow => "Reboot now",
+            PendingChoice::Prune => "Remove from the state file",
+            PendingChoice::Later => "Decide later",
+        }
+    }
+}
+
+impl PendingKind {
+    /// Choices offered, the first being the default
+    pub fn choices(self) -> &'static [PendingChoice] {
+        match self {
+            PendingKind::Batch => &[
+                PendingChoice::Resume,
+                PendingChoice::RetryFailed,
+                PendingChoice::Discard,
+                PendingChoice::Later,
+            ],
+            PendingKind::Reboot => &[PendingChoice::Later, PendingChoice::RebootNow],
+            PendingKind::UnknownIds => &[PendingChoice::Later, PendingChoice::Prune],
+        }
+    }
+}
+
+/// One item needing a decision, with the choice currently selected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingItem {
+    pub kind: PendingKind,
+    choice: usize,
+}
+
+impl PendingItem {
+    fn new(kind: PendingKind) -> Self {
+        Self { kind, choice: 0 }
+    }
+
+    /// The selected choice
+    pub fn choice(&self) -> PendingChoice {
+        self.kind.choices()[self.choice]
+    }
+}
+
+/// Everything awaiting a decision, navigated like a form
+#[derive(Debug, Clone, Default)]
+pub struct PendingItems {
+    pub items: Vec<PendingItem>,
+    /// Index of the item the choice keys apply to
+    pub focus: usize,
+}
+
+impl PendingItems {
+    /// Gather what the last session left undecided
+    pub fn collect(journal: Option<&BatchJournal>, reboot_pending: bool, unknown_ids: bool) -> Self {
+        let mut items = Vec::new();
+        if journal.is_some() {
+            items.push(PendingItem::new(PendingKind::Batch));
+        }
+        if reboot_pending {
+            items.push(PendingItem::new(PendingKind::Reboot));
+        }
+        if unknown_ids {
+            items.push(PendingItem::new(PendingKind::UnknownIds));
+        }
+        Self { items, focus: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Whether an item of this kind is waiting
+    pub fn contains(&self, kind: PendingKind) -> bool {
+        self.items.iter().any(|item| item.kind == kind)
+    }
+
+    /// Move the focus to the previous or next item, stopping at the ends
+    pub fn move_focus(&mut self, down: bool) {
+        self.focus = if down {
+            (self.focus + 1).min(self.items.len().saturating_sub(1))
+        } else {
+            self.focus.saturating_sub(1)
+        };
+    }
+
+    /// Step the focused item's choice, wrapping around
+    pub fn cycle_choice(&mut self, forward: bool) {
+        if let Some(item) = self.items.get_mut(self.focus) {
+            let count = item.kind.choices().len();
+            item.choice = if forward {
+                (item.choice + 1) % count
+            } else {
+                (item.choice + count - 1) % count
+            };
+        }
+    }
+
+    /// Take the items that were decided, keeping the deferred ones
+    ///
+    /// The kept items go back to their default choice for the next review.
+    pub fn take_decided(&mut self) -> Vec<PendingItem> {
+        let (deferred, decided) = std::mem::take(&mut self.items)
+            .into_iter()
+            .partition(|item| item.choice() == PendingChoice::Later);
+        self.items = deferred;
+        self.defer_all();
+        decided
+    }
+
+    /// Put every decision off, back at the default choices for the next review
+    pub fn defer_all(&mut self) {
+        for item in &mut self.items {
+            item.choice = 0;
+        }
+        self.focus = 0;
+    }
+
+    /// Forget an item, e.g. once a new queue replaced the old journal
+    pub fn remove(&mut self, kind: PendingKind) {
+        self.items.retain(|item| item.kind != kind);
+        self.focus = self.focus.min(self.items.len().saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal() -> BatchJournal {
+        BatchJournal {
+            items: Vec::new(),
+            continue_on_failure: false,
+        }
+    }
+
+    /// All three kinds of item, in the order they're collected
+    fn everything() -> PendingItems {
+        PendingItems::collect(Some(&journal()), true, true)
+    }
+
+    fn kinds(pending: &PendingItems) -> Vec<PendingKind> {
+        pending.items.iter().map(|item| item.kind).collect()
+    }
+
+    fn choices(pending: &PendingItems) -> Vec<PendingChoice> {
+        pending.items.iter().map(PendingItem::choice).collect()
+    }
+
+    #[test]
+    fn nothing_left_over_collects_nothing() {
+        let pending = PendingItems::collect(None, false, false);
+        assert!(pending.is_empty());
+        assert!(!pending.contains(PendingKind::Batch));
+    }
+
+    #[test]
+    fn each_leftover_becomes_one_item() {
+        let journal = journal();
+        for (pending, kind) in [
+            (PendingItems::collect(Some(&journal), false, false), PendingKind::Batch),
+            (PendingItems::collect(None, true, false), PendingKind::Reboot),
+            (PendingItems::collect(None, false, true), PendingKind::UnknownIds),
+        ] {
+            assert_eq!(kinds(&pending), [kind]);
+            assert!(pending.contains(kind));
+            assert_eq!(pending.focus, 0);
+        }
+    }
+
+    #[test]
+    fn items_start_on_their_default_choice() {
+        let pending = everything();
+        assert_eq!(kinds(&pending), [PendingKind::Batch, PendingKind::Reboot, PendingKind::UnknownIds]);
+        assert_eq!(choices(&pending), [PendingChoice::Resume, PendingChoice::Later, PendingChoice::Later]);
+    }
+
+    #[test]
+    fn focus_stops_at_the_ends() {
+        let mut pending = everything();
+        pending.move_focus(false);
+        assert_eq!(pending.focus, 0);
+        for _ in 0..5 {
+            pending.move_focus(true);
+        }
+        assert_eq!(pending.focus, 2);
+        pending.move_focus(false);
+        assert_eq!(pending.focus, 1);
+
+        let mut empty = PendingItems::default();
+        empty.move_focus(true);
+        empty.cycle_choice(true);
+        assert_eq!(empty.focus, 0);
+    }
+
+    #[test]
+    fn choices_wrap_both_ways_for_the_focused_item_only() {
+        let mut pending = everything();
+        pending.cycle_choice(false);
+        assert_eq!(pending.items[0].choice(), PendingChoice::Later);
+        pending.cycle_choice(true);
+        pending.cycle_choice(true);
+        assert_eq!(pending.items[0].choice(), PendingChoice::RetryFailed);
+
+        pending.move_focus(true);
+        pending.cycle_choice(true);
+        assert_eq!(choices(&pending), [PendingChoice::RetryFailed, PendingChoice::RebootNow, PendingChoice::Later]);
+        pending.cycle_choice(true);
+        assert_eq!(pending.items[1].choice(), PendingChoice::Later);
+    }
+
+    #[test]
+    fn deciding_keeps_only_what_was_put_off() {
+        let mut pending = everything();
+        pending.cycle_choice(true);
+        pending.move_focus(true);
+        pending.move_focus(true);
+        pending.cycle_choice(true);
+
+        let decided = pending.take_decided();
+        let decided: Vec<_> = decided.iter().map(|item| (item.kind, item.choice())).collect();
+        assert_eq!(
+            decided,
+            [(PendingKind::Batch, PendingChoice::RetryFailed), (PendingKind::UnknownIds, PendingChoice::Prune)]
+        );
+        assert_eq!(kinds(&pending), [PendingKind::Reboot]);
+        assert_eq!(pending.focus, 0);
+    }
+
+    #[test]
+    fn deferred_items_come_back_at_their_defaults() {
+        let mut pending = everything();
+        for _ in 0..3 {
+            pending.cycle_choice(true);
+        }
+        assert_eq!(pending.items[0].choice(), PendingChoice::Later);
+        pending.move_focus(true);
+        pending.cycle_choice(true);
+
+        assert!(pending.take_decided().iter().all(|item| item.kind == PendingKind::Reboot));
+        assert_eq!(choices(&pending), [PendingChoice::Resume, PendingChoice::Later]);
+
+        pending.move_focus(true);
+        pending.cycle_choice(true);
+        pending.defer_all();
+        assert_eq!(choices(&pending), [PendingChoice::Resume, PendingChoice::Later]);
+        assert_eq!(pending.focus, 0);
+    }
+
+    #[test]
+    fn removing_keeps_the_focus_on_an_item() {
+        let mut pending = everything();
+        pending.move_focus(true);
+        pending.move_focus(true);
+        pending.remove(PendingKind::UnknownIds);
+        assert_eq!(kinds(&pending), [PendingKind::Batch, PendingKind::Reboot]);
+        assert_eq!(pending.focus, 1);
+
+        pending.remove(PendingKind::UnknownIds);
+        assert_eq!(pending.items.len(), 2, "removing a missing kind changes nothing");
+        pending.remove(PendingKind::Batch);
+        pending.remove(PendingKind::Reboot);
+        assert!(pending.is_empty());
+        assert_eq!(pending.focus, 0);
+    }
+}
@@ -0,0 +1,363 @@
+//! Background writer for state-file updates
+//!
+//! Saving the state file means a read-modify-write of a JSON file, which on
+//! NFS or a failing disk can take seconds. The UI hands each update to a
+//! dedicated thread instead and only tracks how many are still pending.
+//! Commands are applied strictly in the order they were sent.
+//!
+//! Hashing an option's target files after an install happens here too, and
+//! the hashes come back to the UI with the confirmation.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{
+    add_pending_reboot, detached_note_path, forget_options, mark_installed, save_detached_note, save_last_selected,
+    save_prefs, save_record, save_target_hashes, set_installed_state, state_file_path, OptionRecord, StateRecovered,
+};
+use crate::logs::note_state_edit;
+use crate::modified::hash_targets;
+use crate::panels::{layouts_path, LayoutPrefs};
+use crate::prefs::UiPrefs;
+use crate::wake::{relay, Wake};
+
+/// Target hashes stored for an option
+pub type TargetHashes = (String, BTreeMap<String, String>);
+
+/// A state-file update
+#[derive(Clone)]
+pub enum PersistCommand {
+    /// Store an option's record after a run
+    Record { option_id: String, record: OptionRecord },
+    /// Add an option to the reboot-pending list
+    PendingReboot(String),
+    /// Set the installed state of options as found on the system
+    SetInstalled { option_ids: Vec<String>, installed: bool },
+    /// Mark options installed or not by hand, noting the edit in its own log
+    Mark { option_ids: Vec<String>, installed: bool },
+    /// Hash an option's target files and store the hashes (none clears them)
+    HashTargets { option_id: String, targets: Vec<PathBuf> },
+    /// Write the named panel layouts
+    SaveLayouts(LayoutPrefs),
+    /// Remember the selected option for the next session
+    SaveSelection(String),
+    /// Write the interface preferences
+    SavePrefs(UiPrefs),
+    /// Drop state kept for ids no option has any more
+    Forget(Vec<String>),
+    /// Leave a note about runs that finished after the terminal was lost
+    SaveDetachedNote(String),
+}
+
+/// Confirmation of one applied command
+struct Applied {
+    error: Option<String>,
+    hashes: Option<TargetHashes>,
+    /// The command again when its write didn't go through
+    retry: Option<PersistCommand>,
+}
+
+impl PersistCommand {
+    fn apply(self) -> Applied {
+        let mut hashes = None;
+        let result = match &self {
+            PersistCommand::Record { option_id, record } => save_record(option_id, record),
+            PersistCommand::PendingReboot(option_id) => add_pending_reboot(option_id),
+            PersistCommand::SetInstalled { option_ids, installed } => {
+                set_installed_state(option_ids, *installed)
+            }
+            PersistCommand::Mark { option_ids, installed } => {
+                let result = mark_installed(option_ids, *installed);
+                if result.is_ok() {
+                    let state = if *installed { "installed" } else { "not installed" };
+                    note_state_edit(&format!("marked {} by hand: {}", state, option_ids.join(", ")));
+                }
+                result
+            }
+            PersistCommand::HashTargets { option_id, targets } => {
+                let hashed = hash_targets(targets);
+                let result = save_target_hashes(option_id, &hashed);
+                hashes = Some((option_id.clone(), hashed));
+                result
+            }
+            PersistCommand::SaveLayouts(layouts) => layouts.save(),
+            PersistCommand::SaveSelection(option_id) => save_last_selected(option_id),
+            PersistCommand::SavePrefs(prefs) => save_prefs(prefs),
+            PersistCommand::Forget(option_ids) => forget_options(option_ids),
+            PersistCommand::SaveDetachedNote(note) => save_detached_note(note),
+        };
+        match result {
+            Ok(()) => Applied { error: None, hashes, retry: None },
+            // A write that recovered from a corrupt state file did go
+            // through; it only needs the recovery reported
+            Err(e) => match e.get_ref().and_then(|inner| inner.downcast_ref::<StateRecovered>()) {
+                Some(recovered) => Applied {
+                    error: Some(recovered.to_string()),
+                    hashes,
+                    retry: None,
+                },
+                None => Applied {
+                    error: Some(format!("Could not save {}: {}", self.file().display(), e)),
+                    hashes,
+                    retry: Some(self),
+                },
+            },
+        }
+    }
+
+    /// File the command writes to
+    fn file(&self) -> PathBuf {
+        match self {
+            PersistCommand::SaveLayouts(_) => layouts_path(),
+            PersistCommand::SaveDetachedNote(_) => detached_note_path(),
+            _ => state_file_path(),
+        }
+    }
+}
+
+/// Handle to the writer thread
+pub struct Persister {
+    tx: Option<Sender<PersistCommand>>,
+    /// One message per applied command
+    done_rx: Receiver<Applied>,
+    /// Commands sent but not yet confirmed
+    pending: usize,
+    /// Hashes computed since the last `take_hashes`
+    hashes: Vec<TargetHashes>,
+    /// Commands whose write failed, in the order they were sent
+    failed: Vec<PersistCommand>,
+}
+
+impl Persister {
+    /// Start the writer thread
+    pub fn start() -> Self {
+        Self::with_writer(PersistCommand::apply)
+    }
+
+    /// A persister that hands every command to the receiver instead of writing it
+    #[cfg(test)]
+    pub fn recording() -> (Self, Receiver<PersistCommand>) {
+        let (tx, rx) = mpsc::channel();
+        let persister = Self::with_writer(move |command| {
+            let _ = tx.send(command);
+            Applied { error: None, hashes: None, retry: None }
+        });
+        (persister, rx)
+    }
+
+    /// Start a writer thread that carries out each command with `write`
+    fn with_writer(mut write: impl FnMut(PersistCommand) -> Applied + Send + 'static) -> Self {
+        let (tx, rx) = mpsc::channel::<PersistCommand>();
+        let (done_tx, done_rx) = mpsc::channel();
+        thread::spawn(move || {
+            for command in rx {
+                if done_tx.send(write(command)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            tx: Some(tx),
+            done_rx,
+            pending: 0,
+            hashes: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+
+    /// Wake the event loop whenever a write is confirmed
+    pub fn wake_with(&mut self, wake: Sender<Wake>) {
+        let (_, placeholder) = mpsc::channel();
+        let done_rx = std::mem::replace(&mut self.done_rx, placeholder);
+        self.done_rx = relay(done_rx, wake);
+    }
+
+    /// Queue an update
+    pub fn send(&mut self, command: PersistCommand) {
+        if let Some(tx) = &self.tx {
+            if tx.send(command).is_ok() {
+                self.pending += 1;
+            }
+        }
+    }
+
+    /// Whether updates are still waiting to be written
+    pub fn busy(&self) -> bool {
+        self.pending > 0
+    }
+
+    /// Number of updates whose write failed and can be retried
+    pub fn failed(&self) -> usize {
+        self.failed.len()
+    }
+
+    /// Send every failed update again, returning how many were
+    pub fn retry(&mut self) -> usize {
+        let failed = std::mem::take(&mut self.failed);
+        let count = failed.len();
+        for command in failed {
+            self.send(command);
+        }
+        count
+    }
+
+    /// Collect confirmations, returning the status text of failed writes
+    pub fn poll(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        while let Ok(applied) = self.done_rx.try_recv() {
+            errors.extend(self.confirm(applied));
+        }
+        errors
+    }
+
+    /// Count a confirmation in, keeping its hashes and returning its error
+    fn confirm(&mut self, applied: Applied) -> Option<String> {
+        self.pending -= 1;
+        self.hashes.extend(applied.hashes);
+        self.failed.extend(applied.retry);
+        applied.error
+    }
+
+    /// Target hashes computed since the last call
+    pub fn take_hashes(&mut self) -> Vec<TargetHashes> {
+        std::mem::take(&mut self.hashes)
+    }
+
+    /// Wait up to `timeout` for every queued update to be written
+    ///
+    /// Returns the errors of failed writes and the number of updates still
+    /// unwritten when the wait ran out.
+    pub fn drain(&mut self, timeout: Duration) -> (Vec<String>, usize) {
+        // Closing the channel lets the thread exit once it's done
+        self.tx = None;
+        let deadline = Instant::now() + timeout;
+        let mut errors = self.poll();
+        while self.pending > 0 {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.done_rx.recv_timeout(left) {
+                Ok(applied) => errors.extend(self.confirm(applied)),
+                Err(RecvTimeoutError::Timeout) => break,
+                // The writer died (e.g. panicked); nothing more will arrive
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        (errors, self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn select(option_id: &str) -> PersistCommand {
+        PersistCommand::SaveSelection(option_id.to_string())
+    }
+
+    fn selection(command: &PersistCommand) -> String {
+        match command {
+            PersistCommand::SaveSelection(option_id) => option_id.clone(),
+            _ => panic!("only selections are sent here"),
+        }
+    }
+
+    fn written() -> Applied {
+        Applied { error: None, hashes: None, retry: None }
+    }
+
+    /// A writer taking `delay` per command and noting what it wrote
+    fn slow_writer(delay: Duration) -> (Persister, Arc<Mutex<Vec<String>>>) {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&log);
+        let persister = Persister::with_writer(move |command| {
+            thread::sleep(delay);
+            seen.lock().unwrap().push(selection(&command));
+            written()
+        });
+        (persister, log)
+    }
+
+    /// Poll until every sent command is confirmed
+    fn settle(persister: &mut Persister) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut errors = Vec::new();
+        while persister.busy() {
+            assert!(Instant::now() < deadline, "the writer never caught up");
+            errors.extend(persister.poll());
+            thread::sleep(Duration::from_millis(5));
+        }
+        errors
+    }
+
+    #[test]
+    fn commands_are_written_in_the_order_sent() {
+        let (mut persister, log) = slow_writer(Duration::from_millis(10));
+        for id in ["fish", "neovim", "hypr", "fish"] {
+            persister.send(select(id));
+        }
+        assert!(persister.busy());
+        assert_eq!(persister.drain(Duration::from_secs(5)), (Vec::new(), 0));
+        assert_eq!(*log.lock().unwrap(), ["fish", "neovim", "hypr", "fish"]);
+        assert!(!persister.busy());
+    }
+
+    #[test]
+    fn drain_gives_up_at_its_bound_and_counts_what_is_left() {
+        let (mut persister, log) = slow_writer(Duration::from_millis(200));
+        for id in ["a", "b", "c", "d", "e"] {
+            persister.send(select(id));
+        }
+        let started = Instant::now();
+        let (errors, unwritten) = persister.drain(Duration::from_millis(300));
+        assert!(started.elapsed() < Duration::from_millis(800), "{:?}", started.elapsed());
+        assert!(errors.is_empty());
+        assert!((3..=4).contains(&unwritten), "{} unwritten", unwritten);
+        // Whatever got written was still the oldest
+        let log = log.lock().unwrap();
+        assert_eq!(log[..], ["a", "b", "c", "d", "e"][..log.len()]);
+    }
+
+    #[test]
+    fn a_failed_write_is_reported_and_can_be_retried() {
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&attempts);
+        let mut persister = Persister::with_writer(move |command| {
+            let id = selection(&command);
+            let mut attempts = seen.lock().unwrap();
+            let first_try = !attempts.contains(&id);
+            attempts.push(id);
+            if first_try && attempts.len() == 2 {
+                Applied {
+                    error: Some("Could not save state.json: disk full".to_string()),
+                    hashes: None,
+                    retry: Some(command),
+                }
+            } else {
+                written()
+            }
+        });
+
+        persister.send(select("fish"));
+        persister.send(select("neovim"));
+        assert_eq!(settle(&mut persister), ["Could not save state.json: disk full"]);
+        assert_eq!(persister.failed(), 1);
+
+        assert_eq!(persister.retry(), 1);
+        assert_eq!(persister.failed(), 0);
+        assert_eq!(persister.drain(Duration::from_secs(5)), (Vec::new(), 0));
+        assert_eq!(*attempts.lock().unwrap(), ["fish", "neovim", "neovim"]);
+    }
+
+    #[test]
+    fn a_dead_writer_ends_the_drain() {
+        let mut persister = Persister::with_writer(|_| panic!("the disk caught fire"));
+        persister.send(select("fish"));
+        let started = Instant::now();
+        assert_eq!(persister.drain(Duration::from_secs(5)), (Vec::new(), 1));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
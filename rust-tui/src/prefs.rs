@@ -0,0 +1,192 @@
+//! Interface preferences remembered between sessions
+//!
+//! They live in a `prefs` section of the state file, read leniently: a
+//! missing section, or one that doesn't parse, means the defaults, and never
+//! makes the state file count as corrupt.
+//!
+//! Preferences set by hand rather than remembered, like `always_show_summary`,
+//! are top-level keys of config.toml instead.
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::state_dir;
+use crate::options::{InstallOption, IoniceClass};
+use crate::panels::{MAX_SPLIT, MIN_SPLIT};
+use crate::state::AppState;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiPrefs {
+    /// Whether the output panel is open at startup
+    pub show_output: bool,
+    /// Whether the output follows new lines
+    pub follow_output: bool,
+    /// Flavor last picked with `--theme`, for sessions where neither the
+    /// flag nor config.toml picks one
+    pub theme: Option<String>,
+    /// Option list filter
+    pub filter: String,
+    /// List's share of the list and output width, when moved with `[` and `]`
+    pub split: Option<u16>,
+}
+
+impl Default for UiPrefs {
+    fn default() -> Self {
+        Self {
+            show_output: false,
+            follow_output: true,
+            theme: None,
+            filter: String::new(),
+            split: None,
+        }
+    }
+}
+
+impl UiPrefs {
+    /// Read the saved section, falling back to the defaults
+    pub fn from_value(value: Option<serde_json::Value>) -> Self {
+        value
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Preferences as the session has them now
+    pub fn of(state: &AppState) -> Self {
+        Self {
+            show_output: state.output_on_start,
+            follow_output: state.follow_output,
+            theme: state.theme_choice.clone(),
+            filter: state.filter.clone(),
+            split: state.split,
+        }
+    }
+
+    /// Start a session with these preferences
+    pub fn apply(&self, state: &mut AppState) {
+        state.theme_choice = self.theme.clone();
+        state.show_output = self.show_output;
+        state.output_on_start = self.show_output;
+        state.split = self.split.map(|share| share.clamp(MIN_SPLIT, MAX_SPLIT));
+        state.follow_output = self.follow_output;
+        if !self.filter.is_empty() {
+            state.set_filter(self.filter.clone());
+        }
+    }
+}
+
+/// The `always_show_summary` key of config.toml: whether single runs end
+/// with the summary popup too, not only queues
+pub fn configured_always_show_summary() -> Result<bool, String> {
+    configured_switch("always_show_summary")
+}
+
+/// The `expert` key of config.toml: whether installed state can be edited
+/// by hand, as with --expert
+pub fn configured_expert() -> Result<bool, String> {
+    configured_switch("expert")
+}
+
+/// A true/false top-level key of config.toml, false when it's missing
+fn configured_switch(key: &str) -> Result<bool, String> {
+    let Ok(contents) = fs::read_to_string(state_dir().join("config.toml")) else {
+        return Ok(false);
+    };
+    // Syntax errors are reported by the keymap, which reads the same file
+    let Ok(table) = contents.parse::<toml::Table>() else {
+        return Ok(false);
+    };
+    match table.get(key) {
+        None => Ok(false),
+        Some(toml::Value::Boolean(on)) => Ok(*on),
+        Some(_) => Err(format!("{}: expected true or false", key)),
+    }
+}
+
+/// Scheduling priority for install scripts
+///
+/// The `nice` and `ionice_class` keys of config.toml set it for every
+/// option; an option's own values win over them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Priority {
+    pub nice: Option<i32>,
+    pub ionice_class: Option<IoniceClass>,
+}
+
+impl Priority {
+    /// The priority `option` runs at, its own values over these
+    pub fn for_option(self, option: &InstallOption) -> Self {
+        Self {
+            nice: option.nice.or(self.nice),
+            ionice_class: option.ionice_class.or(self.ionice_class),
+        }
+    }
+}
+
+/// The `nice` and `ionice_class` keys of config.toml, with the problems
+/// found in them; a key with a problem is left unset
+pub fn configured_priority() -> (Priority, Vec<String>) {
+    let Ok(contents) = fs::read_to_string(state_dir().join("config.toml")) else {
+        return (Priority::default(), Vec::new());
+    };
+    // Syntax errors are reported by the keymap, which reads the same file
+    let Ok(table) = contents.parse::<toml::Table>() else {
+        return (Priority::default(), Vec::new());
+    };
+    priority_in(&table)
+}
+
+fn priority_in(table: &toml::Table) -> (Priority, Vec<String>) {
+    let mut priority = Priority::default();
+    let mut problems = Vec::new();
+    match table.get("nice") {
+        None => {}
+        Some(toml::Value::Integer(nice)) if (-20..=19).contains(nice) => priority.nice = Some(*nice as i32),
+        Some(_) => problems.push("nice: expected a number between -20 and 19".to_string()),
+    }
+    match table.get("ionice_class") {
+        None => {}
+        Some(toml::Value::String(name)) if IoniceClass::parse(name).is_some() => {
+            priority.ionice_class = IoniceClass::parse(name);
+        }
+        Some(_) => problems.push("ionice_class: expected realtime, best-effort or idle".to_string()),
+    }
+    (priority, problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn priority_of(toml: &str) -> (Priority, Vec<String>) {
+        priority_in(&toml.parse().unwrap())
+    }
+
+    #[test]
+    fn priority_defaults_come_from_config() {
+        let (priority, problems) = priority_of("nice = 10\nionice_class = \"best-effort\"");
+        assert_eq!(priority.nice, Some(10));
+        assert_eq!(priority.ionice_class, Some(IoniceClass::BestEffort));
+        assert!(problems.is_empty());
+
+        assert_eq!(priority_of(""), (Priority::default(), Vec::new()));
+    }
+
+    #[test]
+    fn bad_priority_keys_are_reported_and_ignored() {
+        let (priority, problems) = priority_of("nice = 40\nionice_class = \"lazy\"");
+        assert_eq!(priority, Priority::default());
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("nice:"));
+        assert!(problems[1].starts_with("ionice_class:"));
+    }
+
+    #[test]
+    fn an_option_s_own_priority_wins() {
+        let defaults = Priority { nice: Some(10), ionice_class: Some(IoniceClass::Idle) };
+        let option = InstallOption { nice: Some(5), ..InstallOption::default() };
+        assert_eq!(defaults.for_option(&option), Priority { nice: Some(5), ionice_class: Some(IoniceClass::Idle) });
+        assert_eq!(defaults.for_option(&InstallOption::default()), defaults);
+    }
+}
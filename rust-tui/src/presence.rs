@@ -0,0 +1,77 @@
+//! Asking the install script what is really installed
+//!
+//! The state file only knows what this installer did; options removed by hand
+//! or lost to a re-image still read as installed. `install check <id>` lets
+//! the script look for itself:
+//!
+//! - exit 0: the option is installed
+//! - exit 3 ([`CHECK_ABSENT`]): it is not
+//! - anything else: the script couldn't tell, or doesn't know `check` (an
+//!   unknown parameter makes the bundled script exit 1), so the recorded
+//!   state is left alone and the option is shown as unknown
+//!
+//! A plain non-zero exit can't mean "absent": a script without `check`
+//! support would then un-install everything on paper.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use crate::check::{run_program, CheckStatus};
+
+/// Exit code with which `install check <id>` reports the option as absent
+pub const CHECK_ABSENT: i32 = 3;
+
+/// Time allowed for each option's check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// What the script said about an option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Presence {
+    Installed,
+    Absent,
+    /// The script couldn't say, with the reason
+    Unknown(String),
+}
+
+/// Progress of a background check, sent back to the UI
+#[derive(Debug, Clone)]
+pub enum PresenceEvent {
+    /// One option was checked
+    Checked { option_id: String, presence: Presence },
+    /// Every option has been checked
+    Done,
+}
+
+/// Run `install check <id>` for one option
+pub fn check_presence(script_dir: &Path, entry: &Path, option_id: &str) -> Presence {
+    let outcome = run_program(entry, &["check", option_id], script_dir, CHECK_TIMEOUT);
+    match outcome.status {
+        CheckStatus::Passed => Presence::Installed,
+        CheckStatus::Failed(CHECK_ABSENT) => Presence::Absent,
+        CheckStatus::Failed(code) => {
+            // The script's first words usually explain an unsupported check best
+            let said = outcome.output.iter().find(|line| !line.trim().is_empty());
+            Presence::Unknown(match said {
+                Some(line) => format!("exit code {}: {}", code, line.trim()),
+                None => format!("exit code {}", code),
+            })
+        }
+        CheckStatus::TimedOut => Presence::Unknown("timed out".to_string()),
+        CheckStatus::Error(e) => Presence::Unknown(format!("could not run: {}", e)),
+    }
+}
+
+/// Check options one after another on a background thread
+pub fn check_all(script_dir: PathBuf, entry: PathBuf, option_ids: Vec<String>, tx: Sender<PresenceEvent>) {
+    thread::spawn(move || {
+        for option_id in option_ids {
+            let presence = check_presence(&script_dir, &entry, &option_id);
+            if tx.send(PresenceEvent::Checked { option_id, presence }).is_err() {
+                return;
+            }
+        }
+        let _ = tx.send(PresenceEvent::Done);
+    });
+}
@@ -0,0 +1,91 @@
+//! Named sets of options installed together
+//!
+//! A `[profiles]` table, in config.toml in the state directory or in the
+//! option manifest, maps a profile name to option ids:
+//!
+//! ```toml
+//! [profiles]
+//! workstation = ["fish", "claude", "ghostty"]
+//! ```
+//!
+//! When both files name the same profile, config.toml wins. Ids that aren't
+//! in the catalog are only known once the options are loaded, so they are
+//! reported when the profile is shown or run rather than here.
+
+use std::fs;
+use std::path::Path;
+
+use crate::config::state_dir;
+use crate::manifest::manifest_path;
+use crate::options::InstallOption;
+
+/// A named set of option ids
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    /// Members in the order they were listed, which is the order they run in
+    pub option_ids: Vec<String>,
+}
+
+impl Profile {
+    /// Members that aren't in `options`
+    pub fn unknown_ids<'a>(&'a self, options: &[InstallOption]) -> Vec<&'a str> {
+        self.option_ids
+            .iter()
+            .filter(|id| !options.iter().any(|o| &o.id == *id))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Load the profiles of the manifest and config.toml, sorted by name,
+/// with the problems found in them
+pub fn load_profiles(script_dir: &Path) -> (Vec<Profile>, Vec<String>) {
+    let mut problems = Vec::new();
+    let mut profiles = manifest_path(script_dir)
+        .map(|path| profiles_in(&path, &mut problems))
+        .unwrap_or_default();
+    for profile in profiles_in(&state_dir().join("config.toml"), &mut problems) {
+        profiles.retain(|p| p.name != profile.name);
+        profiles.push(profile);
+    }
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    (profiles, problems)
+}
+
+/// The `[profiles]` table of a TOML file
+///
+/// A missing file or a syntax error yields nothing; both files already have
+/// their syntax reported by whatever else reads them.
+fn profiles_in(path: &Path, problems: &mut Vec<String>) -> Vec<Profile> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let Ok(table) = contents.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+    let Some(value) = table.get("profiles") else {
+        return Vec::new();
+    };
+    let file = path.file_name().unwrap_or_default().to_string_lossy();
+    let Some(entries) = value.as_table() else {
+        problems.push(format!("{}: profiles: expected a table of name = [option ids]", file));
+        return Vec::new();
+    };
+
+    let mut profiles = Vec::new();
+    for (name, members) in entries {
+        let option_ids: Option<Vec<String>> = members
+            .as_array()
+            .and_then(|ids| ids.iter().map(|id| id.as_str().map(str::to_string)).collect());
+        match option_ids {
+            Some(option_ids) if !option_ids.is_empty() => profiles.push(Profile {
+                name: name.clone(),
+                option_ids,
+            }),
+            Some(_) => problems.push(format!("{}: profiles.{}: lists no options", file, name)),
+            None => problems.push(format!("{}: profiles.{}: expected a list of option ids", file, name)),
+        }
+    }
+    profiles
+}
@@ -0,0 +1,729 @@
+//! Every action a key can trigger, in one table
+//!
+//! Key dispatch, the footer and the help overlay all read [`ENTRIES`], so what
+//! a key is said to do and what it does can't drift apart. An entry names the
+//! group it's listed under (which also decides the mode it works in), its
+//! labels, and when it's available; the keys themselves come from the
+//! keymap, so user overrides show up everywhere at once.
+
+use crossterm::event::KeyEvent;
+
+use crate::keymap::{Action, Keymap};
+use crate::state::AppState;
+
+/// Where an action works
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// The option list, output shown or not
+    Browsing,
+    /// While a script runs; text keys are typed into the script instead
+    Installing,
+    /// Confirmation and choice popups
+    Popup,
+}
+
+/// Heading an action is listed under in the help overlay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Group {
+    Options,
+    View,
+    Session,
+    Installing,
+    Popups,
+}
+
+impl Group {
+    /// Groups in the order the help overlay shows them
+    pub const ALL: [Group; 5] = [
+        Group::Options,
+        Group::View,
+        Group::Session,
+        Group::Installing,
+        Group::Popups,
+    ];
+
+    pub fn heading(self) -> &'static str {
+        match self {
+            Group::Options => "Options",
+            Group::View => "View",
+            Group::Session => "Session",
+            Group::Installing => "Installing",
+            Group::Popups => "Popups",
+        }
+    }
+
+    pub fn scope(self) -> Scope {
+        match self {
+            Group::Options | Group::View | Group::Session => Scope::Browsing,
+            Group::Installing => Scope::Installing,
+            Group::Popups => Scope::Popup,
+        }
+    }
+}
+
+/// Footer text for an action, which may depend on the state
+pub type FooterLabel = fn(&AppState) -> &'static str;
+
+/// One action as the UI presents it
+#[derive(Debug)]
+pub struct Entry {
+    pub action: Action,
+    pub group: Group,
+    /// Description in the help overlay; entries in a row sharing one are listed together
+    pub label: &'static str,
+    /// Text in the footer, or `None` to leave it out
+    pub footer: Option<FooterLabel>,
+    /// Whether the action would do anything right now; keys do nothing otherwise
+    pub available: fn(&AppState) -> bool,
+}
+
+fn always(_: &AppState) -> bool {
+    true
+}
+
+fn has_selection(state: &AppState) -> bool {
+    state.selected_option().is_some()
+}
+
+fn has_queue(state: &AppState) -> bool {
+    !state.queued.is_empty()
+}
+
+fn expert(state: &AppState) -> bool {
+    state.expert
+}
+
+fn navigate_label(state: &AppState) -> &'static str {
+    if state.output_focused() {
+        "Scroll Output"
+    } else {
+        "Navigate"
+    }
+}
+
+/// All actions, in footer order within each scope
+///
+/// Entries without a footer label come last so the footer reads as before.
+pub const ENTRIES: &[Entry] = &[
+    Entry {
+        action: Action::Quit,
+        group: Group::Session,
+        label: "Quit",
+        footer: Some(|_| "Quit"),
+        available: always,
+    },
+    Entry {
+        action: Action::Up,
+        group: Group::Options,
+        label: "Navigate / scroll focused output",
+        footer: Some(navigate_label),
+        available: always,
+    },
+    Entry {
+        action: Action::Down,
+        group: Group::Options,
+        label: "Navigate / scroll focused output",
+        footer: Some(navigate_label),
+        available: always,
+    },
+    Entry {
+        action: Action::Top,
+        group: Group::Options,
+        label: "First option / start of focused output",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::Bottom,
+        group: Group::Options,
+        label: "Last option / latest output when focused",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::Confirm,
+        group: Group::Options,
+        label: "Install / choose action",
+        footer: Some(|_| "Install/Uninstall"),
+        available: always,
+    },
+    Entry {
+        action: Action::Queue,
+        group: Group::Options,
+        label: "Queue option",
+        footer: Some(|_| "Queue"),
+        available: has_selection,
+    },
+    Entry {
+        action: Action::Mark,
+        group: Group::Options,
+        label: "Mark state only (with --expert)",
+        footer: Some(|_| "Mark"),
+        available: expert,
+    },
+    Entry {
+        action: Action::Filter,
+        group: Group::Options,
+        label: "Filter options",
+        footer: Some(|_| "Filter"),
+        available: always,
+    },
+    Entry {
+        action: Action::Jump,
+        group: Group::Options,
+        label: "Jump to an option by typing part of its name",
+        footer: None,
+        available: |state| !state.visible.is_empty(),
+    },
+    Entry {
+        action: Action::ToggleDetails,
+        group: Group::View,
+        label: "Toggle details",
+        footer: Some(|_| "Details"),
+        available: always,
+    },
+    Entry {
+        action: Action::SwitchLayout,
+        group: Group::View,
+        label: "Switch layout",
+        footer: Some(|_| "Layout"),
+        available: always,
+    },
+    Entry {
+        action: Action::Help,
+        group: Group::Session,
+        label: "Help",
+        footer: Some(|_| "Help"),
+        available: always,
+    },
+    Entry {
+        action: Action::CommandLine,
+        group: Group::Session,
+        label: "Command line (:install <id>, :log <id>, ...)",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::InstallQueued,
+        group: Group::Options,
+        label: "Install queued",
+        footer: Some(|_| "Install Queued"),
+        available: has_queue,
+    },
+    Entry {
+        action: Action::Profiles,
+        group: Group::Options,
+        label: "Install a profile",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::ExportScript,
+        group: Group::Options,
+        label: "Export installed as a script",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::LogHistory,
+        group: Group::Options,
+        label: "Browse past run logs",
+        footer: None,
+        available: has_selection,
+    },
+    Entry {
+        action: Action::CheckUpdates,
+        group: Group::Options,
+        label: "Check installed options for updates",
+        footer: None,
+        available: |state| !state.checking_versions,
+    },
+    Entry {
+        action: Action::ContinueOnFailure,
+        group: Group::Options,
+        label: "Queue continue-on-fail",
+        footer: Some(|state| {
+            if state.queue_continue_on_failure {
+                "Stop on Failure"
+            } else {
+                "Continue on Failure"
+            }
+        }),
+        available: has_queue,
+    },
+    Entry {
+        action: Action::Retry,
+        group: Group::Options,
+        label: "Retry failed action",
+        footer: Some(|_| "Retry"),
+        available: AppState::can_retry,
+    },
+    Entry {
+        action: Action::Reboot,
+        group: Group::Session,
+        label: "Reboot when needed",
+        footer: Some(|_| "Reboot"),
+        available: |state| !state.pending_reboot.is_empty(),
+    },
+    Entry {
+        action: Action::FocusNext,
+        group: Group::View,
+        label: "Focus list / output",
+        footer: Some(|state| {
+            if state.output_focused() {
+                "Focus List"
+            } else {
+                "Focus Output"
+            }
+        }),
+        available: |state| state.show_output,
+    },
+    Entry {
+        action: Action::Cancel,
+        group: Group::View,
+        label: "Hide output",
+        footer: Some(|_| "Hide Output"),
+        available: |state| state.show_output,
+    },
+    Entry {
+        action: Action::Collapse,
+        group: Group::Options,
+        label: "Collapse / expand group",
+        footer: None,
+        available: |state| state.selected_category().is_some(),
+    },
+    Entry {
+        action: Action::Expand,
+        group: Group::Options,
+        label: "Collapse / expand group",
+        footer: None,
+        available: |state| state.selected_category().is_some(),
+    },
+    Entry {
+        action: Action::EditLayout,
+        group: Group::View,
+        label: "Edit layout",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::ToggleOutput,
+        group: Group::View,
+        label: "Toggle output",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::NarrowList,
+        group: Group::View,
+        label: "Move the list / output split",
+        footer: None,
+        available: |state| state.show_output,
+    },
+    Entry {
+        action: Action::WidenList,
+        group: Group::View,
+        label: "Move the list / output split",
+        footer: None,
+        available: |state| state.show_output,
+    },
+    Entry {
+        action: Action::FullOutput,
+        group: Group::View,
+        label: "Output full width / back to the split",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::ScrollUp,
+        group: Group::View,
+        label: "Page through the focused list or output",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::ScrollDown,
+        group: Group::View,
+        label: "Page through the focused list or output",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::Timings,
+        group: Group::View,
+        label: "Last run timings",
+        footer: None,
+        available: has_selection,
+    },
+    Entry {
+        action: Action::Update,
+        group: Group::Session,
+        label: "Update scripts",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::Pending,
+        group: Group::Session,
+        label: "Pending items",
+        footer: None,
+        available: |state| !state.pending.is_empty(),
+    },
+    Entry {
+        action: Action::RetrySave,
+        group: Group::Session,
+        label: "Retry failed state saves",
+        footer: None,
+        available: |state| state.unsaved > 0,
+    },
+    Entry {
+        action: Action::Shell,
+        group: Group::Session,
+        label: "Open a shell",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::EditScript,
+        group: Group::Session,
+        label: "Open the option's script in $EDITOR",
+        footer: None,
+        available: |state| state.selected_option().is_some(),
+    },
+    Entry {
+        action: Action::Suspend,
+        group: Group::Session,
+        label: "Suspend to the shell",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::CheckInstalled,
+        group: Group::Session,
+        label: "Check installed status",
+        footer: None,
+        available: |state| !state.checking_presence,
+    },
+    Entry {
+        action: Action::ScrollUp,
+        group: Group::Installing,
+        label: "Scroll output",
+        footer: Some(|_| "Scroll"),
+        available: always,
+    },
+    Entry {
+        action: Action::ScrollDown,
+        group: Group::Installing,
+        label: "Scroll output",
+        footer: Some(|_| "Scroll"),
+        available: always,
+    },
+    Entry {
+        action: Action::Zen,
+        group: Group::Installing,
+        label: "Zen HUD",
+        footer: Some(|_| "Zen"),
+        available: always,
+    },
+    Entry {
+        action: Action::Shell,
+        group: Group::Installing,
+        label: "Open a shell, the run carries on",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::NarrowList,
+        group: Group::Installing,
+        label: "Move the list / output split",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::WidenList,
+        group: Group::Installing,
+        label: "Move the list / output split",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::CancelRun,
+        group: Group::Installing,
+        label: "Cancel run",
+        footer: Some(|_| "Cancel"),
+        available: always,
+    },
+    Entry {
+        action: Action::HalfPageUp,
+        group: Group::Installing,
+        label: "Scroll half page",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::HalfPageDown,
+        group: Group::Installing,
+        label: "Scroll half page",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::FollowOutput,
+        group: Group::Installing,
+        label: "Jump to the latest output",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::Quit,
+        group: Group::Installing,
+        label: "Quit, asking first",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::Up,
+        group: Group::Popups,
+        label: "Select",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::Down,
+        group: Group::Popups,
+        label: "Select",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::Confirm,
+        group: Group::Popups,
+        label: "Confirm",
+        footer: None,
+        available: always,
+    },
+    Entry {
+        action: Action::Cancel,
+        group: Group::Popups,
+        label: "Cancel",
+        footer: None,
+        available: always,
+    },
+];
+
+/// Entries that work in a scope, in table order
+pub fn entries(scope: Scope) -> impl Iterator<Item = &'static Entry> {
+    ENTRIES.iter().filter(move |entry| entry.group.scope() == scope)
+}
+
+/// The entry a key triggers in a scope
+///
+/// While installing, keys that produce text belong to the script.
+pub fn entry_for(keymap: &Keymap, scope: Scope, key: &KeyEvent) -> Option<&'static Entry> {
+    entries(scope).find(|entry| {
+        keymap
+            .keys(entry.action)
+            .iter()
+            .any(|spec| spec.matches(key) && !(scope == Scope::Installing && spec.is_text()))
+    })
+}
+
+/// Keys of an action as shown in a scope, dropping those typed into a running script
+pub fn key_labels(keymap: &Keymap, scope: Scope, action: Action) -> Vec<String> {
+    keymap
+        .keys(action)
+        .iter()
+        .filter(|spec| !(scope == Scope::Installing && spec.is_text()))
+        .map(|spec| spec.label())
+        .collect()
+}
+
+/// Runs of consecutive entries sharing a label, e.g. up and down as "Navigate"
+pub fn runs<'a>(entries: &[&'a Entry], label: impl Fn(&Entry) -> &'static str) -> Vec<Vec<&'a Entry>> {
+    let mut runs: Vec<Vec<&Entry>> = Vec::new();
+    for &entry in entries {
+        match runs.last_mut() {
+            Some(run) if label(run[0]) == label(entry) => run.push(entry),
+            _ => runs.push(vec![entry]),
+        }
+    }
+    runs
+}
+
+/// Keys bound to two actions of the same scope, as problems to report
+///
+/// The first entry in the table wins such a key, so the other never sees it.
+pub fn conflicts(keymap: &Keymap) -> Vec<String> {
+    let mut problems = Vec::new();
+    for scope in [Scope::Browsing, Scope::Installing, Scope::Popup] {
+        let scoped: Vec<&Entry> = entries(scope).collect();
+        for (i, first) in scoped.iter().enumerate() {
+            for second in &scoped[i + 1..] {
+                if first.action == second.action {
+                    continue;
+                }
+                for spec in keymap.keys(second.action) {
+                    if keymap.keys(first.action).contains(spec) {
+                        problems.push(format!(
+                            "keymap: {} is bound to both {} and {}; {} wins",
+                            spec.label(),
+                            first.action.name(),
+                            second.action.name(),
+                            first.action.name()
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+    use crate::installer::Completion;
+    use crate::options::InstallOption;
+    use crate::pending::PendingItems;
+
+    const SCOPES: [Scope; 3] = [Scope::Browsing, Scope::Installing, Scope::Popup];
+
+    /// Every key event a binding could name, with and without modifiers
+    fn candidate_keys() -> Vec<KeyEvent> {
+        let special = [
+            KeyCode::Enter,
+            KeyCode::Esc,
+            KeyCode::Tab,
+            KeyCode::Backspace,
+            KeyCode::Up,
+            KeyCode::Down,
+            KeyCode::Left,
+            KeyCode::Right,
+            KeyCode::Home,
+            KeyCode::End,
+            KeyCode::PageUp,
+            KeyCode::PageDown,
+        ];
+        let codes = special.into_iter().chain((' '..='~').map(KeyCode::Char));
+        codes
+            .flat_map(|code| {
+                [KeyModifiers::NONE, KeyModifiers::CONTROL, KeyModifiers::ALT]
+                    .map(|modifiers| KeyEvent::new(code, modifiers))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_action_is_listed_and_bound() {
+        let keymap = Keymap::default();
+        for action in Action::ALL {
+            assert!(ENTRIES.iter().any(|entry| entry.action == action), "{} has no entry", action.name());
+            assert!(!keymap.keys(action).is_empty(), "{} has no default key", action.name());
+        }
+    }
+
+    #[test]
+    fn every_bound_key_reaches_its_action() {
+        let keymap = Keymap::default();
+        let keys = candidate_keys();
+        for scope in SCOPES {
+            for entry in entries(scope) {
+                for spec in keymap.keys(entry.action) {
+                    if scope == Scope::Installing && spec.is_text() {
+                        continue;
+                    }
+                    let key = keys.iter().find(|key| spec.matches(key)).expect("some event matches the key");
+                    let reached = entry_for(&keymap, scope, key).map(|entry| entry.action);
+                    assert_eq!(reached, Some(entry.action), "{} in {:?}", spec.label(), scope);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn default_keys_do_not_conflict() {
+        assert_eq!(conflicts(&Keymap::default()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn text_keys_go_to_a_running_script() {
+        let keymap = Keymap::default();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert_eq!(entry_for(&keymap, Scope::Browsing, &key(KeyCode::Char('q'))).map(|e| e.action), Some(Action::Quit));
+        assert!(entry_for(&keymap, Scope::Installing, &key(KeyCode::Char('q'))).is_none());
+        let cancel = KeyEvent::new(KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert_eq!(entry_for(&keymap, Scope::Installing, &cancel).map(|e| e.action), Some(Action::CancelRun));
+        assert_eq!(key_labels(&keymap, Scope::Installing, Action::Quit), Vec::<String>::new());
+    }
+
+    fn available(state: &AppState, action: Action) -> bool {
+        let entry = entries(Scope::Browsing).find(|entry| entry.action == action).expect("a browsing entry");
+        (entry.available)(state)
+    }
+
+    /// Keys that need something to act on do nothing until it's there,
+    /// which is what the handlers behind them check for too
+    #[test]
+    fn availability_follows_what_the_handlers_need() {
+        let option = InstallOption {
+            id: "fish".to_string(),
+            name: "Fish".to_string(),
+            ..InstallOption::default()
+        };
+        let mut state = AppState::new(vec![option], HashSet::new(), None);
+        while state.selected_option().is_none() {
+            state.move_down();
+        }
+        let guarded = [
+            Action::Queue,
+            Action::InstallQueued,
+            Action::ContinueOnFailure,
+            Action::Mark,
+            Action::Retry,
+            Action::Reboot,
+            Action::FocusNext,
+            Action::Cancel,
+            Action::Pending,
+            Action::RetrySave,
+        ];
+
+        let idle = AppState::new(Vec::new(), HashSet::new(), None);
+        for action in guarded {
+            assert!(!available(&idle, action), "{} with nothing to act on", action.name());
+        }
+        for action in [Action::Queue, Action::LogHistory, Action::Timings, Action::EditScript] {
+            assert!(available(&state, action), "{} with a selection", action.name());
+            assert!(!available(&idle, action), "{} without a selection", action.name());
+        }
+
+        // The queue keys need a queue
+        state.toggle_queued("fish");
+        assert!(available(&state, Action::InstallQueued) && available(&state, Action::ContinueOnFailure));
+
+        // Retry needs a failed attempt, not just any attempt
+        state.last_attempt = Some(("fish".to_string(), false));
+        state.last_completion = Some(Completion::Exited(0));
+        assert!(!available(&state, Action::Retry));
+        state.last_completion = Some(Completion::Exited(1));
+        assert!(available(&state, Action::Retry));
+
+        state.expert = true;
+        state.pending_reboot.push("fish".to_string());
+        state.show_output = true;
+        state.pending = PendingItems::collect(None, true, false);
+        state.unsaved = 1;
+        let unlocked = [Action::Mark, Action::Reboot, Action::FocusNext, Action::Cancel, Action::Pending, Action::RetrySave];
+        for action in unlocked {
+            assert!(available(&state, action), "{}", action.name());
+        }
+
+        // Checks already running aren't started twice
+        state.checking_versions = true;
+        state.checking_presence = true;
+        assert!(!available(&state, Action::CheckUpdates) && !available(&state, Action::CheckInstalled));
+    }
+}
@@ -0,0 +1,136 @@
+//! Pre-flight checks of what an option says it needs
+//!
+//! An option can declare commands it relies on (`requires_commands`), free
+//! disk space (`min_free_disk_mb`) and a network connection
+//! (`requires_network`). These are checked before the script is started, so
+//! a missing tool fails in a second with a clear reason instead of halfway
+//! through a build.
+//!
+//! The network check only opens a TCP connection to well-known public
+//! resolvers; it does no DNS lookup and can't see proxies or captive portals.
+
+use std::ffi::CString;
+use std::io;
+use std::mem::MaybeUninit;
+use std::net::{SocketAddr, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::installer::find_in_path;
+use crate::options::InstallOption;
+
+/// Addresses tried by the network check, in order
+const NETWORK_PROBES: [&str; 2] = ["1.1.1.1:443", "8.8.8.8:53"];
+
+/// Time allowed for each network probe
+const PROBE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Filesystem whose free space `min_free_disk_mb` refers to
+const DISK_ROOT: &str = "/";
+
+/// Requirements of `option` that aren't met, one description each
+///
+/// Empty when the install can go ahead. A check that can't be carried out
+/// (free space that can't be read) doesn't count as unmet.
+pub fn unmet_requirements(option: &InstallOption) -> Vec<String> {
+    let mut unmet = Vec::new();
+
+    for command in &option.requires_commands {
+        let found = if command.contains('/') {
+            Path::new(command).is_file()
+        } else {
+            find_in_path(command).is_some()
+        };
+        if !found {
+            unmet.push(format!("command `{}` not found in PATH", command));
+        }
+    }
+
+    if let Some(needed) = option.min_free_disk_mb {
+        if let Ok(free) = free_disk_mb(Path::new(DISK_ROOT)) {
+            if free < needed {
+                unmet.push(format!(
+                    "{} MiB free on {}, {} MiB needed",
+                    free, DISK_ROOT, needed
+                ));
+            }
+        }
+    }
+
+    if option.requires_network && !network_reachable() {
+        unmet.push(format!("no network connection (could not reach {})", NETWORK_PROBES.join(" or ")));
+    }
+
+    unmet
+}
+
+/// Space available to unprivileged users on the filesystem holding `path`
+fn free_disk_mb(path: &Path) -> io::Result<u64> {
+    let c_path = CString::new(path.as_os_str().as_encoded_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stats = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is NUL-terminated and `stats` is valid for writes
+    let result = unsafe { libc::statvfs(c_path.as_ptr(), stats.as_mut_ptr()) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: statvfs succeeded, so it filled in `stats`
+    let stats = unsafe { stats.assume_init() };
+    Ok(stats.f_bavail * stats.f_frsize / (1024 * 1024))
+}
+
+/// Whether any of the probe addresses accepts a connection
+fn network_reachable() -> bool {
+    NETWORK_PROBES
+        .iter()
+        .filter_map(|probe| probe.parse::<SocketAddr>().ok())
+        .any(|addr| TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn needing(requires_commands: &[&str], min_free_disk_mb: Option<u64>) -> InstallOption {
+        InstallOption {
+            id: "demo".to_string(),
+            requires_commands: requires_commands.iter().map(|command| command.to_string()).collect(),
+            min_free_disk_mb,
+            ..InstallOption::default()
+        }
+    }
+
+    #[test]
+    fn nothing_declared_is_always_met() {
+        assert!(unmet_requirements(&InstallOption::default()).is_empty());
+    }
+
+    #[test]
+    fn commands_are_looked_up_in_path_or_by_path() {
+        assert!(unmet_requirements(&needing(&["sh", "/bin/sh"], None)).is_empty());
+
+        let unmet = unmet_requirements(&needing(&["sh", "omarchy-cybex-no-such-tool", "/no/such/tool"], None));
+        assert_eq!(
+            unmet,
+            [
+                "command `omarchy-cybex-no-such-tool` not found in PATH",
+                "command `/no/such/tool` not found in PATH",
+            ]
+        );
+    }
+
+    #[test]
+    fn free_space_is_compared_with_what_is_needed() {
+        assert!(unmet_requirements(&needing(&[], Some(0))).is_empty());
+
+        let unmet = unmet_requirements(&needing(&[], Some(u64::MAX)));
+        assert_eq!(unmet.len(), 1);
+        assert!(unmet[0].ends_with(&format!("MiB free on /, {} MiB needed", u64::MAX)), "{}", unmet[0]);
+    }
+
+    #[test]
+    fn free_space_of_a_missing_path_is_an_error() {
+        assert!(free_disk_mb(Path::new("/")).is_ok());
+        assert!(free_disk_mb(Path::new("/no/such/dir")).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! Stable identifiers for individual install/uninstall runs
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::config::RunAction;
+
+/// Per-process sequence number, making ids unique even within one second
+static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+/// Identifier for one run, e.g. `2024-06-02T10:31:05-claude-install-3fa20`
+///
+/// Made of the start time, option id, action and a short suffix. The suffix
+/// combines a random component (distinguishing concurrent processes) with a
+/// per-process sequence number, so two ids generated in the same second by
+/// the same process never collide.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RunId(String);
+
+impl RunId {
+    /// Generate a new id for a run starting now
+    pub fn new(option_id: &str, action: RunAction) -> Self {
+        Self::at(Local::now(), option_id, action)
+    }
+
+    /// Generate a new id for a run starting at `started`
+    pub fn at(started: DateTime<Local>, option_id: &str, action: RunAction) -> Self {
+        let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        let random = RandomState::new().hash_one(sequence) as u16;
+        let action = match action {
+            RunAction::Install => "install",
+            RunAction::Uninstall => "uninstall",
+        };
+        Self(format!(
+            "{}-{}-{}-{:04x}{:x}",
+            started.format("%Y-%m-%dT%H:%M:%S"),
+            sanitize(option_id),
+            action,
+            random,
+            sequence
+        ))
+    }
+}
+
+impl fmt::Display for RunId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Restrict an option id to characters that are safe in file names
+fn sanitize(option_id: &str) -> String {
+    option_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::thread;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn started() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 6, 2, 10, 31, 5).unwrap()
+    }
+
+    #[test]
+    fn ids_read_time_option_action_then_suffix() {
+        let id = RunId::at(started(), "claude", RunAction::Install).to_string();
+        let (stem, suffix) = id.rsplit_once('-').unwrap();
+        assert_eq!(stem, "2024-06-02T10:31:05-claude-install");
+        // Four hex digits of randomness, then the sequence number in hex
+        assert!(suffix.len() > 4, "{}", suffix);
+        assert!(suffix.chars().all(|c| c.is_ascii_hexdigit()), "{}", suffix);
+
+        let id = RunId::at(started(), "claude", RunAction::Uninstall).to_string();
+        assert!(id.starts_with("2024-06-02T10:31:05-claude-uninstall-"), "{}", id);
+    }
+
+    #[test]
+    fn option_ids_are_made_safe_for_file_names() {
+        let id = RunId::at(started(), "hypr/land conf", RunAction::Install).to_string();
+        assert!(id.starts_with("2024-06-02T10:31:05-hypr_land_conf-install-"), "{}", id);
+        assert_eq!(sanitize("neo-vim_2"), "neo-vim_2");
+    }
+
+    #[test]
+    fn ids_serialize_as_plain_strings() {
+        let id = RunId::at(started(), "fish", RunAction::Install);
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"{}\"", id));
+        assert_eq!(serde_json::from_str::<RunId>(&json).unwrap(), id);
+    }
+
+    #[test]
+    fn ids_generated_in_the_same_second_never_collide() {
+        let ids: Vec<RunId> = (0..10_000).map(|_| RunId::at(started(), "fish", RunAction::Install)).collect();
+        let unique: HashSet<&RunId> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+    }
+
+    #[test]
+    fn ids_from_several_threads_never_collide() {
+        let generate = || (0..1000).map(|_| RunId::at(started(), "fish", RunAction::Install)).collect::<Vec<_>>();
+        let threads: Vec<_> = (0..8).map(|_| thread::spawn(generate)).collect();
+        let mut unique = HashSet::new();
+        for thread in threads {
+            for id in thread.join().unwrap() {
+                assert!(unique.insert(id.clone()), "{} generated twice", id);
+            }
+        }
+        assert_eq!(unique.len(), 8000);
+    }
+}
@@ -0,0 +1,333 @@
+//! Containing option scripts in a sandbox
+//!
+//! Options marked `sandbox = true` in the manifest (or every manifest option,
+//! with OMARCHY_CYBEX_SANDBOX=manifest) run inside bubblewrap, or firejail
+//! when bwrap isn't installed. The whole filesystem is read-only except a
+//! private /tmp and the directories holding the option's declared targets,
+//! and the network is cut off unless the option sets `requires_network`.
+//!
+//! Without either tool the installer says so and runs the script only after
+//! an explicit confirmation.
+
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::installer::find_in_path;
+use crate::options::InstallOption;
+
+/// A sandboxing tool found on the system
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SandboxTool {
+    Bubblewrap(PathBuf),
+    Firejail(PathBuf),
+}
+
+impl SandboxTool {
+    /// Look for bwrap, then firejail, in PATH
+    pub fn find() -> Option<Self> {
+        find_in_path("bwrap")
+            .map(SandboxTool::Bubblewrap)
+            .or_else(|| find_in_path("firejail").map(SandboxTool::Firejail))
+    }
+}
+
+/// Which options get sandboxed, and with what
+#[derive(Debug, Clone, Default)]
+pub struct SandboxPolicy {
+    /// Sandbox every option, because they come from a manifest and that was asked for
+    pub all: bool,
+    /// The tool to use, if one is installed
+    pub tool: Option<SandboxTool>,
+}
+
+impl SandboxPolicy {
+    /// Whether the option should run sandboxed
+    pub fn wants(&self, option: &InstallOption) -> bool {
+        self.all || option.sandbox
+    }
+
+    /// Whether the option should run sandboxed but can't be
+    pub fn unavailable_for(&self, option: &InstallOption) -> bool {
+        self.wants(option) && self.tool.is_none()
+    }
+
+    /// The sandbox to run the option in, if it wants one and a tool exists
+    pub fn sandbox_for(&self, script_dir: &Path, option: &InstallOption) -> Option<Sandbox> {
+        let tool = self.tool.clone().filter(|_| self.wants(option))?;
+        Some(Sandbox {
+            tool,
+            script_dir: script_dir.to_path_buf(),
+            home: dirs::home_dir(),
+            writable: writable_dirs(&option.target_paths()),
+            network: option.requires_network,
+        })
+    }
+}
+
+/// Directories a sandboxed script may write to: the parents of its targets
+///
+/// Targets directly in `/` or with no parent are left out rather than
+/// opening up the whole filesystem.
+fn writable_dirs(targets: &[PathBuf]) -> Vec<PathBuf> {
+    targets
+        .iter()
+        .filter_map(|target| target.parent())
+        .filter(|dir| dir.parent().is_some())
+        .map(Path::to_path_buf)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Permissions for one sandboxed run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sandbox {
+    pub tool: SandboxTool,
+    /// Mounted read-only and used as the working directory
+    pub script_dir: PathBuf,
+    /// Home directory, made read-only explicitly for firejail
+    pub home: Option<PathBuf>,
+    /// Directories bound writable
+    pub writable: Vec<PathBuf>,
+    /// Whether the network stays reachable
+    pub network: bool,
+}
+
+impl Sandbox {
+    /// Create the writable directories, which have to exist to be bound
+    pub fn prepare(&self) -> io::Result<()> {
+        for dir in &self.writable {
+            fs::create_dir_all(dir)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", dir.display(), e)))?;
+        }
+        Ok(())
+    }
+
+    /// Wrap `program args…` in the sandbox tool
+    pub fn wrap(&self, program: &Path, args: &[OsString]) -> (PathBuf, Vec<OsString>) {
+        let mut wrapped: Vec<OsString> = Vec::new();
+        let tool = match &self.tool {
+            SandboxTool::Bubblewrap(tool) => {
+                let bind = |flag: &str, path: &Path| [flag.into(), path.into(), path.into()];
+                wrapped.extend(
+                    ["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"]
+                        .map(OsString::from),
+                );
+                // After the tmpfs, so a script dir under /tmp stays visible
+                wrapped.extend(bind("--ro-bind", &self.script_dir));
+                for writable in &self.writable {
+                    wrapped.extend(bind("--bind", writable));
+                }
+                wrapped.push("--unshare-all".into());
+                if self.network {
+                    wrapped.push("--share-net".into());
+                }
+                wrapped.extend(["--die-with-parent".into(), "--chdir".into(), self.script_dir.as_os_str().into()]);
+                tool
+            }
+            SandboxTool::Firejail(tool) => {
+                let flag = |name: &str, path: &Path| {
+                    let mut arg = OsString::from(format!("--{}=", name));
+                    arg.push(path);
+                    arg
+                };
+                wrapped.push("--quiet".into());
+                wrapped.push("--noprofile".into());
+                wrapped.push("--private-tmp".into());
+                if let Some(home) = &self.home {
+                    wrapped.push(flag("read-only", home));
+                }
+                wrapped.push(flag("read-only", &self.script_dir));
+                for writable in &self.writable {
+                    wrapped.push(flag("read-write", writable));
+                }
+                if !self.network {
+                    wrapped.push("--net=none".into());
+                }
+                tool
+            }
+        };
+        wrapped.push("--".into());
+        wrapped.push(program.as_os_str().to_os_string());
+        wrapped.extend(args.iter().cloned());
+        (tool.clone(), wrapped)
+    }
+
+    /// Command line of the wrapped run, for showing before it starts
+    pub fn preview(&self, program: &Path, args: &[OsString]) -> String {
+        let (tool, args) = self.wrap(program, args);
+        std::iter::once(tool.into_os_string())
+            .chain(args)
+            .map(|arg| {
+                let arg = arg.to_string_lossy().into_owned();
+                if arg.contains(char::is_whitespace) {
+                    format!("'{}'", arg)
+                } else {
+                    arg
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn sandbox(tool: SandboxTool, network: bool) -> Sandbox {
+        Sandbox {
+            tool,
+            script_dir: PathBuf::from("/tmp/scripts"),
+            home: Some(PathBuf::from("/home/agent")),
+            writable: vec![PathBuf::from("/home/agent/.config/fish"), PathBuf::from("/home/agent/.local/bin")],
+            network,
+        }
+    }
+
+    fn strings(args: &[OsString]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn bwrap_binds_the_script_dir_and_targets_and_cuts_the_network() {
+        let sandbox = sandbox(SandboxTool::Bubblewrap(PathBuf::from("/usr/bin/bwrap")), false);
+        let (tool, args) = sandbox.wrap(Path::new("/tmp/scripts/install"), &["fish".into()]);
+        assert_eq!(tool, Path::new("/usr/bin/bwrap"));
+        assert_eq!(
+            strings(&args).join(" "),
+            "--ro-bind / / --dev /dev --proc /proc --tmpfs /tmp \
+             --ro-bind /tmp/scripts /tmp/scripts \
+             --bind /home/agent/.config/fish /home/agent/.config/fish \
+             --bind /home/agent/.local/bin /home/agent/.local/bin \
+             --unshare-all --die-with-parent --chdir /tmp/scripts -- /tmp/scripts/install fish"
+        );
+    }
+
+    #[test]
+    fn bwrap_shares_the_network_when_the_option_needs_it() {
+        let sandbox = sandbox(SandboxTool::Bubblewrap(PathBuf::from("bwrap")), true);
+        let args = strings(&sandbox.wrap(Path::new("install"), &[]).1);
+        let unshare = args.iter().position(|arg| arg == "--unshare-all").unwrap();
+        assert_eq!(args[unshare + 1], "--share-net", "sharing has to come after unsharing");
+    }
+
+    #[test]
+    fn firejail_gets_the_same_permissions_as_flags() {
+        let sandbox = sandbox(SandboxTool::Firejail(PathBuf::from("/usr/bin/firejail")), false);
+        let (tool, args) = sandbox.wrap(Path::new("/tmp/scripts/install"), &["fish".into()]);
+        assert_eq!(tool, Path::new("/usr/bin/firejail"));
+        assert_eq!(
+            strings(&args),
+            [
+                "--quiet",
+                "--noprofile",
+                "--private-tmp",
+                "--read-only=/home/agent",
+                "--read-only=/tmp/scripts",
+                "--read-write=/home/agent/.config/fish",
+                "--read-write=/home/agent/.local/bin",
+                "--net=none",
+                "--",
+                "/tmp/scripts/install",
+                "fish",
+            ]
+        );
+        let networked = Sandbox { network: true, ..sandbox };
+        assert!(!strings(&networked.wrap(Path::new("install"), &[]).1).contains(&"--net=none".to_string()));
+    }
+
+    #[test]
+    fn preview_quotes_arguments_with_spaces() {
+        let sandbox = Sandbox {
+            writable: vec![PathBuf::from("/home/agent/My Files")],
+            ..sandbox(SandboxTool::Firejail(PathBuf::from("firejail")), true)
+        };
+        assert_eq!(
+            sandbox.preview(Path::new("install"), &["fish".into()]),
+            "firejail --quiet --noprofile --private-tmp --read-only=/home/agent --read-only=/tmp/scripts \
+             '--read-write=/home/agent/My Files' -- install fish"
+        );
+    }
+
+    #[test]
+    fn writable_dirs_are_unique_parents_below_the_root() {
+        let targets = [
+            "/etc/hosts",
+            "/home/agent/.config/fish/config.fish",
+            "/home/agent/.config/fish/fish_plugins",
+            "/",
+        ]
+        .map(PathBuf::from);
+        assert_eq!(writable_dirs(&targets), [PathBuf::from("/etc"), PathBuf::from("/home/agent/.config/fish")]);
+        assert!(writable_dirs(&[PathBuf::from("/vmlinuz"), PathBuf::from("relative")]).is_empty());
+    }
+
+    #[test]
+    fn only_wanted_options_get_a_sandbox() {
+        let tool = Some(SandboxTool::Bubblewrap(PathBuf::from("bwrap")));
+        let plain = InstallOption::default();
+        let contained = InstallOption {
+            sandbox: true,
+            requires_network: true,
+            ..InstallOption::default()
+        };
+
+        let policy = SandboxPolicy { all: false, tool: tool.clone() };
+        assert!(policy.sandbox_for(Path::new("/scripts"), &plain).is_none());
+        let sandbox = policy.sandbox_for(Path::new("/scripts"), &contained).unwrap();
+        assert!(sandbox.network);
+        assert_eq!(sandbox.script_dir, Path::new("/scripts"));
+
+        let all = SandboxPolicy { all: true, tool };
+        assert!(all.sandbox_for(Path::new("/scripts"), &plain).is_some());
+
+        let missing = SandboxPolicy { all: true, tool: None };
+        assert!(missing.sandbox_for(Path::new("/scripts"), &plain).is_none());
+        assert!(missing.unavailable_for(&plain));
+        assert!(!SandboxPolicy::default().unavailable_for(&plain));
+    }
+
+    #[test]
+    fn bwrap_confines_writes_to_the_target_dirs() {
+        let Some(bwrap) = find_in_path("bwrap") else {
+            return; // bwrap isn't installed here
+        };
+        // Containers often don't allow the user namespaces bwrap needs
+        let usable = Command::new(&bwrap).args(["--ro-bind", "/", "/", "true"]).status();
+        if !usable.is_ok_and(|status| status.success()) {
+            return;
+        }
+
+        let dir = std::env::temp_dir().join(format!("omarchy-cybex-sandbox-{}", std::process::id()));
+        let scripts = dir.join("scripts");
+        let target = dir.join("target");
+        fs::create_dir_all(&scripts).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        let sandbox = Sandbox {
+            tool: SandboxTool::Bubblewrap(bwrap),
+            script_dir: scripts.clone(),
+            home: None,
+            writable: vec![target.clone()],
+            network: false,
+        };
+        let script = format!(
+            "echo ok > {}/allowed; echo no > {}/denied; echo no > {}/denied; true",
+            target.display(),
+            scripts.display(),
+            dir.display()
+        );
+        let (tool, args) = sandbox.wrap(Path::new("/bin/sh"), &["-c".into(), script.into()]);
+        let status = Command::new(tool).args(args).status().unwrap();
+
+        assert!(status.success());
+        assert_eq!(fs::read_to_string(target.join("allowed")).unwrap(), "ok\n");
+        assert!(!scripts.join("denied").exists(), "the script dir is read-only");
+        assert!(!dir.join("denied").exists(), "everything else is read-only");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
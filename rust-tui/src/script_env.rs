@@ -0,0 +1,82 @@
+//! Environment variables the scripts run with
+//!
+//! Every run gets `OMARCHY_CYBEX_TUI=1`, so a script can tell the installer
+//! is driving it. On top of the inherited environment come the `[env]` table
+//! of config.toml, for every option, then an option's own `env` from the
+//! manifest, which wins:
+//!
+//! ```toml
+//! [env]
+//! NONINTERACTIVE = "1"
+//! FORCE_COLOR = 1
+//! ```
+//!
+//! `--noninteractive` adds `NONINTERACTIVE=1` for one session.
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::config::state_dir;
+use crate::options::InstallOption;
+
+/// Set for every run, whatever the config says
+pub const MARKER: (&str, &str) = ("OMARCHY_CYBEX_TUI", "1");
+
+/// Variable `--noninteractive` sets
+pub const NONINTERACTIVE: &str = "NONINTERACTIVE";
+
+/// The `[env]` table of config.toml, with the problems found in it
+///
+/// Strings, integers and booleans are taken as written; anything else, or a
+/// name no environment could hold, is skipped.
+pub fn configured_env() -> (BTreeMap<String, String>, Vec<String>) {
+    let mut problems = Vec::new();
+    let Ok(contents) = fs::read_to_string(state_dir().join("config.toml")) else {
+        return (BTreeMap::new(), problems);
+    };
+    // Syntax errors are reported by the keymap, which reads the same file
+    let Ok(table) = contents.parse::<toml::Table>() else {
+        return (BTreeMap::new(), problems);
+    };
+    let Some(entries) = table.get("env") else {
+        return (BTreeMap::new(), problems);
+    };
+    let Some(entries) = entries.as_table() else {
+        problems.push("env: expected a table of variables".to_string());
+        return (BTreeMap::new(), problems);
+    };
+    let mut env = BTreeMap::new();
+    for (name, value) in entries {
+        let value = match value {
+            toml::Value::String(value) => value.clone(),
+            toml::Value::Integer(value) => value.to_string(),
+            toml::Value::Boolean(value) => value.to_string(),
+            _ => {
+                problems.push(format!("env.{}: expected a string", name));
+                continue;
+            }
+        };
+        if !valid_name(name) {
+            problems.push(format!("env: {:?} can't be an environment variable", name));
+            continue;
+        }
+        env.insert(name.clone(), value);
+    }
+    (env, problems)
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['=', '\0'])
+}
+
+/// Variables one option's runs get: the global ones, its own, then the marker
+pub fn script_env(global: &BTreeMap<String, String>, option: &InstallOption) -> Vec<(String, String)> {
+    let mut env = global.clone();
+    for (name, value) in &option.env {
+        if valid_name(name) {
+            env.insert(name.clone(), value.clone());
+        }
+    }
+    env.insert(MARKER.0.to_string(), MARKER.1.to_string());
+    env.into_iter().collect()
+}
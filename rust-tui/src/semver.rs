@@ -0,0 +1,169 @@
+//! Semantic versions, for the manifest's `min_tui_version`
+//!
+//! Unlike [`crate::compat::Version`], which reads whatever Omarchy and pacman
+//! report, these follow semver precedence: a pre-release like `0.5.0-rc.1`
+//! sorts before `0.5.0`, and build metadata after `+` is ignored. A missing
+//! minor or patch number counts as zero, so `0.5` means `0.5.0`.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// One dot-separated part of a pre-release tag
+///
+/// Numeric parts sort numerically and before any alphanumeric one, which
+/// the variant order gives the derived comparison.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+/// A `major.minor.patch[-pre]` version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemVer {
+    core: [u64; 3],
+    pre: Vec<Identifier>,
+}
+
+impl SemVer {
+    /// Parse "0.5", "1.2.3", "v1.2.3-rc.1" or "1.2.3+build"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text.strip_prefix('v').unwrap_or(text);
+        let text = text.split_once('+').map_or(text, |(version, _)| version);
+        let (core_text, pre_text) = match text.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (text, None),
+        };
+
+        let mut core = [0; 3];
+        let parts: Vec<&str> = core_text.split('.').collect();
+        if parts.len() > core.len() {
+            return None;
+        }
+        for (slot, part) in core.iter_mut().zip(&parts) {
+            *slot = numeric(part)?;
+        }
+
+        let pre = match pre_text {
+            Some(pre) => pre.split('.').map(identifier).collect::<Option<_>>()?,
+            None => Vec::new(),
+        };
+        Some(Self { core, pre })
+    }
+
+    /// The version of this binary
+    pub fn running() -> Self {
+        Self::parse(env!("CARGO_PKG_VERSION")).expect("the crate version is semver")
+    }
+}
+
+/// A number without sign or leading zeros
+fn numeric(part: &str) -> Option<u64> {
+    let digits = !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    let padded = part.len() > 1 && part.starts_with('0');
+    (digits && !padded).then(|| part.parse().ok()).flatten()
+}
+
+/// A pre-release part: a number, or letters, digits and hyphens
+fn identifier(part: &str) -> Option<Identifier> {
+    if let Some(number) = numeric(part) {
+        return Some(Identifier::Numeric(number));
+    }
+    let valid = !part.is_empty() && part.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-');
+    let padded_number = part.bytes().all(|b| b.is_ascii_digit());
+    (valid && !padded_number).then(|| Identifier::Alphanumeric(part.to_string()))
+}
+
+impl Ord for SemVer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // A release outranks its pre-releases; between pre-releases a longer
+        // tag wins when the shorter one is a prefix of it, as Vec's order does
+        self.core.cmp(&other.core).then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => self.pre.cmp(&other.pre),
+        })
+    }
+}
+
+impl PartialOrd for SemVer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for SemVer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [major, minor, patch] = self.core;
+        write!(f, "{}.{}.{}", major, minor, patch)?;
+        for (i, part) in self.pre.iter().enumerate() {
+            f.write_str(if i == 0 { "-" } else { "." })?;
+            match part {
+                Identifier::Numeric(number) => write!(f, "{}", number)?,
+                Identifier::Alphanumeric(text) => f.write_str(text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(text: &str) -> SemVer {
+        SemVer::parse(text).unwrap_or_else(|| panic!("{:?} should parse", text))
+    }
+
+    #[test]
+    fn missing_components_count_as_zero() {
+        assert_eq!(v("0.5"), v("0.5.0"));
+        assert_eq!(v("1"), v("1.0.0"));
+        assert_eq!(v("v1.2.3").to_string(), "1.2.3");
+    }
+
+    #[test]
+    fn components_compare_numerically() {
+        assert!(v("0.10.0") > v("0.9.9"));
+        assert!(v("1.0.0") > v("0.99"));
+        assert!(v("0.5.1") > v("0.5"));
+    }
+
+    #[test]
+    fn pre_releases_sort_before_their_release() {
+        // The chain from the semver spec, lowest first
+        let chain = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+        ];
+        for pair in chain.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn build_metadata_is_ignored() {
+        assert_eq!(v("1.2.3+abc"), v("1.2.3"));
+        assert_eq!(v("1.2.3-rc.1+abc").to_string(), "1.2.3-rc.1");
+    }
+
+    #[test]
+    fn malformed_versions_are_rejected() {
+        for text in ["", "1.2.3.4", "1..2", "01.2", "1.x", "1.2-", "1.2-rc..1", "1.2-01", "one"] {
+            assert_eq!(SemVer::parse(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn the_running_version_parses() {
+        assert_eq!(SemVer::running().to_string(), env!("CARGO_PKG_VERSION"));
+    }
+}
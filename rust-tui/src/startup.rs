@@ -0,0 +1,386 @@
+//! Pre-flight checks run before the terminal is touched
+//!
+//! Everything that can go wrong at startup is gathered into a
+//! [`StartupReport`]. Fatal problems are printed plainly and the process
+//! exits without ever entering the alternate screen; warnings are carried
+//! into the TUI (or printed to stderr by headless runs).
+
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::compat::{probe_base_version, Version};
+use crate::config::{state_dir_problem, state_file_problem};
+use crate::features::{detect_source, FeatureFlags};
+use crate::manifest::{load_options, manifest_path, LoadError, TooOld};
+use crate::notify::DEFAULT_NOTIFY_AFTER;
+use crate::options::InstallOption;
+use crate::prefs::{configured_priority, Priority};
+use crate::sandbox::{SandboxPolicy, SandboxTool};
+use crate::script_env::configured_env;
+use crate::state::DEFAULT_OUTPUT_LIMIT;
+use crate::support::supported_ids;
+use crate::timeout::TimeoutPolicy;
+use crate::tty::RedirectPolicy;
+
+/// Entry script names probed in the script directory, in order of preference
+const ENTRY_SCRIPT_NAMES: [&str; 2] = ["install", "install.sh"];
+
+/// Outcome of the startup checks
+pub struct StartupReport {
+    /// Script that every action runs
+    pub entry: PathBuf,
+    /// Options to offer, empty if the manifest could not be loaded
+    pub options: Vec<InstallOption>,
+    /// Inactivity period after which the UI dims
+    pub idle_dim_after: Option<Duration>,
+    /// Maximum number of output rows kept in memory
+    pub output_limit: usize,
+    /// What the TUI does when stdout isn't a terminal
+    pub redirect_policy: RedirectPolicy,
+    /// Installed Omarchy version, None when probing found nothing
+    pub base_version: Option<Version>,
+    /// What the script directory allows
+    pub features: FeatureFlags,
+    /// Which options run sandboxed, and the tool for it
+    pub sandbox: SandboxPolicy,
+    /// Run length after which finishing sends a desktop notification
+    pub notify_after: Option<Duration>,
+    /// Time limits for runs
+    pub timeout: TimeoutPolicy,
+    /// Variables config.toml's `[env]` sets for every run
+    pub env: BTreeMap<String, String>,
+    /// Niceness and I/O class config.toml sets for options without their own
+    pub priority: Priority,
+    /// Ids of options the entry script says it doesn't accept
+    pub unsupported: HashSet<String>,
+    /// Set when the manifest needs a newer installer; options are empty then
+    pub too_old: Option<TooOld>,
+    /// Problems that prevent running at all
+    pub fatal: Vec<String>,
+    /// Problems worth mentioning that the installer can work around
+    pub warnings: Vec<String>,
+}
+
+impl StartupReport {
+    /// Whether startup must stop before initializing the terminal
+    pub fn is_fatal(&self) -> bool {
+        !self.fatal.is_empty()
+    }
+}
+
+/// Check the script directory, manifest, state file and environment
+///
+/// `entry` overrides the entry script, relative to `script_dir`; without it
+/// the first of `ENTRY_SCRIPT_NAMES` that exists is used.
+pub fn preflight(script_dir: &Path, entry: Option<&Path>) -> StartupReport {
+    let mut fatal = Vec::new();
+    let mut warnings = Vec::new();
+
+    let entry = match entry {
+        Some(entry) => {
+            let entry = script_dir.join(entry);
+            if !entry.is_file() {
+                fatal.push(format!("entry script {} not found", entry.display()));
+            }
+            entry
+        }
+        None => ENTRY_SCRIPT_NAMES
+            .iter()
+            .map(|name| script_dir.join(name))
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| {
+                fatal.push(format!(
+                    "no {} script found in {}",
+                    ENTRY_SCRIPT_NAMES.join(" or "),
+                    script_dir.display()
+                ));
+                script_dir.join(ENTRY_SCRIPT_NAMES[0])
+            }),
+    };
+
+    let mut too_old = None;
+    let options = match load_options(script_dir) {
+        Ok((options, ignored)) => {
+            warnings.extend(ignored);
+            options
+        }
+        Err(LoadError::Invalid(e)) => {
+            fatal.push(e.to_string());
+            Vec::new()
+        }
+        Err(LoadError::TooOld(e)) => {
+            too_old = Some(e);
+            Vec::new()
+        }
+    };
+
+    if let Some(problem) = state_file_problem() {
+        warnings.push(problem);
+    }
+    if let Some(problem) = state_dir_problem() {
+        warnings.push(problem);
+    }
+
+    // Optional inactivity dimming for kiosk-style provisioning stations
+    let idle_dim_after = match env::var("OMARCHY_CYBEX_IDLE_DIM_MINUTES") {
+        Ok(minutes) => match minutes.trim().parse::<u64>() {
+            Ok(0) => None,
+            Ok(minutes) => Some(Duration::from_secs(minutes * 60)),
+            Err(_) => {
+                warnings.push(format!(
+                    "OMARCHY_CYBEX_IDLE_DIM_MINUTES={:?} is not a number of minutes; dimming disabled",
+                    minutes
+                ));
+                None
+            }
+        },
+        Err(_) => None,
+    };
+
+    let output_limit = match env::var("OMARCHY_CYBEX_OUTPUT_LINES") {
+        Ok(lines) => match lines.trim().parse::<usize>() {
+            Ok(lines) if lines > 0 => lines,
+            _ => {
+                warnings.push(format!(
+                    "OMARCHY_CYBEX_OUTPUT_LINES={:?} is not a positive number; keeping {} lines",
+                    lines, DEFAULT_OUTPUT_LIMIT
+                ));
+                DEFAULT_OUTPUT_LIMIT
+            }
+        },
+        Err(_) => DEFAULT_OUTPUT_LIMIT,
+    };
+
+    let notify_after = match env::var("OMARCHY_CYBEX_NOTIFY_SECONDS") {
+        Ok(seconds) if seconds.trim() == "off" => None,
+        Ok(seconds) => match seconds.trim().parse::<u64>() {
+            Ok(0) => None,
+            Ok(seconds) => Some(Duration::from_secs(seconds)),
+            Err(_) => {
+                warnings.push(format!(
+                    "OMARCHY_CYBEX_NOTIFY_SECONDS={:?} is not a number of seconds or 'off'; notifications disabled",
+                    seconds
+                ));
+                None
+            }
+        },
+        Err(_) => Some(DEFAULT_NOTIFY_AFTER),
+    };
+
+    let timeout = TimeoutPolicy::from_env(&mut warnings);
+    let (script_env, env_problems) = configured_env();
+    warnings.extend(env_problems.into_iter().map(|problem| format!("config.toml: {}", problem)));
+    let (priority, priority_problems) = configured_priority();
+    warnings.extend(priority_problems.into_iter().map(|problem| format!("config.toml: {}", problem)));
+
+    let redirect_policy = match env::var("OMARCHY_CYBEX_REDIRECTED") {
+        Ok(value) => RedirectPolicy::parse(&value).unwrap_or_else(|| {
+            warnings.push(format!(
+                "OMARCHY_CYBEX_REDIRECTED={:?} is not 'tty' or 'refuse'; using 'tty'",
+                value
+            ));
+            RedirectPolicy::default()
+        }),
+        Err(_) => RedirectPolicy::default(),
+    };
+
+    // "manifest" contains every option a manifest defines, not just those asking for it
+    let sandbox_all = match env::var("OMARCHY_CYBEX_SANDBOX") {
+        Ok(value) => match value.trim() {
+            "manifest" => manifest_path(script_dir).is_some(),
+            "option" => false,
+            _ => {
+                warnings.push(format!(
+                    "OMARCHY_CYBEX_SANDBOX={:?} is not 'manifest' or 'option'; using 'option'",
+                    value
+                ));
+                false
+            }
+        },
+        Err(_) => false,
+    };
+    let sandbox = SandboxPolicy {
+        all: sandbox_all,
+        tool: SandboxTool::find(),
+    };
+    if sandbox.tool.is_none() && options.iter().any(|option| sandbox.wants(option)) {
+        warnings.push(
+            "neither bwrap nor firejail is installed; sandboxed options will ask before running unsandboxed"
+                .to_string(),
+        );
+    }
+
+    // Compatibility checks are skipped when this finds nothing
+    let base_version = probe_base_version();
+    let features = FeatureFlags::for_source(detect_source(script_dir));
+
+    // Ids the entry script doesn't accept, if it can list the ones it does
+    let probe = fatal.is_empty()
+        && too_old.is_none()
+        && env::var("OMARCHY_CYBEX_SCRIPT_PROBE").map_or(true, |value| value.trim() != "off");
+    let supported = if probe { supported_ids(script_dir, &entry) } else { None };
+    let unsupported: HashSet<String> = match supported {
+        Some(supported) => options
+            .iter()
+            .filter(|option| !supported.contains(&option.id))
+            .map(|option| option.id.clone())
+            .collect(),
+        None => HashSet::new(),
+    };
+    if !unsupported.is_empty() {
+        let mut ids: Vec<&str> = unsupported.iter().map(String::as_str).collect();
+        ids.sort_unstable();
+        warnings.push(format!(
+            "{} doesn't accept these option ids, so they can't be run: {}",
+            entry.file_name().map_or_else(|| entry.display().to_string(), |name| name.to_string_lossy().into_owned()),
+            ids.join(", ")
+        ));
+    }
+
+    StartupReport {
+        entry,
+        options,
+        idle_dim_after,
+        output_limit,
+        redirect_policy,
+        base_version,
+        features,
+        sandbox,
+        notify_after,
+        timeout,
+        env: script_env,
+        priority,
+        unsupported,
+        too_old,
+        fatal,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Entry script listing the ids it accepts
+    const LISTING_SCRIPT: &str = "#!/bin/sh\n[ \"$1\" = --list ] && echo demo\n";
+
+    const MANIFEST: &str = "[[option]]\nid = \"demo\"\n\n[[option]]\nid = \"ghost\"\nicon = \"rocket\"\n";
+
+    /// Script directory holding `files`; executable when named like an entry script
+    fn script_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("omarchy-cybex-preflight-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            let path = dir.join(file);
+            fs::write(&path, contents).unwrap();
+            if ENTRY_SCRIPT_NAMES.contains(file) {
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            }
+        }
+        dir
+    }
+
+    /// Warnings about the script directory, leaving out ones the environment causes
+    fn script_warnings(report: &StartupReport, dir: &Path) -> Vec<String> {
+        let dir = dir.display().to_string();
+        report
+            .warnings
+            .iter()
+            .filter(|warning| warning.contains(&dir) || warning.contains("doesn't accept"))
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn clean_script_directory_has_no_problems() {
+        let dir = script_dir("clean", &[("install", LISTING_SCRIPT), ("options.toml", "[[option]]\nid = \"demo\"\n")]);
+        let report = preflight(&dir, None);
+        assert!(!report.is_fatal(), "{:?}", report.fatal);
+        assert_eq!(report.entry, dir.join("install"));
+        assert_eq!(report.options.len(), 1);
+        assert!(report.unsupported.is_empty());
+        assert!(script_warnings(&report, &dir).is_empty(), "{:?}", report.warnings);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn install_sh_is_used_when_install_is_missing() {
+        let dir = script_dir("install-sh", &[("install.sh", LISTING_SCRIPT), ("options.toml", MANIFEST)]);
+        let report = preflight(&dir, None);
+        assert!(!report.is_fatal(), "{:?}", report.fatal);
+        assert_eq!(report.entry, dir.join("install.sh"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_entry_script_is_fatal_and_skips_the_probe() {
+        let dir = script_dir("no-entry", &[("options.toml", MANIFEST)]);
+        let report = preflight(&dir, None);
+        assert_eq!(
+            report.fatal,
+            [format!("no install or install.sh script found in {}", dir.display())]
+        );
+        assert!(report.unsupported.is_empty(), "nothing to ask without a script");
+        // The manifest is still read, so its warnings come along with the fatal problem
+        assert_eq!(script_warnings(&report, &dir).len(), 1, "{:?}", report.warnings);
+
+        let named = preflight(&dir, Some(Path::new("bin/setup")));
+        assert_eq!(named.fatal, [format!("entry script {} not found", dir.join("bin/setup").display())]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_manifest_is_fatal_and_leaves_no_options() {
+        let dir = script_dir("invalid", &[("install", LISTING_SCRIPT), ("options.toml", "[[option]]\nid = 7\n")]);
+        let report = preflight(&dir, None);
+        assert_eq!(report.fatal.len(), 1, "{:?}", report.fatal);
+        assert!(report.fatal[0].contains("options.toml"), "{}", report.fatal[0]);
+        assert!(report.options.is_empty());
+        assert!(report.unsupported.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn every_fatal_problem_is_reported_together() {
+        let dir = script_dir("fatal-both", &[("options.toml", "[[option]]\nid = 7\n")]);
+        let report = preflight(&dir, None);
+        assert_eq!(report.fatal.len(), 2, "{:?}", report.fatal);
+        assert!(report.fatal[0].starts_with("no install or install.sh script"));
+        assert!(report.fatal[1].contains("options.toml"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn too_old_installer_is_not_fatal_but_offers_nothing() {
+        let manifest = format!("min_tui_version = \"999.0\"\n{}", MANIFEST);
+        let dir = script_dir("too-old", &[("install", LISTING_SCRIPT), ("options.toml", &manifest)]);
+        let report = preflight(&dir, None);
+        assert!(!report.is_fatal(), "{:?}", report.fatal);
+        assert!(report.too_old.is_some());
+        assert!(report.options.is_empty());
+        assert!(report.unsupported.is_empty(), "the probe is skipped");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn ignored_keys_and_unsupported_ids_are_warnings() {
+        let dir = script_dir("warnings", &[("install", LISTING_SCRIPT), ("options.toml", MANIFEST)]);
+        let report = preflight(&dir, None);
+        assert!(!report.is_fatal(), "{:?}", report.fatal);
+        assert_eq!(report.options.len(), 2);
+        assert_eq!(report.unsupported, HashSet::from(["ghost".to_string()]));
+        assert_eq!(
+            script_warnings(&report, &dir),
+            [
+                format!("{}: option 'ghost': ignoring unknown key 'icon'", dir.join("options.toml").display()),
+                "install doesn't accept these option ids, so they can't be run: ghost".to_string(),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,34 @@
 //! Application state management
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use ratatui::layout::Rect;
+
+use crate::compat::{mismatch, Version};
+use crate::config::{OptionRecord, RunAction};
+use crate::editor_locks::EditorLock;
+use crate::features::FeatureFlags;
+use crate::installer::Completion;
+use crate::journal::BatchJournal;
+use crate::keymap::Keymap;
+use crate::logs::LogEntry;
+use crate::manifest::TooOld;
+use crate::options::InstallOption;
+use crate::panels::{LayoutPrefs, Panel, MAX_SPLIT, MIN_SPLIT, SPLIT_STEP};
+use crate::pending::PendingItems;
+use crate::prefs::Priority;
+use crate::profiles::Profile;
+use crate::run_id::RunId;
+use crate::sandbox::SandboxPolicy;
+use crate::timeout::{RunWatch, TimeoutPolicy};
+use crate::update::UpdatePreview;
+use crate::versions::OptionVersions;
+use crate::view::ViewWindow;
+
+/// Output rows kept when OMARCHY_CYBEX_OUTPUT_LINES is unset
+pub const DEFAULT_OUTPUT_LIMIT: usize = 5000;
 
 /// Application mode
 #[derive(Debug, Clone, PartialEq)]
@@ -9,69 +37,667 @@ pub enum AppMode {
     Normal,
     /// Showing action popup for installed item
     ConfirmAction,
+    /// Second confirmation before uninstalling
+    ConfirmUninstall,
+    /// Confirming an install that pulls in dependencies
+    ConfirmDependencies,
+    /// Confirming a state edit that doesn't run the install script
+    ConfirmMark,
+    /// Showing the keybinding help overlay
+    Help,
+    /// Showing where the selected option's last run spent its time
+    Timings,
+    /// Showing problems found during startup
+    StartupWarnings,
+    /// Refusing to start: the manifest needs a newer installer
+    TooOld,
+    /// Deciding what to do about things earlier sessions left pending
+    PendingItems,
+    /// Choosing a profile to install
+    Profiles,
+    /// Listing the selected option's past runs
+    LogBrowser,
+    /// Typing an option list filter
+    Filter,
+    /// Typing part of a name to jump to the option
+    Jump,
+    /// Typing a `:` command
+    Command,
+    /// Warning that target files are open in an editor
+    ConfirmEditorLocks,
+    /// Confirming a reboot for options that need one
+    ConfirmReboot,
+    /// Showing what updating the scripts would change
+    ConfirmUpdate,
+    /// Offering to reinstall options whose scripts were updated
+    ConfirmReinstall,
+    /// Warning that options don't fit the installed Omarchy version
+    ConfirmIncompatible,
+    /// Warning that a reinstall would overwrite hand-edited files
+    ConfirmLocalChanges,
+    /// Confirming a run that should be sandboxed but can't be
+    ConfirmUnsandboxed,
+    /// Asking whether to abort the running install and quit
+    ConfirmQuit,
+    /// Asking whether to kill a run that reached its time limit
+    ConfirmTimeout,
+    /// Explaining that the entry script doesn't accept the selected option
+    Unsupported,
+    /// Collecting the password sudo asked the running script for
+    PasswordPrompt,
+    /// Rearranging the content panels
+    Layout,
+    /// Listing how each item of a finished queue went
+    QueueSummary,
     /// Installing/uninstalling - running subprocess
     Installing,
-    /// Completed - showing results
-    Completed,
+}
+
+impl AppMode {
+    /// Whether the mode is a confirmation or choice popup
+    pub fn is_popup(&self) -> bool {
+        matches!(
+            self,
+            AppMode::ConfirmAction
+                | AppMode::ConfirmUninstall
+                | AppMode::ConfirmDependencies
+                | AppMode::ConfirmMark
+                | AppMode::PendingItems
+                | AppMode::Profiles
+                | AppMode::LogBrowser
+                | AppMode::QueueSummary
+                | AppMode::ConfirmEditorLocks
+                | AppMode::ConfirmReboot
+                | AppMode::ConfirmUpdate
+                | AppMode::ConfirmReinstall
+                | AppMode::ConfirmIncompatible
+                | AppMode::ConfirmLocalChanges
+                | AppMode::ConfirmUnsandboxed
+                | AppMode::ConfirmQuit
+                | AppMode::ConfirmTimeout
+                | AppMode::Unsupported
+        )
+    }
 }
 
 /// Action choice in the confirmation popup
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ActionChoice {
     Reinstall,
+    /// Install without running the option's verify command
+    InstallSkipVerify,
     Uninstall,
 }
 
+/// Choice in the local modifications popup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalChangesChoice {
+    /// Copy the modified files aside, then run
+    BackUp,
+    /// Run and overwrite them
+    Proceed,
+    Cancel,
+}
+
+/// Hand-edited files of an option about to be reinstalled
+#[derive(Debug, Clone)]
+pub struct LocalChanges {
+    pub option_name: String,
+    /// Number of files the last install left hashes for
+    pub managed: usize,
+    /// Those that have changed since
+    pub modified: Vec<PathBuf>,
+}
+
+/// A run held back until the user confirms a warning about it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingRun {
+    /// Install or uninstall the selected option
+    Action { uninstall: bool },
+    /// Start the already-built queue
+    Queue,
+}
+
+/// Work held back until the user accepts a version mismatch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompatPending {
+    /// Carry on with the selected option's action
+    Action,
+    /// Run the queued options in this order
+    Queue(Vec<String>),
+}
+
+/// How long a toast stays on screen
+pub const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// Toasts shown at once; a new one pushes out the oldest
+const MAX_TOASTS: usize = 3;
+
+/// What a toast reports, which decides its colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Success,
+    /// Something stopped short without failing, such as a cancelled run
+    Warning,
+    Error,
+}
+
+/// How one queue item ended, for the summary shown after the queue
+#[derive(Debug, Clone)]
+pub struct QueueResult {
+    pub option_id: String,
+    pub uninstall: bool,
+    /// -1 when the script never ran or couldn't be waited for
+    pub exit_code: i32,
+    pub verify_failed: bool,
+    /// None when nothing was run
+    pub duration: Option<Duration>,
+    pub log: Option<PathBuf>,
+}
+
+impl QueueResult {
+    /// An item that was refused before its script started
+    pub fn not_run(option_id: String, uninstall: bool) -> Self {
+        Self {
+            option_id,
+            uninstall,
+            exit_code: -1,
+            verify_failed: false,
+            duration: None,
+            log: None,
+        }
+    }
+
+    pub fn failed(&self) -> bool {
+        self.exit_code != 0 || self.verify_failed
+    }
+}
+
+/// A timed message floating above the status bar
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub message: String,
+    pub kind: ToastKind,
+    pub expires: Instant,
+}
+
+/// A stored output line, collapsing identical consecutive repeats
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputLine {
+    /// Line text as received
+    pub text: String,
+    /// Number of consecutive occurrences (1 for a unique line)
+    pub repeat: usize,
+    /// Where the line came from
+    pub kind: LineKind,
+}
+
+/// Source of an output line, which decides its colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    /// The script's stdout
+    Output,
+    /// The script's stderr
+    Stderr,
+    /// A problem found by the installer itself, such as a failed pre-flight check
+    Problem,
+    /// The command line at the top of a run and its result at the bottom
+    Frame,
+}
+
+/// A row of the option list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListRow {
+    /// Heading of a category, which collapses and expands the options under it
+    Category(String),
+    /// An option, by index into `options`
+    Option(usize),
+}
+
+/// Heading used for options that don't name a category
+const UNCATEGORIZED: &str = "Other";
+
 /// Main application state
 #[derive(Debug)]
 pub struct AppState {
+    /// Available installation options
+    pub options: Vec<InstallOption>,
+    /// Script directory containing the install script
+    pub script_dir: PathBuf,
+    /// Entry script every action runs
+    pub entry_script: PathBuf,
     /// Current UI mode
     pub mode: AppMode,
-    /// Currently selected option index
+    /// Currently selected row in `rows`
     pub selected_index: usize,
+    /// Substring filter applied to option names and descriptions
+    pub filter: String,
+    /// Indices into `options` of the options matching the filter, grouped by category
+    pub visible: Vec<usize>,
+    /// Letters typed since the jump key
+    pub jump_query: String,
+    /// When the jump query last changed; it ends a second later
+    pub jump_typed: Instant,
+    /// Rows of the option list: category headings and the options under them
+    pub rows: Vec<ListRow>,
+    /// Categories whose options are hidden under their heading
+    pub collapsed: HashSet<String>,
     /// Set of installed option IDs
     pub installed: HashSet<String>,
+    /// Persisted per-option run history
+    pub records: HashMap<String, OptionRecord>,
     /// Scroll offset for output panel
     pub output_scroll: usize,
-    /// Output lines from installation
-    pub output_lines: Vec<String>,
+    /// Output lines from installation, oldest dropped beyond `output_limit`
+    pub output_lines: VecDeque<OutputLine>,
+    /// Maximum number of output rows kept
+    pub output_limit: usize,
+    /// Output lines dropped from the front to honour `output_limit`
+    pub output_omitted: usize,
+    /// Inner height of the output panel as last rendered
+    pub output_height: usize,
+    /// Unterminated output after the last line, e.g. a prompt
+    pub partial_output: Option<String>,
+    /// How long the last run took, kept until the output is cleared
+    pub run_duration: Option<Duration>,
+    /// Last non-blank line the script wrote to stderr, for the failure message
+    pub last_stderr: Option<String>,
+    /// Whether the subprocess terminal is echoing typed input
+    pub input_echo: bool,
+    /// Command being typed after `:`
+    pub command_input: String,
+    /// Commands entered this session, oldest first
+    pub command_history: Vec<String>,
+    /// Position in `command_history` while stepping through it
+    pub command_history_pos: Option<usize>,
+    /// The sudo prompt being answered, until the script moves past it
+    pub password_prompt: Option<String>,
+    /// Password typed into the popup so far, zeroed once sent or dropped
+    pub password_input: Vec<u8>,
+    /// sudo password prompts seen during the current run
+    pub password_attempts: u8,
+    /// Inner width of the output panel at the last render
+    pub output_width: usize,
+    /// Whether the output view follows new lines (disabled by manual scrolling)
+    pub follow_output: bool,
+    /// Lines that arrived while the view wasn't following
+    pub unseen_output: usize,
     /// Current action description (e.g., "Installing Claude Code")
     pub current_action: Option<String>,
-    /// Last subprocess exit code
-    pub last_exit_code: Option<i32>,
+    /// How the last subprocess ended; None while none has, or when cancelled
+    pub last_completion: Option<Completion>,
+    /// Option id and whether it was an uninstall, for the run `r` retries
+    pub last_attempt: Option<(String, bool)>,
     /// Flag to quit the application
     pub should_quit: bool,
+    /// Quit as soon as the aborted run has finished
+    pub quit_after_run: bool,
     /// Status message for the status bar
     pub status_message: String,
+    /// Transient results shown above the status bar, oldest first
+    pub toasts: VecDeque<Toast>,
     /// Whether to show output panel
     pub show_output: bool,
+    /// Whether the output panel opens at startup, as last toggled with `o`;
+    /// runs opening the panel don't change it
+    pub output_on_start: bool,
+    /// Flavor remembered from the last `--theme`, saved with the preferences
+    pub theme_choice: Option<String>,
+    /// Panel the arrow keys act on while the output is shown
+    pub focus: Panel,
+    /// Detail pane choice made with `d`; None opens it on wide terminals
+    pub show_details: Option<bool>,
+    /// Whether the detail pane was drawn in the last frame
+    pub details_shown: bool,
+    /// Whether the last frame used the compact layout, where the output
+    /// panel replaces the list instead of sitting beside it
+    pub compact: bool,
+    /// Named panel layouts and the one in use
+    pub layouts: LayoutPrefs,
+    /// List's share of the list and output width in percent, once moved off
+    /// the layout's weights with `[` and `]`
+    pub split: Option<u16>,
+    /// Output shown full width until toggled back
+    pub output_full: bool,
+    /// Layouts as they were before layout mode, restored by Esc
+    pub layout_backup: Option<LayoutPrefs>,
+    /// Index of the panel being moved or resized in layout mode
+    pub layout_focus: usize,
     /// Selected action in confirmation popup
     pub popup_choice: ActionChoice,
     /// Whether current action is an uninstall (used for completion handling)
     pub is_uninstalling: bool,
+    /// Index of the option the running (or last) action applies to
+    pub active_option: Option<usize>,
+    /// Id of the option whose script is running right now
+    pub running_option: Option<String>,
+    /// Frame counter shared by every spinner, advanced by the event loop
+    pub spinner_tick: usize,
+    /// Identifier of the running (or last) action
+    pub current_run_id: Option<RunId>,
+    /// Option IDs selected with Space for a queued install
+    pub queued: HashSet<String>,
+    /// Option IDs of the running queue, in install order
+    pub queue: Vec<String>,
+    /// Profile the running queue installs, with the member ids it skipped as unknown
+    pub queue_profile: Option<(String, Vec<String>)>,
+    /// Profiles from the manifest and config.toml
+    pub profiles: Vec<Profile>,
+    /// Focused row of the profile popup
+    pub profile_focus: usize,
+    /// Name of the option whose past runs the log browser lists
+    pub log_history_option: String,
+    /// Past runs listed in the log browser, newest first
+    pub log_history: Vec<LogEntry>,
+    /// Focused row of the log browser
+    pub log_focus: usize,
+    /// Position of the current item within `queue`
+    pub queue_position: usize,
+    /// Outcomes of the queue items that have ended
+    pub queue_results: Vec<QueueResult>,
+    /// Focused row of the queue summary
+    pub summary_focus: usize,
+    /// Show the summary after single runs too, not only after queues
+    pub always_show_summary: bool,
+    /// Whether installed state may be edited by hand (--expert)
+    pub expert: bool,
+    /// Whether the queue keeps going after an item fails
+    pub queue_continue_on_failure: bool,
+    /// Skip the verify command for the runs started by the current action
+    pub skip_verify: bool,
+    /// Why the current run's verification failed, if it did
+    pub verify_failure: Option<String>,
+    /// Post-install hooks of the current run that failed, with the reasons
+    pub hook_failures: Vec<String>,
+    /// Dependencies of the selected option that will be installed first
+    pub dependency_plan: Vec<String>,
+    /// Whether the running queue is a dependency chain, which stops at the first failure
+    pub dependency_chain: bool,
+    /// Option IDs installed since the last reboot that need one
+    pub pending_reboot: Vec<String>,
+    /// Installed ids in the state file that no option has
+    pub unknown_ids: Vec<String>,
+    /// Whether state-file writes are still in flight
+    pub saving: bool,
+    /// State-file updates whose write failed, which `S` sends again
+    pub unsaved: usize,
+    /// Whether `install check` is running over the options
+    pub checking_presence: bool,
+    /// Options the last check couldn't place either way
+    pub presence_unknown: HashSet<String>,
+    /// Options the entry script says it doesn't accept
+    pub unsupported: HashSet<String>,
+    /// Versions the script reported for installed options
+    pub versions: HashMap<String, OptionVersions>,
+    /// Whether `install version` is running over the installed options
+    pub checking_versions: bool,
+    /// Key bindings for the configurable actions
+    pub keymap: Keymap,
+    /// Where the option list was last drawn, for mouse hit-testing
+    pub list_area: Rect,
+    /// Index into `rows` of the first row drawn in the option list
+    pub list_offset: usize,
+    /// Where the output panel was last drawn
+    pub output_area: Rect,
+    /// Rows of the action popup's choices, when it is open
+    pub popup_choices_area: Rect,
+    /// Fetched scripts update awaiting confirmation (or just pulled)
+    pub update_preview: Option<UpdatePreview>,
+    /// Updated installed options offered for reinstall
+    pub reinstall_suggestion: Vec<String>,
+    /// Installed Omarchy version, None when it couldn't be determined
+    pub base_version: Option<Version>,
+    /// Started as root with --allow-root; the header says so throughout
+    pub as_root: bool,
+    /// What the script directory allows
+    pub features: FeatureFlags,
+    /// Version mismatches shown in the compatibility popup
+    pub compat_warnings: Vec<String>,
+    /// What continues once the mismatches are accepted
+    pub compat_pending: Option<CompatPending>,
+    /// Option IDs targeted by a pending manual state edit
+    pub mark_targets: Vec<String>,
+    /// Whether the pending manual state edit marks options as installed
+    pub mark_as_installed: bool,
+    /// When the TUI session started
+    pub started_at: Instant,
+    /// Time of the last user input
+    pub last_input: Instant,
+    /// Inactivity period after which the UI dims (None disables dimming)
+    pub idle_dim_after: Option<Duration>,
+    /// Whether the UI is currently dimmed due to inactivity
+    pub dimmed: bool,
+    /// Interrupted batch found at startup
+    pub resume_journal: Option<BatchJournal>,
+    /// Decisions left over from earlier sessions
+    pub pending: PendingItems,
+    /// Non-fatal problems found during startup
+    pub startup_warnings: Vec<String>,
+    /// Why the manifest can't be used by this installer, shown in `AppMode::TooOld`
+    pub too_old: Option<TooOld>,
+    /// Editor artifacts found next to the pending run's target files
+    pub editor_locks: Vec<EditorLock>,
+    /// Run waiting on the editor lock or local modifications confirmation
+    pub pending_run: Option<PendingRun>,
+    /// Hand-edited managed files found before a reinstall
+    pub local_changes: Vec<LocalChanges>,
+    /// Selected choice in the local modifications popup
+    pub local_changes_choice: LocalChangesChoice,
+    /// Which options run sandboxed, and the tool for it
+    pub sandbox: SandboxPolicy,
+    /// Names of options about to run without the sandbox they asked for
+    pub unsandboxed: Vec<String>,
+    /// Start installs without checking their declared requirements
+    pub skip_preflight: bool,
+    /// Variables for every run, from config.toml's `[env]` and --noninteractive
+    pub script_env: BTreeMap<String, String>,
+    /// Niceness and I/O class from config.toml, for options without their own
+    pub priority: Priority,
+    /// Whether the layout is collapsed to the compact zen HUD
+    pub zen: bool,
+    /// When the current run started
+    pub run_started_at: Option<Instant>,
+    /// Run length after which finishing sends a desktop notification
+    pub notify_after: Option<Duration>,
+    /// Time limits for runs
+    pub timeout_policy: TimeoutPolicy,
+    /// The current run's progress towards its time limit, if it has one
+    pub run_watch: Option<RunWatch>,
+    /// The current run reached its limit while another popup was open, so
+    /// asking whether to kill it waits for that popup to close
+    pub timeout_prompt_pending: bool,
+    /// Whether the terminal has focus, if it reports focus changes at all
+    pub focused: Option<bool>,
+    /// Output lines of the current run mentioning an error
+    pub error_count: usize,
+    /// Output lines of the current run mentioning a warning
+    pub warning_count: usize,
 }
 
 impl AppState {
-    /// Create a new AppState with the given installed options
-    pub fn new(installed: HashSet<String>) -> Self {
-        Self {
+    /// Create a new AppState with the given options and installed option IDs
+    ///
+    /// `last_selected` is the id of the option to select first; an id that is
+    /// no longer in the list leaves the first row selected.
+    pub fn new(options: Vec<InstallOption>, mut installed: HashSet<String>, last_selected: Option<&str>) -> Self {
+        // Recorded before the option stopped tracking state; it has none now
+        installed.retain(|id| options.iter().all(|o| &o.id != id || o.tracks_state()));
+        let now = Instant::now();
+        let mut state = Self {
+            options,
+            script_dir: PathBuf::new(),
+            entry_script: PathBuf::new(),
             mode: AppMode::Normal,
             selected_index: 0,
+            filter: String::new(),
+            visible: Vec::new(),
+            jump_query: String::new(),
+            jump_typed: now,
+            rows: Vec::new(),
+            collapsed: HashSet::new(),
             installed,
+            records: HashMap::new(),
             output_scroll: 0,
-            output_lines: Vec::new(),
+            output_lines: VecDeque::new(),
+            output_limit: DEFAULT_OUTPUT_LIMIT,
+            output_omitted: 0,
+            output_height: 0,
+            output_width: 0,
+            partial_output: None,
+            last_stderr: None,
+            run_duration: None,
+            input_echo: true,
+            command_input: String::new(),
+            command_history: Vec::new(),
+            command_history_pos: None,
+            password_prompt: None,
+            password_input: Vec::new(),
+            password_attempts: 0,
+            follow_output: true,
+            unseen_output: 0,
             current_action: None,
-            last_exit_code: None,
+            last_completion: None,
+            last_attempt: None,
             should_quit: false,
+            quit_after_run: false,
             status_message: "Ready - Press Enter to install/uninstall".into(),
+            toasts: VecDeque::new(),
             show_output: false,
+            output_on_start: false,
+            theme_choice: None,
+            focus: Panel::List,
+            show_details: None,
+            details_shown: false,
+            compact: false,
+            layouts: LayoutPrefs::default(),
+            split: None,
+            output_full: false,
+            layout_backup: None,
+            layout_focus: 0,
             popup_choice: ActionChoice::Reinstall,
             is_uninstalling: false,
+            active_option: None,
+            running_option: None,
+            spinner_tick: 0,
+            current_run_id: None,
+            queued: HashSet::new(),
+            queue: Vec::new(),
+            queue_profile: None,
+            profiles: Vec::new(),
+            profile_focus: 0,
+            log_history_option: String::new(),
+            log_history: Vec::new(),
+            log_focus: 0,
+            queue_position: 0,
+            queue_results: Vec::new(),
+            summary_focus: 0,
+            always_show_summary: false,
+            expert: false,
+            queue_continue_on_failure: false,
+            skip_verify: false,
+            verify_failure: None,
+            hook_failures: Vec::new(),
+            dependency_plan: Vec::new(),
+            dependency_chain: false,
+            pending_reboot: Vec::new(),
+            unknown_ids: Vec::new(),
+            saving: false,
+            unsaved: 0,
+            checking_presence: false,
+            presence_unknown: HashSet::new(),
+            unsupported: HashSet::new(),
+            versions: HashMap::new(),
+            checking_versions: false,
+            keymap: Keymap::default(),
+            list_area: Rect::default(),
+            list_offset: 0,
+            output_area: Rect::default(),
+            popup_choices_area: Rect::default(),
+            update_preview: None,
+            reinstall_suggestion: Vec::new(),
+            base_version: None,
+            as_root: false,
+            features: FeatureFlags::default(),
+            compat_warnings: Vec::new(),
+            compat_pending: None,
+            mark_targets: Vec::new(),
+            mark_as_installed: true,
+            started_at: now,
+            last_input: now,
+            idle_dim_after: None,
+            dimmed: false,
+            resume_journal: None,
+            pending: PendingItems::default(),
+            startup_warnings: Vec::new(),
+            too_old: None,
+            editor_locks: Vec::new(),
+            pending_run: None,
+            local_changes: Vec::new(),
+            local_changes_choice: LocalChangesChoice::BackUp,
+            sandbox: SandboxPolicy::default(),
+            unsandboxed: Vec::new(),
+            skip_preflight: false,
+            script_env: BTreeMap::new(),
+            priority: Priority::default(),
+            zen: false,
+            run_started_at: None,
+            notify_after: None,
+            timeout_policy: TimeoutPolicy::default(),
+            run_watch: None,
+            timeout_prompt_pending: false,
+            focused: None,
+            error_count: 0,
+            warning_count: 0,
+        };
+        state.set_filter(String::new());
+        if let Some(index) = last_selected.and_then(|id| state.options.iter().position(|o| o.id == id)) {
+            state.select_option(index);
+        }
+        state
+    }
+
+    /// How long the TUI has been running
+    pub fn session_duration(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.started_at)
+    }
+
+    /// Dim the UI once the inactivity threshold has passed
+    pub fn update_idle(&mut self, now: Instant) {
+        if let Some(after) = self.idle_dim_after {
+            if !self.dimmed && now.saturating_duration_since(self.last_input) >= after {
+                self.dimmed = true;
+            }
         }
     }
 
+    /// Show a transient message for TOAST_DURATION
+    pub fn toast(&mut self, kind: ToastKind, message: impl Into<String>) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+        self.toasts.push_back(Toast {
+            message: message.into(),
+            kind,
+            expires: Instant::now() + TOAST_DURATION,
+        });
+    }
+
+    /// Drop the toasts whose time is up, returning true if any were
+    pub fn expire_toasts(&mut self, now: Instant) -> bool {
+        let before = self.toasts.len();
+        self.toasts.retain(|toast| toast.expires > now);
+        self.toasts.len() != before
+    }
+
+    /// Record user input, returning true if it woke the UI from the dimmed state
+    pub fn register_input(&mut self, now: Instant) -> bool {
+        self.last_input = now;
+        std::mem::replace(&mut self.dimmed, false)
+    }
+
     /// Move selection up (with wrap-around)
-    pub fn move_up(&mut self, total_options: usize) {
+    pub fn move_up(&mut self) {
+        let total_options = self.rows.len();
         if total_options == 0 {
             return;
         }
@@ -83,46 +709,672 @@ impl AppState {
     }
 
     /// Move selection down (with wrap-around)
-    pub fn move_down(&mut self, total_options: usize) {
+    pub fn move_down(&mut self) {
+        let total_options = self.rows.len();
         if total_options == 0 {
             return;
         }
         if self.selected_index < total_options - 1 {
             self.selected_index += 1;
         } else {
+            // Back at the top, with the list scrolled there too
             self.selected_index = 0;
+            self.list_offset = 0;
         }
     }
 
+    /// Move the selection by `delta` rows, stopping at either end
+    pub fn move_by(&mut self, delta: isize) {
+        let last = self.rows.len().saturating_sub(1);
+        self.selected_index = self.selected_index.saturating_add_signed(delta).min(last);
+    }
+
+    /// Select the first row, with the list scrolled to the top
+    pub fn move_to_first(&mut self) {
+        self.selected_index = 0;
+        self.list_offset = 0;
+    }
+
+    /// Select the last row
+    pub fn move_to_last(&mut self) {
+        self.selected_index = self.rows.len().saturating_sub(1);
+    }
+
+    /// Rows of the option list in view, from its last draw
+    pub fn list_page(&self) -> usize {
+        usize::from(self.list_area.height.saturating_sub(2)).max(1)
+    }
+
+    /// Index into `options` of the currently selected option, `None` on a heading
+    pub fn selected_option_index(&self) -> Option<usize> {
+        match self.rows.get(self.selected_index) {
+            Some(ListRow::Option(index)) => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// Category of the selected row, whether it's the heading or an option under it
+    pub fn selected_category(&self) -> Option<String> {
+        match self.rows.get(self.selected_index)? {
+            ListRow::Category(name) => Some(name.clone()),
+            ListRow::Option(index) => self.options.get(*index).map(|o| category_of(o).to_string()),
+        }
+    }
+
+    /// Installed and total number of options in a category, ignoring the filter
+    ///
+    /// Options that don't track state count toward neither.
+    pub fn category_counts(&self, category: &str) -> (usize, usize) {
+        let members = self
+            .options
+            .iter()
+            .filter(|o| category_of(o) == category && o.tracks_state());
+        members.fold((0, 0), |(installed, total), o| {
+            (installed + usize::from(self.is_installed(&o.id)), total + 1)
+        })
+    }
+
+    /// Collapse or expand a category, leaving its heading selected when it folds away
+    pub fn set_collapsed(&mut self, category: &str, collapsed: bool) {
+        if collapsed {
+            self.collapsed.insert(category.to_string());
+        } else {
+            self.collapsed.remove(category);
+        }
+        self.rebuild_rows(self.rows.get(self.selected_index).cloned());
+    }
+
+    /// The currently selected option
+    pub fn selected_option(&self) -> Option<&InstallOption> {
+        self.selected_option_index().and_then(|i| self.options.get(i))
+    }
+
+    /// Replace the filter, keeping the selected row highlighted if it still matches
+    pub fn set_filter(&mut self, filter: String) {
+        let selected = self.rows.get(self.selected_index).cloned();
+        let needle = filter.to_lowercase();
+        let mut matching: Vec<usize> = self
+            .options
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| {
+                o.name.to_lowercase().contains(&needle)
+                    || o.description.to_lowercase().contains(&needle)
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        // Group by category in order of first appearance
+        let mut categories: Vec<&str> = Vec::new();
+        for &i in &matching {
+            let category = category_of(&self.options[i]);
+            if !categories.contains(&category) {
+                categories.push(category);
+            }
+        }
+        matching.sort_by_key(|&i| {
+            let category = category_of(&self.options[i]);
+            categories.iter().position(|&c| c == category)
+        });
+        self.visible = matching;
+        self.filter = filter;
+        self.rebuild_rows(selected);
+    }
+
+    /// Lay out `rows` from `visible`, then select `selected`, its heading, or the first option
+    ///
+    /// Without any categories in the catalog the list stays flat. While a
+    /// filter is typed every group with a match is shown expanded.
+    fn rebuild_rows(&mut self, selected: Option<ListRow>) {
+        let grouped = self.options.iter().any(|o| !o.category.is_empty());
+        self.rows.clear();
+        let mut current: Option<&str> = None;
+        for &i in &self.visible {
+            let category = category_of(&self.options[i]);
+            if grouped && current != Some(category) {
+                current = Some(category);
+                self.rows.push(ListRow::Category(category.to_string()));
+            }
+            if !grouped || !self.filter.is_empty() || !self.collapsed.contains(category) {
+                self.rows.push(ListRow::Option(i));
+            }
+        }
+
+        // An option folded away is represented by its heading
+        let heading = match &selected {
+            Some(ListRow::Option(i)) => self
+                .options
+                .get(*i)
+                .map(|o| ListRow::Category(category_of(o).to_string())),
+            _ => None,
+        };
+        self.selected_index = [selected, heading]
+            .into_iter()
+            .flatten()
+            .find_map(|row| self.rows.iter().position(|r| *r == row))
+            .or_else(|| self.rows.iter().position(|r| matches!(r, ListRow::Option(_))))
+            .unwrap_or(0);
+    }
+
+    /// Remove the filter so every option is visible again
+    pub fn clear_filter(&mut self) {
+        self.set_filter(String::new());
+    }
+
+    /// Select an option by its index into `options`, revealing it if filtered or collapsed away
+    pub fn select_option(&mut self, option_index: usize) {
+        if !self.visible.contains(&option_index) {
+            self.clear_filter();
+        }
+        if let Some(option) = self.options.get(option_index) {
+            let category = category_of(option).to_string();
+            if self.collapsed.contains(&category) {
+                self.set_collapsed(&category, false);
+            }
+        }
+        if let Some(row) = self.rows.iter().position(|r| *r == ListRow::Option(option_index)) {
+            self.selected_index = row;
+        }
+    }
+
+    /// Whether the last run failed and can be retried with `r`
+    pub fn can_retry(&self) -> bool {
+        self.last_attempt.is_some() && self.last_completion.is_some_and(|c| !c.success())
+    }
+
+    /// Choices offered by the action popup for the selected option
+    pub fn popup_choices(&self) -> Vec<ActionChoice> {
+        let Some(option) = self.selected_option() else {
+            return Vec::new();
+        };
+        let mut choices = vec![ActionChoice::Reinstall];
+        if option.verify.is_some() {
+            choices.push(ActionChoice::InstallSkipVerify);
+        }
+        if self.is_installed(&option.id) {
+            choices.push(ActionChoice::Uninstall);
+        }
+        choices
+    }
+
+    /// Names of the options waiting for a reboot
+    pub fn pending_reboot_names(&self) -> Vec<&str> {
+        self.option_names(&self.pending_reboot)
+    }
+
+    /// Display names for option ids, falling back to the id itself
+    pub fn option_names<'a>(&'a self, ids: &'a [String]) -> Vec<&'a str> {
+        ids.iter()
+            .map(|id| {
+                self.options
+                    .iter()
+                    .find(|o| &o.id == id)
+                    .map_or(id.as_str(), |o| o.name.as_str())
+            })
+            .collect()
+    }
+
+    /// Versions of an installed option that has an update, if it has one
+    pub fn update_for(&self, option_id: &str) -> Option<&OptionVersions> {
+        self.versions
+            .get(option_id)
+            .filter(|versions| versions.update_available() && self.is_installed(option_id))
+    }
+
+    /// Why an option doesn't fit the installed Omarchy version, if it doesn't
+    pub fn compat_issue(&self, option: &InstallOption) -> Option<String> {
+        mismatch(option.omarchy.as_ref(), self.base_version.as_ref())
+    }
+
+    /// Version mismatches among the given option ids, one line per option
+    pub fn compat_issues(&self, ids: &[String]) -> Vec<String> {
+        ids.iter()
+            .filter_map(|id| self.options.iter().find(|o| &o.id == id))
+            .filter_map(|option| {
+                self.compat_issue(option)
+                    .map(|issue| format!("{} {}", option.name, issue))
+            })
+            .collect()
+    }
+
+    /// Whether the option's last install failed verification
+    pub fn verify_failed(&self, option_id: &str) -> bool {
+        self.records.get(option_id).is_some_and(|r| r.verify_failed)
+    }
+
+    /// Index of the option with the given ID
+    pub fn option_index(&self, option_id: &str) -> Option<usize> {
+        self.options.iter().position(|o| o.id == option_id)
+    }
+
     /// Check if an option is installed
     pub fn is_installed(&self, option_id: &str) -> bool {
         self.installed.contains(option_id)
     }
 
+    /// Toggle whether an option is selected for the queue
+    pub fn toggle_queued(&mut self, option_id: &str) {
+        if !self.queued.remove(option_id) {
+            self.queued.insert(option_id.to_string());
+        }
+    }
+
+    /// Members of a profile that are installed, out of those with an installed state
+    pub fn profile_progress(&self, profile: &Profile) -> (usize, usize) {
+        let members: Vec<&InstallOption> = profile
+            .option_ids
+            .iter()
+            .filter_map(|id| self.options.iter().find(|o| &o.id == id))
+            .filter(|o| o.tracks_state())
+            .collect();
+        let installed = members.iter().filter(|o| self.is_installed(&o.id)).count();
+        (installed, members.len())
+    }
+
+    /// Check if an option is selected for the queue
+    pub fn is_queued(&self, option_id: &str) -> bool {
+        self.queued.contains(option_id)
+    }
+
+    /// Whether a multi-option queue is currently running
+    pub fn queue_active(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    /// Whether the last recorded attempt for an option failed
+    pub fn last_failed(&self, option_id: &str) -> bool {
+        self.records.get(option_id).is_some_and(OptionRecord::last_failed)
+    }
+
+    /// Whether the arrow keys scroll the output rather than move the selection
+    pub fn output_focused(&self) -> bool {
+        self.show_output && (self.focus == Panel::Output || self.compact)
+    }
+
+    /// How long the current run has been going
+    pub fn run_elapsed(&self, now: Instant) -> Option<Duration> {
+        self.run_started_at
+            .map(|started| now.saturating_duration_since(started))
+    }
+
+    /// Leave the run's mode and prompts, bringing back the full UI
+    pub fn end_run(&mut self, completion: Option<Completion>, now: Instant) {
+        self.last_completion = completion;
+        self.run_duration = self.run_elapsed(now);
+        if completion.is_some_and(Completion::success) {
+            self.last_attempt = None;
+        }
+        self.mode = AppMode::Normal;
+        self.zen = false;
+        self.clear_password();
+        self.password_prompt = None;
+        self.follow_latest_output();
+        self.running_option = None;
+        self.run_watch = None;
+        self.timeout_prompt_pending = false;
+    }
+
+    /// How long a successful run like the current one usually takes
+    pub fn typical_run_duration(&self) -> Option<Duration> {
+        let action = if self.is_uninstalling {
+            RunAction::Uninstall
+        } else {
+            RunAction::Install
+        };
+        self.records.get(self.running_option.as_ref()?)?.typical_duration(action)
+    }
+
     /// Clear output and reset for new operation
     pub fn clear_output(&mut self) {
         self.output_lines.clear();
+        self.output_omitted = 0;
+        self.partial_output = None;
+        self.last_stderr = None;
+        self.run_duration = None;
         self.output_scroll = 0;
-        self.last_exit_code = None;
+        self.follow_output = true;
+        self.unseen_output = 0;
+        self.last_completion = None;
+        self.error_count = 0;
+        self.warning_count = 0;
     }
 
-    /// Add an output line
+    /// Add an output line, coalescing it into the previous one if identical
     pub fn add_output_line(&mut self, line: String) {
-        self.output_lines.push(line);
+        self.push_output_line(line, LineKind::Output);
+    }
+
+    /// Add a line the script wrote to stderr
+    pub fn add_stderr_line(&mut self, line: String) {
+        if !line.trim().is_empty() {
+            self.last_stderr = Some(line.clone());
+        }
+        self.push_output_line(line, LineKind::Stderr);
+    }
+
+    /// Add a line describing a problem the installer found
+    pub fn add_problem_line(&mut self, line: String) {
+        self.push_output_line(line, LineKind::Problem);
+    }
+
+    /// Add the command line or result framing a run
+    pub fn add_frame_line(&mut self, line: String) {
+        self.push_output_line(line, LineKind::Frame);
+    }
+
+    fn push_output_line(&mut self, line: String, kind: LineKind) {
+        // An option id in the command line isn't the script complaining
+        if kind != LineKind::Frame {
+            let lower = line.to_lowercase();
+            if lower.contains("error") {
+                self.error_count += 1;
+            } else if lower.contains("warning") {
+                self.warning_count += 1;
+            }
+        }
+        if !self.follow_output {
+            self.unseen_output += 1;
+        }
+
+        match self.output_lines.back_mut() {
+            Some(last) if last.text == line && last.kind == kind => last.repeat += 1,
+            _ => self.output_lines.push_back(OutputLine {
+                text: line,
+                repeat: 1,
+                kind,
+            }),
+        }
+
+        // Evict the oldest rows, keeping a scrolled-back view on the same text
+        while self.output_lines.len() > self.output_limit.max(1) {
+            if let Some(evicted) = self.output_lines.pop_front() {
+                self.output_omitted += evicted.repeat;
+                self.output_scroll = self.output_scroll.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Overwrite and forget the typed password
+    pub fn clear_password(&mut self) {
+        self.password_input.fill(0);
+        self.password_input.clear();
+    }
+
+    /// Number of displayed output rows, including a pending partial line
+    pub fn output_len(&self) -> usize {
+        self.output_lines.len() + usize::from(self.partial_output.is_some())
+    }
+
+    /// Rows of the output currently in view
+    pub fn output_window(&self) -> ViewWindow {
+        ViewWindow::new(self.output_len(), self.output_height, self.output_scroll)
+    }
+
+    /// Largest valid scroll offset for the current output and panel height
+    pub fn max_output_scroll(&self) -> usize {
+        self.output_window().max_offset()
+    }
+
+    /// Scroll output up, suspending auto-scroll
+    pub fn scroll_output_up(&mut self, lines: usize) {
+        self.scroll_output_by(-(lines as isize));
+    }
+
+    /// Scroll output down, resuming auto-scroll once the bottom is reached
+    pub fn scroll_output_down(&mut self, lines: usize) {
+        self.scroll_output_by(lines as isize);
+    }
+
+    fn scroll_output_by(&mut self, delta: isize) {
+        let mut window = self.output_window();
+        window.scroll_by(delta);
+        self.output_scroll = window.offset();
+        self.follow_output = window.at_end();
+        if self.follow_output {
+            self.unseen_output = 0;
+        }
+    }
+
+    /// Move the list / output split one step, leaving full-width output
+    pub fn shift_split(&mut self, widen_list: bool) {
+        let share = self.split.unwrap_or_else(|| self.layouts.current().list_share());
+        // Steps land on multiples of the step even from an odd layout ratio
+        let snapped = share / SPLIT_STEP * SPLIT_STEP;
+        let moved = if widen_list {
+            snapped + SPLIT_STEP
+        } else if snapped == share {
+            snapped.saturating_sub(SPLIT_STEP)
+        } else {
+            snapped
+        };
+        self.split = Some(moved.clamp(MIN_SPLIT, MAX_SPLIT));
+        self.output_full = false;
+    }
+
+    /// Snap to the newest output and follow it again
+    pub fn follow_latest_output(&mut self) {
+        self.follow_output = true;
+        self.unseen_output = 0;
+        self.auto_scroll_output();
+    }
+
+    /// Keep the view pinned to the newest output while following
+    pub fn auto_scroll_output(&mut self) {
+        if self.follow_output {
+            self.output_scroll = self.max_output_scroll();
+        }
+    }
+}
+
+/// Category an option is listed under
+fn category_of(option: &InstallOption) -> &str {
+    if option.category.is_empty() {
+        UNCATEGORIZED
+    } else {
+        &option.category
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_state() -> AppState {
+        AppState::new(Vec::new(), HashSet::new(), None)
+    }
+
+    fn rows(state: &AppState) -> Vec<(&str, usize)> {
+        state.output_lines.iter().map(|line| (line.text.as_str(), line.repeat)).collect()
+    }
+
+    #[test]
+    fn identical_consecutive_lines_coalesce() {
+        let mut state = empty_state();
+        state.add_output_line("fetching".to_string());
+        for _ in 0..3 {
+            state.add_output_line("warning: font cache stale".to_string());
+        }
+        state.add_output_line("done".to_string());
+        assert_eq!(rows(&state), [("fetching", 1), ("warning: font cache stale", 3), ("done", 1)]);
+    }
+
+    #[test]
+    fn only_byte_identical_lines_of_the_same_kind_coalesce() {
+        let mut state = empty_state();
+        state.add_output_line("retrying".to_string());
+        state.add_output_line("retrying ".to_string());
+        state.add_stderr_line("retrying ".to_string());
+        state.add_output_line("retrying".to_string());
+        assert_eq!(
+            rows(&state),
+            [("retrying", 1), ("retrying ", 1), ("retrying ", 1), ("retrying", 1)]
+        );
+    }
+
+    #[test]
+    fn severity_counts_every_occurrence() {
+        let mut state = empty_state();
+        for _ in 0..4 {
+            state.add_stderr_line("Warning: deprecated flag".to_string());
+        }
+        state.add_output_line("error: build failed".to_string());
+        state.add_output_line("error: build failed".to_string());
+        assert_eq!((state.warning_count, state.error_count), (4, 2));
+        assert_eq!(state.output_lines.len(), 2);
     }
 
-    /// Scroll output up
-    pub fn scroll_output_up(&mut self) {
-        if self.output_scroll > 0 {
-            self.output_scroll -= 1;
+    #[test]
+    fn frame_lines_coalesce_without_counting_as_problems() {
+        let mut state = empty_state();
+        state.add_frame_line("$ install error-pages".to_string());
+        state.add_frame_line("$ install error-pages".to_string());
+        assert_eq!(rows(&state), [("$ install error-pages", 2)]);
+        assert_eq!(state.error_count, 0);
+    }
+
+    #[test]
+    fn evicted_rows_count_all_their_occurrences() {
+        let mut state = empty_state();
+        state.output_limit = 2;
+        for line in ["a", "a", "a", "b", "c"] {
+            state.add_output_line(line.to_string());
         }
+        assert_eq!(rows(&state), [("b", 1), ("c", 1)]);
+        assert_eq!(state.output_omitted, 3);
+    }
+
+    #[test]
+    fn unseen_output_counts_occurrences_while_scrolled_back() {
+        let mut state = empty_state();
+        state.follow_output = false;
+        state.add_output_line("tick".to_string());
+        state.add_output_line("tick".to_string());
+        assert_eq!(state.unseen_output, 2);
+        assert_eq!(state.output_lines.len(), 1);
+    }
+
+    #[test]
+    fn a_queue_item_fails_on_its_exit_code_or_its_verification() {
+        let ran = |exit_code, verify_failed| QueueResult {
+            option_id: "fish".to_string(),
+            uninstall: false,
+            exit_code,
+            verify_failed,
+            duration: Some(Duration::from_secs(1)),
+            log: None,
+        };
+        assert!(!ran(0, false).failed());
+        assert!(ran(0, true).failed());
+        assert!(ran(1, false).failed());
+        assert!(ran(1, true).failed());
+        assert!(QueueResult::not_run("fish".to_string(), false).failed());
+    }
+
+    /// A state that dims after five minutes, with the last input at `start`
+    fn dimming_state(start: Instant) -> AppState {
+        let mut state = empty_state();
+        state.idle_dim_after = Some(Duration::from_secs(300));
+        state.last_input = start;
+        state
+    }
+
+    #[test]
+    fn ui_dims_once_the_threshold_passes() {
+        let start = Instant::now();
+        let mut state = dimming_state(start);
+        state.update_idle(start + Duration::from_secs(299));
+        assert!(!state.dimmed);
+        state.update_idle(start + Duration::from_secs(300));
+        assert!(state.dimmed, "the threshold itself counts");
+        state.update_idle(start + Duration::from_secs(10_000));
+        assert!(state.dimmed);
+    }
+
+    #[test]
+    fn input_wakes_the_ui_and_restarts_the_countdown() {
+        let start = Instant::now();
+        let mut state = dimming_state(start);
+        state.update_idle(start + Duration::from_secs(400));
+        assert!(state.register_input(start + Duration::from_secs(401)), "the first key only wakes it");
+        assert!(!state.dimmed);
+        assert!(!state.register_input(start + Duration::from_secs(402)));
+
+        state.update_idle(start + Duration::from_secs(700));
+        assert!(!state.dimmed, "five minutes haven't passed since the last key");
+        state.update_idle(start + Duration::from_secs(702));
+        assert!(state.dimmed);
     }
 
-    /// Scroll output down
-    pub fn scroll_output_down(&mut self, visible_lines: usize) {
-        let max_scroll = self.output_lines.len().saturating_sub(visible_lines);
-        if self.output_scroll < max_scroll {
-            self.output_scroll += 1;
+    #[test]
+    fn input_before_the_threshold_keeps_the_ui_awake() {
+        let start = Instant::now();
+        let mut state = dimming_state(start);
+        for minute in 1..=20 {
+            let now = start + Duration::from_secs(minute * 60);
+            state.update_idle(now);
+            assert!(!state.register_input(now), "minute {}", minute);
         }
+        assert!(!state.dimmed);
+    }
+
+    #[test]
+    fn ui_never_dims_without_a_threshold() {
+        let start = Instant::now();
+        let mut state = empty_state();
+        state.last_input = start;
+        state.update_idle(start + Duration::from_secs(86_400));
+        assert!(!state.dimmed);
+        assert!(!state.register_input(start + Duration::from_secs(86_401)));
+    }
+
+    #[test]
+    fn input_stamped_after_now_does_not_dim() {
+        let start = Instant::now() + Duration::from_secs(3600);
+        let mut state = dimming_state(start);
+        state.update_idle(start - Duration::from_secs(1800));
+        assert!(!state.dimmed);
+    }
+
+    /// A Shell category of two tracked options and a one-shot task
+    fn shell_state(installed: &[&str]) -> AppState {
+        let option = |id: &str, track_state: Option<bool>| InstallOption {
+            id: id.to_string(),
+            name: id.to_string(),
+            category: "Shell".to_string(),
+            track_state,
+            ..InstallOption::default()
+        };
+        let options = vec![option("fish", None), option("starship", None), option("font-cache", Some(false))];
+        AppState::new(options, installed.iter().map(|id| id.to_string()).collect(), None)
+    }
+
+    #[test]
+    fn installed_ids_of_one_shot_options_are_dropped() {
+        let state = shell_state(&["fish", "font-cache"]);
+        assert!(state.is_installed("fish"));
+        assert!(!state.is_installed("font-cache"));
+    }
+
+    #[test]
+    fn one_shot_options_stay_out_of_category_counts() {
+        assert_eq!(shell_state(&[]).category_counts("Shell"), (0, 2));
+        assert_eq!(shell_state(&["fish", "font-cache"]).category_counts("Shell"), (1, 2));
+        assert_eq!(shell_state(&["fish", "starship"]).category_counts("Shell"), (2, 2));
+    }
+
+    #[test]
+    fn one_shot_options_stay_out_of_profile_progress() {
+        let profile = Profile {
+            name: "desk".to_string(),
+            option_ids: ["fish", "font-cache", "gone"].map(String::from).to_vec(),
+        };
+        assert_eq!(shell_state(&["font-cache"]).profile_progress(&profile), (0, 1));
+        assert_eq!(shell_state(&["fish"]).profile_progress(&profile), (1, 1));
+
+        let only_tasks = Profile {
+            name: "refresh".to_string(),
+            option_ids: vec!["font-cache".to_string()],
+        };
+        assert_eq!(shell_state(&["font-cache"]).profile_progress(&only_tasks), (0, 0));
     }
 }
@@ -0,0 +1,72 @@
+//! Which option ids the entry script accepts
+//!
+//! An id the script doesn't know (a typo in the manifest, or an option newer
+//! than the scripts) would only fail once its run starts. At startup the
+//! script is asked for the ids it accepts:
+//!
+//! - `install --list`, printing one id per line, or failing that
+//! - `install help`, reading the `OPTIONS:` section the bundled script prints
+//!
+//! A script that answers neither isn't checked. `OMARCHY_CYBEX_SCRIPT_PROBE=off`
+//! skips the probe, for scripts that would treat `--list` or `help` as an
+//! option to install.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::check::{run_program, CheckStatus};
+use crate::ui::strip_ansi_codes;
+
+/// Time allowed for each listing command
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Ids the entry script says it accepts, None when it can't say
+pub fn supported_ids(script_dir: &Path, entry: &Path) -> Option<HashSet<String>> {
+    let listed = run_program(entry, &["--list"], script_dir, PROBE_TIMEOUT);
+    if listed.status == CheckStatus::Passed {
+        let ids = parse_list(&listed.output);
+        if !ids.is_empty() {
+            return Some(ids);
+        }
+    }
+    let help = run_program(entry, &["help"], script_dir, PROBE_TIMEOUT);
+    if help.status != CheckStatus::Passed {
+        return None;
+    }
+    let ids = parse_help(&help.output);
+    (!ids.is_empty()).then_some(ids)
+}
+
+/// Ids from `--list` output: the first word of each line, `#` lines skipped
+fn parse_list(output: &[String]) -> HashSet<String> {
+    output
+        .iter()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|id| !id.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Ids from the `OPTIONS:` section of help output
+///
+/// Lines read like "  fish   Install Fish shell (alias: fish-shell)"; the
+/// section ends at the first blank line.
+fn parse_help(output: &[String]) -> HashSet<String> {
+    let mut ids = HashSet::new();
+    let lines = output
+        .iter()
+        .map(|line| strip_ansi_codes(line))
+        .skip_while(|line| line.trim() != "OPTIONS:")
+        .skip(1)
+        .take_while(|line| !line.trim().is_empty());
+    for line in lines {
+        if let Some(id) = line.split_whitespace().next() {
+            ids.insert(id.to_string());
+        }
+        if let Some(alias) = line.split_once("(alias: ").and_then(|(_, rest)| rest.split_once(')')) {
+            ids.insert(alias.0.trim().to_string());
+        }
+    }
+    ids
+}
@@ -0,0 +1,128 @@
+//! Stepping out of the TUI: a shell with `!`, an editor with `E`, or
+//! suspending with Ctrl+Z
+//!
+//! Raw mode turns Ctrl+Z into an ordinary key, so it's bound like any other;
+//! a SIGTSTP sent from elsewhere (`kill -TSTP`) is caught and turned into a
+//! [`Wake::Suspend`] for the loop. Either way the TUI hands the terminal back
+//! first and takes it again once the shell or editor exits or the process
+//! continues.
+
+use std::env;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::FromRawFd;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use crate::tty::{terminal_stdio, TerminalOut};
+use crate::wake::Wake;
+
+/// Ways of stepping out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepOut {
+    /// Run `$SHELL` and come back when it exits
+    Shell,
+    /// Open a file in `$EDITOR` and come back when it exits
+    Edit(PathBuf),
+    /// Stop the process until the shell's `fg`
+    Suspend,
+}
+
+/// Write end of the pipe the SIGTSTP handler signals through, -1 until set up
+static TSTP_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn on_tstp(_: libc::c_int) {
+    let fd = TSTP_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        // SAFETY: write(2) is async-signal-safe and the buffer outlives the call
+        unsafe {
+            libc::write(fd, [0u8].as_ptr().cast(), 1);
+        }
+    }
+}
+
+/// `on_tstp` in the form `signal(2)` takes
+fn tstp_handler() -> libc::sighandler_t {
+    on_tstp as extern "C" fn(libc::c_int) as libc::sighandler_t
+}
+
+/// Catch SIGTSTP, sending a wake for each one instead of stopping
+///
+/// A handler (unlike SIG_IGN) is reset by exec, so scripts still get the
+/// default behaviour.
+pub fn catch_tstp(wake: Sender<Wake>) {
+    let mut fds = [0; 2];
+    // SAFETY: pipe2(2) fills the two-element array on success; close-on-exec
+    // keeps the ends out of the scripts
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return;
+    }
+    TSTP_PIPE.store(fds[1], Ordering::Relaxed);
+    // SAFETY: the handler only touches an atomic and calls write(2)
+    unsafe {
+        libc::signal(libc::SIGTSTP, tstp_handler());
+    }
+    // SAFETY: the read end is a fresh descriptor nothing else owns
+    let mut pipe = unsafe { File::from_raw_fd(fds[0]) };
+    thread::spawn(move || {
+        let mut byte = [0u8];
+        while pipe.read_exact(&mut byte).is_ok() {
+            if wake.send(Wake::Suspend).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+/// Stop the process the way an uncaught SIGTSTP would, returning once it's
+/// continued
+pub fn stop_self() {
+    // SAFETY: only signal dispositions change, and the handler is put back
+    unsafe {
+        libc::signal(libc::SIGTSTP, libc::SIG_DFL);
+        libc::raise(libc::SIGTSTP);
+        if TSTP_PIPE.load(Ordering::Relaxed) >= 0 {
+            libc::signal(libc::SIGTSTP, tstp_handler());
+        }
+    }
+}
+
+/// Run the user's shell (`/bin/sh` without `$SHELL`) in `cwd` until it exits
+pub fn run_shell(cwd: &Path) -> io::Result<ExitStatus> {
+    let shell = env::var_os("SHELL")
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| OsString::from("/bin/sh"));
+    let (stdin, stdout, stderr) = terminal_stdio()?;
+    let _ = writeln!(TerminalOut::current(), "omarchy-cybex is still running - type exit to return");
+    Command::new(shell)
+        .current_dir(cwd)
+        .env("OMARCHY_CYBEX_SHELL", "1")
+        .stdin(stdin)
+        .stdout(stdout)
+        .stderr(stderr)
+        .status()
+}
+
+/// Open `path` in the user's editor (`vi` without `$EDITOR`) until it exits
+///
+/// `$EDITOR` may carry arguments, as in `code --wait`.
+pub fn run_editor(path: &Path, cwd: &Path) -> io::Result<ExitStatus> {
+    let editor = env::var("EDITOR").ok().filter(|editor| !editor.trim().is_empty());
+    let editor = editor.as_deref().unwrap_or("vi");
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let (stdin, stdout, stderr) = terminal_stdio()?;
+    Command::new(program)
+        .args(words)
+        .arg(path)
+        .current_dir(cwd)
+        .stdin(stdin)
+        .stdout(stdout)
+        .stderr(stderr)
+        .status()
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", program, e)))
+}
@@ -1,11 +1,20 @@
-//! Catppuccin Mocha theme colors
+//! Catppuccin theme colors
+//!
+//! All four flavors ship. [`THEME`] reads the one chosen at startup
+//! (`--theme`, or `theme = "latte"` in config.toml), which otherwise is
+//! Latte on a terminal that reports a light background and Mocha elsewhere.
+//! NO_COLOR or `--plain` leave the colors to the terminal instead.
+
+use std::fs;
+use std::ops::Deref;
+use std::sync::OnceLock;
 
 use ratatui::style::Color;
 
-/// Catppuccin Mocha color palette
-pub struct CatppuccinMocha {
-    pub rosewater: Color,
-    pub flamingo: Color,
+use crate::config::state_dir;
+
+/// The Catppuccin colors the interface draws with
+pub struct Palette {
     pub pink: Color,
     pub mauve: Color,
     pub red: Color,
@@ -13,15 +22,11 @@ pub struct CatppuccinMocha {
     pub peach: Color,
     pub yellow: Color,
     pub green: Color,
-    pub teal: Color,
     pub sky: Color,
-    pub sapphire: Color,
     pub blue: Color,
     pub lavender: Color,
     pub text: Color,
-    pub subtext1: Color,
     pub subtext0: Color,
-    pub overlay2: Color,
     pub overlay1: Color,
     pub overlay0: Color,
     pub surface2: Color,
@@ -32,10 +37,80 @@ pub struct CatppuccinMocha {
     pub crust: Color,
 }
 
-/// Catppuccin Mocha theme instance
-pub const THEME: CatppuccinMocha = CatppuccinMocha {
-    rosewater: Color::Rgb(245, 224, 220), // #f5e0dc
-    flamingo: Color::Rgb(242, 205, 205),  // #f2cdcd
+/// Catppuccin Latte, the light flavor
+pub const LATTE: Palette = Palette {
+    pink: Color::Rgb(234, 118, 203),      // #ea76cb
+    mauve: Color::Rgb(136, 57, 239),      // #8839ef
+    red: Color::Rgb(210, 15, 57),         // #d20f39
+    maroon: Color::Rgb(230, 69, 83),      // #e64553
+    peach: Color::Rgb(254, 100, 11),      // #fe640b
+    yellow: Color::Rgb(223, 142, 29),     // #df8e1d
+    green: Color::Rgb(64, 160, 43),       // #40a02b
+    sky: Color::Rgb(4, 165, 229),         // #04a5e5
+    blue: Color::Rgb(30, 102, 245),       // #1e66f5
+    lavender: Color::Rgb(114, 135, 253),  // #7287fd
+    text: Color::Rgb(76, 79, 105),        // #4c4f69
+    subtext0: Color::Rgb(108, 111, 133),  // #6c6f85
+    overlay1: Color::Rgb(140, 143, 161),  // #8c8fa1
+    overlay0: Color::Rgb(156, 160, 176),  // #9ca0b0
+    surface2: Color::Rgb(172, 176, 190),  // #acb0be
+    surface1: Color::Rgb(188, 192, 204),  // #bcc0cc
+    surface0: Color::Rgb(204, 208, 218),  // #ccd0da
+    base: Color::Rgb(239, 241, 245),      // #eff1f5
+    mantle: Color::Rgb(230, 233, 239),    // #e6e9ef
+    crust: Color::Rgb(220, 224, 232),     // #dce0e8
+};
+
+/// Catppuccin Frappé
+pub const FRAPPE: Palette = Palette {
+    pink: Color::Rgb(244, 184, 228),      // #f4b8e4
+    mauve: Color::Rgb(202, 158, 230),     // #ca9ee6
+    red: Color::Rgb(231, 130, 132),       // #e78284
+    maroon: Color::Rgb(234, 153, 156),    // #ea999c
+    peach: Color::Rgb(239, 159, 118),     // #ef9f76
+    yellow: Color::Rgb(229, 200, 144),    // #e5c890
+    green: Color::Rgb(166, 209, 137),     // #a6d189
+    sky: Color::Rgb(153, 209, 219),       // #99d1db
+    blue: Color::Rgb(140, 170, 238),      // #8caaee
+    lavender: Color::Rgb(186, 187, 241),  // #babbf1
+    text: Color::Rgb(198, 208, 245),      // #c6d0f5
+    subtext0: Color::Rgb(165, 173, 206),  // #a5adce
+    overlay1: Color::Rgb(131, 139, 167),  // #838ba7
+    overlay0: Color::Rgb(115, 121, 148),  // #737994
+    surface2: Color::Rgb(98, 104, 128),   // #626880
+    surface1: Color::Rgb(81, 87, 109),    // #51576d
+    surface0: Color::Rgb(65, 69, 89),     // #414559
+    base: Color::Rgb(48, 52, 70),         // #303446
+    mantle: Color::Rgb(41, 44, 60),       // #292c3c
+    crust: Color::Rgb(35, 38, 52),        // #232634
+};
+
+/// Catppuccin Macchiato
+pub const MACCHIATO: Palette = Palette {
+    pink: Color::Rgb(245, 189, 230),      // #f5bde6
+    mauve: Color::Rgb(198, 160, 246),     // #c6a0f6
+    red: Color::Rgb(237, 135, 150),       // #ed8796
+    maroon: Color::Rgb(238, 153, 160),    // #ee99a0
+    peach: Color::Rgb(245, 169, 127),     // #f5a97f
+    yellow: Color::Rgb(238, 212, 159),    // #eed49f
+    green: Color::Rgb(166, 218, 149),     // #a6da95
+    sky: Color::Rgb(145, 215, 227),       // #91d7e3
+    blue: Color::Rgb(138, 173, 244),      // #8aadf4
+    lavender: Color::Rgb(183, 189, 248),  // #b7bdf8
+    text: Color::Rgb(202, 211, 245),      // #cad3f5
+    subtext0: Color::Rgb(165, 173, 203),  // #a5adcb
+    overlay1: Color::Rgb(128, 135, 162),  // #8087a2
+    overlay0: Color::Rgb(110, 115, 141),  // #6e738d
+    surface2: Color::Rgb(91, 96, 120),    // #5b6078
+    surface1: Color::Rgb(73, 77, 100),    // #494d64
+    surface0: Color::Rgb(54, 58, 79),     // #363a4f
+    base: Color::Rgb(36, 39, 58),         // #24273a
+    mantle: Color::Rgb(30, 32, 48),       // #1e2030
+    crust: Color::Rgb(24, 25, 38),        // #181926
+};
+
+/// Catppuccin Mocha, the default
+pub const MOCHA: Palette = Palette {
     pink: Color::Rgb(245, 194, 231),      // #f5c2e7
     mauve: Color::Rgb(203, 166, 247),     // #cba6f7
     red: Color::Rgb(243, 139, 168),       // #f38ba8
@@ -43,15 +118,11 @@ pub const THEME: CatppuccinMocha = CatppuccinMocha {
     peach: Color::Rgb(250, 179, 135),     // #fab387
     yellow: Color::Rgb(249, 226, 175),    // #f9e2af
     green: Color::Rgb(166, 227, 161),     // #a6e3a1
-    teal: Color::Rgb(148, 226, 213),      // #94e2d5
     sky: Color::Rgb(137, 220, 235),       // #89dceb
-    sapphire: Color::Rgb(116, 199, 236),  // #74c7ec
     blue: Color::Rgb(137, 180, 250),      // #89b4fa
     lavender: Color::Rgb(180, 190, 254),  // #b4befe
     text: Color::Rgb(205, 214, 244),      // #cdd6f4
-    subtext1: Color::Rgb(186, 194, 222),  // #bac2de
     subtext0: Color::Rgb(166, 173, 200),  // #a6adc8
-    overlay2: Color::Rgb(147, 153, 178),  // #9399b2
     overlay1: Color::Rgb(127, 132, 156),  // #7f849c
     overlay0: Color::Rgb(108, 112, 134),  // #6c7086
     surface2: Color::Rgb(88, 91, 112),    // #585b70
@@ -61,3 +132,251 @@ pub const THEME: CatppuccinMocha = CatppuccinMocha {
     mantle: Color::Rgb(24, 24, 37),       // #181825
     crust: Color::Rgb(17, 17, 27),        // #11111b
 };
+
+/// The terminal's own colors, for NO_COLOR and `--plain`
+///
+/// A static rather than a const so [`colorless`] can recognise it.
+static COLORLESS: Palette = Palette {
+    pink: Color::Reset,
+    mauve: Color::Reset,
+    red: Color::Reset,
+    maroon: Color::Reset,
+    peach: Color::Reset,
+    yellow: Color::Reset,
+    green: Color::Reset,
+    sky: Color::Reset,
+    blue: Color::Reset,
+    lavender: Color::Reset,
+    text: Color::Reset,
+    subtext0: Color::Reset,
+    overlay1: Color::Reset,
+    overlay0: Color::Reset,
+    surface2: Color::Reset,
+    surface1: Color::Reset,
+    surface0: Color::Reset,
+    base: Color::Reset,
+    mantle: Color::Reset,
+    crust: Color::Reset,
+};
+
+/// A Catppuccin flavor, by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    Latte,
+    Frappe,
+    Macchiato,
+    Mocha,
+}
+
+impl Flavor {
+    /// Names accepted by `--theme` and the config file
+    pub const NAMES: &'static str = "latte, frappe, macchiato or mocha";
+
+    /// Parse a flavor name, ignoring case ("frappé" works too)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "latte" => Some(Flavor::Latte),
+            "frappe" | "frappé" => Some(Flavor::Frappe),
+            "macchiato" => Some(Flavor::Macchiato),
+            "mocha" => Some(Flavor::Mocha),
+            _ => None,
+        }
+    }
+
+    /// Name as `parse` accepts it
+    pub fn name(self) -> &'static str {
+        match self {
+            Flavor::Latte => "latte",
+            Flavor::Frappe => "frappe",
+            Flavor::Macchiato => "macchiato",
+            Flavor::Mocha => "mocha",
+        }
+    }
+
+    pub fn palette(self) -> &'static Palette {
+        match self {
+            Flavor::Latte => &LATTE,
+            Flavor::Frappe => &FRAPPE,
+            Flavor::Macchiato => &MACCHIATO,
+            Flavor::Mocha => &MOCHA,
+        }
+    }
+}
+
+/// Palette chosen for the session
+static ACTIVE: OnceLock<&'static Palette> = OnceLock::new();
+
+/// The session's palette, Mocha until [`select`] is called
+pub struct ActiveTheme;
+
+impl Deref for ActiveTheme {
+    type Target = Palette;
+
+    fn deref(&self) -> &Palette {
+        ACTIVE.get().copied().unwrap_or(&MOCHA)
+    }
+}
+
+/// Colors every UI component draws with
+pub static THEME: ActiveTheme = ActiveTheme;
+
+/// Use `flavor` for the rest of the session; later calls are ignored
+pub fn select(flavor: Flavor) {
+    let _ = ACTIVE.set(flavor.palette());
+}
+
+/// Leave colors to the terminal for the rest of the session
+pub fn select_colorless() {
+    let _ = ACTIVE.set(&COLORLESS);
+}
+
+/// Whether the session draws without colors, so emphasis needs bold or reverse video
+pub fn colorless() -> bool {
+    ACTIVE.get().is_some_and(|palette| std::ptr::eq(*palette, &COLORLESS))
+}
+
+/// Whether the NO_COLOR convention (https://no-color.org) asks for no colors
+pub fn no_color_requested() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Flavor set by the `theme` key of config.toml, if any
+pub fn configured_flavor() -> Result<Option<Flavor>, String> {
+    let Ok(contents) = fs::read_to_string(state_dir().join("config.toml")) else {
+        return Ok(None);
+    };
+    // Syntax errors are reported by the keymap, which reads the same file
+    let Ok(table) = contents.parse::<toml::Table>() else {
+        return Ok(None);
+    };
+    match table.get("theme") {
+        None => Ok(None),
+        Some(toml::Value::String(name)) => Flavor::parse(name)
+            .map(Some)
+            .ok_or_else(|| format!("theme: unknown flavor {:?} (expected {})", name, Flavor::NAMES)),
+        Some(_) => Err(format!("theme: expected a flavor name ({})", Flavor::NAMES)),
+    }
+}
+
+/// Flavor suiting the terminal background, as reported through COLORFGBG
+///
+/// Terminals that set it give "fg;bg" with ANSI color numbers, where 7 and
+/// 15 are light backgrounds. Without it the background is assumed dark.
+pub fn terminal_flavor() -> Flavor {
+    let background = std::env::var("COLORFGBG")
+        .ok()
+        .and_then(|value| value.rsplit(';').next().map(str::to_string));
+    match background.as_deref() {
+        Some("7") | Some("15") => Flavor::Latte,
+        _ => Flavor::Mocha,
+    }
+}
+
+/// Mix `amount` (0.0-1.0) of `tint` into `surface`, for tinted backgrounds
+///
+/// Non-RGB colors are returned unchanged as `surface`.
+pub fn blend(surface: Color, tint: Color, amount: f32) -> Color {
+    match (surface, tint) {
+        (Color::Rgb(r1, g1, b1), Color::Rgb(r2, g2, b2)) => {
+            let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+            Color::Rgb(mix(r1, r2), mix(g1, g2), mix(b1, b2))
+        }
+        _ => surface,
+    }
+}
+
+/// Contrast regular text needs against its background, as WCAG AA asks
+pub const MIN_CONTRAST: f32 = 4.5;
+
+/// WCAG contrast ratio of two colors, from 1 (none) to 21 (black on white)
+///
+/// Colors other than RGB can't be measured and count as the full 21.
+pub fn contrast_ratio(a: Color, b: Color) -> f32 {
+    let luminance = |color: Color| match color {
+        Color::Rgb(r, g, b) => {
+            let linear = |channel: u8| {
+                let c = f32::from(channel) / 255.0;
+                if c <= 0.03928 {
+                    c / 12.92
+                } else {
+                    ((c + 0.055) / 1.055).powf(2.4)
+                }
+            };
+            Some(0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b))
+        }
+        _ => None,
+    };
+    let (Some(a), Some(b)) = (luminance(a), luminance(b)) else {
+        return 21.0;
+    };
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// The strongest blend of `tint` into `surface`, up to `amount`, that keeps
+/// `text` at `MIN_CONTRAST` on top of it
+///
+/// Falls back to `surface` itself when no tint is light (or dark) enough.
+pub fn legible_blend(surface: Color, tint: Color, amount: f32, text: Color) -> Color {
+    (0..=20)
+        .rev()
+        .map(|step| blend(surface, tint, amount * step as f32 / 20.0))
+        .find(|background| contrast_ratio(*background, text) >= MIN_CONTRAST)
+        .unwrap_or(surface)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAVORS: [Flavor; 4] = [Flavor::Latte, Flavor::Frappe, Flavor::Macchiato, Flavor::Mocha];
+
+    #[test]
+    fn blend_mixes_channel_by_channel() {
+        let (black, white) = (Color::Rgb(0, 0, 0), Color::Rgb(255, 255, 255));
+        assert_eq!(blend(black, white, 0.0), black);
+        assert_eq!(blend(black, white, 1.0), white);
+        assert_eq!(blend(black, white, 0.5), Color::Rgb(128, 128, 128));
+        assert_eq!(blend(MOCHA.surface0, MOCHA.green, 0.2), Color::Rgb(72, 85, 87));
+        assert_eq!(blend(Color::Reset, white, 0.5), Color::Reset, "only RGB colors mix");
+    }
+
+    #[test]
+    fn contrast_ratio_runs_from_1_to_21() {
+        let (black, white) = (Color::Rgb(0, 0, 0), Color::Rgb(255, 255, 255));
+        assert!((contrast_ratio(black, white) - 21.0).abs() < 0.01);
+        assert!((contrast_ratio(white, black) - 21.0).abs() < 0.01, "order doesn't matter");
+        assert!((contrast_ratio(MOCHA.text, MOCHA.text) - 1.0).abs() < 0.01);
+        assert!((contrast_ratio(MOCHA.text, MOCHA.surface0) - 8.69).abs() < 0.01);
+        assert_eq!(contrast_ratio(Color::Reset, white), 21.0);
+    }
+
+    #[test]
+    fn legible_blend_keeps_text_readable_in_every_flavor() {
+        for flavor in FLAVORS {
+            let palette = flavor.palette();
+            for tint in [palette.green, palette.red] {
+                let background = legible_blend(palette.surface0, tint, 0.2, palette.text);
+                let ratio = contrast_ratio(background, palette.text);
+                assert!(ratio >= MIN_CONTRAST, "{} text on {:?}: {}", flavor.name(), background, ratio);
+                assert_ne!(background, palette.surface0, "{} still gets a tint", flavor.name());
+            }
+        }
+    }
+
+    #[test]
+    fn legible_blend_uses_the_full_amount_when_it_can() {
+        assert_eq!(
+            legible_blend(MOCHA.surface0, MOCHA.red, 0.2, MOCHA.text),
+            blend(MOCHA.surface0, MOCHA.red, 0.2)
+        );
+        // Latte's dark text loses contrast as red darkens the light surface
+        let latte = legible_blend(LATTE.surface0, LATTE.red, 0.2, LATTE.text);
+        assert_ne!(latte, blend(LATTE.surface0, LATTE.red, 0.2));
+    }
+
+    #[test]
+    fn legible_blend_falls_back_to_the_surface() {
+        let grey = Color::Rgb(128, 128, 128);
+        assert_eq!(legible_blend(grey, Color::Rgb(130, 130, 130), 0.2, grey), grey);
+    }
+}
@@ -0,0 +1,144 @@
+//! Time limits for install runs
+//!
+//! Off unless `OMARCHY_CYBEX_TIMEOUT_MINUTES` or an option's `timeout_minutes`
+//! sets one (`timeout_minutes = 0` exempts an option from the global limit).
+//! Halfway through the limit a run is flagged as running longer than
+//! expected. At the limit the TUI asks whether to kill it, or kills it
+//! straight away with `OMARCHY_CYBEX_TIMEOUT_ACTION=kill`; headless runs and
+//! runs that lost their terminal have nobody to ask and are always killed.
+
+use std::env;
+use std::time::{Duration, Instant};
+
+use crate::options::InstallOption;
+
+/// What happens when a run reaches its limit in the TUI
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeoutAction {
+    /// Ask whether to kill it or keep waiting
+    #[default]
+    Ask,
+    /// Kill it without asking
+    Kill,
+}
+
+/// The configured limits
+#[derive(Debug, Clone, Default)]
+pub struct TimeoutPolicy {
+    /// Limit for options that don't set their own
+    pub default: Option<Duration>,
+    pub action: TimeoutAction,
+}
+
+impl TimeoutPolicy {
+    /// Read the policy from the environment, noting values that make no sense
+    pub fn from_env(warnings: &mut Vec<String>) -> Self {
+        let default = match env::var("OMARCHY_CYBEX_TIMEOUT_MINUTES") {
+            Ok(minutes) if minutes.trim() == "off" => None,
+            Ok(minutes) => match minutes.trim().parse::<u64>() {
+                Ok(0) => None,
+                Ok(minutes) => Some(Duration::from_secs(minutes * 60)),
+                Err(_) => {
+                    warnings.push(format!(
+                        "OMARCHY_CYBEX_TIMEOUT_MINUTES={:?} is not a number of minutes or 'off'; no timeout",
+                        minutes
+                    ));
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+        let action = match env::var("OMARCHY_CYBEX_TIMEOUT_ACTION") {
+            Ok(value) => match value.trim() {
+                "ask" => TimeoutAction::Ask,
+                "kill" => TimeoutAction::Kill,
+                _ => {
+                    warnings.push(format!(
+                        "OMARCHY_CYBEX_TIMEOUT_ACTION={:?} is not 'ask' or 'kill'; using 'ask'",
+                        value
+                    ));
+                    TimeoutAction::Ask
+                }
+            },
+            Err(_) => TimeoutAction::Ask,
+        };
+        Self { default, action }
+    }
+
+    /// Limit for a run of `option`, None when it may take as long as it likes
+    pub fn limit_for(&self, option: &InstallOption) -> Option<Duration> {
+        match option.timeout_minutes {
+            Some(0) => None,
+            Some(minutes) => Some(Duration::from_secs(minutes * 60)),
+            None => self.default,
+        }
+    }
+}
+
+/// How far a run has got towards its limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Overrun {
+    Within,
+    /// Past half the limit
+    Overdue,
+    /// Past the limit
+    Expired,
+}
+
+/// A running action's progress towards its limit
+#[derive(Debug, Clone)]
+pub struct RunWatch {
+    limit: Duration,
+    started: Instant,
+    stage: Overrun,
+}
+
+impl RunWatch {
+    pub fn new(limit: Duration, started: Instant) -> Self {
+        Self {
+            limit,
+            started,
+            stage: Overrun::Within,
+        }
+    }
+
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    /// Whether the run has been flagged as taking long
+    pub fn overdue(&self) -> bool {
+        self.stage >= Overrun::Overdue
+    }
+
+    /// Time until the next stage is reached, None once the limit has passed
+    pub fn until_next(&self, now: Instant) -> Option<Duration> {
+        let at = match self.stage {
+            Overrun::Within => self.limit / 2,
+            Overrun::Overdue => self.limit,
+            Overrun::Expired => return None,
+        };
+        Some(at.saturating_sub(now.saturating_duration_since(self.started)))
+    }
+
+    /// Move on to the stage `now` falls in, returning it when it changed
+    pub fn advance(&mut self, now: Instant) -> Option<Overrun> {
+        let elapsed = now.saturating_duration_since(self.started);
+        let stage = if elapsed >= self.limit {
+            Overrun::Expired
+        } else if elapsed >= self.limit / 2 {
+            Overrun::Overdue
+        } else {
+            Overrun::Within
+        };
+        (stage > self.stage).then(|| {
+            self.stage = stage;
+            stage
+        })
+    }
+}
+
+/// A limit as written in messages, e.g. "30 min"
+pub fn describe_limit(limit: Duration) -> String {
+    format!("{} min", limit.as_secs() / 60)
+}
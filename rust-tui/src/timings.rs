@@ -0,0 +1,245 @@
+//! Where the time of a run goes, stage by stage
+//!
+//! A run is timed from the moment it's set off until its outcome is handed
+//! over for saving. The wall time splits into three stages, run back to back:
+//! launching the script, the script running, and wrapping up after it exits
+//! (draining output, verifying, recording). Time to the first output line and
+//! time spent ingesting output are measured inside the running stage.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Instants the installer's threads note as they happen
+#[derive(Debug, Default)]
+pub struct StageClock {
+    first_output: Mutex<Option<Instant>>,
+    exited: Mutex<Option<Instant>>,
+}
+
+impl StageClock {
+    /// Note that output arrived, keeping the first time only
+    pub fn output(&self) {
+        if let Ok(mut first) = self.first_output.lock() {
+            first.get_or_insert_with(Instant::now);
+        }
+    }
+
+    /// Note that the child exited
+    pub fn exited(&self) {
+        if let Ok(mut exited) = self.exited.lock() {
+            *exited = Some(Instant::now());
+        }
+    }
+
+    fn first_output_at(&self) -> Option<Instant> {
+        self.first_output.lock().ok().and_then(|first| *first)
+    }
+
+    fn exited_at(&self) -> Option<Instant> {
+        self.exited.lock().ok().and_then(|exited| *exited)
+    }
+}
+
+/// Measures one run as it goes
+#[derive(Debug)]
+pub struct RunTimer {
+    requested: Instant,
+    spawned: Option<(Instant, Arc<StageClock>)>,
+    ingest: Duration,
+}
+
+impl RunTimer {
+    /// Start timing a run that is being set off now
+    pub fn start() -> Self {
+        Self {
+            requested: Instant::now(),
+            spawned: None,
+            ingest: Duration::ZERO,
+        }
+    }
+
+    /// Note the spawned child, whose clock tracks output and exit
+    pub fn spawned(&mut self, at: Instant, clock: Arc<StageClock>) {
+        self.spawned = Some((at, clock));
+    }
+
+    /// Add time spent handling the child's output
+    pub fn ingested(&mut self, took: Duration) {
+        self.ingest += took;
+    }
+
+    /// Stage timings as of now, or `None` if the child never ran
+    pub fn finish(&self) -> Option<StageTimings> {
+        let now = Instant::now();
+        let (spawned, clock) = self.spawned.as_ref()?;
+        let exited = clock.exited_at().unwrap_or(now);
+        Some(StageTimings {
+            launch_ms: millis(spawned.saturating_duration_since(self.requested)),
+            first_output_ms: clock
+                .first_output_at()
+                .map(|first| millis(first.saturating_duration_since(*spawned))),
+            running_ms: millis(exited.saturating_duration_since(*spawned)),
+            ingest_ms: millis(self.ingest),
+            wrap_up_ms: millis(now.saturating_duration_since(exited)),
+        })
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+/// How long each stage of a finished run took, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageTimings {
+    /// From setting the run off (journal, sandbox set-up) to the child spawning
+    pub launch_ms: u64,
+    /// From spawning to the first output read, if there was any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_output_ms: Option<u64>,
+    /// From spawning to the child exiting
+    pub running_ms: u64,
+    /// Time spent handling output while the child ran
+    pub ingest_ms: u64,
+    /// From the child exiting to the outcome being recorded, verify included
+    pub wrap_up_ms: u64,
+}
+
+impl StageTimings {
+    /// Running time not spent handling output, i.e. waiting on the script
+    pub fn idle_ms(&self) -> u64 {
+        self.running_ms.saturating_sub(self.ingest_ms)
+    }
+
+    /// Wall time of the whole run; the stages don't overlap
+    pub fn total_ms(&self) -> u64 {
+        self.launch_ms + self.running_ms + self.wrap_up_ms
+    }
+
+    /// Share of the wall time spent outside the script itself
+    pub fn overhead_ms(&self) -> u64 {
+        self.launch_ms + self.ingest_ms + self.wrap_up_ms
+    }
+
+    /// Mean of each stage over several runs
+    pub fn mean<'a>(runs: impl IntoIterator<Item = &'a StageTimings>) -> Option<StageTimings> {
+        let mut count = 0u64;
+        let mut sum = StageTimings::default();
+        let (mut firsts, mut first_sum) = (0u64, 0u64);
+        for run in runs {
+            count += 1;
+            sum.launch_ms += run.launch_ms;
+            sum.running_ms += run.running_ms;
+            sum.ingest_ms += run.ingest_ms;
+            sum.wrap_up_ms += run.wrap_up_ms;
+            if let Some(first) = run.first_output_ms {
+                firsts += 1;
+                first_sum += first;
+            }
+        }
+        (count > 0).then(|| StageTimings {
+            launch_ms: sum.launch_ms / count,
+            first_output_ms: (firsts > 0).then(|| first_sum / firsts),
+            running_ms: sum.running_ms / count,
+            ingest_ms: sum.ingest_ms / count,
+            wrap_up_ms: sum.wrap_up_ms / count,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Plays the installer's part: output after `quiet`, exit after `busy` more
+    fn fake_installer(clock: Arc<StageClock>, quiet: Duration, busy: Duration) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            thread::sleep(quiet);
+            clock.output();
+            thread::sleep(busy / 2);
+            clock.output();
+            thread::sleep(busy / 2);
+            clock.exited();
+        })
+    }
+
+    #[test]
+    fn stages_of_a_run_add_up_to_its_wall_time() {
+        let wall = Instant::now();
+        let mut timer = RunTimer::start();
+        thread::sleep(Duration::from_millis(30));
+
+        let clock = Arc::new(StageClock::default());
+        timer.spawned(Instant::now(), Arc::clone(&clock));
+        let installer = fake_installer(clock, Duration::from_millis(40), Duration::from_millis(80));
+        installer.join().unwrap();
+        timer.ingested(Duration::from_millis(7));
+        timer.ingested(Duration::from_millis(8));
+
+        thread::sleep(Duration::from_millis(50));
+        let timings = timer.finish().unwrap();
+        let wall_ms = millis(wall.elapsed());
+
+        assert!(timings.launch_ms >= 30, "{:?}", timings);
+        let first = timings.first_output_ms.unwrap();
+        assert!(first >= 40, "the first output is kept: {:?}", timings);
+        assert!(first < timings.running_ms, "{:?}", timings);
+        assert!(timings.running_ms >= 120, "{:?}", timings);
+        assert_eq!(timings.ingest_ms, 15);
+        assert_eq!(timings.idle_ms(), timings.running_ms - 15);
+        assert!(timings.wrap_up_ms >= 50, "{:?}", timings);
+        assert_eq!(timings.overhead_ms(), timings.launch_ms + 15 + timings.wrap_up_ms);
+
+        // Stages run back to back, so only rounding and the instants around the timer go missing
+        assert!(timings.total_ms() <= wall_ms, "{} > {}", timings.total_ms(), wall_ms);
+        assert!(wall_ms - timings.total_ms() <= 20, "{} vs {}", timings.total_ms(), wall_ms);
+    }
+
+    #[test]
+    fn unfinished_and_silent_runs() {
+        let mut timer = RunTimer::start();
+        assert_eq!(timer.finish(), None, "nothing was spawned");
+
+        let clock = Arc::new(StageClock::default());
+        timer.spawned(Instant::now(), Arc::clone(&clock));
+        thread::sleep(Duration::from_millis(20));
+        let running = timer.finish().unwrap();
+        assert_eq!(running.first_output_ms, None);
+        assert!(running.running_ms >= 20, "a child still running counts up to now");
+        assert_eq!(running.wrap_up_ms, 0);
+    }
+
+    #[test]
+    fn mean_averages_each_stage() {
+        let runs = [
+            StageTimings {
+                launch_ms: 10,
+                first_output_ms: Some(100),
+                running_ms: 1000,
+                ingest_ms: 40,
+                wrap_up_ms: 20,
+            },
+            StageTimings {
+                launch_ms: 30,
+                first_output_ms: None,
+                running_ms: 3000,
+                ingest_ms: 60,
+                wrap_up_ms: 40,
+            },
+        ];
+        assert_eq!(
+            StageTimings::mean(&runs),
+            Some(StageTimings {
+                launch_ms: 20,
+                first_output_ms: Some(100),
+                running_ms: 2000,
+                ingest_ms: 50,
+                wrap_up_ms: 30,
+            })
+        );
+        assert_eq!(StageTimings::mean(&[]), None);
+    }
+}
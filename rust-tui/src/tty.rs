@@ -0,0 +1,208 @@
+//! Choosing where the TUI draws
+//!
+//! Normally that's stdout. When stdout is redirected (say, piped into `tee`
+//! out of habit) escape sequences would go into the pipe while raw mode
+//! still grabs the keyboard, so the UI draws on the controlling terminal
+//! through `/dev/tty` instead, or refuses to start if the user prefers.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, IsTerminal, Stdout, Write};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crossterm::{
+    cursor::Show,
+    event::{
+        DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+
+/// What to do when stdout isn't a terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedirectPolicy {
+    /// Draw on /dev/tty
+    #[default]
+    Tty,
+    /// Exit with a message pointing at the headless flags
+    Refuse,
+}
+
+impl RedirectPolicy {
+    /// Parse the `OMARCHY_CYBEX_REDIRECTED` value
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "tty" => Some(RedirectPolicy::Tty),
+            "refuse" => Some(RedirectPolicy::Refuse),
+            _ => None,
+        }
+    }
+}
+
+/// Where the TUI should draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalTarget {
+    Stdout,
+    DevTty,
+    Refuse,
+}
+
+/// Decide where to draw given whether stdout is a terminal
+pub fn terminal_target(stdout_is_tty: bool, policy: RedirectPolicy) -> TerminalTarget {
+    match (stdout_is_tty, policy) {
+        (true, _) => TerminalTarget::Stdout,
+        (false, RedirectPolicy::Tty) => TerminalTarget::DevTty,
+        (false, RedirectPolicy::Refuse) => TerminalTarget::Refuse,
+    }
+}
+
+/// Whether stdout is a terminal
+pub fn stdout_is_tty() -> bool {
+    io::stdout().is_terminal()
+}
+
+/// Set once the TUI draws on /dev/tty, so restoring writes there too
+static USING_DEV_TTY: AtomicBool = AtomicBool::new(false);
+
+/// Output the terminal backend writes to
+pub enum TerminalOut {
+    Stdout(Stdout),
+    Tty(File),
+}
+
+impl TerminalOut {
+    /// Open the output for a target other than `Refuse`
+    pub fn open(target: TerminalTarget) -> io::Result<Self> {
+        if target == TerminalTarget::DevTty {
+            let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+            USING_DEV_TTY.store(true, Ordering::Relaxed);
+            Ok(TerminalOut::Tty(tty))
+        } else {
+            Ok(TerminalOut::Stdout(io::stdout()))
+        }
+    }
+
+    /// The output chosen earlier by `open`, for restoring the terminal
+    pub fn current() -> Self {
+        if USING_DEV_TTY.load(Ordering::Relaxed) {
+            if let Ok(tty) = OpenOptions::new().write(true).open("/dev/tty") {
+                return TerminalOut::Tty(tty);
+            }
+        }
+        TerminalOut::Stdout(io::stdout())
+    }
+
+    /// Whether this draws on /dev/tty rather than stdout
+    pub fn is_dev_tty(&self) -> bool {
+        matches!(self, TerminalOut::Tty(_))
+    }
+}
+
+impl Write for TerminalOut {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            TerminalOut::Stdout(out) => out.write(buf),
+            TerminalOut::Tty(out) => out.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            TerminalOut::Stdout(out) => out.flush(),
+            TerminalOut::Tty(out) => out.flush(),
+        }
+    }
+}
+
+/// Stdin, stdout and stderr for a program run on the TUI's terminal
+///
+/// Inherited when the TUI draws on stdout, /dev/tty otherwise.
+pub fn terminal_stdio() -> io::Result<(Stdio, Stdio, Stdio)> {
+    if !USING_DEV_TTY.load(Ordering::Relaxed) {
+        return Ok((Stdio::inherit(), Stdio::inherit(), Stdio::inherit()));
+    }
+    let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    Ok((tty.try_clone()?.into(), tty.try_clone()?.into(), tty.into()))
+}
+
+/// Whether kitty keyboard flags were pushed and need popping on exit
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+
+/// Set the terminal up for the TUI: raw mode, the alternate screen, mouse
+/// and focus reporting
+pub fn enter_tui(out: &mut TerminalOut) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, EnableMouseCapture, EnableFocusChange)?;
+
+    // Ask for press/repeat/release reporting where the kitty protocol is supported;
+    // the support query only works on stdout
+    let keyboard_enhanced = !out.is_dev_tty()
+        && supports_keyboard_enhancement().unwrap_or(false)
+        && execute!(
+            out,
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            )
+        )
+        .is_ok();
+    KEYBOARD_ENHANCED.store(keyboard_enhanced, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Undo the terminal setup, on exit, from the panic hook and before
+/// stepping out to a shell
+///
+/// Errors are ignored: this is best effort, and the panic path has nowhere
+/// to report them.
+pub fn restore_terminal() {
+    let mut out = TerminalOut::current();
+    if KEYBOARD_ENHANCED.swap(false, Ordering::Relaxed) {
+        let _ = execute!(out, PopKeyboardEnhancementFlags);
+    }
+    let _ = disable_raw_mode();
+    let _ = execute!(out, DisableFocusChange, DisableMouseCapture, LeaveAlternateScreen, Show);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terminal_target_decision_table() {
+        for (is_tty, policy, target) in [
+            (true, RedirectPolicy::Tty, TerminalTarget::Stdout),
+            (true, RedirectPolicy::Refuse, TerminalTarget::Stdout),
+            (false, RedirectPolicy::Tty, TerminalTarget::DevTty),
+            (false, RedirectPolicy::Refuse, TerminalTarget::Refuse),
+        ] {
+            assert_eq!(terminal_target(is_tty, policy), target, "tty {} with {:?}", is_tty, policy);
+        }
+    }
+
+    #[test]
+    fn redirected_stdout_draws_on_dev_tty_by_default() {
+        assert_eq!(terminal_target(false, RedirectPolicy::default()), TerminalTarget::DevTty);
+    }
+
+    #[test]
+    fn policy_parses_the_environment_value() {
+        assert_eq!(RedirectPolicy::parse("tty"), Some(RedirectPolicy::Tty));
+        assert_eq!(RedirectPolicy::parse(" refuse\n"), Some(RedirectPolicy::Refuse));
+        for value in ["", "Refuse", "stdout", "no"] {
+            assert_eq!(RedirectPolicy::parse(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn stdout_target_writes_to_stdout() {
+        let out = TerminalOut::open(TerminalTarget::Stdout).unwrap();
+        assert!(!out.is_dev_tty());
+        assert!(!TerminalOut::current().is_dev_tty(), "nothing switched to /dev/tty");
+    }
+}
@@ -0,0 +1,173 @@
+//! Detail pane for the selected option
+
+use chrono::{DateTime, Local, Utc};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+use super::format_typical;
+use super::glyphs::GLYPHS;
+use crate::config::{OptionRecord, RunAction};
+use crate::export::shell_quote;
+use crate::installer::command_line;
+use crate::script_env::script_env;
+use crate::state::AppState;
+use crate::theme::THEME;
+
+/// Width from which the pane opens on its own while the output is hidden
+pub const DETAIL_AUTO_WIDTH: u16 = 120;
+
+/// Local time of a recorded timestamp
+fn format_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// A "label: value" row
+fn field<'a>(label: &'a str, value: String, color: Color) -> Line<'a> {
+    Line::from(vec![
+        Span::styled(format!("{:<10}", label), Style::default().fg(THEME.overlay1)),
+        Span::styled(value, Style::default().fg(color)),
+    ])
+}
+
+/// Render full details of the selected option
+pub fn render_detail_panel(frame: &mut Frame, area: Rect, state: &AppState) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(THEME.mauve))
+        .border_type(GLYPHS.border)
+        .title(" Details ")
+        .title_style(Style::default().fg(THEME.pink))
+        .style(Style::default().bg(THEME.mantle));
+
+    let Some(option) = state.selected_option() else {
+        frame.render_widget(block, area);
+        return;
+    };
+    let record = state.records.get(&option.id);
+    let installed = state.is_installed(&option.id);
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            option.name.as_str(),
+            Style::default().fg(THEME.text).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(option.description.as_str(), Style::default().fg(THEME.subtext0))),
+        Line::default(),
+        field("Id", option.id.clone(), THEME.text),
+        field(
+            "Reboot",
+            if option.requires_reboot { "required" } else { "not needed" }.to_string(),
+            if option.requires_reboot { THEME.yellow } else { THEME.text },
+        ),
+    ];
+
+    let installed_at = record.and_then(|r| r.installed_at);
+    let marked = record.is_some_and(OptionRecord::marked_by_hand);
+    lines.push(match (installed, installed_at, marked) {
+        (true, Some(at), true) => field(
+            "Installed",
+            format!("{} (marked by hand, nothing ran)", format_time(at)),
+            THEME.yellow,
+        ),
+        (true, _, true) => field("Installed", "yes (marked by hand, nothing ran)".to_string(), THEME.yellow),
+        (true, Some(at), false) => field("Installed", format_time(at), THEME.green),
+        (true, None, false) => field("Installed", "yes (no install time recorded)".to_string(), THEME.green),
+        (false, _, true) => field("Installed", "no (marked by hand, nothing ran)".to_string(), THEME.yellow),
+        (false, _, false) => field("Installed", "no".to_string(), THEME.overlay0),
+    });
+
+    if let Some(record) = record {
+        if let (Some(at), Some(completion)) = (record.last_run_at, record.last_completion()) {
+            let action = match record.last_action {
+                Some(RunAction::Uninstall) => "uninstall",
+                _ => "install",
+            };
+            lines.push(field(
+                "Last run",
+                format!("{} {}, {}", action, format_time(at), completion),
+                if completion.success() { THEME.text } else { THEME.red },
+            ));
+        }
+
+        // Typical durations of successful runs, each action on its own
+        let usual: Vec<String> = [(RunAction::Install, "install"), (RunAction::Uninstall, "uninstall")]
+            .into_iter()
+            .filter_map(|(action, name)| {
+                record
+                    .typical_duration(action)
+                    .map(|took| format!("{} usually takes {}", name, format_typical(took)))
+            })
+            .collect();
+        if !usual.is_empty() {
+            lines.push(field("Duration", usual.join(", "), THEME.text));
+        }
+    }
+
+    if let Some(versions) = state.versions.get(&option.id).filter(|_| installed) {
+        lines.push(if versions.update_available() {
+            field(
+                "Upstream",
+                format!("{} installed, {} available", versions.installed, versions.available),
+                THEME.blue,
+            )
+        } else {
+            field("Upstream", format!("{} (up to date)", versions.installed), THEME.text)
+        });
+    }
+
+    if state.unsupported.contains(&option.id) {
+        lines.push(field("Script", "not supported by this script version".to_string(), THEME.red));
+    }
+
+    if let Some(issue) = state.compat_issue(option) {
+        lines.push(field("Version", issue, THEME.peach));
+    }
+
+    if state.sandbox.unavailable_for(option) {
+        lines.push(field("Sandbox", "wanted, but bwrap/firejail not installed".to_string(), THEME.red));
+    } else if let Some(sandbox) = state.sandbox.sandbox_for(&state.script_dir, option) {
+        let network = if sandbox.network { "with network" } else { "no network" };
+        lines.push(field("Sandbox", network.to_string(), THEME.green));
+        lines.push(Line::from(Span::styled(
+            sandbox.preview(&state.entry_script, &[option.id.clone().into()]),
+            Style::default().fg(THEME.overlay1),
+        )));
+    }
+
+    // What the install and uninstall actions run, exactly
+    lines.push(Line::default());
+    lines.push(Line::from(Span::styled(
+        if installed { "Commands" } else { "Command" },
+        Style::default().fg(THEME.overlay1),
+    )));
+    lines.push(Line::from(Span::styled(
+        command_line(&state.entry_script, &option.id, false),
+        Style::default().fg(THEME.peach),
+    )));
+    if installed {
+        lines.push(Line::from(Span::styled(
+            command_line(&state.entry_script, &option.id, true),
+            Style::default().fg(THEME.peach),
+        )));
+    }
+    if !option.post_install.is_empty() {
+        lines.push(Line::from(Span::styled("After install", Style::default().fg(THEME.overlay1))));
+        for hook in &option.post_install {
+            lines.push(Line::from(Span::styled(hook.as_str(), Style::default().fg(THEME.peach))));
+        }
+    }
+
+    // Set on top of the installer's own environment
+    lines.push(Line::from(Span::styled("Environment", Style::default().fg(THEME.overlay1))));
+    for (name, value) in script_env(&state.script_env, option) {
+        lines.push(Line::from(Span::styled(
+            format!("{}={}", name, shell_quote(&value)),
+            Style::default().fg(THEME.text),
+        )));
+    }
+
+    let paragraph = Paragraph::new(lines).block(block).wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
@@ -5,36 +5,129 @@ use ratatui::{
     widgets::Paragraph,
 };
 
+use crate::keymap::Action;
+use crate::registry::{self, Entry, Scope};
 use crate::state::{AppMode, AppState};
 use crate::theme::THEME;
 
+/// Footer items for a scope: the registry's available entries that have a footer label
+///
+/// Neighbours sharing a label are joined, e.g. "↑/↓: Navigate".
+fn registry_keys(state: &AppState, scope: Scope) -> Vec<(String, &'static str)> {
+    let shown: Vec<&Entry> = registry::entries(scope)
+        .filter(|entry| entry.footer.is_some() && (entry.available)(state))
+        .collect();
+    let footer_label = |entry: &Entry| entry.footer.map_or("", |label| label(state));
+    registry::runs(&shown, footer_label)
+        .into_iter()
+        .filter_map(|run| {
+            let keys: Vec<String> = run
+                .iter()
+                .filter_map(|entry| {
+                    registry::key_labels(&state.keymap, scope, entry.action).into_iter().next()
+                })
+                .collect();
+            (!keys.is_empty()).then(|| (keys.join("/"), footer_label(run[0])))
+        })
+        .collect()
+}
+
 /// Render the footer with key bindings
 pub fn render_footer(frame: &mut Frame, area: Rect, state: &AppState) {
-    let keys = match state.mode {
-        AppMode::Installing => vec![
-            ("", "Installing..."),
-        ],
-        AppMode::ConfirmAction => vec![
-            ("↑/↓", "Select"),
-            ("Enter", "Confirm"),
-            ("Esc", "Cancel"),
-        ],
-        AppMode::Normal | AppMode::Completed => {
-            if state.show_output {
-                vec![
-                    ("q", "Quit"),
-                    ("↑/↓", "Navigate"),
-                    ("Enter", "Install/Uninstall"),
-                    ("Esc", "Hide Output"),
-                ]
+    let keymap = &state.keymap;
+    let key = |action| keymap.label(action);
+    let confirm = key(Action::Confirm);
+    let cancel = key(Action::Cancel);
+    let select = format!("{}/{}", key(Action::Up), key(Action::Down));
+
+    let keys: Vec<(String, &str)> = match state.mode {
+        AppMode::Installing => {
+            let typing = if state.input_echo {
+                "Typing goes to the installer"
             } else {
-                vec![
-                    ("q", "Quit"),
-                    ("↑/↓", "Navigate"),
-                    ("Enter", "Install/Uninstall"),
-                ]
+                "Password input hidden"
+            };
+            let mut keys = vec![(String::new(), typing)];
+            keys.extend(registry_keys(state, Scope::Installing));
+            keys
+        }
+        AppMode::Filter => vec![
+            (String::new(), "Type to filter"),
+            ("↑/↓".into(), "Navigate"),
+            ("Enter".into(), "Confirm"),
+            ("Esc".into(), "Clear"),
+        ],
+        AppMode::Jump => vec![
+            (String::new(), "Type part of a name"),
+            ("Enter/Esc".into(), "Done"),
+        ],
+        AppMode::Profiles => vec![
+            (select.clone(), "Profile"),
+            (confirm.clone(), "Install Missing"),
+            (cancel.clone(), "Cancel"),
+        ],
+        AppMode::LogBrowser => vec![
+            (select.clone(), "Run"),
+            (confirm.clone(), "View Log"),
+            (cancel.clone(), "Close"),
+        ],
+        AppMode::QueueSummary => {
+            let focused = state.queue_results.get(state.summary_focus);
+            let mut keys = vec![(select.clone(), "Run")];
+            if focused.is_some_and(|result| result.failed() && result.log.is_some()) {
+                keys.push(("l".into(), "Open Log"));
             }
+            keys.push((cancel.clone(), "Close"));
+            keys
         }
+        AppMode::Command => vec![
+            ("Tab".into(), "Complete"),
+            ("↑/↓".into(), "History"),
+            ("Enter".into(), "Run"),
+            ("Esc".into(), "Cancel"),
+        ],
+        AppMode::Layout => vec![
+            ("Tab".into(), "Focus"),
+            ("←/→".into(), "Move"),
+            ("↑/↓".into(), "Resize"),
+            ("w".into(), "Next Layout"),
+            ("Enter".into(), "Save"),
+            ("Esc".into(), "Revert"),
+        ],
+        AppMode::ConfirmDependencies => vec![(confirm, "Install All"), (cancel, "Cancel")],
+        AppMode::ConfirmUninstall => vec![(confirm, "Confirm Uninstall"), (cancel, "Cancel")],
+        AppMode::ConfirmEditorLocks => vec![(confirm, "Proceed"), (cancel, "Cancel")],
+        AppMode::ConfirmReboot => vec![(confirm, "Reboot"), (cancel, "Cancel")],
+        AppMode::ConfirmUpdate => vec![(confirm, "Update"), (cancel, "Cancel")],
+        AppMode::ConfirmReinstall => vec![(confirm, "Queue Reinstall"), (cancel, "Skip")],
+        AppMode::ConfirmIncompatible => vec![(confirm, "Proceed"), (cancel, "Cancel")],
+        AppMode::PendingItems => vec![
+            ("↑/↓".into(), "Item"),
+            ("←/→".into(), "Choice"),
+            ("Enter".into(), "Apply"),
+            ("Esc".into(), "Decide Later"),
+        ],
+        AppMode::ConfirmQuit => vec![(confirm, "Abort and Quit"), (cancel, "Keep Running")],
+        AppMode::ConfirmTimeout => vec![(confirm, "Kill"), (cancel, "Keep Waiting")],
+        AppMode::Unsupported => vec![(cancel, "Close")],
+        AppMode::PasswordPrompt => vec![
+            (String::new(), "Typing is hidden"),
+            ("Enter".into(), "Send"),
+            ("Esc".into(), "Close"),
+        ],
+        AppMode::ConfirmUnsandboxed => vec![(confirm, "Run Unsandboxed"), (cancel, "Cancel")],
+        AppMode::Help => vec![(String::new(), "Press any key to close help")],
+        AppMode::Timings => vec![(String::new(), "Press any key to close timings")],
+        AppMode::StartupWarnings => vec![(String::new(), "Press any key to continue")],
+        AppMode::TooOld => vec![(String::new(), "Press any key to quit")],
+        AppMode::ConfirmAction
+        | AppMode::ConfirmMark
+        | AppMode::ConfirmLocalChanges => vec![
+            (select, "Select"),
+            (confirm, "Confirm"),
+            (cancel, "Cancel"),
+        ],
+        AppMode::Normal => registry_keys(state, Scope::Browsing),
     };
 
     let spans: Vec<Span> = keys
@@ -46,7 +139,7 @@ pub fn render_footer(frame: &mut Frame, area: Rect, state: &AppState) {
                 spans.push(Span::styled(" │ ", Style::default().fg(THEME.overlay0)));
             }
             if !key.is_empty() {
-                spans.push(Span::styled(*key, Style::default().fg(THEME.mauve)));
+                spans.push(Span::styled(key.as_str(), Style::default().fg(THEME.mauve)));
                 spans.push(Span::styled(": ", Style::default().fg(THEME.overlay0)));
             }
             spans.push(Span::styled(*desc, Style::default().fg(THEME.text)));
@@ -0,0 +1,66 @@
+//! Shared text formatting helpers
+
+use std::path::Path;
+use std::time::Duration;
+
+use super::glyphs::GLYPHS;
+
+/// Format a duration compactly, e.g. "45s", "2m 14s" or "1h 03m"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+/// Format a typical duration, e.g. "~1m 40s", or "<1s" for quick runs
+pub fn format_typical(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        "<1s".to_string()
+    } else {
+        format!("~{}", format_duration(duration))
+    }
+}
+
+/// Format a running time as a clock, e.g. "02:14", or "1:02:14" past an hour
+pub fn format_clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 3600 {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    } else {
+        format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+    }
+}
+
+/// Spinner frame for the event loop's tick counter, so all spinners move together
+pub fn spinner_frame(tick: usize) -> char {
+    let frames = GLYPHS.spinner;
+    frames[tick % frames.len()]
+}
+
+/// Format a count with thousands separators, e.g. "1,742"
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            result.push(',');
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Display a path, abbreviating the home directory to `~`
+pub fn display_path(path: &Path) -> String {
+    if let Some(home) = dirs::home_dir() {
+        if let Ok(rest) = path.strip_prefix(&home) {
+            return Path::new("~").join(rest).display().to_string();
+        }
+    }
+    path.display().to_string()
+}
@@ -0,0 +1,114 @@
+//! Symbols the UI draws with
+//!
+//! Rounded borders, block scrollbars and the other Unicode symbols come out
+//! mangled on some terminals (mosh is a common one). `--plain` switches the
+//! session to [`ASCII`]; otherwise [`GLYPHS`] is [`UNICODE`].
+
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+use ratatui::widgets::BorderType;
+
+/// One set of symbols
+pub struct Glyphs {
+    /// Panel and popup borders
+    pub border: BorderType,
+    pub scroll_begin: &'static str,
+    pub scroll_end: &'static str,
+    pub scroll_track: &'static str,
+    pub scroll_thumb: &'static str,
+    /// Status column of an installed option
+    pub installed: &'static str,
+    /// Status column of an option that isn't installed
+    pub not_installed: &'static str,
+    /// Status column of an option whose last attempt failed
+    pub failed: &'static str,
+    /// Badge of an installed option with a newer version available
+    pub update: &'static str,
+    /// Status column of an option that is simply run again
+    pub rerun: &'static str,
+    pub queued: &'static str,
+    pub unqueued: &'static str,
+    /// Category heading markers
+    pub folded: &'static str,
+    pub unfolded: &'static str,
+    /// Marker of the focused item in the layout editor
+    pub pointer: &'static str,
+    /// Cursor at the end of the filter input
+    pub cursor: &'static str,
+    /// Output panel hints for following and for having scrolled up
+    pub following: &'static str,
+    pub scrolled_up: &'static str,
+    /// Frames of the run spinner
+    pub spinner: &'static [char],
+}
+
+/// The default symbols
+pub const UNICODE: Glyphs = Glyphs {
+    border: BorderType::Rounded,
+    scroll_begin: "▲",
+    scroll_end: "▼",
+    scroll_track: "│",
+    scroll_thumb: "█",
+    installed: "[OK]",
+    not_installed: "[ ]",
+    failed: "[!!]",
+    update: " ↑ update",
+    rerun: " ↻",
+    queued: "● ",
+    unqueued: "○ ",
+    folded: "▸ ",
+    unfolded: "▾ ",
+    pointer: "▸ ",
+    cursor: "█",
+    following: "▼",
+    scrolled_up: "↑",
+    spinner: &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'],
+};
+
+/// Symbols for terminals that only draw ASCII well (`--plain`)
+///
+/// Borders stay box-drawing lines, which survive where rounded corners don't.
+pub const ASCII: Glyphs = Glyphs {
+    border: BorderType::Plain,
+    scroll_begin: "^",
+    scroll_end: "v",
+    scroll_track: "|",
+    scroll_thumb: "#",
+    installed: "[x]",
+    not_installed: "[ ]",
+    failed: "[!!]",
+    update: " ^ update",
+    rerun: " ~",
+    queued: "* ",
+    unqueued: "  ",
+    folded: "+ ",
+    unfolded: "- ",
+    pointer: "> ",
+    cursor: "_",
+    following: "v",
+    scrolled_up: "^",
+    spinner: &['|', '/', '-', '\\'],
+};
+
+/// Symbols chosen for the session
+static ACTIVE: OnceLock<&'static Glyphs> = OnceLock::new();
+
+/// The session's symbols, Unicode unless [`use_ascii`] was called
+pub struct ActiveGlyphs;
+
+impl Deref for ActiveGlyphs {
+    type Target = Glyphs;
+
+    fn deref(&self) -> &Glyphs {
+        ACTIVE.get().copied().unwrap_or(&UNICODE)
+    }
+}
+
+/// Symbols every UI component draws with
+pub static GLYPHS: ActiveGlyphs = ActiveGlyphs;
+
+/// Draw with [`ASCII`] for the rest of the session
+pub fn use_ascii() {
+    let _ = ACTIVE.set(&ASCII);
+}
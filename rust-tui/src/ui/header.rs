@@ -5,10 +5,11 @@ use ratatui::{
     widgets::{Block, Paragraph},
 };
 
+use crate::state::AppState;
 use crate::theme::THEME;
 
 /// ASCII art logo - all lines padded to same width for proper centering
-const ASCII_ART: [&str; 7] = [
+pub(super) const ASCII_ART: [&str; 7] = [
     "                     $$a.                                          ",
     "                      `$$$                                         ",
     " .a&$$$&a, a$$a..a$$a. `$$bd$$$&a,    .a&$\"\"$&a     .a$$a..a$$a. ",
@@ -18,12 +19,54 @@ const ASCII_ART: [&str; 7] = [
     "             @$$P                                                  ",
 ];
 
-/// Render the header banner
-pub fn render_header(frame: &mut Frame, area: Rect) {
-    let lines: Vec<Line> = ASCII_ART
-        .iter()
-        .map(|line| Line::from(Span::styled(*line, Style::default().fg(THEME.mauve))))
-        .collect();
+/// Rows the full banner needs: the art, a gap and the info line
+pub const HEADER_HEIGHT: u16 = ASCII_ART.len() as u16 + 2;
+
+/// Render the header banner with the base version info line
+///
+/// An area shorter than [`HEADER_HEIGHT`] gets a one-line title in place of
+/// the art.
+pub fn render_header(frame: &mut Frame, area: Rect, state: &AppState) {
+    let base = match &state.base_version {
+        Some(version) => Span::styled(format!("Omarchy {}", version), Style::default().fg(THEME.subtext0)),
+        None => Span::styled(
+            "unknown base version — checks skipped",
+            Style::default().fg(THEME.overlay0),
+        ),
+    };
+    let info = vec![
+        base,
+        Span::styled(" · ", Style::default().fg(THEME.overlay0)),
+        Span::styled(state.features.describe(), Style::default().fg(THEME.subtext0)),
+    ];
+
+    let root = Span::styled(
+        " RUNNING AS ROOT — files written now will be owned by root ",
+        Style::default().fg(THEME.base).bg(THEME.red).add_modifier(Modifier::BOLD),
+    );
+
+    let lines = if area.height < HEADER_HEIGHT {
+        let mut title = vec![
+            Span::styled("omarchy-cybex", Style::default().fg(THEME.mauve).add_modifier(Modifier::BOLD)),
+            Span::styled(" · ", Style::default().fg(THEME.overlay0)),
+        ];
+        // Root goes first, so a narrow terminal cuts the info instead
+        if state.as_root {
+            title.insert(0, Span::styled(" ROOT ", root.style));
+            title.insert(1, Span::raw(" "));
+        }
+        title.extend(info);
+        vec![Line::from(title)]
+    } else {
+        let mut lines: Vec<Line> = ASCII_ART
+            .iter()
+            .map(|line| Line::from(Span::styled(*line, Style::default().fg(THEME.mauve))))
+            .collect();
+        // The warning takes the spacer line, so the header keeps its height
+        lines.push(if state.as_root { Line::from(root) } else { Line::default() });
+        lines.push(Line::from(info));
+        lines
+    };
 
     let paragraph = Paragraph::new(lines)
         .block(Block::default())
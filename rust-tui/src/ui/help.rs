@@ -0,0 +1,103 @@
+//! Keybinding help overlay
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use super::display_path;
+use super::glyphs::GLYPHS;
+use super::popup::centered_area;
+use crate::config::state_file_path;
+use crate::logs::log_dir;
+use crate::registry::{self, Entry, Group, ENTRIES};
+use crate::state::AppState;
+use crate::theme::THEME;
+
+/// Rows the registry can't supply: keys the terminal, not a binding, decides
+const INSTALLING_NOTES: &[(&str, &str)] = &[("Typing", "Sent to the script"), ("Ctrl+C", "Quit")];
+
+/// Width of the key column
+const KEY_WIDTH: usize = 14;
+/// Width of the description column
+const DESC_WIDTH: usize = 24;
+
+/// Every registry entry of a group with its keys, e.g. ("↑/↓ k/j", "Navigate")
+///
+/// Neighbours sharing a label are listed together, and actions left without
+/// a usable key are skipped.
+fn group_bindings(state: &AppState, group: Group) -> Vec<(String, &'static str)> {
+    let scope = group.scope();
+    let entries: Vec<&Entry> = ENTRIES.iter().filter(|entry| entry.group == group).collect();
+    registry::runs(&entries, |entry| entry.label)
+        .into_iter()
+        .filter_map(|run| {
+            let keys: Vec<Vec<String>> = run
+                .iter()
+                .map(|entry| registry::key_labels(&state.keymap, scope, entry.action))
+                .collect();
+            let depth = keys.iter().map(Vec::len).max().unwrap_or(0);
+            let columns: Vec<String> = (0..depth)
+                .map(|i| keys.iter().filter_map(|k| k.get(i).cloned()).collect::<Vec<_>>().join("/"))
+                .collect();
+            (!columns.is_empty()).then(|| (columns.join(" "), run[0].label))
+        })
+        .collect()
+}
+
+/// Render the help overlay
+pub fn render_help(frame: &mut Frame, state: &AppState) {
+    let key_style = Style::default().fg(THEME.mauve);
+    let desc_style = Style::default().fg(THEME.text);
+    let heading_style = Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD);
+    let label_style = Style::default().fg(THEME.overlay1);
+
+    let mut lines = Vec::new();
+    for group in Group::ALL {
+        let mut bindings = group_bindings(state, group);
+        if group == Group::Installing {
+            bindings.extend(INSTALLING_NOTES.iter().map(|&(key, desc)| (key.to_string(), desc)));
+        }
+        lines.push(Line::from(Span::styled(group.heading(), heading_style)));
+        for pair in bindings.chunks(2) {
+            let mut spans = vec![Span::raw("  ")];
+            for (key, desc) in pair {
+                spans.push(Span::styled(format!("{:<KEY_WIDTH$}", key), key_style));
+                spans.push(Span::styled(format!("{:<DESC_WIDTH$}", desc), desc_style));
+            }
+            lines.push(Line::from(spans));
+        }
+    }
+
+    lines.push(Line::default());
+    let paths = [
+        ("State file", display_path(&state_file_path())),
+        ("Logs", display_path(&log_dir())),
+        ("Scripts", display_path(&state.entry_script)),
+    ];
+    for (label, path) in paths {
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:<KEY_WIDTH$} ", label), label_style),
+            Span::styled(path, desc_style),
+        ]));
+    }
+    lines.push(Line::default());
+    lines.push(Line::from(Span::styled(
+        "Press any key to close",
+        Style::default().fg(THEME.overlay0),
+    )));
+
+    let popup_area = centered_area(frame.area(), 80, lines.len() as u16 + 2);
+    frame.render_widget(Clear, popup_area);
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(GLYPHS.border)
+            .border_style(Style::default().fg(THEME.mauve))
+            .title(" Help ")
+            .title_style(Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD))
+            .style(Style::default().bg(THEME.base)),
+    );
+    frame.render_widget(paragraph, popup_area);
+}
@@ -0,0 +1,165 @@
+//! Compact zen-mode HUD shown during long installs
+
+use std::time::Instant;
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Paragraph},
+};
+
+use super::{format_duration, spinner_frame};
+use super::output_panel::strip_ansi_codes;
+use crate::state::AppState;
+use crate::theme::THEME;
+
+/// Below this width the error/warning counts are dropped
+const NARROW_WIDTH: u16 = 60;
+
+/// Render the two-line HUD at the bottom of an otherwise empty screen
+pub fn render_hud(frame: &mut Frame, state: &AppState) {
+    let area = frame.area();
+    frame.render_widget(Block::default().style(Style::default().bg(THEME.base)), area);
+
+    let hud_area = Rect {
+        y: area.y + area.height.saturating_sub(2),
+        height: area.height.min(2),
+        ..area
+    };
+
+    let elapsed = state.run_elapsed(Instant::now()).unwrap_or_default();
+    let spinner = spinner_frame(state.spinner_tick);
+    let action = state.current_action.as_deref().unwrap_or("Idle");
+
+    let mut status = vec![
+        Span::styled(format!(" {} ", spinner), Style::default().fg(THEME.mauve)),
+        Span::styled(action, Style::default().fg(THEME.text).add_modifier(Modifier::BOLD)),
+        Span::styled(
+            format!("  {}", format_duration(elapsed)),
+            Style::default().fg(THEME.subtext0),
+        ),
+    ];
+    if area.width >= NARROW_WIDTH {
+        status.push(Span::styled(
+            format!("  {} errors", state.error_count),
+            Style::default().fg(if state.error_count > 0 { THEME.red } else { THEME.overlay0 }),
+        ));
+        status.push(Span::styled(
+            format!("  {} warnings", state.warning_count),
+            Style::default().fg(if state.warning_count > 0 { THEME.yellow } else { THEME.overlay0 }),
+        ));
+    }
+
+    let last_line = state
+        .partial_output
+        .as_deref()
+        .or(state.output_lines.back().map(|line| line.text.as_str()))
+        .map(strip_ansi_codes)
+        .unwrap_or_default();
+
+    let lines = vec![
+        Line::from(status),
+        Line::from(Span::styled(
+            format!("   {}", last_line),
+            Style::default().fg(THEME.overlay1),
+        )),
+    ];
+
+    let paragraph = Paragraph::new(lines).style(Style::default().bg(THEME.mantle));
+    frame.render_widget(paragraph, hud_area);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+    use crate::installer::Completion;
+    use crate::state::AppMode;
+    use crate::ui::render_layout;
+
+    /// A run of fish 75 seconds in, in zen mode
+    fn running() -> AppState {
+        let mut state = AppState::new(Vec::new(), HashSet::new(), None);
+        state.mode = AppMode::Installing;
+        state.zen = true;
+        state.current_action = Some("Installing fish".to_string());
+        state.run_started_at = Instant::now().checked_sub(Duration::from_secs(75));
+        state.add_output_line("\x1b[32m::\x1b[0m fetching fish 3.7.1".to_string());
+        state
+    }
+
+    /// Rows of the screen, trailing blanks trimmed
+    fn draw(state: &mut AppState, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| render_layout(frame, state)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| {
+                let row: String = (0..width).map(|x| buffer[(x, y)].symbol()).collect();
+                row.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hud_snapshot() {
+        let mut state = running();
+        state.warning_count = 2;
+        let spinner = spinner_frame(0);
+        assert_eq!(
+            draw(&mut state, 80, 5),
+            [
+                String::new(),
+                String::new(),
+                String::new(),
+                format!(" {} Installing fish  1m 15s  0 errors  2 warnings", spinner),
+                "   :: fetching fish 3.7.1".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn narrow_hud_drops_the_counts() {
+        let mut state = running();
+        state.error_count = 3;
+        state.partial_output = Some("Password: ".to_string());
+        let spinner = spinner_frame(0);
+        assert_eq!(
+            draw(&mut state, 40, 2),
+            [format!(" {} Installing fish  1m 15s", spinner), "   Password:".to_string()]
+        );
+    }
+
+    #[test]
+    fn error_count_turns_red_once_there_are_errors() {
+        let mut state = running();
+        let count_color = |state: &mut AppState| {
+            let mut terminal = Terminal::new(TestBackend::new(80, 2)).unwrap();
+            terminal.draw(|frame| render_hud(frame, state)).unwrap();
+            let row: String = (0..80).map(|x| terminal.backend().buffer()[(x, 0)].symbol().to_string()).collect();
+            let x = row[..row.find("errors").unwrap()].chars().count() as u16;
+            terminal.backend().buffer()[(x, 0)].fg
+        };
+        assert_eq!(count_color(&mut state), THEME.overlay0);
+        state.error_count = 1;
+        assert_eq!(count_color(&mut state), THEME.red);
+    }
+
+    #[test]
+    fn finishing_the_run_brings_the_full_ui_back() {
+        let mut state = running();
+        let hud = draw(&mut state, 120, 40);
+        assert!(hud.iter().any(|row| row.contains("Installing fish")));
+        assert_eq!(hud.iter().filter(|row| !row.is_empty()).count(), 2, "only the HUD is drawn");
+
+        state.end_run(Some(Completion::Exited(0)), Instant::now());
+        assert!(!state.zen);
+        assert_eq!(state.mode, AppMode::Normal);
+        let full = draw(&mut state, 120, 40);
+        assert!(full.iter().filter(|row| !row.is_empty()).count() > 2, "{:#?}", full);
+        assert!(full.last().is_some_and(|footer| footer.contains("Quit")), "{:#?}", full);
+    }
+}
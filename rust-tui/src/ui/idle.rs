@@ -0,0 +1,50 @@
+//! Low-contrast screen shown after a period of inactivity
+
+use std::time::Instant;
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Paragraph},
+};
+
+use super::format_duration;
+use super::header::ASCII_ART;
+use crate::state::{AppMode, AppState};
+use crate::theme::THEME;
+
+/// Render the dimmed idle screen with only the banner and a wake hint
+pub fn render_idle_screen(frame: &mut Frame, state: &AppState) {
+    let area = frame.area();
+    frame.render_widget(Block::default().style(Style::default().bg(THEME.crust)), area);
+
+    let mut lines: Vec<Line> = ASCII_ART
+        .iter()
+        .map(|line| Line::from(Span::styled(*line, Style::default().fg(THEME.surface1))))
+        .collect();
+    lines.push(Line::default());
+
+    let activity = if state.mode == AppMode::Installing {
+        "Install running · "
+    } else {
+        ""
+    };
+    lines.push(Line::from(Span::styled(
+        format!(
+            "{}Session {} · Press any key",
+            activity,
+            format_duration(state.session_duration(Instant::now()))
+        ),
+        Style::default().fg(THEME.overlay0),
+    )));
+
+    let height = (lines.len() as u16).min(area.height);
+    let content_area = Rect {
+        x: area.x,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width: area.width,
+        height,
+    };
+
+    let paragraph = Paragraph::new(lines).alignment(Alignment::Center);
+    frame.render_widget(paragraph, content_area);
+}
@@ -1,13 +1,128 @@
 //! Main layout composition
 
-use ratatui::prelude::*;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+};
 
-use super::{render_footer, render_header, render_option_list, render_output_panel, render_popup, render_status_bar};
+use super::{
+    render_dependencies_popup, render_detail_panel, render_editor_locks_popup, render_footer,
+    render_header, render_help, render_hud, render_idle_screen, render_incompatible_popup,
+    render_local_changes_popup, render_log_browser, render_mark_popup, render_option_list, render_output_panel,
+    render_password_prompt, render_pending_popup, render_popup, render_profiles_popup, render_queue_summary,
+    render_quit_confirm, render_reboot_confirm, render_reinstall_popup,
+    render_startup_warnings, render_status_bar, render_timeout_confirm, render_timings, render_toasts, render_too_old,
+    render_uninstall_confirm, render_unsandboxed_popup, render_unsupported_popup, render_update_popup, DETAIL_AUTO_WIDTH,
+    HEADER_HEIGHT,
+};
+use super::glyphs::GLYPHS;
+use crate::panels::Panel;
 use crate::state::{AppMode, AppState};
 use crate::theme::THEME;
 
+/// Smallest terminal the layout is drawn in; anything smaller gets a notice
+const MIN_WIDTH: u16 = 70;
+const MIN_HEIGHT: u16 = 20;
+
+/// Below this size the banner shrinks to one line and the output panel
+/// takes the whole content area instead of sharing it
+const COMPACT_WIDTH: u16 = 100;
+const COMPACT_HEIGHT: u16 = 30;
+
+/// Border colour of a panel, highlighting the one the arrow keys act on
+///
+/// With the output hidden the list is the only candidate, so nothing stands out.
+pub(super) fn panel_border(state: &AppState, panel: Panel) -> Color {
+    let focused = if state.output_focused() { Panel::Output } else { Panel::List };
+    if state.show_output && panel == focused {
+        THEME.lavender
+    } else {
+        THEME.mauve
+    }
+}
+
+/// Outline a panel in layout mode with its position and weight, highlighting the focused one
+fn render_layout_frame(frame: &mut Frame, area: Rect, state: &AppState, index: usize) {
+    let Some(slot) = state.layouts.current().slots().get(index) else {
+        return;
+    };
+    let focused = index == state.layout_focus;
+    let color = if focused { THEME.yellow } else { THEME.overlay0 };
+    let title = format!(
+        " {}{} · {} ",
+        if focused { GLYPHS.pointer } else { "" },
+        slot.panel.name(),
+        slot.weight
+    );
+    // No style, so only the border cells are redrawn over the panel
+    let outline = Block::default()
+        .borders(Borders::ALL)
+        .border_type(if focused { BorderType::Thick } else { GLYPHS.border })
+        .border_style(Style::default().fg(color))
+        .title_bottom(Line::from(title).right_aligned())
+        .title_style(Style::default().fg(color).add_modifier(Modifier::BOLD));
+    frame.render_widget(outline, area);
+}
+
+/// Centered notice shown instead of the layout on a too-small terminal
+fn render_too_small(frame: &mut Frame) {
+    let area = frame.area();
+    frame.render_widget(Block::default().style(Style::default().bg(THEME.base)), area);
+    let text = format!(
+        "Terminal too small — need at least {}x{} (current {}x{})",
+        MIN_WIDTH, MIN_HEIGHT, area.width, area.height
+    );
+    // Rows once wrapped, give or take a word break
+    let height = (text.chars().count() as u16)
+        .div_ceil(area.width.max(1))
+        .min(area.height);
+    let notice = Paragraph::new(text)
+    .style(Style::default().fg(THEME.yellow))
+    .alignment(Alignment::Center)
+    .wrap(Wrap { trim: true });
+    let notice_area = Rect {
+        y: area.y + area.height.saturating_sub(height) / 2,
+        height,
+        ..area
+    };
+    frame.render_widget(notice, notice_area);
+}
+
 /// Render the main layout
-pub fn render_layout(frame: &mut Frame, state: &AppState) {
+pub fn render_layout(frame: &mut Frame, state: &mut AppState) {
+    let size = frame.area();
+
+    // Hit-test areas are re-recorded by whatever gets drawn this frame
+    state.list_area = Rect::default();
+    state.output_area = Rect::default();
+    state.popup_choices_area = Rect::default();
+
+    // Zen mode's HUD fits anywhere; nothing else does below the minimum
+    let hud = state.zen && !state.dimmed;
+    if (size.width < MIN_WIDTH || size.height < MIN_HEIGHT) && !hud {
+        render_too_small(frame);
+        return;
+    }
+    state.compact = size.width < COMPACT_WIDTH || size.height < COMPACT_HEIGHT;
+
+    // A manifest this installer is too old for leaves nothing else to show
+    if state.mode == AppMode::TooOld {
+        render_too_old(frame, state);
+        return;
+    }
+
+    // Inactivity overlay replaces everything else
+    if state.dimmed {
+        render_idle_screen(frame, state);
+        return;
+    }
+
+    // Zen mode collapses everything to a two-line HUD
+    if state.zen {
+        render_hud(frame, state);
+        return;
+    }
+
     // Clear with base background
     frame.render_widget(
         ratatui::widgets::Block::default().style(Style::default().bg(THEME.base)),
@@ -18,7 +133,7 @@ pub fn render_layout(frame: &mut Frame, state: &AppState) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(9),  // Header banner
+            Constraint::Length(if state.compact { 1 } else { HEADER_HEIGHT }),  // Header banner
             Constraint::Min(10),    // Main content area
             Constraint::Length(1),  // Status bar
             Constraint::Length(1),  // Footer
@@ -26,24 +141,37 @@ pub fn render_layout(frame: &mut Frame, state: &AppState) {
         .split(frame.area());
 
     // Render header banner
-    render_header(frame, chunks[0]);
-
-    // Main content: option list, or split with output panel
-    if state.show_output || state.mode == AppMode::Installing {
-        // Split horizontally: list on left, output on right
-        let content_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(40),
-                Constraint::Percentage(60),
-            ])
-            .split(chunks[1]);
-
-        render_option_list(frame, content_chunks[0], state);
-        render_output_panel(frame, content_chunks[1], state);
-    } else {
-        // Full width option list
-        render_option_list(frame, chunks[1], state);
+    render_header(frame, chunks[0], state);
+
+    // The detail pane opens on its own on wide terminals unless toggled with d;
+    // layout mode shows every panel so each can be placed
+    let editing = state.mode == AppMode::Layout;
+    let output_shown = editing || state.show_output || state.mode == AppMode::Installing;
+    state.details_shown = editing
+        || state
+            .show_details
+            .unwrap_or(!output_shown && frame.area().width >= DETAIL_AUTO_WIDTH);
+
+    // Main content: the shown panels in the order and widths of the active
+    // layout, the list / output split moved as asked except while editing the
+    // layout. A compact terminal, or `f`, shows the output on its own.
+    let output_alone = (state.compact || state.output_full) && output_shown && !editing;
+    state.details_shown &= !output_alone;
+    let split = state.split.filter(|_| !editing);
+    let panels = state.layouts.current().split(chunks[1], split, |panel| match panel {
+        Panel::List => !output_alone,
+        Panel::Output => output_shown,
+        Panel::Details => state.details_shown,
+    });
+    for (index, &(panel, area)) in panels.iter().enumerate() {
+        match panel {
+            Panel::List => render_option_list(frame, area, state),
+            Panel::Output => render_output_panel(frame, area, state),
+            Panel::Details => render_detail_panel(frame, area, state),
+        }
+        if editing {
+            render_layout_frame(frame, area, state, index);
+        }
     }
 
     // Status bar
@@ -52,8 +180,204 @@ pub fn render_layout(frame: &mut Frame, state: &AppState) {
     // Footer with key bindings
     render_footer(frame, chunks[3], state);
 
-    // Render popup overlay if in confirm action mode
-    if state.mode == AppMode::ConfirmAction {
-        render_popup(frame, state);
+    // Render popup overlay for confirmation modes
+    match state.mode {
+        AppMode::ConfirmAction => render_popup(frame, state),
+        AppMode::ConfirmUninstall => render_uninstall_confirm(frame, state),
+        AppMode::ConfirmDependencies => render_dependencies_popup(frame, state),
+        AppMode::ConfirmMark => render_mark_popup(frame, state),
+        AppMode::Help => render_help(frame, state),
+        AppMode::Timings => render_timings(frame, state),
+        AppMode::StartupWarnings => render_startup_warnings(frame, state),
+        AppMode::PendingItems => render_pending_popup(frame, state),
+        AppMode::Profiles => render_profiles_popup(frame, state),
+        AppMode::LogBrowser => render_log_browser(frame, state),
+        AppMode::QueueSummary => render_queue_summary(frame, state),
+        AppMode::ConfirmEditorLocks => render_editor_locks_popup(frame, state),
+        AppMode::ConfirmReboot => render_reboot_confirm(frame, state),
+        AppMode::ConfirmUpdate => render_update_popup(frame, state),
+        AppMode::ConfirmReinstall => render_reinstall_popup(frame, state),
+        AppMode::ConfirmIncompatible => render_incompatible_popup(frame, state),
+        AppMode::ConfirmLocalChanges => render_local_changes_popup(frame, state),
+        AppMode::ConfirmUnsandboxed => render_unsandboxed_popup(frame, state),
+        AppMode::ConfirmQuit => render_quit_confirm(frame, state),
+        AppMode::ConfirmTimeout => render_timeout_confirm(frame, state),
+        AppMode::Unsupported => render_unsupported_popup(frame, state),
+        AppMode::PasswordPrompt => render_password_prompt(frame, state),
+        _ => {}
+    }
+
+    // Toasts float over everything, popups included
+    render_toasts(frame, chunks[2], state);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+    use crate::options::InstallOption;
+
+    fn option(id: &str, name: &str, category: &str) -> InstallOption {
+        InstallOption {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: format!("Installs {}", name),
+            category: category.to_string(),
+            ..InstallOption::default()
+        }
+    }
+
+    fn state() -> AppState {
+        let options = vec![
+            option("fish", "Fish Shell", "Shell"),
+            option("hot-corners", "Hot Corners", "Desktop"),
+            option("waybar-idle", "Waybar Idle Toggle", "Desktop"),
+        ];
+        AppState::new(options, HashSet::new(), None)
+    }
+
+    /// Rows of the screen, trailing blanks trimmed
+    fn draw(state: &mut AppState, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| render_layout(frame, state)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| {
+                let row: String = (0..width).map(|x| buffer[(x, y)].symbol()).collect();
+                row.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compact_snapshot() {
+        // Below 100 columns the banner is one line and the details stay closed
+        const BLANK: &str = "│                                                                              │";
+        let mut state = state();
+        assert_eq!(
+            draw(&mut state, 80, 24),
+            [
+            "omarchy-cybex · unknown base version — checks skipped · scripts from a local dir",
+            "╭ Options ─────────────────────────────────────────────────────────────────────╮",
+            "│  ▾ Shell (0/1 installed)                                                     │",
+            "│> ○ [ ]  Fish Shell            Installs Fish Shell                            │",
+            "│  ▾ Desktop (0/2 installed)                                                   │",
+            "│  ○ [ ]  Hot Corners           Installs Hot Corners                           │",
+            "│  ○ [ ]  Waybar Idle Toggle    Installs Waybar Idle Toggle                    │",
+            BLANK,
+            BLANK,
+            BLANK,
+            BLANK,
+            BLANK,
+            BLANK,
+            BLANK,
+            BLANK,
+            BLANK,
+            BLANK,
+            BLANK,
+            BLANK,
+            BLANK,
+            BLANK,
+            "╰──────────────────────────────────────────────────────────────────────────────╯",
+            "Ready - Press Enter to install/uninstall          0/3 installed · option 1 of 3",
+            "q: Quit │ ↑/↓: Navigate │ Enter: Install/Uninstall │ Space: Queue │ /: Filter │",
+            ]
+        );
+        assert!(state.compact);
+        assert!(!state.details_shown);
+        assert_eq!(state.list_area, Rect::new(0, 1, 80, 21));
+    }
+
+    #[test]
+    fn wide_snapshot_opens_the_details() {
+        let mut state = state();
+        let screen = draw(&mut state, 120, 40);
+        assert_eq!(screen[9], format!("╭ Options {}╮╭ Details {}╮", "─".repeat(55), "─".repeat(43)));
+        assert_eq!(screen[10], format!("│  ▾ Shell (0/1 installed){}││Fish Shell{}│", " ".repeat(39), " ".repeat(42)));
+        assert_eq!(
+            screen[38],
+            format!("Ready - Press Enter to install/uninstall{}0/3 installed · option 1 of 3", " ".repeat(50))
+        );
+        assert_eq!(
+            screen[39].trim_start(),
+            concat!(
+                "q: Quit │ ↑/↓: Navigate │ Enter: Install/Uninstall │ Space: Queue │ /: Filter │ ",
+                "d: Details │ w: Layout │ ?: Help"
+            )
+        );
+        assert!(!state.compact);
+        assert!(state.details_shown);
+        assert_eq!(state.list_area, Rect::new(0, 9, 66, 29));
+    }
+
+    #[test]
+    fn medium_snapshot_keeps_the_banner_without_details() {
+        let mut state = state();
+        let screen = draw(&mut state, 100, 30);
+        assert!(screen[8].contains("unknown base version"), "{:#?}", screen);
+        assert_eq!(screen[9], format!("╭ Options {}╮", "─".repeat(89)));
+        assert!(screen[28].starts_with("Ready - Press Enter"));
+        assert!(!state.compact);
+        assert!(!state.details_shown);
+        assert_eq!(state.list_area, Rect::new(0, 9, 100, 19));
+    }
+
+    #[test]
+    fn installing_shows_the_output_beside_the_list() {
+        let mut state = state();
+        state.mode = AppMode::Installing;
+        let screen = draw(&mut state, 120, 40);
+        assert!(screen[9].starts_with("╭ Options"), "{}", screen[9]);
+        assert!(!screen[9].contains("Details"), "the output takes the details' place");
+        assert!(!state.details_shown);
+        assert_eq!(state.list_area.y, 9);
+        assert_eq!(state.output_area.right(), 120);
+        assert_eq!(state.list_area.width + state.output_area.width, 120);
+    }
+
+    #[test]
+    fn compact_installs_give_the_output_the_whole_width() {
+        let mut state = state();
+        state.mode = AppMode::Installing;
+        draw(&mut state, 80, 24);
+        assert_eq!(state.list_area, Rect::default(), "the list isn't drawn");
+        assert_eq!(state.output_area.width, 80);
+    }
+
+    #[test]
+    fn smallest_terminal_still_gets_the_layout() {
+        let mut state = state();
+        let screen = draw(&mut state, MIN_WIDTH, MIN_HEIGHT);
+        assert!(screen[0].starts_with("omarchy-cybex · "));
+        assert_eq!(screen[1], format!("╭ Options {}╮", "─".repeat(59)));
+        assert_eq!(screen[18], "Ready - Press Enter to install/uninstall");
+        assert!(state.compact);
+    }
+
+    #[test]
+    fn too_small_terminals_get_a_notice() {
+        for (width, height) in [(MIN_WIDTH - 1, MIN_HEIGHT), (MIN_WIDTH, MIN_HEIGHT - 1), (30, 5)] {
+            let mut state = state();
+            let screen = draw(&mut state, width, height);
+            let notice: Vec<&str> = screen.iter().map(|row| row.trim()).filter(|row| !row.is_empty()).collect();
+            assert_eq!(
+                notice.join(" "),
+                format!("Terminal too small — need at least 70x20 (current {}x{})", width, height)
+            );
+            assert_eq!(state.list_area, Rect::default());
+        }
+    }
+
+    #[test]
+    fn zen_hud_fits_below_the_minimum() {
+        let mut state = state();
+        state.mode = AppMode::Installing;
+        state.zen = true;
+        let screen = draw(&mut state, 40, 5);
+        assert!(screen[3].contains("Idle"), "{:#?}", screen);
+        assert!(screen.iter().all(|row| !row.contains("too small")));
     }
 }
@@ -1,17 +1,40 @@
 //! UI components for the TUI
 
+mod detail_panel;
 mod footer;
+mod format;
+mod glyphs;
 mod header;
+mod help;
+mod hud;
+mod idle;
 mod layout;
 mod option_list;
+mod outdated;
 mod output_panel;
 mod popup;
 mod status_bar;
+mod timings;
+mod toasts;
 
+pub use detail_panel::{render_detail_panel, DETAIL_AUTO_WIDTH};
 pub use footer::render_footer;
-pub use header::render_header;
+pub use glyphs::use_ascii;
+pub use format::{display_path, format_clock, format_count, format_duration, format_typical, spinner_frame};
+pub use header::{render_header, HEADER_HEIGHT};
+pub use help::render_help;
+pub use hud::render_hud;
+pub use idle::render_idle_screen;
 pub use layout::render_layout;
 pub use option_list::render_option_list;
-pub use output_panel::render_output_panel;
-pub use popup::render_popup;
+pub use outdated::render_too_old;
+pub use output_panel::{render_output_panel, strip_ansi_codes};
+pub use popup::{
+    render_dependencies_popup, render_editor_locks_popup, render_incompatible_popup,
+    render_local_changes_popup, render_log_browser, render_mark_popup, render_password_prompt, render_pending_popup, render_popup, render_profiles_popup,
+    render_queue_summary, render_quit_confirm, render_reboot_confirm, render_reinstall_popup, render_startup_warnings,
+    render_timeout_confirm, render_uninstall_confirm, render_unsandboxed_popup, render_unsupported_popup, render_update_popup,
+};
 pub use status_bar::render_status_bar;
+pub use timings::render_timings;
+pub use toasts::render_toasts;
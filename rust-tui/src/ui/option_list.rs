@@ -2,45 +2,204 @@
 
 use ratatui::{
     prelude::*,
-    widgets::{Block, BorderType, Borders, List, ListItem, ListState},
+    widgets::{Block, Borders, List, ListItem, ListState, Scrollbar, ScrollbarOrientation, ScrollbarState},
 };
 
-use crate::options::OPTIONS;
-use crate::state::AppState;
-use crate::theme::THEME;
+use super::glyphs::GLYPHS;
+use super::layout::panel_border;
+use super::spinner_frame;
+use crate::options::InstallOption;
+use crate::panels::Panel;
+use crate::state::{AppState, ListRow};
+use crate::theme::{colorless, legible_blend, THEME};
 
-/// Render the option list
-pub fn render_option_list(frame: &mut Frame, area: Rect, state: &AppState) {
-    let items: Vec<ListItem> = OPTIONS
-        .iter()
-        .map(|opt| {
-            let is_installed = state.is_installed(opt.id);
-
-            // Status indicator [OK] or [ ] - padded to 4 chars for alignment
-            let status_text = if is_installed { "[OK]" } else { "[ ]" };
-            let status = Span::styled(
-                format!("{:<4}", status_text),
-                Style::default().fg(if is_installed { THEME.green } else { THEME.overlay0 }),
-            );
+/// Rows kept in view above and below the selection while scrolling
+///
+/// Smaller when the list is too short for it, so 3 rows still show the
+/// selection with one row of context on each side.
+const SCROLL_PADDING: usize = 2;
 
-            // Option name (padded for alignment)
-            let name = Span::styled(
-                format!(" {:<22}", opt.name),
-                Style::default().fg(THEME.text),
-            );
+/// Status of a row as far as its highlight is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowStatus {
+    Installed,
+    /// The last recorded attempt failed
+    Failed,
+    Default,
+}
+
+impl RowStatus {
+    fn of(state: &AppState, id: &str) -> Self {
+        if state.last_failed(id) {
+            RowStatus::Failed
+        } else if state.is_installed(id) {
+            RowStatus::Installed
+        } else {
+            RowStatus::Default
+        }
+    }
+}
+
+/// Highlight for the selected row
+///
+/// The tints are at most a fifth of the accent over surface0, less where that
+/// would leave the text under `MIN_CONTRAST` on top of them. Without colors
+/// the row is shown in reverse video.
+pub fn highlight_style(status: RowStatus) -> Style {
+    if colorless() {
+        return Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD);
+    }
+    let bg = match status {
+        RowStatus::Installed => legible_blend(THEME.surface0, THEME.green, 0.2, THEME.text),
+        RowStatus::Failed => legible_blend(THEME.surface0, THEME.red, 0.2, THEME.text),
+        RowStatus::Default => THEME.surface0,
+    };
+    Style::default().bg(bg).add_modifier(Modifier::BOLD)
+}
+
+/// Heading row for a category, e.g. "▾ Desktop (2/4 installed)"
+fn category_item<'a>(state: &AppState, category: &'a str) -> ListItem<'a> {
+    let (installed, total) = state.category_counts(category);
+    let folded = state.filter.is_empty() && state.collapsed.contains(category);
+    let line = Line::from(vec![
+        Span::styled(
+            if folded { GLYPHS.folded } else { GLYPHS.unfolded },
+            Style::default().fg(THEME.overlay1),
+        ),
+        Span::styled(category, Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD)),
+        Span::styled(
+            format!(" ({}/{} installed)", installed, total),
+            Style::default().fg(THEME.overlay1),
+        ),
+    ]);
+    ListItem::new(line)
+}
+
+/// Row for an option with its queue mark, status and badges
+fn option_item<'a>(state: &AppState, opt: &'a InstallOption) -> ListItem<'a> {
+    let is_installed = state.is_installed(&opt.id);
+
+    // Queue checkbox
+    let checkbox = if state.is_queued(&opt.id) {
+        Span::styled(GLYPHS.queued, Style::default().fg(THEME.mauve))
+    } else {
+        Span::styled(GLYPHS.unqueued, Style::default().fg(THEME.surface2))
+    };
+
+    // Status indicator [OK], [ ] or [!!] - padded to 4 chars for alignment;
+    // options without an installed state are just run again. The running
+    // option shows the spinner instead, with its name in yellow.
+    let running = state.running_option.as_deref() == Some(opt.id.as_str());
+    let status = if running {
+        Span::styled(
+            format!(" {:<3}", spinner_frame(state.spinner_tick)),
+            Style::default().fg(THEME.yellow),
+        )
+    } else if !opt.tracks_state() {
+        Span::styled(format!("{:<4}", GLYPHS.rerun), Style::default().fg(THEME.sky))
+    } else if state.last_failed(&opt.id) {
+        Span::styled(format!("{:<4}", GLYPHS.failed), Style::default().fg(THEME.red))
+    } else {
+        let status_text = if is_installed { GLYPHS.installed } else { GLYPHS.not_installed };
+        Span::styled(
+            format!("{:<4}", status_text),
+            Style::default().fg(if is_installed { THEME.green } else { THEME.overlay0 }),
+        )
+    };
+
+    // Greyed out when the entry script doesn't accept the option
+    let unsupported = state.unsupported.contains(&opt.id);
+
+    // Option name (padded for alignment)
+    let name_color = match () {
+        _ if running => THEME.yellow,
+        _ if unsupported => THEME.overlay0,
+        _ => THEME.text,
+    };
+    let name = Span::styled(format!(" {:<22}", opt.name), Style::default().fg(name_color));
+
+    // Description
+    let desc = Span::styled(
+        opt.description.as_str(),
+        Style::default().fg(if unsupported { THEME.overlay0 } else { THEME.subtext0 }),
+    );
+
+    // Reboot indicator
+    let reboot = if opt.requires_reboot {
+        Span::styled(" [reboot]", Style::default().fg(THEME.yellow))
+    } else {
+        Span::raw("")
+    };
+
+    // The script reports a newer version than the installed one
+    let update = if state.update_for(&opt.id).is_some() {
+        Span::styled(GLYPHS.update, Style::default().fg(THEME.blue))
+    } else {
+        Span::raw("")
+    };
+
+    // Failure badge from the last recorded attempt
+    let failed = if state.last_failed(&opt.id) {
+        Span::styled(" [failed]", Style::default().fg(THEME.red))
+    } else {
+        Span::raw("")
+    };
 
-            // Description
-            let desc = Span::styled(opt.description, Style::default().fg(THEME.subtext0));
+    // Drift marker: installed, but the verify command didn't pass
+    let unverified = if is_installed && state.verify_failed(&opt.id) {
+        Span::styled(" [unverified]", Style::default().fg(THEME.yellow))
+    } else {
+        Span::raw("")
+    };
 
-            // Reboot indicator
-            let reboot = if opt.requires_reboot {
-                Span::styled(" [reboot]", Style::default().fg(THEME.yellow))
-            } else {
-                Span::raw("")
-            };
+    // Outside the option's declared Omarchy version range
+    let incompatible = if state.compat_issue(opt).is_some() {
+        Span::styled(" [incompatible]", Style::default().fg(THEME.peach))
+    } else {
+        Span::raw("")
+    };
 
-            let line = Line::from(vec![status, name, desc, reboot]);
-            ListItem::new(line)
+    // The script's own check couldn't say whether it's installed
+    let unknown = if state.presence_unknown.contains(&opt.id) {
+        Span::styled(" [unknown]", Style::default().fg(THEME.overlay1))
+    } else {
+        Span::raw("")
+    };
+
+    let unsupported = if unsupported {
+        Span::styled(" [unsupported]", Style::default().fg(THEME.overlay1))
+    } else {
+        Span::raw("")
+    };
+
+    let line = Line::from(vec![
+        checkbox,
+        status,
+        name,
+        desc,
+        reboot,
+        update,
+        failed,
+        unverified,
+        incompatible,
+        unknown,
+        unsupported,
+    ]);
+    ListItem::new(line)
+}
+
+/// Render the option list
+///
+/// The offset is kept from the previous frame so the list only scrolls when
+/// the selection gets near an edge, and records where it ended up for mouse
+/// hit-testing.
+pub fn render_option_list(frame: &mut Frame, area: Rect, state: &mut AppState) {
+    let items: Vec<ListItem> = state
+        .rows
+        .iter()
+        .filter_map(|row| match row {
+            ListRow::Category(category) => Some(category_item(state, category)),
+            ListRow::Option(i) => state.options.get(*i).map(|opt| option_item(state, opt)),
         })
         .collect();
 
@@ -48,21 +207,150 @@ pub fn render_option_list(frame: &mut Frame, area: Rect, state: &AppState) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(THEME.mauve))
-                .border_type(BorderType::Rounded)
-                .title(" Options ")
+                .border_style(Style::default().fg(panel_border(state, Panel::List)))
+                .border_type(GLYPHS.border)
+                .title(if state.filter.is_empty() {
+                    " Options ".to_string()
+                } else {
+                    format!(" Options ({}/{}) ", state.visible.len(), state.options.len())
+                })
                 .title_style(Style::default().fg(THEME.pink))
                 .style(Style::default().bg(THEME.mantle)),
         )
-        .highlight_style(
-            Style::default()
-                .bg(THEME.surface0)
-                .add_modifier(Modifier::BOLD),
-        )
-        .highlight_symbol("> ");
+        .highlight_style(highlight_style(
+            state
+                .selected_option()
+                .map_or(RowStatus::Default, |opt| RowStatus::of(state, &opt.id)),
+        ))
+        .highlight_symbol("> ")
+        .scroll_padding(SCROLL_PADDING);
 
-    let mut list_state = ListState::default();
-    list_state.select(Some(state.selected_index));
+    // Never scroll past the point where the last row is at the bottom, e.g.
+    // after a filter or collapse has removed rows
+    let inner_height = area.height.saturating_sub(2) as usize;
+    let max_offset = state.rows.len().saturating_sub(inner_height);
+    let mut list_state = ListState::default()
+        .with_offset(state.list_offset.min(max_offset))
+        .with_selected((!state.rows.is_empty()).then_some(state.selected_index));
 
     frame.render_stateful_widget(list, area, &mut list_state);
+    state.list_area = area;
+    state.list_offset = list_state.offset();
+
+    if max_offset > 0 && area.width > 0 {
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some(GLYPHS.scroll_begin))
+            .end_symbol(Some(GLYPHS.scroll_end))
+            .track_symbol(Some(GLYPHS.scroll_track))
+            .thumb_symbol(GLYPHS.scroll_thumb);
+
+        let mut scrollbar_state = ScrollbarState::new(max_offset + 1)
+            .viewport_content_length(inner_height)
+            .position(state.list_offset);
+
+        // Drawn over the right border, like the output panel's
+        let scrollbar_area = Rect {
+            x: area.x + area.width - 1,
+            y: area.y + 1,
+            width: 1,
+            height: area.height.saturating_sub(2),
+        };
+
+        frame.render_stateful_widget(scrollbar, scrollbar_area, &mut scrollbar_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+    use crate::config::OptionRecord;
+    use crate::theme::{contrast_ratio, MIN_CONTRAST};
+
+    /// Fish installed, corners failed last time, waybar neither
+    fn state() -> AppState {
+        let option = |id: &str, name: &str| InstallOption {
+            id: id.to_string(),
+            name: name.to_string(),
+            category: "Desktop".to_string(),
+            ..InstallOption::default()
+        };
+        let options = vec![option("fish", "Fish Shell"), option("corners", "Hot Corners"), option("waybar", "Waybar")];
+        let mut state = AppState::new(options, HashSet::from(["fish".to_string(), "corners".to_string()]), None);
+        let failed = OptionRecord {
+            last_exit_code: Some(1),
+            ..OptionRecord::default()
+        };
+        state.records.insert("corners".to_string(), failed);
+        state
+    }
+
+    /// The list with row `selected` highlighted: its text, and the background of each row
+    fn draw(state: &mut AppState, selected: usize) -> (Vec<String>, Vec<Color>) {
+        state.selected_index = selected;
+        let mut terminal = Terminal::new(TestBackend::new(50, 6)).unwrap();
+        terminal.draw(|frame| render_option_list(frame, frame.area(), state)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows = (1..5)
+            .map(|y| (1..49).map(|x| buffer[(x, y)].symbol()).collect::<String>().trim_end().to_string())
+            .collect();
+        let backgrounds = (1..5).map(|y| buffer[(10, y)].bg).collect();
+        (rows, backgrounds)
+    }
+
+    #[test]
+    fn highlight_is_tinted_by_status() {
+        let style = |status| highlight_style(status).bg.unwrap();
+        assert_eq!(style(RowStatus::Default), THEME.surface0);
+        assert_eq!(style(RowStatus::Installed), Color::Rgb(72, 85, 87));
+        assert_eq!(style(RowStatus::Failed), Color::Rgb(88, 68, 88));
+        for status in [RowStatus::Default, RowStatus::Installed, RowStatus::Failed] {
+            assert!(highlight_style(status).add_modifier.contains(Modifier::BOLD));
+            assert!(contrast_ratio(style(status), THEME.text) >= MIN_CONTRAST, "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn failure_outranks_installed() {
+        let state = state();
+        assert_eq!(RowStatus::of(&state, "fish"), RowStatus::Installed);
+        assert_eq!(RowStatus::of(&state, "corners"), RowStatus::Failed, "installed, but the last attempt failed");
+        assert_eq!(RowStatus::of(&state, "waybar"), RowStatus::Default);
+        assert_eq!(RowStatus::of(&state, "unknown"), RowStatus::Default);
+    }
+
+    #[test]
+    fn selected_row_snapshots() {
+        let mut state = state();
+        let plain = THEME.mantle;
+        for (selected, status) in [(1, RowStatus::Installed), (2, RowStatus::Failed), (3, RowStatus::Default)] {
+            let (rows, backgrounds) = draw(&mut state, selected);
+            let marker = |row| if row == selected { "> " } else { "  " };
+            assert_eq!(
+                rows,
+                [
+                    format!("{}▾ Desktop (2/3 installed)", marker(0)),
+                    format!("{}○ [OK] Fish Shell", marker(1)),
+                    format!("{}○ [!!] Hot Corners            [failed]", marker(2)),
+                    format!("{}○ [ ]  Waybar", marker(3)),
+                ],
+                "{:?}",
+                status
+            );
+            let expected: Vec<Color> = (0..4)
+                .map(|row| if row == selected { highlight_style(status).bg.unwrap() } else { plain })
+                .collect();
+            assert_eq!(backgrounds, expected, "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn category_rows_get_the_plain_highlight() {
+        let mut state = state();
+        let (_, backgrounds) = draw(&mut state, 0);
+        assert_eq!(backgrounds[0], THEME.surface0);
+    }
 }
@@ -0,0 +1,103 @@
+//! Screen shown instead of the installer when the manifest needs a newer one
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Paragraph, Wrap},
+};
+
+use super::header::ASCII_ART;
+use crate::manifest::TooOld;
+use crate::state::AppState;
+use crate::theme::THEME;
+
+/// Widest the explanation gets, so it reads as a paragraph on wide terminals
+const TEXT_WIDTH: u16 = 80;
+
+/// Render the blocking screen explaining the version mismatch
+pub fn render_too_old(frame: &mut Frame, state: &AppState) {
+    let area = frame.area();
+    frame.render_widget(Block::default().style(Style::default().bg(THEME.base)), area);
+    let Some(too_old) = &state.too_old else {
+        return;
+    };
+
+    let mut lines: Vec<Line> = ASCII_ART
+        .iter()
+        .map(|line| Line::from(Span::styled(*line, Style::default().fg(THEME.surface1))))
+        .collect();
+    lines.extend(explanation(too_old));
+
+    let width = area.width.min(TEXT_WIDTH);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let content_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    };
+    let paragraph = Paragraph::new(lines).alignment(Alignment::Center).wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, content_area);
+}
+
+/// What went wrong, both versions, and how to fix it
+fn explanation(too_old: &TooOld) -> Vec<Line<'static>> {
+    vec![
+        Line::default(),
+        Line::from(Span::styled(
+            "This installer is too old for the manifest",
+            Style::default().fg(THEME.red).add_modifier(Modifier::BOLD),
+        )),
+        Line::default(),
+        Line::from(Span::styled(too_old.to_string(), Style::default().fg(THEME.text))),
+        Line::from(vec![
+            Span::styled("Needs ", Style::default().fg(THEME.subtext0)),
+            Span::styled(too_old.required.to_string(), Style::default().fg(THEME.green)),
+            Span::styled(" · running ", Style::default().fg(THEME.subtext0)),
+            Span::styled(too_old.running.to_string(), Style::default().fg(THEME.red)),
+        ]),
+        Line::default(),
+        Line::from(Span::styled(TooOld::ADVICE, Style::default().fg(THEME.subtext0))),
+        Line::default(),
+        Line::from(Span::styled("Press any key to quit", Style::default().fg(THEME.overlay0))),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+    use crate::semver::SemVer;
+    use crate::state::AppMode;
+    use crate::ui::render_layout;
+
+    #[test]
+    fn too_old_manifest_blocks_the_layout() {
+        let mut state = AppState::new(Vec::new(), HashSet::new(), None);
+        state.mode = AppMode::TooOld;
+        state.too_old = Some(TooOld {
+            path: PathBuf::from("/scripts/options.toml"),
+            required: SemVer::parse("9.1").unwrap(),
+            running: SemVer::parse("0.1.0").unwrap(),
+        });
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        terminal.draw(|frame| render_layout(frame, &mut state)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+
+        assert!(screen.contains("This installer is too old for the manifest"));
+        assert!(screen.contains("Needs 9.1.0 · running 0.1.0"));
+        assert!(screen.contains("Press any key to quit"));
+        // The option list and footer are not drawn behind it
+        assert!(!screen.contains("Quit"));
+    }
+}
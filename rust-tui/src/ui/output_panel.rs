@@ -1,72 +1,120 @@
 //! Output panel component for showing installation output
 
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+
 use ratatui::{
     prelude::*,
-    widgets::{Block, BorderType, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
+    widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
 };
 
-use crate::state::{AppMode, AppState};
+use super::layout::panel_border;
+use super::glyphs::GLYPHS;
+use super::{format_clock, format_count, format_duration, format_typical, spinner_frame};
+use crate::installer::Completion;
+use crate::keymap::Action;
+use crate::panels::Panel;
+use crate::registry::{self, Scope};
+use crate::state::{AppMode, AppState, LineKind};
 use crate::theme::THEME;
 
+/// Elapsed time past this many typical runs counts as unusually slow
+const SLOW_RUN_FACTOR: u32 = 2;
+
 /// Render the output panel
-pub fn render_output_panel(frame: &mut Frame, area: Rect, state: &AppState) {
+///
+/// Records the inner size in `state` so scrolling, auto-scroll and the
+/// subprocess terminal use the real viewport.
+pub fn render_output_panel(frame: &mut Frame, area: Rect, state: &mut AppState) {
     // Panel title based on mode
-    let title = match &state.current_action {
-        Some(action) => format!(" {} ", action),
-        None => " Output ".to_string(),
+    let title = match (&state.current_action, state.run_duration) {
+        (Some(action), _) if state.mode == AppMode::Installing => running_title(state, action),
+        (Some(action), Some(took)) => Line::from(format!(" {} ", finished_title(state, action, took))),
+        (Some(action), None) => Line::from(format!(" {} ", action)),
+        (None, _) => Line::from(" Output "),
     };
 
     // Status indicator in title
+    let succeeded = state.last_completion.is_some_and(Completion::success);
     let title_style = match state.mode {
         AppMode::Installing => Style::default().fg(THEME.yellow),
-        AppMode::Completed => {
-            if state.last_exit_code == Some(0) {
-                Style::default().fg(THEME.green)
-            } else {
-                Style::default().fg(THEME.red)
-            }
-        }
+        _ if state.run_duration.is_some() => Style::default().fg(if succeeded { THEME.green } else { THEME.red }),
         _ => Style::default().fg(THEME.pink),
     };
 
+    state.output_area = area;
+
     // Calculate visible area (account for borders)
     let inner_height = area.height.saturating_sub(2) as usize;
+    state.output_width = area.width.saturating_sub(2) as usize;
+    if state.output_height != inner_height {
+        state.output_height = inner_height;
+        state.auto_scroll_output();
+    }
+    let window = state.output_window();
+    state.output_scroll = window.offset();
 
-    // Create paragraph from output lines
+    // Only the rows in view are turned into spans, borrowing the text
+    let range = window.range();
+    let stored = range.start.min(state.output_lines.len())..range.end.min(state.output_lines.len());
+    let partial = state.partial_output.as_deref().filter(|_| range.end > state.output_lines.len());
     let visible_lines: Vec<Line> = state
         .output_lines
-        .iter()
-        .skip(state.output_scroll)
-        .take(inner_height)
-        .map(|line| {
+        .range(stored)
+        .map(|line| (line.text.as_str(), line.repeat, line.kind))
+        .chain(partial.map(|text| (text, 1, LineKind::Output)))
+        .map(|(text, repeat, kind)| {
             // Strip ANSI codes for display (ratatui doesn't handle them)
-            let clean_line = strip_ansi_codes(line);
-            Line::from(Span::styled(clean_line, Style::default().fg(THEME.text)))
+            let color = match kind {
+                LineKind::Output => THEME.text,
+                LineKind::Stderr => THEME.maroon,
+                LineKind::Problem => THEME.red,
+                LineKind::Frame => THEME.overlay1,
+            };
+            let mut spans = vec![Span::styled(strip_ansi_codes(text), Style::default().fg(color))];
+            if repeat > 1 {
+                spans.push(Span::styled(
+                    format!("  (×{})", format_count(repeat)),
+                    Style::default().fg(THEME.overlay0),
+                ));
+            }
+            Line::from(spans)
         })
         .collect();
 
-    let paragraph = Paragraph::new(visible_lines).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(THEME.mauve))
-            .border_type(BorderType::Rounded)
-            .title(title)
-            .title_style(title_style)
-            .style(Style::default().bg(THEME.crust)),
-    );
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(panel_border(state, Panel::Output)))
+        .border_type(GLYPHS.border)
+        .title(title)
+        .title_style(title_style)
+        .style(Style::default().bg(THEME.crust));
+    if state.output_omitted > 0 {
+        block = block.title_bottom(Span::styled(
+            format!(" … {} earlier lines omitted ", format_count(state.output_omitted)),
+            Style::default().fg(THEME.overlay0),
+        ));
+    }
+
+    if window.is_scrollable() {
+        block = block.title_bottom(follow_hint(state).right_aligned());
+    }
+
+    let paragraph = Paragraph::new(visible_lines).block(block);
 
     frame.render_widget(paragraph, area);
 
     // Render scrollbar if needed
-    if state.output_lines.len() > inner_height {
+    if window.is_scrollable() {
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
-            .begin_symbol(Some("▲"))
-            .end_symbol(Some("▼"))
-            .track_symbol(Some("│"))
-            .thumb_symbol("█");
+            .begin_symbol(Some(GLYPHS.scroll_begin))
+            .end_symbol(Some(GLYPHS.scroll_end))
+            .track_symbol(Some(GLYPHS.scroll_track))
+            .thumb_symbol(GLYPHS.scroll_thumb);
 
-        let mut scrollbar_state = ScrollbarState::new(state.output_lines.len())
-            .position(state.output_scroll);
+        let mut scrollbar_state = ScrollbarState::new(window.max_offset() + 1)
+            .viewport_content_length(inner_height)
+            .position(window.offset());
 
         // Render scrollbar in the right border area
         let scrollbar_area = Rect {
@@ -80,8 +128,76 @@ pub fn render_output_panel(frame: &mut Frame, area: Rect, state: &AppState) {
     }
 }
 
+/// Bottom-border hint saying whether the view keeps up with new output
+fn follow_hint(state: &AppState) -> Line<'static> {
+    if state.follow_output {
+        return Line::styled(format!(" {} following ", GLYPHS.following), Style::default().fg(THEME.overlay0));
+    }
+    // While a script runs, text keys such as G are typed into it
+    let scope = if state.mode == AppMode::Installing {
+        Scope::Installing
+    } else {
+        Scope::Browsing
+    };
+    let key = registry::key_labels(&state.keymap, scope, Action::FollowOutput)
+        .into_iter()
+        .next();
+    let back = key.map(|key| format!(" · {} to follow", key)).unwrap_or_default();
+    if state.unseen_output > 0 {
+        Line::styled(
+            format!(" {} {} new lines{} ", GLYPHS.scrolled_up, format_count(state.unseen_output), back),
+            Style::default().fg(THEME.peach),
+        )
+    } else {
+        Line::styled(format!(" {} scrolled up{} ", GLYPHS.scrolled_up, back), Style::default().fg(THEME.overlay0))
+    }
+}
+
+/// Title while a run goes, with its usual duration once one is known, e.g.
+/// "Installing Claude Code · 02:10 / ~1m 40s"
+///
+/// The elapsed time turns yellow once the run takes twice as long as usual.
+fn running_title<'a>(state: &AppState, action: &str) -> Line<'a> {
+    let elapsed = state.run_elapsed(Instant::now()).unwrap_or_default();
+    let head = format!(" {} {} · ", spinner_frame(state.spinner_tick), action);
+    let Some(typical) = state.typical_run_duration() else {
+        return Line::from(format!("{}{} ", head, format_clock(elapsed)));
+    };
+    let elapsed_style = if elapsed > typical * SLOW_RUN_FACTOR {
+        Style::default().fg(THEME.yellow).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(THEME.subtext0)
+    };
+    Line::from(vec![
+        Span::raw(head),
+        Span::styled(format_clock(elapsed), elapsed_style),
+        Span::styled(format!(" / {} ", format_typical(typical)), Style::default().fg(THEME.overlay1)),
+    ])
+}
+
+/// Title for a finished run, e.g. "Installed Claude Code in 2m 14s"
+fn finished_title(state: &AppState, action: &str, took: Duration) -> String {
+    let took = format_duration(took);
+    let Some(option) = state.active_option.and_then(|i| state.options.get(i)) else {
+        return format!("{} - {}", action, took);
+    };
+    match state.last_completion {
+        Some(c) if c.success() && state.is_uninstalling => format!("Uninstalled {} in {}", option.name, took),
+        Some(c) if c.success() && !option.tracks_state() => format!("Ran {} in {}", option.name, took),
+        Some(c) if c.success() => format!("Installed {} in {}", option.name, took),
+        Some(c) => format!("{} failed after {} ({})", option.name, took, c),
+        None => format!("{} stopped after {}", option.name, took),
+    }
+}
+
 /// Strip ANSI escape codes from a string
-fn strip_ansi_codes(s: &str) -> String {
+///
+/// Most lines have none, and those are borrowed rather than copied.
+pub fn strip_ansi_codes(s: &str) -> Cow<'_, str> {
+    if !s.contains('\x1b') {
+        return Cow::Borrowed(s);
+    }
+
     let mut result = String::new();
     let mut in_escape = false;
 
@@ -97,5 +213,5 @@ fn strip_ansi_codes(s: &str) -> String {
         }
     }
 
-    result
+    Cow::Owned(result)
 }
@@ -1,31 +1,33 @@
 //! Action confirmation popup
 
+use std::time::Instant;
+
 use ratatui::{
     prelude::*,
-    widgets::{Block, BorderType, Borders, Clear, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
 };
 
-use crate::options::OPTIONS;
-use crate::state::{ActionChoice, AppState};
-use crate::theme::THEME;
+use super::glyphs::GLYPHS;
+use super::{display_path, format_duration};
+use crate::config::{describe_unknown, RunAction};
+use crate::pending::PendingKind;
+use crate::state::{ActionChoice, AppState, LocalChangesChoice};
+use crate::timeout::describe_limit;
+use crate::theme::{colorless, THEME};
+use crate::view::ViewWindow;
 
 /// Render the action confirmation popup
-pub fn render_popup(frame: &mut Frame, state: &AppState) {
-    let option_name = OPTIONS
-        .get(state.selected_index)
-        .map(|o| o.name)
+pub fn render_popup(frame: &mut Frame, state: &mut AppState) {
+    let option_name = state
+        .selected_option()
+        .map(|o| o.name.as_str())
         .unwrap_or("Unknown");
 
     // Calculate popup size and position (centered)
-    let popup_width = 40;
-    let popup_height = 7;
-    let area = frame.area();
-    let popup_area = Rect {
-        x: area.width.saturating_sub(popup_width) / 2,
-        y: area.height.saturating_sub(popup_height) / 2,
-        width: popup_width.min(area.width),
-        height: popup_height.min(area.height),
-    };
+    let choices = state.popup_choices();
+    let also = dependency_names(state);
+    let height = choices.len() as u16 + if also.is_empty() { 5 } else { 7 };
+    let popup_area = centered_area(frame.area(), 44, height);
 
     // Clear the area behind the popup
     frame.render_widget(Clear, popup_area);
@@ -33,7 +35,7 @@ pub fn render_popup(frame: &mut Frame, state: &AppState) {
     // Render popup background
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_type(BorderType::Rounded)
+        .border_type(GLYPHS.border)
         .border_style(Style::default().fg(THEME.mauve))
         .title(format!(" {} ", option_name))
         .title_style(Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD))
@@ -49,46 +51,1135 @@ pub fn render_popup(frame: &mut Frame, state: &AppState) {
         height: popup_area.height.saturating_sub(3),
     };
 
-    // Reinstall option
-    let reinstall_style = if state.popup_choice == ActionChoice::Reinstall {
-        Style::default().fg(THEME.green).add_modifier(Modifier::BOLD)
-    } else {
-        Style::default().fg(THEME.text)
+    let installed = state
+        .selected_option()
+        .is_some_and(|o| state.is_installed(&o.id));
+    let update = state
+        .selected_option()
+        .and_then(|o| state.update_for(&o.id))
+        .map(|versions| format!("Update to {}", versions.available));
+    let mut lines: Vec<Line> = choices
+        .iter()
+        .map(|&choice| {
+            let (label, color) = match choice {
+                ActionChoice::Reinstall if update.is_some() => (update.as_deref().unwrap_or_default(), THEME.blue),
+                ActionChoice::Reinstall if installed => ("Install / Update", THEME.green),
+                ActionChoice::Reinstall => ("Install", THEME.green),
+                ActionChoice::InstallSkipVerify => ("Install without verification", THEME.yellow),
+                ActionChoice::Uninstall => ("Uninstall", THEME.red),
+            };
+            let (prefix, style) = if state.popup_choice == choice {
+                ("> ", Style::default().fg(color).add_modifier(Modifier::BOLD))
+            } else {
+                ("  ", Style::default().fg(THEME.text))
+            };
+            Line::from(Span::styled(format!("{}{}", prefix, label), style))
+        })
+        .collect();
+    lines.push(Line::default());
+    if !also.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("Install also runs: {}", also),
+            Style::default().fg(THEME.subtext0),
+        )));
+        lines.push(Line::default());
+    }
+    lines.push(Line::from(Span::styled(
+        "↑/↓: Select  Enter: Confirm  Esc: Cancel",
+        Style::default().fg(THEME.overlay0),
+    )));
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, inner_area);
+    state.popup_choices_area = Rect {
+        height: (choices.len() as u16).min(inner_area.height),
+        ..inner_area
+    };
+}
+
+/// Names of the pending dependencies, comma separated
+fn dependency_names(state: &AppState) -> String {
+    state
+        .dependency_plan
+        .iter()
+        .map(|id| {
+            state
+                .options
+                .iter()
+                .find(|o| &o.id == id)
+                .map_or(id.as_str(), |o| o.name.as_str())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Render the confirmation for an install that pulls in dependencies
+pub fn render_dependencies_popup(frame: &mut Frame, state: &AppState) {
+    let option_name = state
+        .selected_option()
+        .map(|o| o.name.as_str())
+        .unwrap_or("Unknown");
+
+    let popup_area = centered_area(frame.area(), 50, 8);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.mauve))
+        .title(format!(" {} ", option_name))
+        .title_style(Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
+    };
+
+    let lines = vec![
+        Line::from(Span::styled(
+            format!("Will also install: {}", dependency_names(state)),
+            Style::default().fg(THEME.text),
+        )),
+        Line::default(),
+        Line::from(Span::styled(
+            "Dependencies run first; a failure stops the chain.",
+            Style::default().fg(THEME.subtext0),
+        )),
+        Line::default(),
+        Line::from(Span::styled(
+            "Enter: Install all  Esc: Cancel",
+            Style::default().fg(THEME.overlay0),
+        )),
+    ];
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the second confirmation before uninstalling
+pub fn render_uninstall_confirm(frame: &mut Frame, state: &AppState) {
+    let option_name = state
+        .selected_option()
+        .map(|o| o.name.as_str())
+        .unwrap_or("Unknown");
+
+    let popup_area = centered_area(frame.area(), 44, 7);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.red))
+        .title(" Confirm Uninstall ")
+        .title_style(Style::default().fg(THEME.red).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 2,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(3),
+    };
+
+    let lines = vec![
+        Line::from(Span::styled(
+            format!("Uninstall {}?", option_name),
+            Style::default().fg(THEME.red).add_modifier(Modifier::BOLD),
+        )),
+        Line::default(),
+        Line::from(Span::styled(
+            "Enter: Uninstall  Esc: Cancel",
+            Style::default().fg(THEME.overlay0),
+        )),
+    ];
+
+    let paragraph = Paragraph::new(lines);
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the reboot confirmation
+pub fn render_reboot_confirm(frame: &mut Frame, state: &AppState) {
+    let popup_area = centered_area(frame.area(), 50, 8);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.yellow))
+        .title(" Reboot ")
+        .title_style(Style::default().fg(THEME.yellow).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 2,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(3),
+    };
+
+    let lines = vec![
+        Line::from(Span::styled(
+            "Reboot now?",
+            Style::default().fg(THEME.yellow).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            format!("Needed for: {}", state.pending_reboot_names().join(", ")),
+            Style::default().fg(THEME.subtext0),
+        )),
+        Line::default(),
+        Line::from(Span::styled(
+            "Enter: Reboot  Esc: Cancel",
+            Style::default().fg(THEME.overlay0),
+        )),
+    ];
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the question whether to abort the running install and quit
+pub fn render_quit_confirm(frame: &mut Frame, state: &AppState) {
+    let popup_area = centered_area(frame.area(), 56, 8);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.red))
+        .title(" Quit ")
+        .title_style(Style::default().fg(THEME.red).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 2,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(3),
+    };
+
+    let running = state.current_action.as_deref().unwrap_or("An install");
+    let lines = vec![
+        Line::from(Span::styled(
+            "An install is running - quit and abort it?",
+            Style::default().fg(THEME.red).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(running, Style::default().fg(THEME.subtext0))),
+        Line::default(),
+        Line::from(Span::styled(
+            "Enter: Abort and quit  Esc: Keep running",
+            Style::default().fg(THEME.overlay0),
+        )),
+    ];
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the question asked when a run reaches its time limit
+pub fn render_timeout_confirm(frame: &mut Frame, state: &AppState) {
+    let popup_area = centered_area(frame.area(), 56, 9);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.yellow))
+        .title(" Time Limit ")
+        .title_style(Style::default().fg(THEME.yellow).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 2,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(3),
+    };
+
+    let running = state.current_action.as_deref().unwrap_or("The run");
+    let elapsed = state.run_elapsed(Instant::now()).unwrap_or_default();
+    let limit = state.run_watch.as_ref().map(|watch| describe_limit(watch.limit())).unwrap_or_default();
+    let lines = vec![
+        Line::from(Span::styled(
+            format!("{} has run for {}, past its {} limit", running, format_duration(elapsed), limit),
+            Style::default().fg(THEME.yellow).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            "Kill it and everything it started? It won't be marked installed.",
+            Style::default().fg(THEME.subtext0),
+        )),
+        Line::default(),
+        Line::from(Span::styled(
+            "Enter: Kill  Esc: Keep waiting",
+            Style::default().fg(THEME.overlay0),
+        )),
+    ];
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the explanation shown instead of running an option the entry
+/// script doesn't accept
+pub fn render_unsupported_popup(frame: &mut Frame, state: &AppState) {
+    let popup_area = centered_area(frame.area(), 60, 10);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.red))
+        .title(" Not Supported ")
+        .title_style(Style::default().fg(THEME.red).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 2,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(3),
+    };
+
+    let (name, id) = state
+        .selected_option()
+        .map_or(("This option", ""), |option| (option.name.as_str(), option.id.as_str()));
+    let lines = vec![
+        Line::from(Span::styled(
+            format!("{} is not supported by this script version", name),
+            Style::default().fg(THEME.red).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            format!(
+                "{} doesn't list {:?} among the options it accepts, so running it would fail. Update the scripts, or fix the id in the manifest.",
+                display_path(&state.entry_script),
+                id
+            ),
+            Style::default().fg(THEME.subtext0),
+        )),
+        Line::default(),
+        Line::from(Span::styled("Enter/Esc: Close", Style::default().fg(THEME.overlay0))),
+    ];
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the popup collecting the password sudo asked the script for
+///
+/// Only a mask of the typed characters is drawn.
+pub fn render_password_prompt(frame: &mut Frame, state: &AppState) {
+    let popup_area = centered_area(frame.area(), 56, 9);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.yellow))
+        .title(" sudo ")
+        .title_style(Style::default().fg(THEME.yellow).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 2,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(3),
     };
-    let reinstall_prefix = if state.popup_choice == ActionChoice::Reinstall {
-        "> "
+
+    let prompt = state.password_prompt.as_deref().unwrap_or("Password:");
+    let typed = String::from_utf8_lossy(&state.password_input).chars().count();
+    let retry = if state.password_attempts > 1 {
+        format!("Sorry, try again (attempt {})", state.password_attempts)
     } else {
-        "  "
+        String::new()
     };
+    let lines = vec![
+        Line::from(Span::styled(prompt, Style::default().fg(THEME.text))),
+        Line::from(vec![
+            Span::styled("> ", Style::default().fg(THEME.mauve)),
+            Span::styled("*".repeat(typed), Style::default().fg(THEME.text)),
+            Span::styled(GLYPHS.cursor, Style::default().fg(THEME.overlay1)),
+        ]),
+        Line::from(Span::styled(retry, Style::default().fg(THEME.red))),
+        Line::default(),
+        Line::from(Span::styled(
+            "Enter: Send  Esc: Type it in the output instead",
+            Style::default().fg(THEME.overlay0),
+        )),
+    ];
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, inner_area);
+}
 
-    // Uninstall option
-    let uninstall_style = if state.popup_choice == ActionChoice::Uninstall {
-        Style::default().fg(THEME.red).add_modifier(Modifier::BOLD)
+/// Render the preview of a scripts update
+pub fn render_update_popup(frame: &mut Frame, state: &AppState) {
+    let Some(preview) = &state.update_preview else {
+        return;
+    };
+
+    let popup_area = centered_area(frame.area(), 60, 10);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.mauve))
+        .title(" Update Scripts ")
+        .title_style(Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
+    };
+
+    // Installed options are flagged; they'll be offered for reinstall afterwards
+    let affected = if preview.affected.is_empty() {
+        "Updating won't change any option's scripts.".to_string()
     } else {
-        Style::default().fg(THEME.text)
+        let names: Vec<String> = preview
+            .affected
+            .iter()
+            .zip(state.option_names(&preview.affected))
+            .map(|(id, name)| {
+                if state.is_installed(id) {
+                    format!("{} (installed)", name)
+                } else {
+                    name.to_string()
+                }
+            })
+            .collect();
+        format!("Updating will change scripts for: {}", names.join(", "))
+    };
+
+    let lines = vec![
+        Line::from(Span::styled(
+            format!(
+                "{} new commit(s), {} file(s) changed",
+                preview.commits,
+                preview.changed.len()
+            ),
+            Style::default().fg(THEME.subtext0),
+        )),
+        Line::default(),
+        Line::from(Span::styled(affected, Style::default().fg(THEME.text))),
+        Line::default(),
+        Line::from(Span::styled(
+            "Enter: Update  Esc: Cancel",
+            Style::default().fg(THEME.overlay0),
+        )),
+    ];
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the offer to reinstall options whose scripts were updated
+pub fn render_reinstall_popup(frame: &mut Frame, state: &AppState) {
+    let popup_area = centered_area(frame.area(), 56, 8);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.mauve))
+        .title(" Scripts Updated ")
+        .title_style(Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
     };
-    let uninstall_prefix = if state.popup_choice == ActionChoice::Uninstall {
-        "> "
+
+    let lines = vec![
+        Line::from(Span::styled(
+            format!(
+                "Reinstall updated options: {}?",
+                state.option_names(&state.reinstall_suggestion).join(", ")
+            ),
+            Style::default().fg(THEME.text),
+        )),
+        Line::default(),
+        Line::from(Span::styled(
+            "Enter: Queue reinstall  Esc: Skip",
+            Style::default().fg(THEME.overlay0),
+        )),
+    ];
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the popup for marking options installed/not installed without running them
+pub fn render_mark_popup(frame: &mut Frame, state: &AppState) {
+    let popup_area = centered_area(frame.area(), 50, 9);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.yellow))
+        .title(" Edit Installed State ")
+        .title_style(Style::default().fg(THEME.yellow).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
+    };
+
+    let names: Vec<&str> = state
+        .mark_targets
+        .iter()
+        .filter_map(|id| state.options.iter().find(|o| &o.id == id))
+        .map(|o| o.name.as_str())
+        .collect();
+    let targets = if names.len() == 1 {
+        names[0].to_string()
     } else {
-        "  "
+        format!("{} options: {}", names.len(), names.join(", "))
+    };
+
+    let choice = |label: &str, selected: bool, color: Color| {
+        let (prefix, style) = if selected {
+            ("> ", Style::default().fg(color).add_modifier(Modifier::BOLD))
+        } else {
+            ("  ", Style::default().fg(THEME.text))
+        };
+        Line::from(Span::styled(format!("{}{}", prefix, label), style))
     };
 
     let lines = vec![
+        Line::from(Span::styled(targets, Style::default().fg(THEME.subtext0))),
+        Line::default(),
+        choice("Mark as installed", state.mark_as_installed, THEME.green),
+        choice("Mark as not installed", !state.mark_as_installed, THEME.red),
+        Line::default(),
+        Line::from(Span::styled(
+            "The install script is not run",
+            Style::default().fg(THEME.overlay0),
+        )),
+    ];
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the warning for target files that are open in an editor
+pub fn render_editor_locks_popup(frame: &mut Frame, state: &AppState) {
+    let shown = state.editor_locks.len().min(8);
+    let popup_area = centered_area(frame.area(), 70, shown as u16 * 2 + 7);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.yellow))
+        .title(" Files Open in an Editor ")
+        .title_style(Style::default().fg(THEME.yellow).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
+    };
+
+    let mut lines = vec![
         Line::from(Span::styled(
-            format!("{}Install / Update", reinstall_prefix),
-            reinstall_style,
+            "The run will overwrite files that may have unsaved changes:",
+            Style::default().fg(THEME.text),
         )),
+        Line::default(),
+    ];
+    for lock in state.editor_locks.iter().take(shown) {
+        lines.push(Line::from(Span::styled(
+            display_path(&lock.target),
+            Style::default().fg(THEME.peach),
+        )));
+        lines.push(Line::from(Span::styled(
+            format!("  {}: {}", lock.editor, display_path(&lock.lock)),
+            Style::default().fg(THEME.subtext0),
+        )));
+    }
+    if state.editor_locks.len() > shown {
+        lines.push(Line::from(Span::styled(
+            format!("...and {} more", state.editor_locks.len() - shown),
+            Style::default().fg(THEME.overlay0),
+        )));
+    }
+    lines.push(Line::default());
+    lines.push(Line::from(Span::styled(
+        "Enter: Proceed anyway  Esc: Cancel",
+        Style::default().fg(THEME.overlay0),
+    )));
+
+    let paragraph = Paragraph::new(lines);
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the warning for options outside their Omarchy version range
+pub fn render_incompatible_popup(frame: &mut Frame, state: &AppState) {
+    let shown = state.compat_warnings.len().min(8);
+    let more = state.compat_warnings.len() > shown;
+    let popup_area = centered_area(frame.area(), 70, shown as u16 + 8 + more as u16);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.yellow))
+        .title(" Version Mismatch ")
+        .title_style(Style::default().fg(THEME.yellow).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
+    };
+
+    let mut lines = vec![
         Line::from(Span::styled(
-            format!("{}Uninstall", uninstall_prefix),
-            uninstall_style,
+            "Not made for the installed Omarchy release:",
+            Style::default().fg(THEME.text),
         )),
         Line::default(),
+    ];
+    for warning in state.compat_warnings.iter().take(shown) {
+        lines.push(Line::from(Span::styled(warning.as_str(), Style::default().fg(THEME.peach))));
+    }
+    if more {
+        lines.push(Line::from(Span::styled(
+            format!("...and {} more", state.compat_warnings.len() - shown),
+            Style::default().fg(THEME.overlay0),
+        )));
+    }
+    lines.push(Line::default());
+    lines.push(Line::from(Span::styled(
+        "They may not apply cleanly or may break parts of the desktop.",
+        Style::default().fg(THEME.subtext0),
+    )));
+    lines.push(Line::default());
+    lines.push(Line::from(Span::styled(
+        "Enter: Proceed anyway  Esc: Cancel",
+        Style::default().fg(THEME.overlay0),
+    )));
+
+    let paragraph = Paragraph::new(lines);
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the warning for options that would run without their sandbox
+pub fn render_unsandboxed_popup(frame: &mut Frame, state: &AppState) {
+    let shown = state.unsandboxed.len().min(8);
+    let more = state.unsandboxed.len() > shown;
+    let popup_area = centered_area(frame.area(), 70, shown as u16 + 8 + more as u16);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.red))
+        .title(" No Sandbox Available ")
+        .title_style(Style::default().fg(THEME.red).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
+    };
+
+    let mut lines = vec![
         Line::from(Span::styled(
-            "↑/↓: Select  Enter: Confirm  Esc: Cancel",
+            "These options should run sandboxed:",
+            Style::default().fg(THEME.text),
+        )),
+        Line::default(),
+    ];
+    for name in state.unsandboxed.iter().take(shown) {
+        lines.push(Line::from(Span::styled(format!("  {}", name), Style::default().fg(THEME.peach))));
+    }
+    if more {
+        lines.push(Line::from(Span::styled(
+            format!("...and {} more", state.unsandboxed.len() - shown),
             Style::default().fg(THEME.overlay0),
+        )));
+    }
+    lines.push(Line::default());
+    lines.push(Line::from(Span::styled(
+        "No bwrap or firejail found; they would run with full access.",
+        Style::default().fg(THEME.subtext0),
+    )));
+    lines.push(Line::default());
+    lines.push(Line::from(Span::styled(
+        "Enter: Run unsandboxed  Esc: Cancel",
+        Style::default().fg(THEME.overlay0),
+    )));
+
+    let paragraph = Paragraph::new(lines);
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the warning for hand-edited files a reinstall would overwrite
+pub fn render_local_changes_popup(frame: &mut Frame, state: &AppState) {
+    const MAX_FILES: usize = 8;
+    let mut body = vec![];
+    let mut shown = 0;
+    for changes in &state.local_changes {
+        body.push(Line::from(Span::styled(
+            format!(
+                "{} of {} files managed by {} have local modifications",
+                changes.modified.len(),
+                changes.managed,
+                changes.option_name
+            ),
+            Style::default().fg(THEME.text),
+        )));
+        for path in &changes.modified {
+            if shown == MAX_FILES {
+                break;
+            }
+            body.push(Line::from(Span::styled(
+                format!("  {}", display_path(path)),
+                Style::default().fg(THEME.peach),
+            )));
+            shown += 1;
+        }
+    }
+    let total: usize = state.local_changes.iter().map(|c| c.modified.len()).sum();
+    if total > shown {
+        body.push(Line::from(Span::styled(
+            format!("  ...and {} more", total - shown),
+            Style::default().fg(THEME.overlay0),
+        )));
+    }
+
+    let popup_area = centered_area(frame.area(), 70, body.len() as u16 + 6);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.yellow))
+        .title(" Local Modifications ")
+        .title_style(Style::default().fg(THEME.yellow).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
+    };
+
+    let choice = |label: &str, value: LocalChangesChoice, color: Color| {
+        let (prefix, style) = if state.local_changes_choice == value {
+            ("> ", Style::default().fg(color).add_modifier(Modifier::BOLD))
+        } else {
+            ("  ", Style::default().fg(THEME.text))
+        };
+        Line::from(Span::styled(format!("{}{}", prefix, label), style))
+    };
+
+    let mut lines = body;
+    lines.push(Line::default());
+    lines.push(choice("Back up the files, then reinstall", LocalChangesChoice::BackUp, THEME.green));
+    lines.push(choice("Reinstall and overwrite them", LocalChangesChoice::Proceed, THEME.yellow));
+    lines.push(choice("Cancel", LocalChangesChoice::Cancel, THEME.red));
+
+    let paragraph = Paragraph::new(lines);
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the overlay listing decisions left over from earlier sessions
+pub fn render_pending_popup(frame: &mut Frame, state: &AppState) {
+    let items = &state.pending.items;
+    let popup_area = centered_area(frame.area(), 70, items.len() as u16 * 4 + 5);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.mauve))
+        .title(" Pending Items ")
+        .title_style(Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "The last session left these undecided:",
+            Style::default().fg(THEME.text),
+        )),
+        Line::default(),
+    ];
+    for (index, item) in items.iter().enumerate() {
+        let focused = index == state.pending.focus;
+        let (title, detail) = match item.kind {
+            PendingKind::Batch => {
+                let detail = match &state.resume_journal {
+                    Some(journal) => {
+                        let summary = journal.summary();
+                        let mut progress = format!(
+                            "{} done, {} failed, {} remaining",
+                            summary.done, summary.failed, summary.remaining
+                        );
+                        if summary.unknown > 0 {
+                            progress.push_str(&format!(", {} interrupted", summary.unknown));
+                        }
+                        progress
+                    }
+                    None => String::new(),
+                };
+                ("Unfinished batch", detail)
+            }
+            PendingKind::Reboot => (
+                "Reboot pending",
+                format!("Needed for: {}", state.pending_reboot_names().join(", ")),
+            ),
+            PendingKind::UnknownIds => ("Unknown options in the state file", describe_unknown(&state.unknown_ids)),
+        };
+        let title_style = if focused {
+            Style::default().fg(THEME.mauve).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(THEME.text)
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{}{}", if focused { "> " } else { "  " }, title),
+            title_style,
+        )));
+        lines.push(Line::from(Span::styled(format!("  {}", detail), Style::default().fg(THEME.subtext0))));
+
+        // The choices sit on one row, the selected one highlighted
+        let mut choices = vec![Span::raw("  ")];
+        for &choice in item.kind.choices() {
+            let style = match (choice == item.choice(), focused) {
+                (true, true) if colorless() => Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+                (true, true) => Style::default().fg(THEME.base).bg(THEME.mauve).add_modifier(Modifier::BOLD),
+                (true, false) => Style::default().fg(THEME.mauve).add_modifier(Modifier::BOLD),
+                (false, _) => Style::default().fg(THEME.overlay1),
+            };
+            choices.push(Span::styled(format!(" {} ", choice.label()), style));
+            choices.push(Span::raw(" "));
+        }
+        lines.push(Line::from(choices));
+        lines.push(Line::default());
+    }
+    lines.push(Line::from(Span::styled(
+        "↑/↓: Item  ←/→: Choice  Enter: Apply  Esc: Decide all later",
+        Style::default().fg(THEME.overlay0),
+    )));
+
+    let paragraph = Paragraph::new(lines);
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the profile chooser, one row per profile with its progress
+pub fn render_profiles_popup(frame: &mut Frame, state: &AppState) {
+    let profiles = &state.profiles;
+    let popup_area = centered_area(frame.area(), 64, profiles.len() as u16 * 2 + 6);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.mauve))
+        .title(" Profiles ")
+        .title_style(Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Install the options of a profile that aren't installed yet:",
+            Style::default().fg(THEME.text),
+        )),
+        Line::default(),
+    ];
+    for (index, profile) in profiles.iter().enumerate() {
+        let focused = index == state.profile_focus;
+        let (installed, total) = state.profile_progress(profile);
+        let unknown = profile.unknown_ids(&state.options).len();
+        let name_style = if focused {
+            Style::default().fg(THEME.mauve).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(THEME.text)
+        };
+        let mut title = vec![
+            Span::styled(format!("{}{}", if focused { "> " } else { "  " }, profile.name), name_style),
+            Span::styled(
+                format!("  {}/{} installed", installed, total),
+                Style::default().fg(if installed == total { THEME.green } else { THEME.subtext0 }),
+            ),
+        ];
+        if unknown > 0 {
+            title.push(Span::styled(
+                format!("  {} unknown id{}", unknown, if unknown == 1 { "" } else { "s" }),
+                Style::default().fg(THEME.yellow),
+            ));
+        }
+        lines.push(Line::from(title));
+        lines.push(Line::from(Span::styled(
+            format!("  {}", state.option_names(&profile.option_ids).join(", ")),
+            Style::default().fg(THEME.overlay1),
+        )));
+    }
+    lines.push(Line::default());
+    lines.push(Line::from(Span::styled(
+        "↑/↓: Profile  Enter: Install missing  Esc: Cancel",
+        Style::default().fg(THEME.overlay0),
+    )));
+
+    let paragraph = Paragraph::new(lines);
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the list of an option's past runs, newest first
+pub fn render_log_browser(frame: &mut Frame, state: &AppState) {
+    let history = &state.log_history;
+    // Rows left for runs once the borders, intro and key hints are drawn
+    let rows = (frame.area().height.saturating_sub(8) as usize).clamp(1, history.len().max(1));
+    let popup_area = centered_area(frame.area(), 72, rows as u16 + 6);
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(THEME.mauve))
+        .title(format!(" Logs: {} ", state.log_history_option))
+        .title_style(Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} past run(s), newest first:", history.len()),
+            Style::default().fg(THEME.text),
         )),
+        Line::default(),
     ];
+    // Keep the focused run in view
+    let window = ViewWindow::new(history.len(), rows, state.log_focus.saturating_sub(rows - 1));
+    for index in window.range() {
+        let entry = &history[index];
+        let focused = index == state.log_focus;
+        let row_style = if focused {
+            Style::default().fg(THEME.mauve).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(THEME.text)
+        };
+        let action = match entry.action {
+            RunAction::Install => "install",
+            RunAction::Uninstall => "uninstall",
+        };
+        let (result, result_color) = match &entry.result {
+            Some(result) if entry.succeeded() => (result.as_str(), THEME.green),
+            Some(result) => (result.as_str(), THEME.red),
+            None => ("unfinished", THEME.yellow),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{}{}  {:<9}  ", if focused { "> " } else { "  " }, entry.started, action),
+                row_style,
+            ),
+            Span::styled(result.to_string(), Style::default().fg(result_color)),
+            Span::styled(format!("  {} KiB", entry.size.div_ceil(1024)), Style::default().fg(THEME.overlay1)),
+        ]));
+    }
+    lines.push(Line::default());
+    lines.push(Line::from(Span::styled(
+        "↑/↓: Run  Enter: View in output panel  Esc: Close",
+        Style::default().fg(THEME.overlay0),
+    )));
 
     let paragraph = Paragraph::new(lines);
     frame.render_widget(paragraph, inner_area);
 }
+
+/// Render how each item of the finished queue went
+pub fn render_queue_summary(frame: &mut Frame, state: &AppState) {
+    let results = &state.queue_results;
+    let rows = (frame.area().height.saturating_sub(8) as usize).clamp(1, results.len().max(1));
+    let popup_area = centered_area(frame.area(), 72, rows as u16 + 6);
+    frame.render_widget(Clear, popup_area);
+
+    let failed = results.iter().filter(|result| result.failed()).count();
+    let border = if failed > 0 { THEME.red } else { THEME.green };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(GLYPHS.border)
+        .border_style(Style::default().fg(border))
+        .title(" Summary ")
+        .title_style(Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(THEME.base));
+
+    frame.render_widget(block, popup_area);
+
+    let inner_area = Rect {
+        x: popup_area.x + 2,
+        y: popup_area.y + 1,
+        width: popup_area.width.saturating_sub(4),
+        height: popup_area.height.saturating_sub(2),
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} run(s), {} failed:", results.len(), failed),
+            Style::default().fg(THEME.text),
+        )),
+        Line::default(),
+    ];
+    let window = ViewWindow::new(results.len(), rows, state.summary_focus.saturating_sub(rows - 1));
+    for index in window.range() {
+        let result = &results[index];
+        let focused = index == state.summary_focus;
+        let color = if result.failed() { THEME.red } else { THEME.text };
+        let mut row_style = Style::default().fg(color);
+        if focused {
+            row_style = row_style.add_modifier(Modifier::BOLD);
+        }
+        let name = state
+            .options
+            .iter()
+            .find(|o| o.id == result.option_id)
+            .map_or(result.option_id.as_str(), |o| o.name.as_str());
+        let action = if result.uninstall { "uninstall" } else { "install" };
+        let (outcome, outcome_color) = match result.duration {
+            None => ("not run".to_string(), THEME.red),
+            Some(_) if result.exit_code != 0 => (format!("exit {}", result.exit_code), THEME.red),
+            Some(_) if result.verify_failed => ("exit 0, verify failed".to_string(), THEME.red),
+            Some(_) => ("exit 0".to_string(), THEME.green),
+        };
+        let duration = result.duration.map(format_duration).unwrap_or_default();
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{}{:<24}  {:<9}  ", if focused { "> " } else { "  " }, name, action),
+                row_style,
+            ),
+            Span::styled(format!("{:<21}", outcome), Style::default().fg(outcome_color)),
+            Span::styled(format!("  {}", duration), Style::default().fg(THEME.overlay1)),
+        ]));
+    }
+    lines.push(Line::default());
+    let focused = results.get(state.summary_focus);
+    let hint = if focused.is_some_and(|result| result.failed() && result.log.is_some()) {
+        "↑/↓: Run  l: Open log  Enter/Esc: Close"
+    } else {
+        "↑/↓: Run  Enter/Esc: Close"
+    };
+    lines.push(Line::from(Span::styled(hint, Style::default().fg(THEME.overlay0))));
+
+    let paragraph = Paragraph::new(lines);
+    frame.render_widget(paragraph, inner_area);
+}
+
+/// Render the problems found during startup
+pub fn render_startup_warnings(frame: &mut Frame, state: &AppState) {
+    let mut lines: Vec<Line> = Vec::new();
+    for warning in &state.startup_warnings {
+        lines.push(Line::from(vec![
+            Span::styled("• ", Style::default().fg(THEME.yellow)),
+            Span::styled(warning.as_str(), Style::default().fg(THEME.text)),
+        ]));
+    }
+    lines.push(Line::default());
+    lines.push(Line::from(Span::styled(
+        "Press any key to continue",
+        Style::default().fg(THEME.overlay0),
+    )));
+
+    // Leave room for wrapped warnings
+    let height = state.startup_warnings.len() as u16 * 3 + 4;
+    let popup_area = centered_area(frame.area(), 70, height);
+    frame.render_widget(Clear, popup_area);
+
+    let paragraph = Paragraph::new(lines)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(GLYPHS.border)
+                .border_style(Style::default().fg(THEME.yellow))
+                .title(" Startup Warnings ")
+                .title_style(Style::default().fg(THEME.yellow).add_modifier(Modifier::BOLD))
+                .style(Style::default().bg(THEME.base)),
+        );
+    frame.render_widget(paragraph, popup_area);
+}
+
+/// A centered rectangle of the given size, clamped to the available area
+pub(super) fn centered_area(area: Rect, width: u16, height: u16) -> Rect {
+    Rect {
+        x: area.width.saturating_sub(width) / 2,
+        y: area.height.saturating_sub(height) / 2,
+        width: width.min(area.width),
+        height: height.min(area.height),
+    }
+}
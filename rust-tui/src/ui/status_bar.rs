@@ -5,14 +5,117 @@ use ratatui::{
     widgets::Paragraph,
 };
 
-use crate::state::AppState;
+use super::glyphs::GLYPHS;
+use crate::pending::PendingKind;
+use crate::state::{AppMode, AppState};
+use crate::timeout::describe_limit;
 use crate::theme::THEME;
 
 /// Render the status bar
 pub fn render_status_bar(frame: &mut Frame, area: Rect, state: &AppState) {
     let style = Style::default().fg(THEME.yellow).bg(THEME.mantle);
 
-    let paragraph = Paragraph::new(state.status_message.as_str()).style(style);
+    // The filter input line takes over the status bar while typing
+    if state.mode == AppMode::Filter {
+        let line = Line::from(vec![
+            Span::styled("/", Style::default().fg(THEME.mauve)),
+            Span::styled(state.filter.as_str(), Style::default().fg(THEME.text)),
+            Span::styled(GLYPHS.cursor, Style::default().fg(THEME.overlay1)),
+        ]);
+        frame.render_widget(Paragraph::new(line).style(style), area);
+        return;
+    }
 
-    frame.render_widget(paragraph, area);
+    // So does a jump query, with the option it found on the right
+    if state.mode == AppMode::Jump {
+        let line = Line::from(vec![
+            Span::styled("'", Style::default().fg(THEME.mauve)),
+            Span::styled(state.jump_query.as_str(), Style::default().fg(THEME.text)),
+            Span::styled(GLYPHS.cursor, Style::default().fg(THEME.overlay1)),
+        ]);
+        let found = Line::styled(state.status_message.as_str(), Style::default().fg(THEME.overlay0)).right_aligned();
+        frame.render_widget(Paragraph::new(line).style(style), area);
+        frame.render_widget(Paragraph::new(found), area);
+        return;
+    }
+
+    // So does the command line, with hints and completion notes on the right
+    if state.mode == AppMode::Command {
+        let line = Line::from(vec![
+            Span::styled(":", Style::default().fg(THEME.mauve)),
+            Span::styled(state.command_input.as_str(), Style::default().fg(THEME.text)),
+            Span::styled(GLYPHS.cursor, Style::default().fg(THEME.overlay1)),
+        ]);
+        let hint = Line::styled(state.status_message.as_str(), Style::default().fg(THEME.overlay0)).right_aligned();
+        frame.render_widget(Paragraph::new(line).style(style), area);
+        frame.render_widget(Paragraph::new(hint), area);
+        return;
+    }
+
+    // Saving and pending reboots stay visible on the right whatever the message says
+    let names = state.pending_reboot_names();
+    let mut notice = String::new();
+    if state.saving {
+        notice.push_str(" saving…");
+    }
+    if state.unsaved > 0 {
+        notice.push_str(&format!(" {} unsaved (S retries)", state.unsaved));
+    }
+    if let Some(watch) = state.run_watch.as_ref().filter(|watch| watch.overdue()) {
+        notice.push_str(&format!(" Running longer than expected ({} limit)", describe_limit(watch.limit())));
+    }
+    if state.pending.contains(PendingKind::Batch) {
+        notice.push_str(" Unfinished batch (P)");
+    }
+    if state.pending.contains(PendingKind::UnknownIds) {
+        notice.push_str(&format!(" {} unknown in state (P)", state.unknown_ids.len()));
+    }
+    if !names.is_empty() {
+        notice.push_str(&format!(" Reboot required for: {} (R)", names.join(", ")));
+    }
+    let notice_width = (notice.chars().count() as u16).min(area.width / 2);
+
+    // Catalog progress goes rightmost, dropped when it would crowd the message
+    let progress = progress_segment(state);
+    let progress_width = progress.chars().count() as u16;
+    let message_width = state.status_message.chars().count() as u16;
+    let show_progress = message_width + notice_width + progress_width < area.width;
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(notice_width),
+            Constraint::Length(if show_progress { progress_width } else { 0 }),
+        ])
+        .split(area);
+
+    frame.render_widget(Paragraph::new(state.status_message.as_str()).style(style), chunks[0]);
+    if !notice.is_empty() {
+        let notice = Paragraph::new(notice)
+            .style(style.add_modifier(Modifier::BOLD))
+            .alignment(Alignment::Right);
+        frame.render_widget(notice, chunks[1]);
+    }
+    if show_progress {
+        let progress = Paragraph::new(progress)
+            .style(Style::default().fg(THEME.subtext0).bg(THEME.mantle))
+            .alignment(Alignment::Right);
+        frame.render_widget(progress, chunks[2]);
+    }
+}
+
+/// "3/9 installed · option 2 of 9", counting only the options in the catalog
+///
+/// The installed count leaves out options that don't track state.
+fn progress_segment(state: &AppState) -> String {
+    let total = state.options.iter().filter(|option| option.tracks_state()).count();
+    let installed = state.options.iter().filter(|option| state.is_installed(&option.id)).count();
+    let mut segment = format!(" {}/{} installed", installed, total);
+    let selected = state.selected_option_index();
+    if let Some(position) = selected.and_then(|i| state.visible.iter().position(|&v| v == i)) {
+        segment.push_str(&format!(" · option {} of {}", position + 1, state.visible.len()));
+    }
+    segment.push(' ');
+    segment
 }
@@ -0,0 +1,114 @@
+//! Stage timings overlay, for telling installer overhead from script time
+
+use std::time::Duration;
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use super::format_duration;
+use super::glyphs::GLYPHS;
+use super::popup::centered_area;
+use crate::state::AppState;
+use crate::theme::THEME;
+use crate::timings::StageTimings;
+
+/// Width of the stage name column
+const LABEL_WIDTH: usize = 22;
+/// Width of each timing column
+const VALUE_WIDTH: usize = 14;
+
+/// Format milliseconds, keeping sub-second precision for short stages
+fn format_millis(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms < 10_000 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else {
+        format_duration(Duration::from_millis(ms))
+    }
+}
+
+/// Reads one stage out of a set of timings
+type Stage = fn(&StageTimings) -> Option<u64>;
+
+/// Rows of the table: label and the stage shown
+const ROWS: [(&str, Stage); 8] = [
+    ("Enter to spawn", |t| Some(t.launch_ms)),
+    ("Spawn to first output", |t| t.first_output_ms),
+    ("Script running", |t| Some(t.running_ms)),
+    ("  ingesting output", |t| Some(t.ingest_ms)),
+    ("  waiting on script", |t| Some(t.idle_ms())),
+    ("Exit to completion", |t| Some(t.wrap_up_ms)),
+    ("Total", |t| Some(t.total_ms())),
+    ("Installer overhead", |t| Some(t.overhead_ms())),
+];
+
+/// Render the selected option's last run timings beside the mean over all options
+pub fn render_timings(frame: &mut Frame, state: &AppState) {
+    let label_style = Style::default().fg(THEME.overlay1);
+    let value_style = Style::default().fg(THEME.text);
+    let heading_style = Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD);
+
+    let option = state.selected_option();
+    let last = option
+        .and_then(|option| state.records.get(&option.id))
+        .and_then(|record| record.last_timings);
+    let recorded: Vec<&StageTimings> = state
+        .records
+        .values()
+        .filter_map(|record| record.last_timings.as_ref())
+        .collect();
+    let mean = StageTimings::mean(recorded.iter().copied());
+
+    let mut lines = vec![Line::from(vec![
+        Span::styled(format!("{:<LABEL_WIDTH$}", ""), label_style),
+        Span::styled(format!("{:>VALUE_WIDTH$}", "last run"), heading_style),
+        Span::styled(
+            format!("{:>VALUE_WIDTH$}", format!("mean of {}", recorded.len())),
+            heading_style,
+        ),
+    ])];
+    for (label, value) in ROWS {
+        let cell = |timings: Option<StageTimings>| {
+            timings
+                .as_ref()
+                .and_then(value)
+                .map(format_millis)
+                .unwrap_or_else(|| "-".to_string())
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:<LABEL_WIDTH$}", label), label_style),
+            Span::styled(format!("{:>VALUE_WIDTH$}", cell(last)), value_style),
+            Span::styled(format!("{:>VALUE_WIDTH$}", cell(mean)), value_style),
+        ]));
+    }
+
+    lines.push(Line::default());
+    if last.is_none() {
+        lines.push(Line::from(Span::styled(
+            "No timed run of this option yet",
+            Style::default().fg(THEME.subtext0),
+        )));
+    }
+    lines.push(Line::from(Span::styled(
+        "Press any key to close",
+        Style::default().fg(THEME.overlay0),
+    )));
+
+    let width = (LABEL_WIDTH + 2 * VALUE_WIDTH + 4) as u16;
+    let popup_area = centered_area(frame.area(), width, lines.len() as u16 + 2);
+    frame.render_widget(Clear, popup_area);
+
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(GLYPHS.border)
+            .border_style(Style::default().fg(THEME.mauve))
+            .title(format!(" Run Timings: {} ", option.map(|o| o.name.as_str()).unwrap_or("-")))
+            .title_style(Style::default().fg(THEME.pink).add_modifier(Modifier::BOLD))
+            .style(Style::default().bg(THEME.base)),
+    );
+    frame.render_widget(paragraph, popup_area);
+}
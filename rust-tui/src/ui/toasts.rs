@@ -0,0 +1,37 @@
+//! Transient messages floating above the status bar
+
+use ratatui::{
+    prelude::*,
+    widgets::{Clear, Paragraph},
+};
+
+use crate::state::{AppState, ToastKind};
+use crate::theme::THEME;
+
+/// Render the toasts right-aligned above the status bar, newest lowest
+pub fn render_toasts(frame: &mut Frame, status_bar: Rect, state: &AppState) {
+    let mut bottom = status_bar.y;
+    for toast in state.toasts.iter().rev() {
+        if bottom <= frame.area().y {
+            break;
+        }
+        let color = match toast.kind {
+            ToastKind::Success => THEME.green,
+            ToastKind::Warning => THEME.yellow,
+            ToastKind::Error => THEME.red,
+        };
+        let text = format!(" {} ", toast.message);
+        // Inset from the right so the panel border under it stays visible
+        let width = (text.chars().count() as u16).min(status_bar.width.saturating_sub(2));
+        let area = Rect {
+            x: status_bar.right().saturating_sub(width + 2),
+            y: bottom - 1,
+            width,
+            height: 1,
+        };
+        let style = Style::default().fg(color).bg(THEME.surface0).add_modifier(Modifier::BOLD);
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(text).style(style), area);
+        bottom -= 1;
+    }
+}
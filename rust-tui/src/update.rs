@@ -0,0 +1,241 @@
+//! Updating the scripts repository with git
+//!
+//! Before pulling, the upstream branch is fetched and diffed so the user can
+//! see which options' sources would change. Those that are installed are
+//! candidates for a reinstall once the pull has gone through.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::options::InstallOption;
+
+/// Result of background git work, sent back to the UI
+pub enum UpdateEvent {
+    Previewed(Result<UpdatePreview, String>),
+    Pulled(Result<(), String>),
+}
+
+/// What pulling the fetched upstream would change
+#[derive(Debug, Clone, Default)]
+pub struct UpdatePreview {
+    /// Commits the local branch is behind by
+    pub commits: usize,
+    /// Changed paths, relative to the repository root
+    pub changed: Vec<String>,
+    /// Options whose sources changed
+    pub affected: Vec<String>,
+}
+
+impl UpdatePreview {
+    /// Whether there is anything to pull
+    pub fn is_empty(&self) -> bool {
+        self.commits == 0
+    }
+}
+
+/// Run git in `dir`, returning trimmed stdout or a one-line error
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("could not run git: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.lines().find(|l| !l.trim().is_empty()).unwrap_or("failed");
+        Err(format!("git {}: {}", args[0], message.trim()))
+    }
+}
+
+/// Options with a source at or under one of `changed`
+///
+/// Sources are relative to `prefix` (the script directory's path within the
+/// repository, empty when they're the same) and match whole path components.
+fn options_for_paths(options: &[InstallOption], prefix: &str, changed: &[String]) -> Vec<String> {
+    options
+        .iter()
+        .filter(|option| {
+            option.sources.iter().any(|source| {
+                let source = format!("{}{}", prefix, source.trim_end_matches('/'));
+                changed.iter().any(|path| {
+                    path == &source
+                        || path
+                            .strip_prefix(&source)
+                            .is_some_and(|rest| rest.starts_with('/'))
+                })
+            })
+        })
+        .map(|option| option.id.clone())
+        .collect()
+}
+
+/// Fetch the upstream branch and work out what pulling it would change
+///
+/// Blocks on the network; call it off the UI thread.
+pub fn preview_update(script_dir: &Path, options: &[InstallOption]) -> Result<UpdatePreview, String> {
+    git(script_dir, &["fetch", "--quiet"])?;
+    let commits = git(script_dir, &["rev-list", "--count", "HEAD..@{upstream}"])?
+        .parse()
+        .unwrap_or(0);
+    let changed: Vec<String> = git(script_dir, &["diff", "--name-only", "HEAD...@{upstream}"])?
+        .lines()
+        .map(str::to_string)
+        .collect();
+    let prefix = git(script_dir, &["rev-parse", "--show-prefix"])?;
+    let affected = options_for_paths(options, &prefix, &changed);
+    Ok(UpdatePreview {
+        commits,
+        changed,
+        affected,
+    })
+}
+
+/// Fast-forward to the fetched upstream
+pub fn pull(script_dir: &Path) -> Result<(), String> {
+    git(script_dir, &["merge", "--ff-only", "--quiet", "@{upstream}"]).map(|_| ())
+}
+
+/// Affected options worth reinstalling after a pull: those that are installed
+pub fn reinstall_candidates(preview: &UpdatePreview, is_installed: impl Fn(&str) -> bool) -> Vec<String> {
+    preview
+        .affected
+        .iter()
+        .filter(|id| is_installed(id))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn option(id: &str, sources: &[&str]) -> InstallOption {
+        InstallOption {
+            id: id.to_string(),
+            sources: sources.iter().map(|source| source.to_string()).collect(),
+            ..InstallOption::default()
+        }
+    }
+
+    fn options() -> Vec<InstallOption> {
+        vec![
+            option("fish", &["config/fish/", "config/starship"]),
+            option("bindings", &["config/hyprland/bindings.conf"]),
+            option("corners", &["config/waycorner"]),
+            option("kernel", &[]),
+        ]
+    }
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[test]
+    fn changed_paths_map_to_the_options_they_belong_to() {
+        let changed = paths(&["config/fish/config.fish", "config/hyprland/bindings.conf", "README.md"]);
+        assert_eq!(options_for_paths(&options(), "", &changed), ["fish", "bindings"]);
+    }
+
+    #[test]
+    fn sources_match_whole_path_components() {
+        let changed = paths(&["config/fishy/theme", "config/hyprland/bindings.conf.bak", "config/waycorner"]);
+        assert_eq!(options_for_paths(&options(), "", &changed), ["corners"]);
+    }
+
+    #[test]
+    fn sources_are_relative_to_the_script_dir() {
+        let changed = paths(&["scripts/config/starship/starship.toml", "config/waycorner/config.toml"]);
+        assert_eq!(options_for_paths(&options(), "scripts/", &changed), ["fish"]);
+        assert!(options_for_paths(&options(), "scripts/", &[]).is_empty());
+    }
+
+    #[test]
+    fn only_installed_options_are_reinstalled() {
+        let preview = UpdatePreview {
+            commits: 2,
+            changed: Vec::new(),
+            affected: paths(&["fish", "bindings", "corners"]),
+        };
+        assert_eq!(reinstall_candidates(&preview, |id| id != "bindings"), ["fish", "corners"]);
+        assert!(reinstall_candidates(&preview, |_| false).is_empty());
+    }
+
+    /// Git with an identity, so commits work without any configuration
+    fn commit_all(dir: &Path, message: &str) {
+        git(dir, &["add", "-A"]).unwrap();
+        let identity = ["-c", "user.name=test", "-c", "user.email=test@example.com"];
+        git(dir, &[&identity[..], &["commit", "-q", "-m", message]].concat()).unwrap();
+    }
+
+    fn write(dir: &Path, path: &str, contents: &str) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    /// An upstream repository and a clone of it, scripts under `scripts/`
+    fn fixture(name: &str) -> Option<(PathBuf, PathBuf, PathBuf)> {
+        let root = std::env::temp_dir().join(format!("omarchy-cybex-update-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (upstream, clone) = (root.join("upstream"), root.join("clone"));
+        fs::create_dir_all(&upstream).unwrap();
+        // None when git isn't installed
+        git(&upstream, &["init", "-q"]).ok()?;
+        write(&upstream, "scripts/config/fish/config.fish", "set -g fish_greeting\n");
+        write(&upstream, "scripts/config/hyprland/bindings.conf", "bind = SUPER, Q, exec, kitty\n");
+        write(&upstream, "README.md", "scripts\n");
+        commit_all(&upstream, "initial");
+        git(&root, &["clone", "-q", "upstream", "clone"]).unwrap();
+        Some((root, upstream, clone))
+    }
+
+    #[test]
+    fn preview_lists_upstream_changes_and_the_options_they_touch() {
+        let Some((root, upstream, clone)) = fixture("preview") else {
+            return;
+        };
+        write(&upstream, "scripts/config/fish/config.fish", "set -g fish_greeting hello\n");
+        commit_all(&upstream, "greet");
+        write(&upstream, "README.md", "the scripts\n");
+        commit_all(&upstream, "docs");
+
+        let scripts = clone.join("scripts");
+        let preview = preview_update(&scripts, &options()).unwrap();
+        assert_eq!(preview.commits, 2);
+        assert_eq!(preview.changed, ["README.md", "scripts/config/fish/config.fish"]);
+        assert_eq!(preview.affected, ["fish"]);
+        assert!(!preview.is_empty());
+
+        pull(&scripts).unwrap();
+        let contents = fs::read_to_string(scripts.join("config/fish/config.fish")).unwrap();
+        assert_eq!(contents, "set -g fish_greeting hello\n");
+        assert!(preview_update(&scripts, &options()).unwrap().is_empty(), "nothing left to pull");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn preview_of_an_up_to_date_clone_is_empty() {
+        let Some((root, _, clone)) = fixture("current") else {
+            return;
+        };
+        let preview = preview_update(&clone, &options()).unwrap();
+        assert!(preview.is_empty());
+        assert!(preview.changed.is_empty() && preview.affected.is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn git_errors_name_the_command() {
+        let dir = std::env::temp_dir().join(format!("omarchy-cybex-update-plain-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let error = preview_update(&dir, &options()).unwrap_err();
+        assert!(error.starts_with("git fetch: "), "{}", error);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
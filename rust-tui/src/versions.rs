@@ -0,0 +1,81 @@
+//! Asking the install script whether installed options have updates
+//!
+//! `install version <id>` prints one line like `installed=1.2.0 available=1.3.1`:
+//! the version on the system and the one the script would install now. Any
+//! other answer, including the failure the bundled script gives for the
+//! unknown `version` parameter, means the option has no version to show.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use crate::check::{run_program, CheckStatus};
+use crate::compat::Version;
+
+/// Time allowed for each option's version query
+const VERSION_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Versions the script reported for an option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionVersions {
+    pub installed: Version,
+    pub available: Version,
+}
+
+impl OptionVersions {
+    /// Whether installing again would bring a newer version
+    pub fn update_available(&self) -> bool {
+        self.available > self.installed
+    }
+}
+
+/// Progress of a background version query, sent back to the UI
+#[derive(Debug, Clone)]
+pub enum VersionEvent {
+    /// One option was asked; None when the script gave no versions
+    Checked {
+        option_id: String,
+        versions: Option<OptionVersions>,
+    },
+    /// Every option has been asked
+    Done,
+}
+
+/// Run `install version <id>` for one option
+pub fn query_versions(script_dir: &Path, entry: &Path, option_id: &str) -> Option<OptionVersions> {
+    let outcome = run_program(entry, &["version", option_id], script_dir, VERSION_TIMEOUT);
+    if outcome.status != CheckStatus::Passed {
+        return None;
+    }
+    outcome.output.iter().find_map(|line| parse_versions(line))
+}
+
+/// Read an `installed=X available=Y` line, in either order
+fn parse_versions(line: &str) -> Option<OptionVersions> {
+    let (mut installed, mut available) = (None, None);
+    for field in line.split_whitespace() {
+        match field.split_once('=') {
+            Some(("installed", version)) => installed = Version::parse(version),
+            Some(("available", version)) => available = Version::parse(version),
+            _ => {}
+        }
+    }
+    Some(OptionVersions {
+        installed: installed?,
+        available: available?,
+    })
+}
+
+/// Query options one after another on a background thread
+pub fn query_all(script_dir: PathBuf, entry: PathBuf, option_ids: Vec<String>, tx: Sender<VersionEvent>) {
+    thread::spawn(move || {
+        for option_id in option_ids {
+            let versions = query_versions(&script_dir, &entry, &option_id);
+            if tx.send(VersionEvent::Checked { option_id, versions }).is_err() {
+                return;
+            }
+        }
+        let _ = tx.send(VersionEvent::Done);
+    });
+}
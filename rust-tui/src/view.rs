@@ -0,0 +1,129 @@
+//! Visible window over a scrollable list of rows
+//!
+//! The output panel, its scrollbar and scrolling all work out which rows are
+//! on screen from the same [`ViewWindow`], so they can't disagree about it.
+
+use std::ops::Range;
+
+/// Rows `offset..offset + height` of a buffer of `len` rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ViewWindow {
+    offset: usize,
+    height: usize,
+    len: usize,
+}
+
+impl ViewWindow {
+    /// A window at `offset`, clamped so it never runs past the end
+    pub fn new(len: usize, height: usize, offset: usize) -> Self {
+        let mut window = Self { offset, height, len };
+        window.offset = offset.min(window.max_offset());
+        window
+    }
+
+    /// First visible row
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Largest offset that still fills the viewport
+    pub fn max_offset(&self) -> usize {
+        self.len.saturating_sub(self.height)
+    }
+
+    /// Whether the buffer is longer than the viewport
+    pub fn is_scrollable(&self) -> bool {
+        self.len > self.height
+    }
+
+    /// Whether the last row is visible
+    pub fn at_end(&self) -> bool {
+        self.offset >= self.max_offset()
+    }
+
+    /// Indices of the visible rows
+    pub fn range(&self) -> Range<usize> {
+        self.offset..(self.offset + self.height).min(self.len)
+    }
+
+    /// Move by `delta` rows, staying within bounds
+    pub fn scroll_by(&mut self, delta: isize) {
+        self.offset = self.offset.saturating_add_signed(delta).min(self.max_offset());
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every window over buffers and viewports up to a few rows, at offsets past the end
+    fn windows() -> impl Iterator<Item = ViewWindow> {
+        (0..12).flat_map(|len| {
+            (0..12).flat_map(move |height| (0..16).map(move |offset| ViewWindow::new(len, height, offset)))
+        })
+    }
+
+    #[test]
+    fn offset_never_passes_max_offset() {
+        for mut window in windows() {
+            assert!(window.offset() <= window.max_offset(), "{:?}", window);
+            for delta in [-20, -3, -1, 0, 1, 3, 20, isize::MAX, isize::MIN] {
+                window.scroll_by(delta);
+                assert!(window.offset() <= window.max_offset(), "{:?} after {}", window, delta);
+            }
+        }
+    }
+
+    #[test]
+    fn scrolling_past_the_end_lands_at_the_end() {
+        for mut window in windows() {
+            window.scroll_by(window.len as isize + 1);
+            assert!(window.at_end(), "{:?}", window);
+            assert_eq!(window.offset(), window.max_offset());
+            assert_eq!(window.range().end, window.len, "the last row is visible");
+        }
+    }
+
+    #[test]
+    fn scrolling_before_the_start_lands_at_the_top() {
+        for mut window in windows() {
+            window.scroll_by(-(window.len as isize) - 1);
+            assert_eq!(window.offset(), 0, "{:?}", window);
+        }
+    }
+
+    #[test]
+    fn short_buffers_show_everything_and_dont_scroll() {
+        for window in windows().filter(|window| window.len <= window.height) {
+            assert_eq!(window.offset(), 0, "{:?}", window);
+            assert_eq!(window.range(), 0..window.len);
+            assert!(window.at_end());
+            assert!(!window.is_scrollable());
+        }
+    }
+
+    #[test]
+    fn range_fits_the_viewport_and_the_buffer() {
+        for window in windows() {
+            let range = window.range();
+            assert_eq!(range.start, window.offset());
+            assert!(range.end <= window.len, "{:?}", window);
+            assert_eq!(range.len(), window.height.min(window.len), "{:?}", window);
+            assert_eq!(window.is_scrollable(), window.len > window.height);
+        }
+    }
+
+    #[test]
+    fn scrolling_moves_by_the_delta_within_bounds() {
+        let mut window = ViewWindow::new(100, 10, 0);
+        window.scroll_by(5);
+        assert_eq!(window.range(), 5..15);
+        window.scroll_by(-2);
+        assert_eq!(window.offset(), 3);
+        assert!(!window.at_end());
+        window.scroll_by(87);
+        assert_eq!(window.range(), 90..100);
+        assert!(window.at_end());
+    }
+}
@@ -0,0 +1,90 @@
+//! The single channel the event loop sleeps on
+//!
+//! Terminal input is read on its own thread and sent here, and every worker
+//! channel (installer, update check, installed-status check, state writes) is
+//! relayed so that a new message also sends a [`Wake::Worker`]. The loop
+//! blocks until something arrives or a tick is due, instead of polling.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crossterm::event::{self, Event};
+
+/// How often the input thread looks at its stop flag while no input arrives
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Something for the event loop to look at
+pub enum Wake {
+    /// A terminal event, or the error that ended terminal input
+    Terminal(io::Result<Event>),
+    /// A worker channel has a new message or was closed
+    Worker,
+    /// SIGTSTP arrived from outside the terminal
+    Suspend,
+}
+
+/// Forward `rx` through a new channel, sending a wake for every message
+///
+/// The relay ends when either side hangs up, waking the loop once more so
+/// a closed channel is noticed.
+pub fn relay<T: Send + 'static>(rx: Receiver<T>, wake: Sender<Wake>) -> Receiver<T> {
+    let (tx, relayed) = mpsc::channel();
+    thread::spawn(move || {
+        for message in rx {
+            if tx.send(message).is_err() {
+                return;
+            }
+            let _ = wake.send(Wake::Worker);
+        }
+        drop(tx);
+        let _ = wake.send(Wake::Worker);
+    });
+    relayed
+}
+
+/// Thread reading terminal events into the wake channel
+///
+/// Stopped (and joined) when dropped, so nothing keeps reading the terminal
+/// once the UI has given it back.
+pub struct InputThread {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl InputThread {
+    /// Start reading terminal events
+    pub fn start(wake: Sender<Wake>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                let event = match event::poll(INPUT_POLL_INTERVAL) {
+                    Ok(false) => continue,
+                    Ok(true) => event::read(),
+                    Err(e) => Err(e),
+                };
+                let failed = event.is_err();
+                if wake.send(Wake::Terminal(event)).is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for InputThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}